@@ -1,11 +1,10 @@
-use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::io::Cursor;
 
 use base64::{Engine as _, engine::general_purpose};
 use image::{GrayImage, ImageFormat};
 
-use crate::ai_connector;
+use crate::ai_connector::{self, ConnectorCapabilities, ConnectorTimeouts};
 use crate::ai_processing::{
     AiDepthMaskParameters, AiForegroundMaskParameters, AiSkyMaskParameters,
     AiSubjectMaskParameters, CachedDepthMap, generate_image_embeddings, get_or_init_ai_models,
@@ -13,7 +12,7 @@ use crate::ai_processing::{
 };
 use crate::app_settings::load_settings;
 use crate::app_state::AppState;
-use crate::cache_utils::GEOMETRY_KEYS;
+use crate::cache_utils::{GEOMETRY_KEYS, StableHasher, canonical_json};
 use crate::get_cached_full_warped_image;
 
 fn encode_to_base64_png(image: &GrayImage) -> Result<String, String> {
@@ -107,11 +106,11 @@ pub async fn generate_ai_depth_mask(
     let path_hash = {
         let mut hasher = blake3::Hasher::new();
         hasher.update(path.as_bytes());
-        let mut geo_hasher = DefaultHasher::new();
+        let mut geo_hasher = StableHasher::new();
         for key in GEOMETRY_KEYS {
             if let Some(val) = js_adjustments.get(key) {
                 key.hash(&mut geo_hasher);
-                val.to_string().hash(&mut geo_hasher);
+                canonical_json(val).hash(&mut geo_hasher);
             }
         }
         hasher.update(&geo_hasher.finish().to_le_bytes());
@@ -196,11 +195,11 @@ pub async fn generate_ai_subject_mask(
     let path_hash = {
         let mut hasher = blake3::Hasher::new();
         hasher.update(path.as_bytes());
-        let mut geo_hasher = DefaultHasher::new();
+        let mut geo_hasher = StableHasher::new();
         for key in GEOMETRY_KEYS {
             if let Some(val) = js_adjustments.get(key) {
                 key.hash(&mut geo_hasher);
-                val.to_string().hash(&mut geo_hasher);
+                canonical_json(val).hash(&mut geo_hasher);
             }
         }
         hasher.update(&geo_hasher.finish().to_le_bytes());
@@ -342,11 +341,11 @@ pub async fn precompute_ai_subject_mask(
     let path_hash = {
         let mut hasher = blake3::Hasher::new();
         hasher.update(path.as_bytes());
-        let mut geo_hasher = DefaultHasher::new();
+        let mut geo_hasher = StableHasher::new();
         for key in GEOMETRY_KEYS {
             if let Some(val) = js_adjustments.get(key) {
                 key.hash(&mut geo_hasher);
-                val.to_string().hash(&mut geo_hasher);
+                canonical_json(val).hash(&mut geo_hasher);
             }
         }
         hasher.update(&geo_hasher.finish().to_le_bytes());
@@ -372,26 +371,45 @@ pub async fn precompute_ai_subject_mask(
     Ok(())
 }
 
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionTestResult {
+    pub latency_ms: u64,
+    pub capabilities: ConnectorCapabilities,
+}
+
 #[tauri::command]
 pub async fn check_ai_connector_status(app_handle: tauri::AppHandle) {
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
-    let is_connected = if let Some(address) = settings.ai_connector_address {
-        ai_connector::check_status(&address).await.unwrap_or(false)
+    let capabilities = if let Some(address) = &settings.ai_connector_address {
+        ai_connector::check_status(address, ConnectorTimeouts::from_settings(&settings))
+            .await
+            .ok()
     } else {
-        false
+        None
     };
     use tauri::Emitter;
     let _ = app_handle.emit(
         "ai-connector-status-update",
-        serde_json::json!({ "connected": is_connected }),
+        serde_json::json!({
+            "connected": capabilities.is_some(),
+            "capabilities": capabilities,
+        }),
     );
 }
 
 #[tauri::command]
-pub async fn test_ai_connector_connection(address: String) -> Result<(), String> {
-    match ai_connector::check_status(&address).await {
-        Ok(true) => Ok(()),
-        Ok(false) => Err("Server reachable but returned bad health status".to_string()),
-        Err(e) => Err(e.to_string()),
-    }
+pub async fn test_ai_connector_connection(
+    address: String,
+    app_handle: tauri::AppHandle,
+) -> Result<ConnectionTestResult, String> {
+    let settings = load_settings(app_handle).unwrap_or_default();
+    let (latency, capabilities) =
+        ai_connector::measure_latency(&address, ConnectorTimeouts::from_settings(&settings))
+            .await
+            .map_err(|e| e.to_string())?;
+    Ok(ConnectionTestResult {
+        latency_ms: latency.as_millis() as u64,
+        capabilities,
+    })
 }
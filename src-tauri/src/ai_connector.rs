@@ -3,12 +3,146 @@ use base64::{Engine as _, engine::general_purpose};
 use image::{
     DynamicImage, GenericImageView, ImageFormat, RgbaImage, codecs::jpeg::JpegEncoder, imageops,
 };
-use reqwest::{Client, multipart};
+use reqwest::{Client, RequestBuilder, Response, multipart};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Cursor;
 use std::path::Path;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::app_settings::{
+    AppSettings, DEFAULT_AI_CONNECTOR_CONNECT_TIMEOUT_SECS, DEFAULT_AI_CONNECTOR_TIMEOUT_SECS,
+};
+
+const MAX_REQUEST_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(750);
+const JOB_MAX_DURATION: Duration = Duration::from_secs(15 * 60);
+
+/// Features advertised by the connector in its `/health` response. Older connectors
+/// reply without a body; those only ever supported blocking inpainting.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ConnectorCapabilities {
+    pub inpaint: bool,
+    pub outpaint: bool,
+    pub upscale: bool,
+    pub jobs: bool,
+    pub models: Vec<String>,
+    #[serde(alias = "max_resolution")]
+    pub max_resolution: Option<u32>,
+}
+
+impl Default for ConnectorCapabilities {
+    fn default() -> Self {
+        Self {
+            inpaint: true,
+            outpaint: false,
+            upscale: false,
+            jobs: false,
+            models: Vec::new(),
+            max_resolution: None,
+        }
+    }
+}
+
+/// How an inpaint request is run. `Job` submits to the asynchronous job API and polls
+/// it, reporting progress in the 0..=1 range through the callback.
+#[derive(Clone, Copy)]
+pub enum InpaintMode<'a> {
+    Blocking,
+    Job(&'a (dyn Fn(f32) + Sync)),
+}
+
+#[derive(Clone, Copy)]
+pub struct ConnectorTimeouts {
+    pub connect: Duration,
+    pub request: Duration,
+}
+
+impl ConnectorTimeouts {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        let connect = settings
+            .ai_connector_connect_timeout_secs
+            .unwrap_or(DEFAULT_AI_CONNECTOR_CONNECT_TIMEOUT_SECS)
+            .max(1);
+        let request = settings
+            .ai_connector_timeout_secs
+            .unwrap_or(DEFAULT_AI_CONNECTOR_TIMEOUT_SECS)
+            .max(1);
+        Self {
+            connect: Duration::from_secs(connect as u64),
+            request: Duration::from_secs(request as u64),
+        }
+    }
+
+    fn client(&self) -> Result<Client> {
+        Ok(Client::builder()
+            .connect_timeout(self.connect)
+            .timeout(self.request)
+            .build()?)
+    }
+}
+
+/// Sends the request built by `build` with exponential backoff between attempts.
+/// Connection failures are always retried since the request never reached the server.
+/// Timeouts and 5xx responses are only retried when `idempotent` is set; a generation
+/// request may still be running after either, so it is not submitted twice. Other
+/// statuses are returned to the caller as-is.
+async fn send_with_retry(
+    attempts: u32,
+    what: &str,
+    idempotent: bool,
+    build: impl Fn() -> Result<RequestBuilder>,
+) -> Result<Response> {
+    let attempts = attempts.max(1);
+    let mut last_error = String::new();
+    for attempt in 0..attempts {
+        if attempt > 0 {
+            tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+        }
+        match build()?.send().await {
+            Ok(response) if idempotent && response.status().is_server_error() => {
+                last_error = format!("server returned {}", response.status());
+            }
+            Ok(response) => return Ok(response),
+            Err(e) if e.is_connect() || (idempotent && e.is_timeout()) => {
+                last_error = if e.is_timeout() {
+                    "request timed out".to_string()
+                } else {
+                    format!("could not connect ({})", e)
+                };
+            }
+            Err(e) => return Err(e.into()),
+        }
+        log::warn!(
+            "AI connector {} attempt {}/{} failed: {}",
+            what,
+            attempt + 1,
+            attempts,
+            last_error
+        );
+    }
+    Err(anyhow!(
+        "AI connector {} failed after {} attempts: {}",
+        what,
+        attempts,
+        last_error
+    ))
+}
+
+const DEFAULT_NEGATIVE_PROMPT: &str = "blur, low quality, distortion, watermark";
+
+/// Prompt and sampler settings for a generative request. Unset values are left to the
+/// backend's defaults.
+#[derive(Clone, Default)]
+pub struct GenerationParams {
+    pub prompt: String,
+    pub negative_prompt: Option<String>,
+    pub guidance_scale: Option<f32>,
+    pub steps: Option<u32>,
+    pub seed: Option<i64>,
+}
 
 #[derive(Serialize)]
 struct InpaintRequest {
@@ -17,6 +151,10 @@ struct InpaintRequest {
     negative_prompt: String,
     mask_image_base64: String,
     seed: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    guidance_scale: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    steps: Option<u32>,
 }
 
 #[derive(Deserialize)]
@@ -26,7 +164,48 @@ struct MiddlewareResponse {
     color: String,
 }
 
-pub fn generate_source_id(path_str: &str) -> Result<String> {
+#[derive(Serialize)]
+struct UpscaleRequest {
+    image_base64: String,
+    scale: u32,
+}
+
+#[derive(Deserialize)]
+struct UpscaleResponse {
+    image: String,
+}
+
+#[derive(Deserialize)]
+struct SubmittedJob {
+    job_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Deserialize)]
+struct JobState {
+    status: JobStatus,
+    #[serde(default)]
+    progress: f32,
+    result: Option<MiddlewareResponse>,
+    error: Option<String>,
+}
+
+/// Identifies the uploaded source by file, modification time, the region that was sent and
+/// its pixels. Hashing the pixels keeps expanded canvases and re-composited patches from
+/// reusing an upload whose region coordinates happen to match.
+pub fn generate_source_id(
+    path_str: &str,
+    region: (u32, u32, u32, u32),
+    image: &DynamicImage,
+) -> Result<String> {
     let path = Path::new(path_str);
     let metadata = fs::metadata(path)?;
     let mod_time = metadata
@@ -38,6 +217,10 @@ pub fn generate_source_id(path_str: &str) -> Result<String> {
     let mut hasher = blake3::Hasher::new();
     hasher.update(path_str.as_bytes());
     hasher.update(&mod_time.to_le_bytes());
+    for value in [region.0, region.1, region.2, region.3] {
+        hasher.update(&value.to_le_bytes());
+    }
+    hasher.update(image.as_bytes());
     Ok(hasher.finalize().to_hex().to_string())
 }
 
@@ -63,23 +246,25 @@ async fn upload_source_image(
 ) -> Result<()> {
     let jpeg_bytes = image_to_jpeg_bytes(image, 95)?;
 
-    let part = multipart::Part::bytes(jpeg_bytes)
-        .file_name("source.jpg")
-        .mime_str("image/jpeg")?;
+    let res = send_with_retry(MAX_REQUEST_ATTEMPTS, "upload", true, || {
+        let part = multipart::Part::bytes(jpeg_bytes.clone())
+            .file_name("source.jpg")
+            .mime_str("image/jpeg")?;
 
-    let form = multipart::Form::new()
-        .text("source_id", source_id.to_string())
-        .part("file", part);
+        let form = multipart::Form::new()
+            .text("source_id", source_id.to_string())
+            .part("file", part);
 
-    let mut req = client
-        .post(format!("{}/upload_source", base_url))
-        .multipart(form);
+        let mut req = client
+            .post(format!("{}/upload_source", base_url))
+            .multipart(form);
 
-    if let Some(auth_token) = token {
-        req = req.bearer_auth(auth_token);
-    }
-
-    let res = req.send().await?;
+        if let Some(auth_token) = token {
+            req = req.bearer_auth(auth_token);
+        }
+        Ok(req)
+    })
+    .await?;
 
     if !res.status().is_success() {
         return Err(anyhow!("Upload failed: {}", res.text().await?));
@@ -106,66 +291,185 @@ fn composite_full_res(
     Ok(full_color)
 }
 
-pub async fn check_status(address: &str) -> Result<bool> {
-    let client = Client::new();
-    let res = client
-        .get(format!("http://{}/health", address))
-        .send()
-        .await;
-    Ok(res.is_ok())
+/// Single health check. Fails when the connector is unreachable or unhealthy.
+pub async fn check_status(
+    address: &str,
+    timeouts: ConnectorTimeouts,
+) -> Result<ConnectorCapabilities> {
+    Ok(health_round_trip(address, timeouts, 1).await?.1)
+}
+
+/// Health check that retries briefly before giving up, returning the round trip time
+/// alongside the advertised capabilities.
+pub async fn measure_latency(
+    address: &str,
+    timeouts: ConnectorTimeouts,
+) -> Result<(Duration, ConnectorCapabilities)> {
+    health_round_trip(address, timeouts, MAX_REQUEST_ATTEMPTS).await
 }
 
+async fn health_round_trip(
+    address: &str,
+    timeouts: ConnectorTimeouts,
+    attempts: u32,
+) -> Result<(Duration, ConnectorCapabilities)> {
+    let client = timeouts.client()?;
+    let url = format!("http://{}/health", address);
+    let start = Instant::now();
+    let res = send_with_retry(attempts, "health check", true, || Ok(client.get(&url))).await?;
+    let elapsed = start.elapsed();
+    if !res.status().is_success() {
+        return Err(anyhow!(
+            "Server reachable but returned bad health status ({})",
+            res.status()
+        ));
+    }
+    let capabilities = res.json().await.unwrap_or_default();
+    Ok((elapsed, capabilities))
+}
+
+async fn poll_inpaint_job(
+    client: &Client,
+    base_url: &str,
+    job_id: &str,
+    token: Option<&str>,
+    on_progress: &(dyn Fn(f32) + Sync),
+) -> Result<MiddlewareResponse> {
+    let url = format!("{}/inpaint/jobs/{}", base_url, job_id);
+    let started = Instant::now();
+    loop {
+        let res = send_with_retry(MAX_REQUEST_ATTEMPTS, "job status", true, || {
+            let mut req = client.get(&url);
+            if let Some(auth_token) = token {
+                req = req.bearer_auth(auth_token);
+            }
+            Ok(req)
+        })
+        .await?;
+        if !res.status().is_success() {
+            return Err(anyhow!("AI job status failed: {}", res.text().await?));
+        }
+
+        let job: JobState = res.json().await?;
+        on_progress(job.progress.clamp(0.0, 1.0));
+        match job.status {
+            JobStatus::Done => {
+                return job
+                    .result
+                    .ok_or_else(|| anyhow!("AI job {} finished without a result", job_id));
+            }
+            JobStatus::Failed => {
+                return Err(anyhow!(
+                    "AI generation failed: {}",
+                    job.error.unwrap_or_else(|| "unknown error".to_string())
+                ));
+            }
+            JobStatus::Queued | JobStatus::Running => {}
+        }
+
+        if started.elapsed() > JOB_MAX_DURATION {
+            return Err(anyhow!(
+                "AI job {} did not finish within {} minutes",
+                job_id,
+                JOB_MAX_DURATION.as_secs() / 60
+            ));
+        }
+        tokio::time::sleep(JOB_POLL_INTERVAL).await;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn process_inpainting(
     base_url: &str,
     source_path: &str,
-    full_source_image: &DynamicImage,
+    source_region: (u32, u32, u32, u32),
+    source_image: &DynamicImage,
     mask_image: &DynamicImage,
-    prompt: String,
+    params: GenerationParams,
     token: Option<&str>,
+    timeouts: ConnectorTimeouts,
+    mode: InpaintMode<'_>,
 ) -> Result<RgbaImage> {
-    let client = Client::new();
-    let source_id = generate_source_id(source_path)?;
+    let client = timeouts.client()?;
+    let source_id = generate_source_id(source_path, source_region, source_image)?;
     let mask_b64 = image_to_base64(mask_image)?;
-    let (w, h) = full_source_image.dimensions();
+    let (w, h) = source_image.dimensions();
 
     let payload = InpaintRequest {
         source_id: source_id.clone(),
-        prompt,
-        negative_prompt: "blur, low quality, distortion, watermark".to_string(),
+        prompt: params.prompt,
+        negative_prompt: params
+            .negative_prompt
+            .unwrap_or_else(|| DEFAULT_NEGATIVE_PROMPT.to_string()),
         mask_image_base64: mask_b64,
-        seed: 0,
+        seed: params.seed.unwrap_or(0),
+        guidance_scale: params.guidance_scale,
+        steps: params.steps,
     };
 
-    let url = format!("{}/inpaint", base_url);
-
-    let mut req = client.post(&url).json(&payload);
-    if let Some(auth_token) = token {
-        req = req.bearer_auth(auth_token);
-    }
-
-    let response = req.send().await?;
-
-    let middleware_data: MiddlewareResponse = if response.status() == 404 {
-        upload_source_image(&client, base_url, &source_id, full_source_image, token).await?;
+    let url = match mode {
+        InpaintMode::Blocking => format!("{}/inpaint", base_url),
+        InpaintMode::Job(_) => format!("{}/inpaint/jobs", base_url),
+    };
 
-        let mut retry_req = client.post(&url).json(&payload);
+    let build_request = || {
+        let mut req = client.post(&url).json(&payload);
         if let Some(auth_token) = token {
-            retry_req = retry_req.bearer_auth(auth_token);
+            req = req.bearer_auth(auth_token);
         }
+        Ok(req)
+    };
+
+    let mut response =
+        send_with_retry(MAX_REQUEST_ATTEMPTS, "inpaint", false, build_request).await?;
 
-        let retry_res = retry_req.send().await?;
-        if !retry_res.status().is_success() {
+    if response.status() == 404 {
+        upload_source_image(&client, base_url, &source_id, source_image, token).await?;
+
+        response = send_with_retry(MAX_REQUEST_ATTEMPTS, "inpaint", false, build_request).await?;
+        if !response.status().is_success() {
             return Err(anyhow!(
                 "AI generation failed after upload: {}",
-                retry_res.text().await?
+                response.text().await?
             ));
         }
-        retry_res.json().await?
     } else if !response.status().is_success() {
         return Err(anyhow!("AI generation failed: {}", response.text().await?));
-    } else {
-        response.json().await?
+    }
+
+    let middleware_data: MiddlewareResponse = match mode {
+        InpaintMode::Blocking => response.json().await?,
+        InpaintMode::Job(on_progress) => {
+            let job: SubmittedJob = response.json().await?;
+            poll_inpaint_job(&client, base_url, &job.job_id, token, on_progress).await?
+        }
     };
 
     composite_full_res(middleware_data, w, h)
 }
+
+pub async fn process_upscale(
+    base_url: &str,
+    image: &DynamicImage,
+    scale: u32,
+    timeouts: ConnectorTimeouts,
+) -> Result<DynamicImage> {
+    let client = timeouts.client()?;
+    let payload = UpscaleRequest {
+        image_base64: image_to_base64(image)?,
+        scale,
+    };
+    let url = format!("{}/upscale", base_url);
+
+    let response = send_with_retry(MAX_REQUEST_ATTEMPTS, "upscale", false, || {
+        Ok(client.post(&url).json(&payload))
+    })
+    .await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("AI upscale failed: {}", response.text().await?));
+    }
+
+    let data: UpscaleResponse = response.json().await?;
+    let bytes = general_purpose::STANDARD.decode(&data.image)?;
+    Ok(image::load_from_memory(&bytes)?)
+}
@@ -1,12 +1,23 @@
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
+use std::sync::atomic::Ordering;
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tauri::{AppHandle, Manager};
 
 use crate::app_state::AppState;
+use crate::color_management::OutputColorSpace;
+use crate::export_processing::OutputSharpening;
+
+pub const DEFAULT_EDIT_HISTORY_LIMIT: u32 = 50;
+pub const DEFAULT_GEOMETRY_CACHE_SIZE: u32 = 6;
+pub const DEFAULT_MASK_CACHE_SIZE: u32 = 50;
+pub const DEFAULT_LUT_CACHE_SIZE: u32 = 16;
+pub const DEFAULT_AI_CONNECTOR_CONNECT_TIMEOUT_SECS: u32 = 10;
+pub const DEFAULT_AI_CONNECTOR_TIMEOUT_SECS: u32 = 180;
+pub const DEFAULT_RENDER_CACHE_SIZE_MB: u32 = 1024;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -79,81 +90,148 @@ pub enum PasteMode {
     Replace,
 }
 
-pub fn all_available_adjustments() -> HashSet<String> {
-    [
-        "exposure",
-        "brightness",
-        "contrast",
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum DemosaicQuality {
+    Fast,
+    #[default]
+    High,
+}
+
+/// Every adjustment key, grouped into the sections used for partial copy/paste. This is
+/// the single list presets, copy/paste and sync filter against.
+const ADJUSTMENT_SECTIONS: &[(&str, &[&str])] = &[
+    (
+        "tone",
+        &[
+            "exposure",
+            "brightness",
+            "contrast",
+            "highlights",
+            "shadows",
+            "whites",
+            "blacks",
+            "toneMapper",
+        ],
+    ),
+    ("whiteBalance", &["temperature", "tint", "rawWbMultipliers"]),
+    (
         "curves",
-        "pointCurves",
-        "parametricCurve",
-        "curveMode",
-        "highlights",
-        "shadows",
-        "whites",
-        "blacks",
-        "toneMapper",
-        "temperature",
-        "tint",
-        "saturation",
-        "vibrance",
-        "hsl",
-        "hue",
-        "colorGrading",
-        "colorCalibration",
-        "clarity",
-        "structure",
-        "dehaze",
-        "sharpness",
-        "sharpnessThreshold",
-        "centré",
-        "lumaNoiseReduction",
-        "colorNoiseReduction",
-        "chromaticAberrationRedCyan",
-        "chromaticAberrationBlueYellow",
-        "vignetteAmount",
-        "vignetteFeather",
-        "vignetteMidpoint",
-        "vignetteRoundness",
-        "grainAmount",
-        "grainRoughness",
-        "grainSize",
-        "lutIntensity",
-        "lutName",
-        "lutPath",
-        "lutSize",
-        "lutData",
-        "glowAmount",
-        "halationAmount",
-        "flareAmount",
-        "crop",
-        "aspectRatio",
-        "rotation",
-        "flipHorizontal",
-        "flipVertical",
-        "orientationSteps",
-        "transformDistortion",
-        "transformVertical",
-        "transformHorizontal",
-        "transformRotate",
-        "transformAspect",
-        "transformScale",
-        "transformXOffset",
-        "transformYOffset",
-        "masks",
-        "lensCorrectionMode",
-        "lensMaker",
-        "lensModel",
-        "lensDistortionAmount",
-        "lensVignetteAmount",
-        "lensTcaAmount",
-        "lensDistortionEnabled",
-        "lensTcaEnabled",
-        "lensVignetteEnabled",
-    ]
-    .iter()
-    .map(|s| s.to_string())
-    .collect()
+        &["curves", "pointCurves", "parametricCurve", "curveMode"],
+    ),
+    (
+        "color",
+        &[
+            "saturation",
+            "vibrance",
+            "hsl",
+            "hue",
+            "colorGrading",
+            "colorCalibration",
+        ],
+    ),
+    (
+        "details",
+        &[
+            "clarity",
+            "structure",
+            "texture",
+            "dehaze",
+            "sharpness",
+            "sharpnessThreshold",
+            "centré",
+            "lumaNoiseReduction",
+            "colorNoiseReduction",
+            "chromaticAberrationRedCyan",
+            "chromaticAberrationBlueYellow",
+            "defringePurpleAmount",
+            "defringePurpleHue",
+            "defringeGreenAmount",
+            "defringeGreenHue",
+            "defringePurpleRange",
+            "defringeGreenRange",
+        ],
+    ),
+    (
+        "effects",
+        &[
+            "vignetteAmount",
+            "vignetteFeather",
+            "vignetteMidpoint",
+            "vignetteRoundness",
+            "vignetteHighlightPriority",
+            "grainAmount",
+            "grainRoughness",
+            "grainSize",
+            "grainSeed",
+            "glowAmount",
+            "halationAmount",
+            "flareAmount",
+        ],
+    ),
+    (
+        "lut",
+        &[
+            "lutIntensity",
+            "lutName",
+            "lutPath",
+            "lutSize",
+            "lutData",
+            "luts",
+        ],
+    ),
+    (
+        "geometry",
+        &[
+            "crop",
+            "aspectRatio",
+            "rotation",
+            "flipHorizontal",
+            "flipVertical",
+            "orientationSteps",
+            "transformDistortion",
+            "transformVertical",
+            "transformHorizontal",
+            "transformRotate",
+            "transformAspect",
+            "transformScale",
+            "transformXOffset",
+            "transformYOffset",
+        ],
+    ),
+    (
+        "lens",
+        &[
+            "lensCorrectionMode",
+            "lensMaker",
+            "lensModel",
+            "lensDistortionAmount",
+            "lensVignetteAmount",
+            "lensTcaAmount",
+            "lensDistortionEnabled",
+            "lensTcaEnabled",
+            "lensVignetteEnabled",
+            "manualCaRed",
+            "manualCaBlue",
+        ],
+    ),
+    ("masks", &["masks"]),
+];
+
+pub fn all_available_adjustments() -> HashSet<String> {
+    ADJUSTMENT_SECTIONS
+        .iter()
+        .flat_map(|(_, keys)| keys.iter())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Keys belonging to one section of `ADJUSTMENT_SECTIONS`.
+pub fn adjustment_section_keys(section: &str) -> Option<&'static [&'static str]> {
+    ADJUSTMENT_SECTIONS
+        .iter()
+        .find(|(name, _)| *name == section)
+        .map(|(_, keys)| *keys)
 }
 
 pub fn default_included_adjustments() -> HashSet<String> {
@@ -184,6 +262,8 @@ pub fn default_included_adjustments() -> HashSet<String> {
         "lensDistortionEnabled",
         "lensTcaEnabled",
         "lensVignetteEnabled",
+        "manualCaRed",
+        "manualCaBlue",
     ];
 
     for item in off_by_default.iter() {
@@ -193,6 +273,52 @@ pub fn default_included_adjustments() -> HashSet<String> {
     defaults
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClippingOverlaySettings {
+    pub highlight_color: String,
+    pub shadow_color: String,
+    pub highlight_threshold: u8,
+    pub shadow_threshold: u8,
+    #[serde(default)]
+    pub zebra: bool,
+}
+
+impl Default for ClippingOverlaySettings {
+    fn default() -> Self {
+        Self {
+            highlight_color: "#ff0000".to_string(),
+            shadow_color: "#0000ff".to_string(),
+            highlight_threshold: 255,
+            shadow_threshold: 0,
+            zebra: false,
+        }
+    }
+}
+
+pub fn hex_to_rgb(hex: &str) -> Option<[f32; 3]> {
+    let hex = hex.trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some([
+        ((value >> 16) & 0xff) as f32 / 255.0,
+        ((value >> 8) & 0xff) as f32 / 255.0,
+        (value & 0xff) as f32 / 255.0,
+    ])
+}
+
+impl ClippingOverlaySettings {
+    pub fn highlight_rgb(&self) -> [f32; 3] {
+        hex_to_rgb(&self.highlight_color).unwrap_or([1.0, 0.0, 0.0])
+    }
+
+    pub fn shadow_rgb(&self) -> [f32; 3] {
+        hex_to_rgb(&self.shadow_color).unwrap_or([0.0, 0.0, 1.0])
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct CopyPasteSettings {
@@ -228,6 +354,8 @@ pub struct ExportPreset {
     pub resize_value: u32,
     pub dont_enlarge: bool,
     pub keep_metadata: bool,
+    #[serde(default)]
+    pub preserve_timestamps: Option<bool>,
     pub strip_gps: bool,
     pub filename_template: String,
     pub enable_watermark: bool,
@@ -242,6 +370,24 @@ pub struct ExportPreset {
     pub preserve_folders: Option<bool>,
     #[serde(default)]
     pub last_export_path: Option<String>,
+    #[serde(default)]
+    pub artist: Option<String>,
+    #[serde(default)]
+    pub copyright: Option<String>,
+    #[serde(default)]
+    pub contact_url: Option<String>,
+    #[serde(default)]
+    pub write_xmp_sidecar: Option<bool>,
+    #[serde(default)]
+    pub output_color_space: Option<OutputColorSpace>,
+    #[serde(default)]
+    pub output_sharpening: Option<OutputSharpening>,
+    #[serde(default)]
+    pub output_icc_profile: Option<String>,
+    #[serde(default)]
+    pub skip_existing: Option<bool>,
+    #[serde(default)]
+    pub skip_existing_only_if_newer: Option<bool>,
 }
 
 pub fn default_export_presets() -> Vec<ExportPreset> {
@@ -256,6 +402,7 @@ pub fn default_export_presets() -> Vec<ExportPreset> {
             resize_value: 2048,
             dont_enlarge: true,
             keep_metadata: true,
+            preserve_timestamps: Some(false),
             strip_gps: false,
             filename_template: "{original_filename}".to_string(),
             enable_watermark: false,
@@ -267,6 +414,15 @@ pub fn default_export_presets() -> Vec<ExportPreset> {
             export_masks: Some(false),
             preserve_folders: Some(false),
             last_export_path: None,
+            artist: None,
+            copyright: None,
+            contact_url: None,
+            write_xmp_sidecar: Some(false),
+            output_color_space: Some(OutputColorSpace::Srgb),
+            output_sharpening: Some(OutputSharpening::default()),
+            output_icc_profile: None,
+            skip_existing: Some(false),
+            skip_existing_only_if_newer: Some(false),
         },
         ExportPreset {
             id: "default-fast".to_string(),
@@ -278,6 +434,7 @@ pub fn default_export_presets() -> Vec<ExportPreset> {
             resize_value: 2048,
             dont_enlarge: true,
             keep_metadata: false,
+            preserve_timestamps: Some(false),
             strip_gps: true,
             filename_template: "{original_filename}_web".to_string(),
             enable_watermark: false,
@@ -289,6 +446,15 @@ pub fn default_export_presets() -> Vec<ExportPreset> {
             export_masks: Some(false),
             preserve_folders: Some(false),
             last_export_path: None,
+            artist: None,
+            copyright: None,
+            contact_url: None,
+            write_xmp_sidecar: Some(false),
+            output_color_space: Some(OutputColorSpace::Srgb),
+            output_sharpening: Some(OutputSharpening::default()),
+            output_icc_profile: None,
+            skip_existing: Some(false),
+            skip_existing_only_if_newer: Some(false),
         },
     ]
 }
@@ -339,6 +505,10 @@ pub struct AppSettings {
     #[serde(default)]
     pub thumbnail_resolution: Option<u32>,
     #[serde(default)]
+    pub thumbnail_format: Option<String>,
+    #[serde(default)]
+    pub thumbnail_show_edits: Option<bool>,
+    #[serde(default)]
     pub enable_zoom_hifi: Option<bool>,
     #[serde(default)]
     pub use_full_dpi_rendering: Option<bool>,
@@ -356,6 +526,12 @@ pub struct AppSettings {
     pub decorations: Option<bool>,
     #[serde(alias = "comfyuiAddress")]
     pub ai_connector_address: Option<String>,
+    #[serde(default)]
+    pub ai_connector_connect_timeout_secs: Option<u32>,
+    #[serde(default)]
+    pub ai_connector_timeout_secs: Option<u32>,
+    #[serde(default)]
+    pub ffmpeg_path: Option<String>,
     pub last_folder_state: Option<LastFolderState>,
     pub ui_visibility: Option<Value>,
     pub enable_ai_tagging: Option<bool>,
@@ -376,10 +552,16 @@ pub struct AppSettings {
     #[serde(default)]
     pub copy_paste_settings: CopyPasteSettings,
     #[serde(default)]
+    pub clipping_overlay: ClippingOverlaySettings,
+    #[serde(default)]
     pub raw_highlight_compression: Option<f32>,
     #[serde(default)]
     pub processing_backend: Option<String>,
     #[serde(default)]
+    pub preferred_gpu_adapter: Option<String>,
+    #[serde(default)]
+    pub gpu_memory_budget_mb: Option<u32>,
+    #[serde(default)]
     pub linux_gpu_optimization: Option<bool>,
     #[serde(default)]
     pub linux_gpu_optimization_migrated_v1: Option<bool>,
@@ -396,10 +578,22 @@ pub struct AppSettings {
     #[serde(default = "default_linear_raw_mode")]
     pub linear_raw_mode: String,
     #[serde(default)]
+    pub linear_scene_referred: Option<bool>,
+    #[serde(default)]
+    pub demosaic_quality: Option<DemosaicQuality>,
+    #[serde(default)]
+    pub half_res_raw_preview: Option<bool>,
+    #[serde(default)]
+    pub calibration_dark_frame: Option<String>,
+    #[serde(default)]
+    pub calibration_flat_field: Option<String>,
+    #[serde(default)]
     pub enable_xmp_sync: Option<bool>,
     #[serde(default)]
     pub create_xmp_if_missing: Option<bool>,
     #[serde(default)]
+    pub write_rating_to_file: Option<bool>,
+    #[serde(default)]
     pub is_waveform_visible: Option<bool>,
     #[serde(default)]
     pub waveform_height: Option<u32>,
@@ -416,8 +610,26 @@ pub struct AppSettings {
     #[serde(default)]
     pub thumbnail_worker_threads: Option<u32>,
     #[serde(default)]
+    pub thumbnail_max_concurrency: Option<u32>,
+    #[serde(default)]
+    pub use_embedded_raw_thumbnails: Option<bool>,
+    #[serde(default)]
+    pub refine_embedded_raw_thumbnails: Option<bool>,
+    #[serde(default)]
     pub image_cache_size: Option<u32>,
     #[serde(default)]
+    pub geometry_cache_size: Option<u32>,
+    #[serde(default)]
+    pub mask_cache_size: Option<u32>,
+    #[serde(default)]
+    pub full_res_scopes: Option<bool>,
+    #[serde(default)]
+    pub lut_cache_size: Option<u32>,
+    #[serde(default)]
+    pub render_cache_enabled: Option<bool>,
+    #[serde(default)]
+    pub render_cache_size_mb: Option<u32>,
+    #[serde(default)]
     pub tonemapper_override_enabled: Option<bool>,
     #[serde(default)]
     pub default_raw_tonemapper: Option<String>,
@@ -434,6 +646,8 @@ pub struct AppSettings {
     #[serde(default)]
     pub apply_preprocessing_to_non_raws: Option<bool>,
     #[serde(default)]
+    pub auto_orient_from_exif: Option<bool>,
+    #[serde(default)]
     pub exif_overlay: Option<String>,
     #[serde(default)]
     pub language: Option<String>,
@@ -441,6 +655,12 @@ pub struct AppSettings {
     pub folder_tree_sort: Option<FolderTreeSort>,
     #[serde(default)]
     pub library_display_mode: Option<String>,
+    #[serde(default)]
+    pub edit_history_limit: Option<u32>,
+    #[serde(default)]
+    pub enable_online_geocoding: Option<bool>,
+    #[serde(default)]
+    pub geocoding_service_address: Option<String>,
 }
 
 impl Default for AppSettings {
@@ -450,6 +670,8 @@ impl Default for AppSettings {
             root_folders: Vec::new(),
             pinned_folders: Vec::new(),
             thumbnail_resolution: Some(720),
+            thumbnail_format: Some("jpeg".to_string()),
+            thumbnail_show_edits: Some(true),
             #[cfg(target_os = "android")]
             editor_preview_resolution: Some(1280),
             #[cfg(not(target_os = "android"))]
@@ -464,6 +686,9 @@ impl Default for AppSettings {
             font_family: None,
             decorations: Some(false),
             ai_connector_address: None,
+            ai_connector_connect_timeout_secs: Some(DEFAULT_AI_CONNECTOR_CONNECT_TIMEOUT_SECS),
+            ai_connector_timeout_secs: Some(DEFAULT_AI_CONNECTOR_TIMEOUT_SECS),
+            ffmpeg_path: None,
             last_folder_state: None,
             ui_visibility: None,
             enable_ai_tagging: Some(false),
@@ -480,8 +705,11 @@ impl Default for AppSettings {
             adjustment_visibility: default_adjustment_visibility(),
             open_tree_sections: default_open_tree_sections(),
             copy_paste_settings: CopyPasteSettings::default(),
+            clipping_overlay: ClippingOverlaySettings::default(),
             raw_highlight_compression: Some(2.5),
             processing_backend: Some("auto".to_string()),
+            preferred_gpu_adapter: None,
+            gpu_memory_budget_mb: Some(2048),
             linux_gpu_optimization: Some(false),
             linux_gpu_optimization_migrated_v1: Some(true),
             library_view_mode: Some("flat".to_string()),
@@ -494,8 +722,14 @@ impl Default for AppSettings {
             enable_folder_image_counts: Some(false),
             display_edit_icon: Some(true),
             linear_raw_mode: default_linear_raw_mode(),
+            linear_scene_referred: Some(false),
+            demosaic_quality: Some(DemosaicQuality::High),
+            half_res_raw_preview: Some(false),
+            calibration_dark_frame: None,
+            calibration_flat_field: None,
             enable_xmp_sync: Some(true),
             create_xmp_if_missing: Some(false),
+            write_rating_to_file: Some(false),
             is_waveform_visible: Some(false),
             waveform_height: Some(220),
             active_waveform_channel: Some("luma".to_string()),
@@ -510,10 +744,19 @@ impl Default for AppSettings {
             thumbnail_worker_threads: Some(2),
             #[cfg(not(target_os = "android"))]
             thumbnail_worker_threads: Some(4),
+            thumbnail_max_concurrency: None,
+            use_embedded_raw_thumbnails: Some(true),
+            refine_embedded_raw_thumbnails: Some(false),
             #[cfg(target_os = "android")]
             image_cache_size: Some(2),
             #[cfg(not(target_os = "android"))]
             image_cache_size: Some(5),
+            geometry_cache_size: Some(DEFAULT_GEOMETRY_CACHE_SIZE),
+            mask_cache_size: Some(DEFAULT_MASK_CACHE_SIZE),
+            full_res_scopes: Some(false),
+            lut_cache_size: Some(DEFAULT_LUT_CACHE_SIZE),
+            render_cache_enabled: Some(false),
+            render_cache_size_mb: Some(DEFAULT_RENDER_CACHE_SIZE_MB),
             tonemapper_override_enabled: Some(false),
             default_raw_tonemapper: Some("agx".to_string()),
             default_non_raw_tonemapper: Some("basic".to_string()),
@@ -522,10 +765,14 @@ impl Default for AppSettings {
             raw_preprocessing_color_nr: Some(0.5),
             raw_preprocessing_sharpening: Some(0.35),
             apply_preprocessing_to_non_raws: Some(false),
+            auto_orient_from_exif: Some(true),
             exif_overlay: Some("off".to_string()),
             language: Some("en".to_string()),
             folder_tree_sort: Some(FolderTreeSort::default()),
             library_display_mode: Some("grid".to_string()),
+            edit_history_limit: Some(DEFAULT_EDIT_HISTORY_LIMIT),
+            enable_online_geocoding: Some(false),
+            geocoding_service_address: None,
         }
     }
 }
@@ -617,11 +864,83 @@ pub fn save_settings(settings: AppSettings, app_handle: AppHandle) -> Result<(),
     fs::write(path, json_string).map_err(|e| e.to_string())?;
 
     let state = app_handle.state::<AppState>();
-    let cache_size = settings.image_cache_size.unwrap_or(5) as usize;
+    crate::cache_utils::apply_cache_settings(&state, &settings);
+    let budget_mb = settings.gpu_memory_budget_mb.unwrap_or(2048) as u64;
     state
-        .decoded_image_cache
-        .lock()
-        .unwrap()
-        .set_capacity(cache_size);
+        .gpu_memory_budget
+        .store(budget_mb * 1024 * 1024, Ordering::SeqCst);
+    state
+        .thumbnail_manager
+        .set_max_concurrency(settings.thumbnail_max_concurrency);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export_processing::OutputSharpeningMedium;
+
+    #[test]
+    fn export_preset_round_trips_every_export_setting() {
+        let preset = ExportPreset {
+            id: "print-tiff".to_string(),
+            name: "Print TIFF full-res with ICC".to_string(),
+            file_format: "tiff".to_string(),
+            jpeg_quality: 100,
+            enable_resize: false,
+            resize_mode: "longEdge".to_string(),
+            resize_value: 4096,
+            dont_enlarge: true,
+            keep_metadata: true,
+            preserve_timestamps: Some(true),
+            strip_gps: true,
+            filename_template: "{original_filename}_print".to_string(),
+            enable_watermark: true,
+            watermark_path: Some("/marks/logo.png".to_string()),
+            watermark_anchor: Some("bottomLeft".to_string()),
+            watermark_scale: 12,
+            watermark_spacing: 4,
+            watermark_opacity: 60,
+            export_masks: Some(true),
+            preserve_folders: Some(true),
+            last_export_path: Some("/exports".to_string()),
+            artist: Some("Jane Doe".to_string()),
+            copyright: Some("(c) Jane Doe".to_string()),
+            contact_url: Some("https://example.com".to_string()),
+            write_xmp_sidecar: Some(true),
+            output_color_space: Some(OutputColorSpace::AdobeRgb),
+            output_sharpening: Some(OutputSharpening {
+                medium: OutputSharpeningMedium::PrintMatte,
+                amount: 70.0,
+            }),
+            output_icc_profile: Some("/profiles/printer.icc".to_string()),
+            skip_existing: Some(true),
+            skip_existing_only_if_newer: Some(true),
+        };
+
+        let json = serde_json::to_value(&preset).unwrap();
+        assert_eq!(json["outputIccProfile"], "/profiles/printer.icc");
+        assert_eq!(json["skipExisting"], true);
+        assert_eq!(json["skipExistingOnlyIfNewer"], true);
+
+        let restored: ExportPreset = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&restored).unwrap(), json);
+    }
+
+    #[test]
+    fn export_presets_saved_before_the_new_fields_still_load() {
+        let mut json = serde_json::to_value(&default_export_presets()[0]).unwrap();
+        let obj = json.as_object_mut().unwrap();
+        for key in [
+            "outputIccProfile",
+            "skipExisting",
+            "skipExistingOnlyIfNewer",
+        ] {
+            obj.remove(key);
+        }
+
+        let preset: ExportPreset = serde_json::from_value(json).unwrap();
+        assert_eq!(preset.output_icc_profile, None);
+        assert_eq!(preset.skip_existing, None);
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Condvar, Mutex};
 
@@ -11,11 +11,14 @@ use tokio::task::JoinHandle;
 use wgpu::{Texture, TextureView};
 
 use crate::ai_processing::AiState;
-use crate::cache_utils::DecodedImageCache;
+use crate::cache_utils::{DecodedImageCache, LruCache};
+use crate::folder_watch::FolderWatch;
 use crate::gpu_processing::GpuProcessor;
+use crate::hdr_deghosting::HdrFrame;
 use crate::image_processing::GpuContext;
 use crate::lens_correction::LensDatabase;
 use crate::lut_processing::Lut;
+use crate::session::EditorSession;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -60,12 +63,14 @@ pub struct GpuImageCache {
     pub width: u32,
     pub height: u32,
     pub transform_hash: u64,
+    pub size_bytes: u64,
 }
 
 pub struct GpuProcessorState {
     pub processor: GpuProcessor,
     pub width: u32,
     pub height: u32,
+    pub size_bytes: u64,
 }
 
 pub struct PreviewJob {
@@ -75,6 +80,8 @@ pub struct PreviewJob {
     pub roi: Option<(f32, f32, f32, f32)>,
     pub compute_waveform: bool,
     pub active_waveform_channel: Option<String>,
+    pub histogram_region: Option<(f32, f32, f32, f32)>,
+    pub generation: usize,
     pub responder: tokio::sync::oneshot::Sender<Vec<u8>>,
 }
 
@@ -83,15 +90,24 @@ pub struct AnalyticsJob {
     pub image: Arc<DynamicImage>,
     pub compute_waveform: bool,
     pub active_waveform_channel: Option<String>,
+    pub histogram_region: Option<(f32, f32, f32, f32)>,
 }
 
 pub struct AnalyticsConfig {
     pub path: String,
     pub compute_waveform: bool,
     pub active_waveform_channel: Option<String>,
+    pub histogram_region: Option<(f32, f32, f32, f32)>,
     pub sender: Sender<AnalyticsJob>,
 }
 
+pub struct FullResScopesJob {
+    pub loaded_image: LoadedImage,
+    pub adjustments: serde_json::Value,
+    pub analytics: AnalyticsConfig,
+    pub generation: usize,
+}
+
 pub struct ThumbnailProgressTracker {
     pub total: usize,
     pub completed: usize,
@@ -101,6 +117,7 @@ pub struct ThumbnailManager {
     pub queue: Mutex<VecDeque<String>>,
     pub cvar: Condvar,
     pub processing_now: Mutex<HashSet<String>>,
+    pub max_concurrency: AtomicUsize,
 }
 
 impl ThumbnailManager {
@@ -109,8 +126,70 @@ impl ThumbnailManager {
             queue: Mutex::new(VecDeque::new()),
             cvar: Condvar::new(),
             processing_now: Mutex::new(HashSet::new()),
+            max_concurrency: AtomicUsize::new(usize::MAX),
         })
     }
+
+    pub fn set_max_concurrency(&self, limit: Option<u32>) {
+        let limit = limit.map_or(usize::MAX, |l| l.max(1) as usize);
+        self.max_concurrency.store(limit, Ordering::SeqCst);
+        let _queue = self.queue.lock().unwrap();
+        self.cvar.notify_all();
+    }
+
+    fn has_free_slot(&self) -> bool {
+        self.processing_now.lock().unwrap().len() < self.max_concurrency.load(Ordering::SeqCst)
+    }
+
+    /// Blocks until a path is queued and the concurrency limit allows another job, then
+    /// marks it as processing. The back of the queue is the highest priority. Returns
+    /// `None` if the popped path is already being processed by another worker.
+    pub fn wait_for_next(&self) -> Option<String> {
+        let mut queue = self.queue.lock().unwrap();
+        while queue.is_empty() || !self.has_free_slot() {
+            queue = self.cvar.wait(queue).unwrap();
+        }
+        let path = queue.pop_back().unwrap();
+
+        let mut processing = self.processing_now.lock().unwrap();
+        if processing.contains(&path) {
+            return None;
+        }
+        processing.insert(path.clone());
+        Some(path)
+    }
+
+    pub fn finish(&self, path: &str) {
+        self.processing_now.lock().unwrap().remove(path);
+        let _queue = self.queue.lock().unwrap();
+        self.cvar.notify_all();
+    }
+
+    /// Moves the given paths to the back of the queue so workers pick them up next.
+    /// `paths` is ordered by priority, highest first.
+    pub fn prioritize(&self, paths: &[String]) -> usize {
+        let mut queue = self.queue.lock().unwrap();
+        let wanted: HashSet<&String> = paths.iter().collect();
+        let mut found: Vec<String> = Vec::new();
+        queue.retain(|p| {
+            if wanted.contains(p) {
+                found.push(p.clone());
+                false
+            } else {
+                true
+            }
+        });
+
+        let moved = found.len();
+        let found: HashSet<String> = found.into_iter().collect();
+        for path in paths.iter().rev() {
+            if found.contains(path) {
+                queue.push_back(path.clone());
+            }
+        }
+        self.cvar.notify_all();
+        moved
+    }
 }
 
 pub struct PendingMetadata {
@@ -135,6 +214,14 @@ impl MetadataManager {
     }
 }
 
+/// Target of the read-only preview mirror shown in the secondary window.
+#[derive(Default)]
+pub struct PreviewMirror {
+    pub target_label: Option<String>,
+    pub last_adjustments: Option<serde_json::Value>,
+    pub worker_tx: Option<Sender<crate::secondary_window::MirrorJob>>,
+}
+
 pub type TransformedImageCache = (u64, Arc<DynamicImage>, (f32, f32));
 
 pub struct AppState {
@@ -145,23 +232,39 @@ pub struct AppState {
     pub gpu_context: Mutex<Option<GpuContext>>,
     pub gpu_image_cache: Mutex<Option<GpuImageCache>>,
     pub gpu_processor: Mutex<Option<GpuProcessorState>>,
+    pub gpu_degraded_mode: AtomicBool,
+    pub gpu_memory_budget: AtomicU64,
     pub ai_state: Mutex<Option<AiState>>,
     pub ai_init_lock: TokioMutex<()>,
     pub export_task_handle: Mutex<Option<JoinHandle<()>>>,
+    pub export_cancellation_token: Arc<AtomicBool>,
     pub hdr_result: Arc<Mutex<Option<DynamicImage>>>,
+    pub hdr_frames: Arc<Mutex<Option<Vec<HdrFrame>>>>,
+    pub hdr_cancellation_token: Arc<AtomicBool>,
+    pub panorama_cancellation_token: Arc<AtomicBool>,
+    pub print_cancellation_token: Arc<AtomicBool>,
     pub panorama_result: Arc<Mutex<Option<DynamicImage>>>,
     pub denoise_result: Arc<Mutex<Option<DynamicImage>>>,
+    pub expand_result: Arc<Mutex<Option<DynamicImage>>>,
+    pub upscale_result: Arc<Mutex<Option<DynamicImage>>>,
     pub indexing_task_handle: Mutex<Option<JoinHandle<()>>>,
-    pub lut_cache: Mutex<HashMap<String, Arc<Lut>>>,
+    pub lut_cache: Mutex<LruCache<String, Arc<Lut>>>,
     pub initial_file_path: Mutex<Option<String>>,
     pub pending_edit_session: Mutex<Option<ExternalEditSession>>,
+    pub pending_session: Mutex<Option<EditorSession>>,
     pub thumbnail_cancellation_token: Arc<AtomicBool>,
     pub thumbnail_progress: Mutex<ThumbnailProgressTracker>,
     pub preview_worker_tx: Mutex<Option<Sender<PreviewJob>>>,
+    pub preview_job_generation: Arc<AtomicUsize>,
+    pub preview_mirror: Mutex<PreviewMirror>,
+    pub preview_mirror_generation: AtomicUsize,
     pub analytics_worker_tx: Mutex<Option<Sender<AnalyticsJob>>>,
-    pub mask_cache: Mutex<HashMap<u64, GrayImage>>,
+    pub full_res_scopes_tx: Mutex<Option<Sender<FullResScopesJob>>>,
+    pub mask_cache: Mutex<LruCache<u64, GrayImage>>,
+    /// Source of truth for AI patch and mask data stripped from adjustments sent by the
+    /// frontend, so it must not evict; it is cleared when a new image is loaded.
     pub patch_cache: Mutex<HashMap<String, serde_json::Value>>,
-    pub geometry_cache: Mutex<HashMap<u64, DynamicImage>>,
+    pub geometry_cache: Mutex<LruCache<u64, DynamicImage>>,
     pub thumbnail_geometry_cache: Mutex<HashMap<String, (u64, DynamicImage, f32)>>,
     pub lens_db: Mutex<Option<Arc<LensDatabase>>>,
     pub load_image_generation: Arc<AtomicUsize>,
@@ -170,4 +273,5 @@ pub struct AppState {
     pub decoded_image_cache: Mutex<DecodedImageCache>,
     pub thumbnail_manager: Arc<ThumbnailManager>,
     pub metadata_manager: Arc<MetadataManager>,
+    pub folder_watch: Mutex<Option<FolderWatch>>,
 }
@@ -1,9 +1,21 @@
 use crate::AppState;
-use image::DynamicImage;
+use crate::app_settings::{
+    AppSettings, DEFAULT_GEOMETRY_CACHE_SIZE, DEFAULT_LUT_CACHE_SIZE, DEFAULT_MASK_CACHE_SIZE,
+    DEFAULT_RENDER_CACHE_SIZE_MB,
+};
+use crate::lut_processing::lut_stack_from_adjustments;
+use image::{DynamicImage, ImageBuffer};
+use serde_json::Value;
+use std::borrow::Borrow;
 use std::collections::HashMap;
-use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write as _;
+use std::fs;
 use std::hash::{Hash, Hasher};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tauri::Manager;
 
 pub const GEOMETRY_KEYS: &[&str] = &[
     "transformDistortion",
@@ -23,13 +35,120 @@ pub const GEOMETRY_KEYS: &[&str] = &[
     "lensDistortionEnabled",
     "lensTcaEnabled",
     "lensVignetteEnabled",
+    "manualCaRed",
+    "manualCaBlue",
 ];
 
+/// `Hasher` backed by BLAKE3 with fixed-width little-endian integers, so a hash is the
+/// same across runs, platforms and compiler versions. `DefaultHasher` promises none of
+/// that, which matters once hashes name files on disk.
+pub struct StableHasher(blake3::Hasher);
+
+impl StableHasher {
+    pub fn new() -> Self {
+        Self(blake3::Hasher::new())
+    }
+
+    pub fn finish_hex(&self) -> String {
+        self.0.finalize().to_hex().to_string()
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.0.update(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.0.update(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0.update(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.0.update(&(i as u64).to_le_bytes());
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.0.update(&(i as i64).to_le_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        let hash = self.0.finalize();
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&hash.as_bytes()[..8]);
+        u64::from_le_bytes(bytes)
+    }
+}
+
+/// Serializes adjustments with sorted object keys and floats rounded to six decimals, so
+/// `50`, `50.0` and `49.9999999` from different code paths hash the same.
+pub fn canonical_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical_json(value, &mut out);
+    out
+}
+
+fn write_canonical_json(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                let _ = write!(out, "{}", i);
+            } else if let Some(u) = n.as_u64() {
+                let _ = write!(out, "{}", u);
+            } else {
+                let formatted = format!("{:.6}", n.as_f64().unwrap_or(0.0));
+                let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+                out.push_str(if trimmed == "-0" { "0" } else { trimmed });
+            }
+        }
+        Value::String(s) => out.push_str(&Value::String(s.clone()).to_string()),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical_json(&map[key], out);
+            }
+            out.push('}');
+        }
+    }
+}
+
 pub fn calculate_geometry_hash(adjustments: &serde_json::Value) -> u64 {
-    let mut hasher = DefaultHasher::new();
+    let mut hasher = StableHasher::new();
 
     if let Some(patches) = adjustments.get("aiPatches") {
-        patches.to_string().hash(&mut hasher);
+        canonical_json(patches).hash(&mut hasher);
     }
 
     adjustments["orientationSteps"].as_u64().hash(&mut hasher);
@@ -37,7 +156,7 @@ pub fn calculate_geometry_hash(adjustments: &serde_json::Value) -> u64 {
     for key in GEOMETRY_KEYS {
         if let Some(val) = adjustments.get(key) {
             key.hash(&mut hasher);
-            val.to_string().hash(&mut hasher);
+            canonical_json(val).hash(&mut hasher);
         }
     }
 
@@ -45,11 +164,13 @@ pub fn calculate_geometry_hash(adjustments: &serde_json::Value) -> u64 {
 }
 
 pub fn calculate_visual_hash(path: &str, adjustments: &serde_json::Value) -> u64 {
-    let mut hasher = DefaultHasher::new();
+    let mut hasher = StableHasher::new();
     path.hash(&mut hasher);
 
     if let Some(obj) = adjustments.as_object() {
-        for (key, value) in obj {
+        let mut entries: Vec<(&String, &Value)> = obj.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, value) in entries {
             if GEOMETRY_KEYS.contains(&key.as_str()) {
                 continue;
             }
@@ -58,7 +179,7 @@ pub fn calculate_visual_hash(path: &str, adjustments: &serde_json::Value) -> u64
                 "crop" | "rotation" | "orientationSteps" | "flipHorizontal" | "flipVertical" => (),
                 _ => {
                     key.hash(&mut hasher);
-                    value.to_string().hash(&mut hasher);
+                    canonical_json(value).hash(&mut hasher);
                 }
             }
         }
@@ -68,7 +189,7 @@ pub fn calculate_visual_hash(path: &str, adjustments: &serde_json::Value) -> u64
 }
 
 pub fn calculate_transform_hash(adjustments: &serde_json::Value) -> u64 {
-    let mut hasher = DefaultHasher::new();
+    let mut hasher = StableHasher::new();
 
     let orientation_steps = adjustments["orientationSteps"].as_u64().unwrap_or(0);
     orientation_steps.hash(&mut hasher);
@@ -85,20 +206,20 @@ pub fn calculate_transform_hash(adjustments: &serde_json::Value) -> u64 {
     if let Some(crop_val) = adjustments.get("crop")
         && !crop_val.is_null()
     {
-        crop_val.to_string().hash(&mut hasher);
+        canonical_json(crop_val).hash(&mut hasher);
     }
 
     for key in GEOMETRY_KEYS {
         if let Some(val) = adjustments.get(key) {
             key.hash(&mut hasher);
-            val.to_string().hash(&mut hasher);
+            canonical_json(val).hash(&mut hasher);
         }
     }
 
     if let Some(patches_val) = adjustments.get("aiPatches")
         && let Some(patches_arr) = patches_val.as_array()
     {
-        patches_arr.len().hash(&mut hasher);
+        (patches_arr.len() as u64).hash(&mut hasher);
 
         for patch in patches_arr {
             if let Some(id) = patch.get("id").and_then(|v| v.as_str()) {
@@ -135,7 +256,7 @@ pub fn calculate_transform_hash(adjustments: &serde_json::Value) -> u64 {
             }
 
             if let Some(sub_masks_val) = patch.get("subMasks") {
-                sub_masks_val.to_string().hash(&mut hasher);
+                canonical_json(sub_masks_val).hash(&mut hasher);
             }
 
             let invert = patch
@@ -150,15 +271,250 @@ pub fn calculate_transform_hash(adjustments: &serde_json::Value) -> u64 {
 }
 
 pub fn calculate_full_job_hash(path: &str, adjustments: &serde_json::Value) -> u64 {
-    let mut hasher = DefaultHasher::new();
+    let mut hasher = StableHasher::new();
     path.hash(&mut hasher);
-    adjustments.to_string().hash(&mut hasher);
+    canonical_json(adjustments).hash(&mut hasher);
     hasher.finish()
 }
 
+fn hash_file_stamp(path: &Path, hasher: &mut StableHasher) {
+    let Ok(meta) = fs::metadata(path) else {
+        0u64.hash(hasher);
+        return;
+    };
+    meta.len().hash(hasher);
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64);
+    modified.hash(hasher);
+}
+
+/// Bumped whenever the entry layout changes. The app version is hashed into every key as
+/// well, so a pipeline change in a new release never serves renders from an older one.
+const RENDER_CACHE_FORMAT_VERSION: u32 = 1;
+const RENDER_CACHE_MAGIC: &[u8; 4] = b"RRC1";
+const RENDER_CACHE_EXTENSION: &str = "rrc";
+
+/// On-disk cache of full-resolution export renders, taken before resizing, watermarking
+/// and encoding. Entries hold the raw pixel buffer behind a small header, so a miss costs
+/// a plain file write rather than an encode. Disabled unless turned on in the settings;
+/// least recently used files are evicted past the configured size.
+pub struct RenderCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl RenderCache {
+    pub fn open(app_handle: &tauri::AppHandle, settings: &AppSettings) -> Option<Self> {
+        if !settings.render_cache_enabled.unwrap_or(false) {
+            return None;
+        }
+        let max_mb = settings
+            .render_cache_size_mb
+            .unwrap_or(DEFAULT_RENDER_CACHE_SIZE_MB);
+        if max_mb == 0 {
+            return None;
+        }
+        let dir = app_handle.path().app_cache_dir().ok()?.join("renders");
+        Self::with_dir(dir, max_mb as u64 * 1024 * 1024)
+    }
+
+    fn with_dir(dir: PathBuf, max_bytes: u64) -> Option<Self> {
+        if let Err(e) = fs::create_dir_all(&dir) {
+            log::warn!(
+                "Render cache disabled, could not create {}: {}",
+                dir.display(),
+                e
+            );
+            return None;
+        }
+        Some(Self { dir, max_bytes })
+    }
+
+    /// Covers everything that changes the pixels: the app version, the source, LUT and
+    /// calibration files on disk, the adjustments, the decode and tone mapping settings and
+    /// whether the GPU or the CPU fallback rendered it.
+    pub fn key(
+        source_path: &str,
+        adjustments: &Value,
+        settings: &AppSettings,
+        gpu_rendered: bool,
+    ) -> String {
+        let mut hasher = StableHasher::new();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        RENDER_CACHE_FORMAT_VERSION.hash(&mut hasher);
+        source_path.hash(&mut hasher);
+        hash_file_stamp(Path::new(source_path), &mut hasher);
+        canonical_json(adjustments).hash(&mut hasher);
+        for entry in lut_stack_from_adjustments(adjustments) {
+            hash_file_stamp(Path::new(&entry.path), &mut hasher);
+        }
+        for frame in [
+            &settings.calibration_dark_frame,
+            &settings.calibration_flat_field,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            hash_file_stamp(Path::new(frame), &mut hasher);
+        }
+
+        let decode_settings = serde_json::json!({
+            "applyPreprocessingToNonRaws": settings.apply_preprocessing_to_non_raws,
+            "autoOrientFromExif": settings.auto_orient_from_exif,
+            "demosaicQuality": settings.demosaic_quality,
+            "linearRawMode": settings.linear_raw_mode,
+            "linearSceneReferred": settings.linear_scene_referred,
+            "rawHighlightCompression": settings.raw_highlight_compression,
+            "rawPreprocessingColorNr": settings.raw_preprocessing_color_nr,
+            "rawPreprocessingSharpening": settings.raw_preprocessing_sharpening,
+            "tonemapperOverrideEnabled": settings.tonemapper_override_enabled,
+            "defaultRawTonemapper": settings.default_raw_tonemapper,
+            "defaultNonRawTonemapper": settings.default_non_raw_tonemapper,
+            "calibrationDarkFrame": settings.calibration_dark_frame,
+            "calibrationFlatField": settings.calibration_flat_field,
+            "renderBackend": if gpu_rendered { "gpu" } else { "cpu" },
+        });
+        canonical_json(&decode_settings).hash(&mut hasher);
+        hasher.finish_hex()
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, RENDER_CACHE_EXTENSION))
+    }
+
+    pub fn get(&self, key: &str) -> Option<DynamicImage> {
+        let path = self.entry_path(key);
+        let bytes = fs::read(&path).ok()?;
+        match decode_render_entry(&bytes) {
+            Some(image) => {
+                let _ = filetime::set_file_mtime(&path, filetime::FileTime::now());
+                Some(image)
+            }
+            None => {
+                log::warn!("Dropping unreadable render cache entry {}", key);
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, key: &str, image: &DynamicImage) {
+        let path = self.entry_path(key);
+        // Written under a temporary name and renamed, so a parallel export never reads a
+        // half-written entry.
+        let tmp_path = self.dir.join(format!("{}.tmp", key));
+        let result = fs::File::create(&tmp_path)
+            .and_then(|mut file| write_render_entry(&mut file, image))
+            .and_then(|_| fs::rename(&tmp_path, &path));
+        if let Err(e) = result {
+            log::warn!("Failed to write render cache entry {}: {}", key, e);
+            let _ = fs::remove_file(&tmp_path);
+            return;
+        }
+        self.evict();
+    }
+
+    fn evict(&self) {
+        let Ok(read_dir) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut entries: Vec<(PathBuf, u64, std::time::SystemTime)> = read_dir
+            .filter_map(|e| e.ok())
+            .filter(|e| {
+                e.path()
+                    .extension()
+                    .is_some_and(|ext| ext == RENDER_CACHE_EXTENSION)
+            })
+            .filter_map(|e| {
+                let meta = e.metadata().ok()?;
+                Some((e.path(), meta.len(), meta.modified().ok()?))
+            })
+            .collect();
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        if total <= self.max_bytes {
+            return;
+        }
+        entries.sort_by_key(|(_, _, modified)| *modified);
+        for (path, len, _) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total = total.saturating_sub(len);
+            }
+        }
+    }
+}
+
+fn write_render_entry(file: &mut fs::File, image: &DynamicImage) -> std::io::Result<()> {
+    let converted;
+    let (kind, image) = match image {
+        DynamicImage::ImageRgb8(_) => (0u8, image),
+        DynamicImage::ImageRgba8(_) => (1, image),
+        DynamicImage::ImageRgb16(_) => (2, image),
+        DynamicImage::ImageRgba16(_) => (3, image),
+        DynamicImage::ImageRgb32F(_) => (4, image),
+        DynamicImage::ImageRgba32F(_) => (5, image),
+        _ => {
+            converted = DynamicImage::ImageRgba32F(image.to_rgba32f());
+            (5, &converted)
+        }
+    };
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(RENDER_CACHE_MAGIC);
+    header.push(kind);
+    header.extend_from_slice(&image.width().to_le_bytes());
+    header.extend_from_slice(&image.height().to_le_bytes());
+    file.write_all(&header)?;
+    file.write_all(image.as_bytes())
+}
+
+fn decode_render_entry(bytes: &[u8]) -> Option<DynamicImage> {
+    let (header, data) = bytes.split_at_checked(13)?;
+    if &header[..4] != RENDER_CACHE_MAGIC {
+        return None;
+    }
+    let width = u32::from_le_bytes(header[5..9].try_into().ok()?);
+    let height = u32::from_le_bytes(header[9..13].try_into().ok()?);
+    // The pixel data sits after the 13 byte header and is not aligned for a direct cast.
+    let u16s = || {
+        let values: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+            .collect();
+        Some(values)
+    };
+    let f32s = || {
+        let values: Vec<f32> = data
+            .chunks_exact(4)
+            .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        Some(values)
+    };
+    match header[4] {
+        0 => ImageBuffer::from_raw(width, height, data.to_vec()).map(DynamicImage::ImageRgb8),
+        1 => ImageBuffer::from_raw(width, height, data.to_vec()).map(DynamicImage::ImageRgba8),
+        2 => ImageBuffer::from_raw(width, height, u16s()?).map(DynamicImage::ImageRgb16),
+        3 => ImageBuffer::from_raw(width, height, u16s()?).map(DynamicImage::ImageRgba16),
+        4 => ImageBuffer::from_raw(width, height, f32s()?).map(DynamicImage::ImageRgb32F),
+        5 => ImageBuffer::from_raw(width, height, f32s()?).map(DynamicImage::ImageRgba32F),
+        _ => None,
+    }
+}
+
+type DecodedImageEntry = (
+    String,
+    Arc<DynamicImage>,
+    HashMap<String, String>,
+    Option<f32>,
+);
+
 pub struct DecodedImageCache {
     capacity: usize,
-    items: Vec<(String, Arc<DynamicImage>, HashMap<String, String>)>,
+    items: Vec<DecodedImageEntry>,
 }
 
 impl DecodedImageCache {
@@ -176,10 +532,13 @@ impl DecodedImageCache {
         }
     }
 
-    pub fn get(&mut self, path: &str) -> Option<(Arc<DynamicImage>, HashMap<String, String>)> {
-        if let Some(pos) = self.items.iter().position(|(p, _, _)| p == path) {
+    pub fn get(
+        &mut self,
+        path: &str,
+    ) -> Option<(Arc<DynamicImage>, HashMap<String, String>, Option<f32>)> {
+        if let Some(pos) = self.items.iter().position(|(p, _, _, _)| p == path) {
             let item = self.items.remove(pos);
-            let result = (item.1.clone(), item.2.clone());
+            let result = (item.1.clone(), item.2.clone(), item.3);
             self.items.push(item);
             Some(result)
         } else {
@@ -196,16 +555,91 @@ impl DecodedImageCache {
         path: String,
         image: Arc<DynamicImage>,
         exif: HashMap<String, String>,
+        clipped_highlight_percentage: Option<f32>,
     ) {
-        if let Some(pos) = self.items.iter().position(|(p, _, _)| *p == path) {
+        if let Some(pos) = self.items.iter().position(|(p, _, _, _)| *p == path) {
+            self.items.remove(pos);
+        } else if self.items.len() >= self.capacity {
+            self.items.remove(0);
+        }
+        self.items
+            .push((path, image, exif, clipped_highlight_percentage));
+    }
+}
+
+/// Small least-recently-used map. Lookups move the entry to the back, so the front
+/// always holds the entry that will be evicted next.
+pub struct LruCache<K, V> {
+    capacity: usize,
+    items: Vec<(K, V)>,
+}
+
+impl<K: PartialEq, V> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            items: Vec::new(),
+        }
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        if self.items.len() > self.capacity {
+            let excess = self.items.len() - self.capacity;
+            self.items.drain(..excess);
+        }
+    }
+
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        let pos = self.items.iter().position(|(k, _)| k.borrow() == key)?;
+        let item = self.items.remove(pos);
+        self.items.push(item);
+        self.items.last().map(|(_, v)| v)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if let Some(pos) = self.items.iter().position(|(k, _)| *k == key) {
             self.items.remove(pos);
         } else if self.items.len() >= self.capacity {
             self.items.remove(0);
         }
-        self.items.push((path, image, exif));
+        self.items.push((key, value));
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
     }
 }
 
+pub fn apply_cache_settings(state: &AppState, settings: &AppSettings) {
+    let image_cache_size = settings.image_cache_size.unwrap_or(5) as usize;
+    state
+        .decoded_image_cache
+        .lock()
+        .unwrap()
+        .set_capacity(image_cache_size);
+    let geometry_cache_size = settings
+        .geometry_cache_size
+        .unwrap_or(DEFAULT_GEOMETRY_CACHE_SIZE) as usize;
+    state
+        .geometry_cache
+        .lock()
+        .unwrap()
+        .set_capacity(geometry_cache_size);
+    let mask_cache_size = settings.mask_cache_size.unwrap_or(DEFAULT_MASK_CACHE_SIZE) as usize;
+    state
+        .mask_cache
+        .lock()
+        .unwrap()
+        .set_capacity(mask_cache_size);
+    let lut_cache_size = settings.lut_cache_size.unwrap_or(DEFAULT_LUT_CACHE_SIZE) as usize;
+    state.lut_cache.lock().unwrap().set_capacity(lut_cache_size);
+}
+
 #[tauri::command]
 pub fn clear_image_caches(state: tauri::State<AppState>) {
     if let Ok(mut decoded_cache) = state.decoded_image_cache.lock() {
@@ -237,3 +671,174 @@ pub fn clear_session_caches(state: tauri::State<AppState>) {
         geometry_cache.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write_source(dir: &Path, contents: &[u8]) -> String {
+        let path = dir.join("source.jpg");
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn canonical_json_ignores_key_order_and_float_noise() {
+        let a = json!({"exposure": 0.5, "contrast": 10, "curves": {"luma": [1.0, 2.0]}});
+        let b = json!({"curves": {"luma": [1.0, 2.0000000001]}, "contrast": 10, "exposure": 0.5});
+        assert_eq!(canonical_json(&a), canonical_json(&b));
+        assert_ne!(
+            canonical_json(&a),
+            canonical_json(
+                &json!({"exposure": 0.51, "contrast": 10, "curves": {"luma": [1.0, 2.0]}})
+            )
+        );
+    }
+
+    #[test]
+    fn render_cache_key_is_stable_for_unchanged_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = write_source(dir.path(), b"pixels");
+        let settings = AppSettings::default();
+        let adjustments = json!({"exposure": 1.0, "contrast": 5});
+        assert_eq!(
+            RenderCache::key(&source, &adjustments, &settings, true),
+            RenderCache::key(&source, &adjustments, &settings, true)
+        );
+    }
+
+    #[test]
+    fn render_cache_key_changes_with_adjustments() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = write_source(dir.path(), b"pixels");
+        let settings = AppSettings::default();
+        let before = RenderCache::key(&source, &json!({"exposure": 1.0}), &settings);
+        let after = RenderCache::key(&source, &json!({"exposure": 1.5}), &settings);
+        assert_ne!(before, after);
+    }
+
+    #[test]
+    fn render_cache_key_changes_when_source_file_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = write_source(dir.path(), b"pixels");
+        let settings = AppSettings::default();
+        let adjustments = json!({"exposure": 1.0});
+        let before = RenderCache::key(&source, &adjustments, &settings, true);
+
+        write_source(dir.path(), b"different pixels");
+        let after = RenderCache::key(&source, &adjustments, &settings, true);
+        assert_ne!(before, after);
+
+        let later = filetime::FileTime::from_unix_time(2_000_000_000, 0);
+        filetime::set_file_mtime(&source, later).unwrap();
+        assert_ne!(
+            after,
+            RenderCache::key(&source, &adjustments, &settings, true)
+        );
+    }
+
+    #[test]
+    fn render_cache_key_changes_with_decode_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = write_source(dir.path(), b"pixels");
+        let adjustments = json!({"exposure": 1.0});
+        let settings = AppSettings::default();
+        let changed = AppSettings {
+            default_raw_tonemapper: Some("basic".to_string()),
+            ..AppSettings::default()
+        };
+        assert_ne!(
+            RenderCache::key(&source, &adjustments, &settings, true),
+            RenderCache::key(&source, &adjustments, &changed, true)
+        );
+    }
+
+    #[test]
+    fn render_cache_key_changes_with_calibration_frames_and_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = write_source(dir.path(), b"pixels");
+        let adjustments = json!({"exposure": 1.0, "applyCalibrationFrames": true});
+        let dark = dir.path().join("dark.dng");
+        let flat = dir.path().join("flat.dng");
+        fs::write(&dark, b"dark").unwrap();
+        fs::write(&flat, b"flat").unwrap();
+
+        let settings = AppSettings {
+            calibration_dark_frame: Some(dark.to_string_lossy().to_string()),
+            calibration_flat_field: Some(flat.to_string_lossy().to_string()),
+            ..AppSettings::default()
+        };
+        let base = RenderCache::key(&source, &adjustments, &settings, true);
+
+        let without_flat = AppSettings {
+            calibration_flat_field: None,
+            ..settings.clone()
+        };
+        assert_ne!(
+            base,
+            RenderCache::key(&source, &adjustments, &without_flat, true)
+        );
+
+        let other_dark = dir.path().join("dark2.dng");
+        fs::write(&other_dark, b"dark").unwrap();
+        let moved_dark = AppSettings {
+            calibration_dark_frame: Some(other_dark.to_string_lossy().to_string()),
+            ..settings.clone()
+        };
+        assert_ne!(
+            base,
+            RenderCache::key(&source, &adjustments, &moved_dark, true)
+        );
+
+        let later = filetime::FileTime::from_unix_time(2_000_000_000, 0);
+        filetime::set_file_mtime(&flat, later).unwrap();
+        let restamped = RenderCache::key(&source, &adjustments, &settings, true);
+        assert_ne!(base, restamped);
+
+        assert_ne!(
+            restamped,
+            RenderCache::key(&source, &adjustments, &settings, false)
+        );
+    }
+
+    #[test]
+    fn render_cache_round_trips_and_drops_corrupt_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = RenderCache::with_dir(dir.path().join("renders"), u64::MAX).unwrap();
+        let image = DynamicImage::ImageRgb32F(image::Rgb32FImage::from_fn(5, 3, |x, y| {
+            image::Rgb([x as f32 / 4.0, y as f32 / 2.0, 0.25])
+        }));
+
+        assert!(cache.get("missing").is_none());
+        cache.put("entry", &image);
+        let cached = cache.get("entry").unwrap();
+        assert_eq!(cached.as_bytes(), image.as_bytes());
+        assert_eq!(cached.color(), image.color());
+
+        fs::write(cache.entry_path("entry"), b"RRC1").unwrap();
+        assert!(cache.get("entry").is_none());
+        assert!(!cache.entry_path("entry").exists());
+    }
+
+    #[test]
+    fn render_cache_evicts_least_recently_used_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = DynamicImage::ImageRgb8(image::RgbImage::new(16, 16));
+        let entry_size = 13 + image.as_bytes().len() as u64;
+        let cache = RenderCache::with_dir(dir.path().join("renders"), entry_size * 2).unwrap();
+
+        cache.put("a", &image);
+        filetime::set_file_mtime(
+            cache.entry_path("a"),
+            filetime::FileTime::from_unix_time(1_000, 0),
+        )
+        .unwrap();
+        cache.put("b", &image);
+        cache.put("c", &image);
+
+        assert!(!cache.entry_path("a").exists());
+        assert!(cache.entry_path("b").exists());
+        assert!(cache.entry_path("c").exists());
+    }
+}
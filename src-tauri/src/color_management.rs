@@ -0,0 +1,324 @@
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb, RgbImage};
+use lcms2::{
+    CIExyY, CIExyYTRIPLE, ColorSpaceSignature, Intent, PixelFormat, Profile, ToneCurve, Transform,
+};
+use serde::{Deserialize, Serialize};
+
+pub const GAMUT_WARNING_COLOR: [u8; 3] = [255, 0, 255];
+// Round-trip error (in 8-bit steps) above which a pixel counts as out of gamut.
+// Anything lower is quantization noise from the profile's LUTs.
+const GAMUT_TOLERANCE: i16 = 6;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputColorSpace {
+    #[default]
+    Srgb,
+    DisplayP3,
+    AdobeRgb,
+}
+
+const D65_WHITE: CIExyY = CIExyY {
+    x: 0.3127,
+    y: 0.3290,
+    Y: 1.0,
+};
+
+fn xy(x: f64, y: f64) -> CIExyY {
+    CIExyY { x, y, Y: 1.0 }
+}
+
+impl OutputColorSpace {
+    pub fn profile(self) -> Result<Profile, String> {
+        let (primaries, curve) = match self {
+            OutputColorSpace::Srgb => return Ok(Profile::new_srgb()),
+            OutputColorSpace::DisplayP3 => (
+                CIExyYTRIPLE {
+                    Red: xy(0.680, 0.320),
+                    Green: xy(0.265, 0.690),
+                    Blue: xy(0.150, 0.060),
+                },
+                // Display P3 shares the sRGB transfer function.
+                ToneCurve::new_parametric(
+                    4,
+                    &[2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045],
+                )
+                .map_err(|e| e.to_string())?,
+            ),
+            OutputColorSpace::AdobeRgb => (
+                CIExyYTRIPLE {
+                    Red: xy(0.640, 0.330),
+                    Green: xy(0.210, 0.710),
+                    Blue: xy(0.150, 0.060),
+                },
+                ToneCurve::new(563.0 / 256.0),
+            ),
+        };
+        Profile::new_rgb(&D65_WHITE, &primaries, &[&curve, &curve, &curve])
+            .map_err(|e| e.to_string())
+    }
+}
+
+pub fn load_icc_profile(path: &str) -> Result<Profile, String> {
+    Profile::new_file(path).map_err(|e| format!("Failed to load ICC profile '{}': {}", path, e))
+}
+
+fn round_trip_through<P: Copy + Default>(
+    pixels: &[[u8; 3]],
+    target: &Profile,
+    target_format: PixelFormat,
+) -> Result<Vec<[u8; 3]>, String> {
+    let srgb = Profile::new_srgb();
+    let to_target = Transform::new(
+        &srgb,
+        PixelFormat::RGB_8,
+        target,
+        target_format,
+        Intent::RelativeColorimetric,
+    )
+    .map_err(|e| e.to_string())?;
+    let to_srgb = Transform::new(
+        target,
+        target_format,
+        &srgb,
+        PixelFormat::RGB_8,
+        Intent::RelativeColorimetric,
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut target_pixels = vec![P::default(); pixels.len()];
+    to_target.transform_pixels(pixels, &mut target_pixels);
+    let mut proofed = vec![[0u8; 3]; pixels.len()];
+    to_srgb.transform_pixels(&target_pixels, &mut proofed);
+    Ok(proofed)
+}
+
+/// Simulates how `image` (display-referred sRGB) reproduces on the device described by
+/// `profile`. Out-of-gamut pixels are painted with `GAMUT_WARNING_COLOR` when
+/// `gamut_warning` is set. Also returns the fraction of pixels that were out of gamut.
+pub fn soft_proof(
+    image: &DynamicImage,
+    profile: &Profile,
+    gamut_warning: bool,
+) -> Result<(RgbImage, f32), String> {
+    let rgb = image.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let pixels: Vec<[u8; 3]> = rgb
+        .as_raw()
+        .chunks_exact(3)
+        .map(|p| [p[0], p[1], p[2]])
+        .collect();
+
+    let proofed = match profile.color_space() {
+        ColorSpaceSignature::CmykData => {
+            round_trip_through::<[u8; 4]>(&pixels, profile, PixelFormat::CMYK_8)?
+        }
+        ColorSpaceSignature::RgbData => {
+            round_trip_through::<[u8; 3]>(&pixels, profile, PixelFormat::RGB_8)?
+        }
+        ColorSpaceSignature::GrayData => {
+            round_trip_through::<[u8; 1]>(&pixels, profile, PixelFormat::GRAY_8)?
+        }
+        other => return Err(format!("Unsupported ICC color space: {:?}", other)),
+    };
+
+    let mut out_of_gamut = 0usize;
+    let mut data = Vec::with_capacity(pixels.len() * 3);
+    for (original, proof) in pixels.iter().zip(&proofed) {
+        let clipped = original
+            .iter()
+            .zip(proof)
+            .any(|(&a, &b)| (a as i16 - b as i16).abs() > GAMUT_TOLERANCE);
+        if clipped {
+            out_of_gamut += 1;
+        }
+        if clipped && gamut_warning {
+            data.extend_from_slice(&GAMUT_WARNING_COLOR);
+        } else {
+            data.extend_from_slice(proof);
+        }
+    }
+
+    let buffer: RgbImage = ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, data)
+        .ok_or("Failed to build soft proof image")?;
+    let fraction = if pixels.is_empty() {
+        0.0
+    } else {
+        out_of_gamut as f32 / pixels.len() as f32
+    };
+    Ok((buffer, fraction))
+}
+
+/// Converts display-referred sRGB pixels into the RGB space of `profile` so the
+/// result can be tagged with that profile on export. Alpha is carried over as is.
+pub fn convert_to_profile(image: &DynamicImage, profile: &Profile) -> Result<DynamicImage, String> {
+    if profile.color_space() != ColorSpaceSignature::RgbData {
+        return Err("Only RGB output profiles can be used for export".to_string());
+    }
+
+    let srgb = Profile::new_srgb();
+    let transform: Transform<[u16; 3], [u16; 3]> = Transform::new(
+        &srgb,
+        PixelFormat::RGB_16,
+        profile,
+        PixelFormat::RGB_16,
+        Intent::Perceptual,
+    )
+    .map_err(|e| e.to_string())?;
+
+    let rgb = image.to_rgb16();
+    let (width, height) = rgb.dimensions();
+    let pixels: Vec<[u16; 3]> = rgb
+        .as_raw()
+        .chunks_exact(3)
+        .map(|p| [p[0], p[1], p[2]])
+        .collect();
+    let mut converted = vec![[0u16; 3]; pixels.len()];
+    transform.transform_pixels(&pixels, &mut converted);
+
+    if image.color().has_alpha() {
+        let alpha = image.to_rgba16();
+        let data: Vec<u16> = converted
+            .iter()
+            .zip(alpha.pixels())
+            .flat_map(|(p, a)| [p[0], p[1], p[2], a[3]])
+            .collect();
+        let buffer =
+            ImageBuffer::from_raw(width, height, data).ok_or("Failed to build converted image")?;
+        return Ok(DynamicImage::ImageRgba16(buffer));
+    }
+
+    let data: Vec<u16> = converted.into_iter().flatten().collect();
+    let buffer =
+        ImageBuffer::from_raw(width, height, data).ok_or("Failed to build converted image")?;
+    Ok(DynamicImage::ImageRgb16(buffer))
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorSample {
+    pub rgb: [f32; 3],
+    pub hsl: [f32; 3],
+    pub lab: [f32; 3],
+    pub sample_count: u32,
+}
+
+fn srgb_channel_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// CIE L*a*b* (D65) of a display-referred sRGB color with channels in 0..1.
+pub fn srgb_to_lab(rgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(srgb_channel_to_linear);
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// Inverse of `srgb_to_lab`, but stops at linear sRGB and does not clip.
+pub fn lab_to_linear_srgb(lab: [f32; 3]) -> [f32; 3] {
+    let [l, a, b] = lab;
+    let fy = (l + 16.0) / 116.0;
+    let fx = fy + a / 500.0;
+    let fz = fy - b / 200.0;
+    let f_inv = |t: f32| {
+        if t > 0.206893 {
+            t * t * t
+        } else {
+            (t - 16.0 / 116.0) / 7.787
+        }
+    };
+    let x = f_inv(fx) * 0.95047;
+    let y = f_inv(fy);
+    let z = f_inv(fz) * 1.08883;
+    [
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z,
+    ]
+}
+
+/// Hue in degrees, saturation and lightness in percent.
+pub fn rgb_to_hsl(rgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta <= f32::EPSILON {
+        return [0.0, 0.0, lightness * 100.0];
+    }
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        60.0 * (((g - b) / delta).rem_euclid(6.0))
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    [hue, saturation * 100.0, lightness * 100.0]
+}
+
+/// Averages a processed (display-referred sRGB) image over a square of `radius` pixels
+/// around the normalized point `x`/`y`. RGB and HSL are reported in `space`, Lab is
+/// always relative to D65.
+pub fn sample_processed_color(
+    image: &DynamicImage,
+    x: f32,
+    y: f32,
+    radius: u32,
+    space: OutputColorSpace,
+) -> Result<ColorSample, String> {
+    let (width, height) = image.dimensions();
+    if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) || width == 0 || height == 0 {
+        return Err("Sample point is outside the image".to_string());
+    }
+    let cx = ((x * width as f32) as u32).min(width - 1);
+    let cy = ((y * height as f32) as u32).min(height - 1);
+    let x0 = cx.saturating_sub(radius);
+    let y0 = cy.saturating_sub(radius);
+    let x1 = (cx + radius).min(width - 1);
+    let y1 = (cy + radius).min(height - 1);
+
+    let mut sum = [0.0f64; 3];
+    let mut count = 0u32;
+    for py in y0..=y1 {
+        for px in x0..=x1 {
+            let p = image.get_pixel(px, py);
+            for (acc, v) in sum.iter_mut().zip(&p.0[..3]) {
+                *acc += *v as f64 / 255.0;
+            }
+            count += 1;
+        }
+    }
+    let srgb = sum.map(|v| (v / count as f64) as f32);
+
+    let rgb = if space == OutputColorSpace::Srgb {
+        srgb
+    } else {
+        let pixel = ImageBuffer::<Rgb<f32>, _>::from_raw(1, 1, srgb.to_vec())
+            .ok_or("Failed to build sample pixel")?;
+        let converted = convert_to_profile(&DynamicImage::ImageRgb32F(pixel), &space.profile()?)?;
+        converted.to_rgb32f().get_pixel(0, 0).0
+    };
+
+    Ok(ColorSample {
+        rgb: rgb.map(|v| v * 255.0),
+        hsl: rgb_to_hsl(rgb),
+        lab: srgb_to_lab(srgb),
+        sample_count: count,
+    })
+}
@@ -0,0 +1,281 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ab_glyph::{FontVec, PxScale};
+use fontdb::{Database, Family, Query};
+use image::codecs::jpeg::JpegEncoder;
+use image::{GenericImageView, ImageFormat, Rgb, RgbImage, imageops};
+use imageproc::drawing::{draw_text_mut, text_size};
+use rayon::prelude::*;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::app_settings::{hex_to_rgb, load_settings};
+use crate::file_management::parse_virtual_path;
+use crate::formats::is_raw_file;
+use crate::image_loader::load_base_image_from_bytes;
+use crate::image_processing::{apply_cpu_default_raw_processing, downscale_f32_image};
+
+const MAX_COLUMNS: u32 = 64;
+const MAX_THUMB_SIZE: u32 = 2048;
+const LABEL_FONT_RATIO: f32 = 0.08;
+const MIN_LABEL_FONT_PX: f32 = 11.0;
+const CONTACT_SHEET_JPEG_QUALITY: u8 = 92;
+
+const MAX_SHEET_PIXELS: u64 = 150_000_000;
+
+// Tried in order before the generic sans-serif family, which fontdb maps to Arial.
+const LABEL_FONT_FAMILIES: &[&str] = &[
+    "DejaVu Sans",
+    "Liberation Sans",
+    "Noto Sans",
+    "Segoe UI",
+    "Helvetica",
+    "Roboto",
+];
+
+#[derive(Serialize, Clone)]
+struct ContactSheetProgress {
+    current: usize,
+    total: usize,
+}
+
+struct SheetCell {
+    label: String,
+    thumbnail: Option<RgbImage>,
+}
+
+fn parse_background(background: Option<&str>) -> Rgb<u8> {
+    let hex = background.unwrap_or("#ffffff");
+    match hex_to_rgb(hex) {
+        Some(rgb) => Rgb(rgb.map(|c| (c * 255.0).round() as u8)),
+        None => {
+            log::warn!("Invalid contact sheet background '{}'", hex);
+            Rgb([255, 255, 255])
+        }
+    }
+}
+
+fn load_label_font() -> Option<FontVec> {
+    let mut db = Database::new();
+    db.load_system_fonts();
+    let families: Vec<Family> = LABEL_FONT_FAMILIES
+        .iter()
+        .map(|&name| Family::Name(name))
+        .chain(std::iter::once(Family::SansSerif))
+        .collect();
+    let id = db.query(&Query {
+        families: &families,
+        ..Query::default()
+    })?;
+    db.with_face_data(id, |data, index| {
+        FontVec::try_from_vec_and_index(data.to_vec(), index).ok()
+    })
+    .flatten()
+}
+
+fn fit_label(text: &str, font: &FontVec, scale: PxScale, max_width: u32) -> String {
+    if text_size(scale, font, text).0 <= max_width {
+        return text.to_string();
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate = format!("{}...", chars.iter().collect::<String>());
+        if text_size(scale, font, &candidate).0 <= max_width {
+            return candidate;
+        }
+    }
+    String::new()
+}
+
+fn render_thumbnail(
+    path: &str,
+    thumb_size: u32,
+    settings: &crate::app_settings::AppSettings,
+) -> Result<RgbImage, String> {
+    let (source_path, _) = parse_virtual_path(path);
+    let source_path_str = source_path.to_string_lossy().to_string();
+    let bytes = fs::read(&source_path).map_err(|e| e.to_string())?;
+    let mut image = load_base_image_from_bytes(&bytes, &source_path_str, true, settings, None)
+        .map_err(|e| e.to_string())?;
+    if is_raw_file(&source_path_str) {
+        apply_cpu_default_raw_processing(&mut image);
+    }
+    Ok(downscale_f32_image(&image, thumb_size, thumb_size).to_rgb8())
+}
+
+fn default_output_path(first_path: &str) -> Result<PathBuf, String> {
+    let (first_source, _) = parse_virtual_path(first_path);
+    let parent_dir = first_source
+        .parent()
+        .ok_or_else(|| "Could not determine parent directory of the first image.".to_string())?;
+    let stem = first_source
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("contact_sheet");
+    Ok(parent_dir.join(format!("{}_ContactSheet.png", stem)))
+}
+
+fn encode_sheet(sheet: &RgbImage, output_path: &Path) -> Result<Vec<u8>, String> {
+    let extension = output_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    let mut bytes = Vec::new();
+    if matches!(extension.as_str(), "jpg" | "jpeg") {
+        let encoder = JpegEncoder::new_with_quality(&mut bytes, CONTACT_SHEET_JPEG_QUALITY);
+        sheet
+            .write_with_encoder(encoder)
+            .map_err(|e| e.to_string())?;
+    } else {
+        sheet
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(bytes)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn generate_contact_sheet(
+    paths: Vec<String>,
+    columns: u32,
+    thumb_size: u32,
+    spacing: u32,
+    background: Option<String>,
+    show_filenames: Option<bool>,
+    output_path: Option<String>,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    if paths.is_empty() {
+        return Err("No images selected for the contact sheet.".to_string());
+    }
+    let columns = columns.clamp(1, MAX_COLUMNS).min(paths.len() as u32);
+    let thumb_size = thumb_size.clamp(16, MAX_THUMB_SIZE);
+    let spacing = spacing.min(thumb_size);
+    let background = parse_background(background.as_deref());
+    let output_path = match output_path {
+        Some(path) => PathBuf::from(path),
+        None => default_output_path(&paths[0])?,
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let font = if show_filenames.unwrap_or(false) {
+            let font = load_label_font();
+            if font.is_none() {
+                log::warn!("No system font found, contact sheet will be drawn without filenames");
+            }
+            font
+        } else {
+            None
+        };
+        let label_scale =
+            PxScale::from((thumb_size as f32 * LABEL_FONT_RATIO).max(MIN_LABEL_FONT_PX));
+        let label_height = if font.is_some() {
+            label_scale.y.ceil() as u32 + spacing.max(4)
+        } else {
+            0
+        };
+
+        let rows = (paths.len() as u32).div_ceil(columns);
+        let cell_height = thumb_size + label_height;
+        let sheet_width = columns * thumb_size + (columns + 1) * spacing;
+        let sheet_height = rows as u64 * cell_height as u64 + (rows as u64 + 1) * spacing as u64;
+        if sheet_width as u64 * sheet_height > MAX_SHEET_PIXELS {
+            return Err(format!(
+                "A {}x{} contact sheet is too large. Use fewer images, more columns or a smaller thumbnail size.",
+                sheet_width, sheet_height
+            ));
+        }
+        let sheet_height = sheet_height as u32;
+
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
+        let total = paths.len();
+        let completed = Arc::new(AtomicUsize::new(0));
+
+        let cells: Vec<SheetCell> = paths
+            .par_iter()
+            .map(|path| {
+                let thumbnail = match render_thumbnail(path, thumb_size, &settings) {
+                    Ok(thumbnail) => Some(thumbnail),
+                    Err(e) => {
+                        log::warn!("Contact sheet skipped '{}': {}", path, e);
+                        None
+                    }
+                };
+                let current = completed.fetch_add(1, Ordering::Relaxed) + 1;
+                let _ = app_handle.emit(
+                    "contact-sheet-progress",
+                    ContactSheetProgress { current, total },
+                );
+                let label = parse_virtual_path(path)
+                    .0
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                SheetCell { label, thumbnail }
+            })
+            .collect();
+
+        let mut sheet = RgbImage::from_pixel(sheet_width, sheet_height, background);
+
+        let background_luma = 0.299 * background[0] as f32
+            + 0.587 * background[1] as f32
+            + 0.114 * background[2] as f32;
+        let label_color = if background_luma > 128.0 {
+            Rgb([20, 20, 20])
+        } else {
+            Rgb([235, 235, 235])
+        };
+
+        for (index, cell) in cells.iter().enumerate() {
+            let column = index as u32 % columns;
+            let row = index as u32 / columns;
+            let cell_x = spacing + column * (thumb_size + spacing);
+            let cell_y = spacing + row * (cell_height + spacing);
+
+            if let Some(thumbnail) = &cell.thumbnail {
+                let (w, h) = thumbnail.dimensions();
+                let x = cell_x + (thumb_size - w.min(thumb_size)) / 2;
+                let y = cell_y + (thumb_size - h.min(thumb_size)) / 2;
+                imageops::replace(&mut sheet, thumbnail, x as i64, y as i64);
+            }
+
+            if let Some(font) = &font {
+                let label = fit_label(&cell.label, font, label_scale, thumb_size);
+                let (text_w, _) = text_size(label_scale, font, &label);
+                let text_x = cell_x + (thumb_size - text_w.min(thumb_size)) / 2;
+                let text_y = cell_y + thumb_size + spacing.max(4) / 2;
+                draw_text_mut(
+                    &mut sheet,
+                    label_color,
+                    text_x as i32,
+                    text_y as i32,
+                    label_scale,
+                    font,
+                    &label,
+                );
+            }
+        }
+
+        let bytes = encode_sheet(&sheet, &output_path)?;
+        fs::write(&output_path, &bytes)
+            .map_err(|e| format!("Failed to save contact sheet: {}", e))?;
+        log::info!(
+            "Saved {}x{} contact sheet with {} images to {}",
+            sheet_width,
+            sheet_height,
+            cells.len(),
+            output_path.display()
+        );
+        Ok(output_path.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
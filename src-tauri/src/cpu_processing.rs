@@ -0,0 +1,492 @@
+use std::time::Instant;
+
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
+use rayon::prelude::*;
+
+use crate::gpu_processing::{RenderRequest, Roi};
+use crate::image_processing::{
+    AllAdjustments, ColorGradeSettings, HslColor, Point, TONEMAPPER_FLAT,
+};
+use crate::lut_processing::Lut;
+
+const LUMA_COEFF: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+// (center, width) in degrees for red, orange, yellow, green, aqua, blue, purple, magenta.
+const HSL_RANGES: [(f32, f32); 8] = [
+    (358.0, 35.0),
+    (25.0, 45.0),
+    (60.0, 40.0),
+    (115.0, 90.0),
+    (180.0, 60.0),
+    (225.0, 60.0),
+    (280.0, 55.0),
+    (330.0, 50.0),
+];
+
+#[derive(Clone, Copy)]
+struct PixelParams {
+    exposure: f32,
+    brightness: f32,
+    contrast: f32,
+    saturation: f32,
+    temperature: f32,
+    tint: f32,
+}
+
+fn get_luma(c: [f32; 3]) -> f32 {
+    c[0] * LUMA_COEFF[0] + c[1] * LUMA_COEFF[1] + c[2] * LUMA_COEFF[2]
+}
+
+fn srgb_to_linear(x: f32) -> f32 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(x: f32) -> f32 {
+    let x = x.clamp(0.0, 1.0);
+    if x <= 0.0031308 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn apply_white_balance(c: [f32; 3], temp: f32, tint: f32) -> [f32; 3] {
+    [
+        c[0] * (1.0 + temp * 0.2) * (1.0 + tint * 0.25),
+        c[1] * (1.0 + temp * 0.05) * (1.0 - tint * 0.25),
+        c[2] * (1.0 - temp * 0.2) * (1.0 + tint * 0.25),
+    ]
+}
+
+fn apply_filmic_exposure(c: [f32; 3], brightness: f32) -> [f32; 3] {
+    if brightness == 0.0 {
+        return c;
+    }
+    const RATIONAL_CURVE_MIX: f32 = 0.95;
+    const MIDTONE_STRENGTH: f32 = 1.2;
+    const TOP_ANCHOR: f32 = 1.06;
+    let original_luma = get_luma(c);
+    if original_luma.abs() < 0.00001 {
+        return c;
+    }
+    let scale = 2.0_f32.powf(brightness * (1.0 - RATIONAL_CURVE_MIX));
+    let k = 2.0_f32.powf(-brightness * RATIONAL_CURVE_MIX * MIDTONE_STRENGTH);
+    let luma_abs = original_luma.abs();
+    let luma_floor = (luma_abs / TOP_ANCHOR).floor() * TOP_ANCHOR;
+    let luma_norm = (luma_abs - luma_floor) / TOP_ANCHOR;
+    let shaped_norm = luma_norm / (luma_norm + (1.0 - luma_norm) * k);
+    let new_luma = original_luma.signum() * (luma_floor + shaped_norm * TOP_ANCHOR) * scale;
+    let total_luma_scale = new_luma / original_luma;
+    let luma_weight = new_luma.clamp(0.0, 2.0) * 0.5;
+    let dynamic_exp = 0.95 + (0.65 - 0.95) * luma_weight;
+    let highlight_rolloff = 1.0 / (1.0 + (new_luma - 0.9).max(0.0) * 2.0);
+    let chroma_scale = total_luma_scale.max(0.0).powf(dynamic_exp) * highlight_rolloff;
+    [
+        new_luma + (c[0] - original_luma) * chroma_scale,
+        new_luma + (c[1] - original_luma) * chroma_scale,
+        new_luma + (c[2] - original_luma) * chroma_scale,
+    ]
+}
+
+fn apply_contrast(c: [f32; 3], contrast: f32) -> [f32; 3] {
+    if contrast == 0.0 {
+        return c;
+    }
+    const G: f32 = 2.2;
+    let strength = 2.0_f32.powf(contrast * 1.25);
+    c.map(|v| {
+        let safe = v.max(0.0);
+        let perceptual = safe.powf(1.0 / G).clamp(0.0, 1.0);
+        let curved = if perceptual < 0.5 {
+            0.5 * (2.0 * perceptual).powf(strength)
+        } else {
+            1.0 - 0.5 * (2.0 * (1.0 - perceptual)).powf(strength)
+        };
+        let adjusted = curved.powf(G);
+        let mix_factor = smoothstep(1.0, 1.01, safe);
+        adjusted + (v - adjusted) * mix_factor
+    })
+}
+
+fn apply_saturation(c: [f32; 3], saturation: f32) -> [f32; 3] {
+    if saturation == 0.0 {
+        return c;
+    }
+    let luma = get_luma(c);
+    c.map(|v| luma + (v - luma) * (1.0 + saturation))
+}
+
+fn rgb_to_hsv(c: [f32; 3]) -> [f32; 3] {
+    let c_max = c[0].max(c[1]).max(c[2]);
+    let c_min = c[0].min(c[1]).min(c[2]);
+    let delta = c_max - c_min;
+    let mut h = 0.0;
+    if delta > 0.0 {
+        h = if c_max == c[0] {
+            60.0 * (((c[1] - c[2]) / delta) % 6.0)
+        } else if c_max == c[1] {
+            60.0 * (((c[2] - c[0]) / delta) + 2.0)
+        } else {
+            60.0 * (((c[0] - c[1]) / delta) + 4.0)
+        };
+    }
+    if h < 0.0 {
+        h += 360.0;
+    }
+    let s = if c_max > 0.0 { delta / c_max } else { 0.0 };
+    [h, s, c_max]
+}
+
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [f32; 3] {
+    let c = v * s;
+    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+    let m = v - c;
+    let (r, g, b) = if h < 60.0 {
+        (c, x, 0.0)
+    } else if h < 120.0 {
+        (x, c, 0.0)
+    } else if h < 180.0 {
+        (0.0, c, x)
+    } else if h < 240.0 {
+        (0.0, x, c)
+    } else if h < 300.0 {
+        (x, 0.0, c)
+    } else {
+        (c, 0.0, x)
+    };
+    [r + m, g + m, b + m]
+}
+
+fn hsl_influence(hue: f32, center: f32, width: f32) -> f32 {
+    let dist = (hue - center).abs().min(360.0 - (hue - center).abs());
+    let falloff = dist / (width * 0.5);
+    (-1.5 * falloff * falloff).exp()
+}
+
+fn apply_hsl_panel(c: [f32; 3], hsl: &[HslColor; 8]) -> [f32; 3] {
+    let safe = c.map(|v| v.max(0.0));
+    if (safe[0] - safe[1]).abs() < 0.001 && (safe[1] - safe[2]).abs() < 0.001 {
+        return safe;
+    }
+    let [hue, sat, value] = rgb_to_hsv(safe);
+    let original_luma = get_luma(safe);
+    let saturation_mask = smoothstep(0.05, 0.20, sat);
+    let luminance_weight = smoothstep(0.0, 1.0, sat);
+    if saturation_mask < 0.001 && luminance_weight < 0.001 {
+        return safe;
+    }
+
+    let influences = HSL_RANGES.map(|(center, width)| hsl_influence(hue, center, width));
+    let total_influence: f32 = influences.iter().sum();
+
+    let mut hue_shift = 0.0;
+    let mut sat_multiplier = 0.0;
+    let mut lum_adjust = 0.0;
+    for (influence, band) in influences.iter().zip(hsl) {
+        let normalized = influence / total_influence;
+        hue_shift += band.hue * 2.0 * normalized * saturation_mask;
+        sat_multiplier += band.saturation * normalized * saturation_mask;
+        lum_adjust += band.luminance * normalized * luminance_weight;
+    }
+
+    let target_luma = original_luma * (1.0 + lum_adjust);
+    if sat * (1.0 + sat_multiplier) < 0.0001 {
+        return [target_luma; 3];
+    }
+    let shifted = hsv_to_rgb(
+        (hue + hue_shift + 360.0) % 360.0,
+        (sat * (1.0 + sat_multiplier)).clamp(0.0, 1.0),
+        value,
+    );
+    let new_luma = get_luma(shifted);
+    if new_luma < 0.0001 {
+        return [target_luma.max(0.0); 3];
+    }
+    shifted.map(|v| v * (target_luma / new_luma))
+}
+
+fn apply_grade_range(
+    c: [f32; 3],
+    grade: &ColorGradeSettings,
+    mask: f32,
+    sat_strength: f32,
+    lum_strength: f32,
+) -> [f32; 3] {
+    let mut out = c;
+    if grade.saturation > 0.001 {
+        let tint = hsv_to_rgb(grade.hue, 1.0, 1.0);
+        for (v, t) in out.iter_mut().zip(tint) {
+            *v += (t - 0.5) * grade.saturation * mask * sat_strength;
+        }
+    }
+    out.map(|v| v + grade.luminance * mask * lum_strength)
+}
+
+fn apply_color_grading(c: [f32; 3], adjustments: &AllAdjustments) -> [f32; 3] {
+    let g = &adjustments.global;
+    let luma = get_luma(c.map(|v| v.max(0.0)));
+    let balance = g.color_grading_balance;
+    let shadow_crossover = 0.1 + (-balance).max(0.0) * 0.5;
+    let highlight_crossover = 0.5 - balance.max(0.0) * 0.5;
+    let feather = 0.2 * g.color_grading_blending;
+    let shadow_crossover = shadow_crossover.min(highlight_crossover - 0.01);
+    let shadow_mask =
+        1.0 - smoothstep(shadow_crossover - feather, shadow_crossover + feather, luma);
+    let highlight_mask = smoothstep(
+        highlight_crossover - feather,
+        highlight_crossover + feather,
+        luma,
+    );
+    let midtone_mask = (1.0 - shadow_mask - highlight_mask).max(0.0);
+
+    let mut out = apply_grade_range(c, &g.color_grading_shadows, shadow_mask, 0.3, 0.5);
+    out = apply_grade_range(out, &g.color_grading_midtones, midtone_mask, 0.6, 0.8);
+    out = apply_grade_range(out, &g.color_grading_highlights, highlight_mask, 0.8, 1.0);
+    apply_grade_range(out, &g.color_grading_global, 1.0, 1.0, 1.0)
+}
+
+fn apply_curve(val: f32, points: &[Point; 16], count: u32) -> f32 {
+    if count < 2 {
+        return val;
+    }
+    let count = count.min(16) as usize;
+    let x = val * 255.0;
+    if x <= points[0].x {
+        return points[0].y / 255.0;
+    }
+    if x >= points[count - 1].x {
+        return points[count - 1].y / 255.0;
+    }
+    for i in 0..count - 1 {
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        if x <= p2.x {
+            let p0 = points[i.saturating_sub(1)];
+            let p3 = points[(i + 2).min(count - 1)];
+            let delta_before = (p1.y - p0.y) / (p1.x - p0.x).max(0.001);
+            let delta_current = (p2.y - p1.y) / (p2.x - p1.x).max(0.001);
+            let delta_after = (p3.y - p2.y) / (p3.x - p2.x).max(0.001);
+            let mut m1 = if i == 0 {
+                delta_current
+            } else if delta_before * delta_current <= 0.0 {
+                0.0
+            } else {
+                (delta_before + delta_current) / 2.0
+            };
+            let mut m2 = if i + 1 == count - 1 {
+                delta_current
+            } else if delta_current * delta_after <= 0.0 {
+                0.0
+            } else {
+                (delta_current + delta_after) / 2.0
+            };
+            if delta_current != 0.0 {
+                let alpha = m1 / delta_current;
+                let beta = m2 / delta_current;
+                if alpha * alpha + beta * beta > 9.0 {
+                    let tau = 3.0 / (alpha * alpha + beta * beta).sqrt();
+                    m1 *= tau;
+                    m2 *= tau;
+                }
+            }
+            let dx = p2.x - p1.x;
+            if dx <= 0.0 {
+                return (p1.y / 255.0).clamp(0.0, 1.0);
+            }
+            let t = (x - p1.x) / dx;
+            let t2 = t * t;
+            let t3 = t2 * t;
+            let y = (2.0 * t3 - 3.0 * t2 + 1.0) * p1.y
+                + (t3 - 2.0 * t2 + t) * m1 * dx
+                + (-2.0 * t3 + 3.0 * t2) * p2.y
+                + (t3 - t2) * m2 * dx;
+            return (y / 255.0).clamp(0.0, 1.0);
+        }
+    }
+    points[count - 1].y / 255.0
+}
+
+fn is_default_curve(points: &[Point; 16], count: u32) -> bool {
+    if count < 2 {
+        return false;
+    }
+    let count = count.min(16) as usize;
+    let first = points[0];
+    let last = points[count - 1];
+    points[..count].iter().all(|p| (p.x - p.y).abs() <= 0.5)
+        && first.x.abs() < 0.1
+        && first.y.abs() < 0.1
+        && (last.x - 255.0).abs() < 0.1
+        && (last.y - 255.0).abs() < 0.1
+}
+
+fn apply_all_curves(c: [f32; 3], adjustments: &AllAdjustments) -> [f32; 3] {
+    let g = &adjustments.global;
+    let rgb_curves_are_active = !is_default_curve(&g.red_curve, g.red_curve_count)
+        || !is_default_curve(&g.green_curve, g.green_curve_count)
+        || !is_default_curve(&g.blue_curve, g.blue_curve_count);
+
+    if !rgb_curves_are_active {
+        return c.map(|v| apply_curve(v, &g.luma_curve, g.luma_curve_count));
+    }
+
+    let graded = [
+        apply_curve(c[0], &g.red_curve, g.red_curve_count),
+        apply_curve(c[1], &g.green_curve, g.green_curve_count),
+        apply_curve(c[2], &g.blue_curve, g.blue_curve_count),
+    ];
+    let luma_target = apply_curve(get_luma(c), &g.luma_curve, g.luma_curve_count);
+    let luma_graded = get_luma(graded);
+    let mut result = if luma_graded > 0.001 {
+        graded.map(|v| v * (luma_target / luma_graded))
+    } else {
+        [luma_target; 3]
+    };
+    let max_comp = result[0].max(result[1]).max(result[2]);
+    if max_comp > 1.0 {
+        result = result.map(|v| v / max_comp);
+    }
+    result
+}
+
+fn pixel_params(request: &RenderRequest, x: u32, y: u32) -> PixelParams {
+    let adjustments = &request.adjustments;
+    let g = &adjustments.global;
+    let mut params = PixelParams {
+        exposure: g.exposure,
+        brightness: g.brightness,
+        contrast: g.contrast,
+        saturation: g.saturation,
+        temperature: g.temperature,
+        tint: g.tint,
+    };
+
+    let mask_count = (adjustments.mask_count as usize).min(request.mask_bitmaps.len());
+    for (mask, m) in request.mask_bitmaps[..mask_count]
+        .iter()
+        .zip(adjustments.mask_adjustments.iter())
+    {
+        if x >= mask.width() || y >= mask.height() {
+            continue;
+        }
+        let influence = mask.get_pixel(x, y)[0] as f32 / 255.0;
+        if influence > 0.001 {
+            params.exposure += m.exposure * influence;
+            params.brightness += m.brightness * influence;
+            params.contrast += m.contrast * influence;
+            params.saturation += m.saturation * influence;
+            params.temperature += m.temperature * influence;
+            params.tint += m.tint * influence;
+        }
+    }
+    params
+}
+
+fn process_pixel(
+    rgb: [f32; 3],
+    params: PixelParams,
+    adjustments: &AllAdjustments,
+    lut: Option<&Lut>,
+) -> [f32; 3] {
+    let is_raw = adjustments.global.is_raw_image == 1;
+    let mut c = if is_raw { rgb } else { rgb.map(srgb_to_linear) };
+
+    if params.exposure != 0.0 {
+        let factor = 2.0_f32.powf(params.exposure);
+        c = c.map(|v| v * factor);
+    }
+    c = apply_white_balance(c, params.temperature, params.tint);
+    c = apply_filmic_exposure(c, params.brightness);
+    c = apply_contrast(c, params.contrast);
+    c = apply_hsl_panel(c, &adjustments.global.hsl);
+    c = apply_saturation(c, params.saturation);
+    c = apply_color_grading(c, adjustments);
+
+    // AgX has no CPU port yet, so fall back to the default display transform.
+    let mut srgb = if is_raw && adjustments.global.tonemapper_mode != TONEMAPPER_FLAT {
+        c.map(|v| {
+            let emulated = linear_to_srgb(v).powf(1.0 / 1.1);
+            let contrast_curve = emulated * emulated * (3.0 - 2.0 * emulated);
+            emulated + (contrast_curve - emulated) * 0.75
+        })
+    } else {
+        c.map(linear_to_srgb)
+    };
+
+    srgb = apply_all_curves(srgb, adjustments);
+
+    if adjustments.global.has_lut == 1
+        && let Some(lut) = lut
+    {
+        let lut_color = lut.sample_trilinear(srgb);
+        let intensity = adjustments.global.lut_intensity;
+        srgb = [
+            srgb[0] + (lut_color[0] - srgb[0]) * intensity,
+            srgb[1] + (lut_color[1] - srgb[1]) * intensity,
+            srgb[2] + (lut_color[2] - srgb[2]) * intensity,
+        ];
+    }
+
+    srgb
+}
+
+pub fn process_image_cpu(base_image: &DynamicImage, request: &RenderRequest) -> DynamicImage {
+    let start_time = Instant::now();
+    let (width, height) = base_image.dimensions();
+    let bounds = request.roi.unwrap_or(Roi {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    });
+    let out_width = bounds.width.min(width.saturating_sub(bounds.x));
+    let out_height = bounds.height.min(height.saturating_sub(bounds.y));
+
+    let input = base_image.to_rgba32f();
+    let lut = request.lut.as_deref();
+    let mut output = vec![0u8; (out_width * out_height * 4) as usize];
+
+    output
+        .par_chunks_mut((out_width * 4).max(1) as usize)
+        .enumerate()
+        .for_each(|(row, out_row)| {
+            let y = bounds.y + row as u32;
+            for (col, out_pixel) in out_row.chunks_exact_mut(4).enumerate() {
+                let x = bounds.x + col as u32;
+                let pixel = input.get_pixel(x, y);
+                let params = pixel_params(request, x, y);
+                let rgb = process_pixel(
+                    [pixel[0], pixel[1], pixel[2]],
+                    params,
+                    &request.adjustments,
+                    lut,
+                );
+                out_pixel[0] = (rgb[0].clamp(0.0, 1.0) * 255.0).round() as u8;
+                out_pixel[1] = (rgb[1].clamp(0.0, 1.0) * 255.0).round() as u8;
+                out_pixel[2] = (rgb[2].clamp(0.0, 1.0) * 255.0).round() as u8;
+                out_pixel[3] = (pixel[3].clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        });
+
+    log::info!(
+        "{}x{} processed (ROI: {}x{}) on CPU fallback in {:?}",
+        width,
+        height,
+        out_width,
+        out_height,
+        start_time.elapsed()
+    );
+
+    let img_buf = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(out_width, out_height, output)
+        .unwrap_or_else(|| ImageBuffer::new(0, 0));
+    DynamicImage::ImageRgba8(img_buf)
+}
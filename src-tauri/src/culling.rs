@@ -54,6 +54,28 @@ struct CullingProgress {
     stage: String,
 }
 
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatePair {
+    pub first: String,
+    pub second: String,
+    pub distance: u32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub paths: Vec<String>,
+    pub distances: Vec<DuplicatePair>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateResults {
+    pub groups: Vec<DuplicateGroup>,
+    pub failed_paths: Vec<String>,
+}
+
 struct ImageAnalysisData {
     hash: image_hasher::ImageHash,
     result: ImageAnalysisResult,
@@ -63,6 +85,9 @@ const WEIGHT_SHARPNESS: f64 = 0.40;
 const WEIGHT_CENTER_FOCUS: f64 = 0.35;
 const WEIGHT_EXPOSURE: f64 = 0.25;
 
+const DUPLICATE_HASH_DIM: u32 = 256;
+const DEFAULT_DUPLICATE_THRESHOLD: u32 = 4;
+
 fn calculate_laplacian_variance(image: &GrayImage) -> f64 {
     let (width, height) = image.dimensions();
     if width < 3 || height < 3 {
@@ -316,3 +341,126 @@ pub async fn cull_images(
     let _ = app_handle.emit("culling-complete", &suggestions);
     Ok(suggestions)
 }
+
+fn hash_for_duplicates(
+    path: &str,
+    hasher: &image_hasher::Hasher,
+    settings: &crate::app_settings::AppSettings,
+) -> Result<image_hasher::ImageHash, String> {
+    let (source_path, _) = crate::file_management::parse_virtual_path(path);
+    if crate::file_management::is_cloud_placeholder(&source_path) {
+        return Err(format!("'{}' is stored in iCloud and not downloaded", path));
+    }
+
+    let source_path_str = source_path.to_string_lossy();
+    let file_bytes = std::fs::read(&source_path).map_err(|e| e.to_string())?;
+    let img = image_loader::load_base_image_from_bytes(
+        &file_bytes,
+        &source_path_str,
+        true,
+        settings,
+        None,
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(hasher.hash_image(&img.thumbnail(DUPLICATE_HASH_DIM, DUPLICATE_HASH_DIM)))
+}
+
+#[tauri::command]
+pub async fn find_duplicates(
+    paths: Vec<String>,
+    threshold: Option<u32>,
+    app_handle: AppHandle,
+) -> Result<DuplicateResults, String> {
+    if paths.is_empty() {
+        return Ok(DuplicateResults::default());
+    }
+
+    let threshold = threshold.unwrap_or(DEFAULT_DUPLICATE_THRESHOLD);
+    let app_settings = load_settings(app_handle.clone()).unwrap_or_default();
+
+    tokio::task::spawn_blocking(move || {
+        let total_count = paths.len();
+        let completed_count = AtomicUsize::new(0);
+
+        // Plain 8x8 dHash: small distances mean the frames are visually the same,
+        // unlike the larger hash used for burst grouping in `cull_images`.
+        let hasher = HasherConfig::new()
+            .hash_alg(HashAlg::Gradient)
+            .hash_size(8, 8)
+            .to_hasher();
+
+        let hash_results: Vec<Result<(String, image_hasher::ImageHash), String>> = paths
+            .par_iter()
+            .map(|path| {
+                let result = hash_for_duplicates(path, &hasher, &app_settings);
+                let completed = completed_count.fetch_add(1, Ordering::Relaxed) + 1;
+                let _ = app_handle.emit(
+                    "duplicates-progress",
+                    CullingProgress {
+                        current: completed,
+                        total: total_count,
+                        stage: "Hashing images...".to_string(),
+                    },
+                );
+                result.map(|hash| (path.clone(), hash)).map_err(|e| {
+                    log::warn!("Failed to hash image {}: {}", path, e);
+                    path.clone()
+                })
+            })
+            .collect();
+
+        let mut results = DuplicateResults::default();
+        let mut hashes = Vec::new();
+        for res in hash_results {
+            match res {
+                Ok(entry) => hashes.push(entry),
+                Err(path) => results.failed_paths.push(path),
+            }
+        }
+
+        let mut processed = vec![false; hashes.len()];
+        for i in 0..hashes.len() {
+            if processed[i] {
+                continue;
+            }
+            processed[i] = true;
+
+            let mut members = vec![i];
+            let mut queue = VecDeque::from([i]);
+            while let Some(current) = queue.pop_front() {
+                for j in 0..hashes.len() {
+                    if !processed[j] && hashes[current].1.dist(&hashes[j].1) <= threshold {
+                        processed[j] = true;
+                        members.push(j);
+                        queue.push_back(j);
+                    }
+                }
+            }
+
+            if members.len() < 2 {
+                continue;
+            }
+
+            let mut distances = Vec::new();
+            for (n, &a) in members.iter().enumerate() {
+                for &b in &members[n + 1..] {
+                    distances.push(DuplicatePair {
+                        first: hashes[a].0.clone(),
+                        second: hashes[b].0.clone(),
+                        distance: hashes[a].1.dist(&hashes[b].1),
+                    });
+                }
+            }
+
+            results.groups.push(DuplicateGroup {
+                paths: members.iter().map(|&idx| hashes[idx].0.clone()).collect(),
+                distances,
+            });
+        }
+
+        Ok(results)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,202 @@
+use image::DynamicImage;
+
+use crate::exif_processing::DngSourceInfo;
+use crate::image_processing::apply_srgb_to_linear;
+
+const TYPE_BYTE: u16 = 1;
+const TYPE_ASCII: u16 = 2;
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+const TYPE_RATIONAL: u16 = 5;
+const TYPE_SRATIONAL: u16 = 10;
+
+const PHOTOMETRIC_LINEAR_RAW: u16 = 34892;
+const SAMPLE_FORMAT_FLOAT: u16 = 3;
+const ILLUMINANT_D65: u16 = 21;
+
+// XYZ (D65) to linear sRGB. DNG expects ColorMatrix1 to map XYZ into camera space,
+// and our "camera" space is linear sRGB.
+const XYZ_TO_LINEAR_SRGB: [f64; 9] = [
+    3.2404542, -1.5371385, -0.4985314, -0.9692660, 1.8760108, 0.0415560, 0.0556434, -0.2040259,
+    1.0572252,
+];
+
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    data: Vec<u8>,
+}
+
+impl IfdEntry {
+    fn bytes(tag: u16, field_type: u16, data: &[u8]) -> Self {
+        Self {
+            tag,
+            field_type,
+            count: data.len() as u32,
+            data: data.to_vec(),
+        }
+    }
+
+    fn ascii(tag: u16, value: &str) -> Self {
+        let mut data: Vec<u8> = value.bytes().filter(|b| b.is_ascii() && *b != 0).collect();
+        data.push(0);
+        Self::bytes(tag, TYPE_ASCII, &data)
+    }
+
+    fn shorts(tag: u16, values: &[u16]) -> Self {
+        Self {
+            tag,
+            field_type: TYPE_SHORT,
+            count: values.len() as u32,
+            data: values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        }
+    }
+
+    fn long(tag: u16, value: u32) -> Self {
+        Self {
+            tag,
+            field_type: TYPE_LONG,
+            count: 1,
+            data: value.to_le_bytes().to_vec(),
+        }
+    }
+
+    fn rationals(tag: u16, values: &[f64]) -> Self {
+        let data = values
+            .iter()
+            .flat_map(|v| {
+                let num = (v.max(0.0) * 10000.0).round() as u32;
+                [num.to_le_bytes(), 10000u32.to_le_bytes()].concat()
+            })
+            .collect();
+        Self {
+            tag,
+            field_type: TYPE_RATIONAL,
+            count: values.len() as u32,
+            data,
+        }
+    }
+
+    fn srationals(tag: u16, values: &[f64]) -> Self {
+        let data = values
+            .iter()
+            .flat_map(|v| {
+                let num = (v * 10000.0).round() as i32;
+                [num.to_le_bytes(), 10000i32.to_le_bytes()].concat()
+            })
+            .collect();
+        Self {
+            tag,
+            field_type: TYPE_SRATIONAL,
+            count: values.len() as u32,
+            data,
+        }
+    }
+}
+
+/// Writes a *linear* DNG: the fully processed, demosaiced image stored as 32-bit
+/// float RGB (`LinearRaw`) in linear sRGB primaries. This is not a round-trip of the
+/// original sensor mosaic, so raw-level steps like demosaicing cannot be redone from it.
+pub fn encode_linear_dng(image: &DynamicImage, info: &DngSourceInfo) -> Result<Vec<u8>, String> {
+    let linear = apply_srgb_to_linear(DynamicImage::ImageRgb32F(image.to_rgb32f())).into_rgb32f();
+    let (width, height) = linear.dimensions();
+    let pixel_data: Vec<u8> = linear
+        .as_raw()
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let pixel_len = u32::try_from(pixel_data.len())
+        .map_err(|_| "Image is too large for a single-strip DNG".to_string())?;
+
+    let unique_camera_model = match (&info.make, &info.model) {
+        (Some(make), Some(model)) if model.starts_with(make.as_str()) => model.clone(),
+        (Some(make), Some(model)) => format!("{} {}", make, model),
+        (None, Some(model)) => model.clone(),
+        _ => "RapidRAW Linear".to_string(),
+    };
+
+    let mut entries = vec![
+        IfdEntry::long(254, 0),
+        IfdEntry::long(256, width),
+        IfdEntry::long(257, height),
+        IfdEntry::shorts(258, &[32, 32, 32]),
+        IfdEntry::shorts(259, &[1]),
+        IfdEntry::shorts(262, &[PHOTOMETRIC_LINEAR_RAW]),
+        IfdEntry::shorts(274, &[1]),
+        IfdEntry::shorts(277, &[3]),
+        IfdEntry::long(278, height),
+        IfdEntry::long(279, pixel_len),
+        IfdEntry::shorts(284, &[1]),
+        IfdEntry::ascii(305, "RapidRAW"),
+        IfdEntry::shorts(339, &[SAMPLE_FORMAT_FLOAT; 3]),
+        IfdEntry::bytes(50706, TYPE_BYTE, &[1, 4, 0, 0]),
+        IfdEntry::bytes(50707, TYPE_BYTE, &[1, 4, 0, 0]),
+        IfdEntry::ascii(50708, &unique_camera_model),
+        IfdEntry::srationals(50721, &XYZ_TO_LINEAR_SRGB),
+        IfdEntry::rationals(50728, &[1.0, 1.0, 1.0]),
+        IfdEntry::shorts(50778, &[ILLUMINANT_D65]),
+    ];
+    if let Some(make) = &info.make {
+        entries.push(IfdEntry::ascii(271, make));
+    }
+    if let Some(model) = &info.model {
+        entries.push(IfdEntry::ascii(272, model));
+    }
+    if let Some(artist) = &info.artist {
+        entries.push(IfdEntry::ascii(315, artist));
+    }
+    if let Some(copyright) = &info.copyright {
+        entries.push(IfdEntry::ascii(33432, copyright));
+    }
+    if let Some(capture_date) = &info.capture_date {
+        entries.push(IfdEntry::ascii(36867, capture_date));
+    }
+
+    // StripOffsets is filled in once the layout is known.
+    entries.push(IfdEntry::long(273, 0));
+    entries.sort_by_key(|e| e.tag);
+
+    let ifd_offset = 8u32;
+    let ifd_len = 2 + entries.len() as u32 * 12 + 4;
+    let overflow_len: u32 = entries
+        .iter()
+        .filter(|e| e.data.len() > 4)
+        .map(|e| e.data.len().next_multiple_of(2) as u32)
+        .sum();
+    let strip_offset = ifd_offset + ifd_len + overflow_len;
+    if let Some(entry) = entries.iter_mut().find(|e| e.tag == 273) {
+        entry.data = strip_offset.to_le_bytes().to_vec();
+    }
+
+    let mut out = Vec::with_capacity(strip_offset as usize + pixel_data.len());
+    out.extend_from_slice(b"II");
+    out.extend_from_slice(&42u16.to_le_bytes());
+    out.extend_from_slice(&ifd_offset.to_le_bytes());
+
+    let mut overflow = Vec::with_capacity(overflow_len as usize);
+    let overflow_start = ifd_offset + ifd_len;
+    out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for entry in &entries {
+        out.extend_from_slice(&entry.tag.to_le_bytes());
+        out.extend_from_slice(&entry.field_type.to_le_bytes());
+        out.extend_from_slice(&entry.count.to_le_bytes());
+        if entry.data.len() <= 4 {
+            let mut inline = [0u8; 4];
+            inline[..entry.data.len()].copy_from_slice(&entry.data);
+            out.extend_from_slice(&inline);
+        } else {
+            let offset = overflow_start + overflow.len() as u32;
+            out.extend_from_slice(&offset.to_le_bytes());
+            overflow.extend_from_slice(&entry.data);
+            if overflow.len() % 2 == 1 {
+                overflow.push(0);
+            }
+        }
+    }
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&overflow);
+    out.extend_from_slice(&pixel_data);
+
+    Ok(out)
+}
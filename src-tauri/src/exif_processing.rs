@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::fs;
-use std::io::{BufReader, Cursor};
+use std::io::{BufReader, Cursor, Read};
 use std::path::{Path, PathBuf};
 
 use crate::formats::is_raw_file;
@@ -216,22 +216,107 @@ pub fn read_raw_metadata(file_bytes: &[u8]) -> Option<RawMetadata> {
     decoder.raw_metadata(&raw_source, &Default::default()).ok()
 }
 
+pub fn exposure_time_from_map(map: &HashMap<String, String>) -> Option<f32> {
+    let val_str = map.get("ExposureTime").or(map.get("ShutterSpeedValue"))?;
+    let cleaned = val_str.replace(" s", "");
+    if cleaned.contains('/') {
+        let parts: Vec<&str> = cleaned.split('/').collect();
+        if parts.len() == 2
+            && let (Ok(num), Ok(den)) = (parts[0].parse::<f32>(), parts[1].parse::<f32>())
+            && den != 0.0
+        {
+            return Some(num / den);
+        }
+        None
+    } else {
+        cleaned.parse::<f32>().ok()
+    }
+}
+
+pub fn iso_from_map(map: &HashMap<String, String>) -> Option<u32> {
+    map.get("ISOSpeed")
+        .or(map.get("PhotographicSensitivity"))
+        .or(map.get("ISOSpeedRatings"))
+        .and_then(|v| v.parse::<u32>().ok())
+}
+
+fn leading_number(value: &str) -> Option<f32> {
+    let trimmed = value.trim().trim_start_matches("f/");
+    let end = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    trimmed[..end].parse::<f32>().ok()
+}
+
+fn format_exposure_time(secs: f32) -> String {
+    if secs > 0.0 && secs < 1.0 {
+        format!("1/{}s", (1.0 / secs).round())
+    } else {
+        format!("{}s", (secs * 10.0).round() / 10.0)
+    }
+}
+
+/// Display fields derived from a raw EXIF map: the 35mm-equivalent focal length (needs the
+/// sensor crop factor), the shutter actuation count when the camera records it, and a short
+/// exposure summary such as "1/250s, f/2.8, ISO 400". Existing keys are never replaced.
+pub fn computed_exif_fields(
+    map: &HashMap<String, String>,
+    crop_factor: Option<f32>,
+) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+
+    let focal_length = map.get("FocalLength").and_then(|v| leading_number(v));
+    let equivalent = match (focal_length, crop_factor) {
+        (Some(focal), Some(crop)) if focal > 0.0 && crop > 0.0 => Some(focal * crop),
+        _ => map
+            .get("FocalLengthIn35mmFilm")
+            .and_then(|v| leading_number(v))
+            .filter(|eq| *eq > 0.0 && Some(*eq) != focal_length),
+    };
+    if let Some(equivalent) = equivalent {
+        fields.push((
+            "FocalLength35mmEquivalent".to_string(),
+            format!("{} mm", equivalent.round()),
+        ));
+    }
+    if let Some(crop) = crop_factor {
+        fields.push(("CropFactor".to_string(), format!("{:.2}", crop)));
+    }
+
+    // ImageNumber and similar file counters reset with the card or folder, so only a real
+    // shutter count is reported.
+    if let Some(count) = map
+        .get("ShutterCount")
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|count| *count > 0)
+    {
+        fields.push(("ShutterCount".to_string(), count.to_string()));
+    }
+
+    let summary: Vec<String> = [
+        exposure_time_from_map(map).map(format_exposure_time),
+        map.get("FNumber")
+            .or(map.get("ApertureValue"))
+            .and_then(|v| leading_number(v))
+            .map(|f| format!("f/{}", (f * 10.0).round() / 10.0)),
+        iso_from_map(map).map(|iso| format!("ISO {}", iso)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !summary.is_empty() {
+        fields.push(("ExposureSummary".to_string(), summary.join(", ")));
+    }
+
+    fields.retain(|(key, _)| !map.contains_key(key));
+    fields
+}
+
 pub fn read_exposure_time_secs(path: &str, file_bytes: &[u8]) -> Option<f32> {
     if let Some(map) = read_rrexif_sidecar(Path::new(path))
-        && let Some(val_str) = map.get("ExposureTime").or(map.get("ShutterSpeedValue"))
+        && let Some(val) = exposure_time_from_map(&map)
     {
-        let cleaned = val_str.replace(" s", "");
-        if cleaned.contains('/') {
-            let parts: Vec<&str> = cleaned.split('/').collect();
-            if parts.len() == 2
-                && let (Ok(num), Ok(den)) = (parts[0].parse::<f32>(), parts[1].parse::<f32>())
-                && den != 0.0
-            {
-                return Some(num / den);
-            }
-        } else if let Ok(val) = cleaned.parse::<f32>() {
-            return Some(val);
-        }
+        return Some(val);
     }
 
     if is_raw_file(path)
@@ -289,11 +374,7 @@ pub fn read_exposure_time_secs(path: &str, file_bytes: &[u8]) -> Option<f32> {
 
 pub fn read_iso(path: &str, file_bytes: &[u8]) -> Option<u32> {
     if let Some(map) = read_rrexif_sidecar(Path::new(path))
-        && let Some(val_str) = map
-            .get("ISOSpeed")
-            .or(map.get("PhotographicSensitivity"))
-            .or(map.get("ISOSpeedRatings"))
-        && let Ok(val) = val_str.parse::<u32>()
+        && let Some(val) = iso_from_map(&map)
     {
         return Some(val);
     }
@@ -723,6 +804,172 @@ pub fn extract_metadata(file_bytes: &[u8]) -> Option<HashMap<String, String>> {
     Some(map)
 }
 
+fn parse_gps_component(value: &str) -> Option<f64> {
+    let numbers: Vec<f64> = value
+        .split(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .filter_map(|part| part.parse().ok())
+        .collect();
+    match numbers.as_slice() {
+        [deg] => Some(*deg),
+        [deg, min] => Some(deg + min / 60.0),
+        [deg, min, sec, ..] => Some(deg + min / 60.0 + sec / 3600.0),
+        _ => None,
+    }
+}
+
+/// Decimal (latitude, longitude) from the `GPSLatitude`/`GPSLongitude` entries of an
+/// EXIF map as produced by `read_exif_data`, with south and west made negative.
+pub fn gps_coordinates(exif: &HashMap<String, String>) -> Option<(f64, f64)> {
+    let mut lat = parse_gps_component(exif.get("GPSLatitude")?)?;
+    let mut lon = parse_gps_component(exif.get("GPSLongitude")?)?;
+    let is_ref = |key: &str, hemisphere: &str| {
+        exif.get(key)
+            .is_some_and(|r| r.trim().eq_ignore_ascii_case(hemisphere))
+    };
+    if is_ref("GPSLatitudeRef", "S") {
+        lat = -lat.abs();
+    }
+    if is_ref("GPSLongitudeRef", "W") {
+        lon = -lon.abs();
+    }
+
+    let in_range = (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon);
+    // Cameras without a fix often write all-zero coordinates.
+    (in_range && (lat != 0.0 || lon != 0.0)).then_some((lat, lon))
+}
+
+fn gps_rationals(value: f64) -> Vec<uR64> {
+    let value = value.abs();
+    let degrees = value.trunc();
+    let minutes = ((value - degrees) * 60.0).trunc();
+    let seconds = (value - degrees - minutes / 60.0) * 3600.0;
+    vec![
+        uR64 {
+            nominator: degrees as u32,
+            denominator: 1,
+        },
+        uR64 {
+            nominator: minutes as u32,
+            denominator: 1,
+        },
+        uR64 {
+            nominator: (seconds * 1000.0).round() as u32,
+            denominator: 1000,
+        },
+    ]
+}
+
+fn set_gps_tags(metadata: &mut Metadata, lat: f64, lon: f64) {
+    metadata.set_tag(ExifTag::GPSLatitudeRef(
+        if lat < 0.0 { "S" } else { "N" }.to_string(),
+    ));
+    metadata.set_tag(ExifTag::GPSLatitude(gps_rationals(lat)));
+    metadata.set_tag(ExifTag::GPSLongitudeRef(
+        if lon < 0.0 { "W" } else { "E" }.to_string(),
+    ));
+    metadata.set_tag(ExifTag::GPSLongitude(gps_rationals(lon)));
+}
+
+/// EXIF map entries for a decimal coordinate, in the same format `read_exif_data` reports.
+pub fn gps_exif_entries(lat: f64, lon: f64) -> HashMap<String, String> {
+    let fmt = |value: f64| {
+        let r = gps_rationals(value);
+        format!(
+            "{} deg {} min {} sec",
+            r[0].nominator,
+            r[1].nominator,
+            r[2].nominator as f64 / r[2].denominator as f64
+        )
+    };
+    HashMap::from([
+        ("GPSLatitude".to_string(), fmt(lat)),
+        (
+            "GPSLatitudeRef".to_string(),
+            if lat < 0.0 { "S" } else { "N" }.to_string(),
+        ),
+        ("GPSLongitude".to_string(), fmt(lon)),
+        (
+            "GPSLongitudeRef".to_string(),
+            if lon < 0.0 { "W" } else { "E" }.to_string(),
+        ),
+    ])
+}
+
+/// Writes GPS coordinates into the EXIF block of a JPEG, PNG or WebP file in place.
+pub fn write_embedded_gps(path: &Path, lat: f64, lon: f64) -> Result<(), String> {
+    let mut metadata = Metadata::new_from_path(path).map_err(|e| e.to_string())?;
+    set_gps_tags(&mut metadata, lat, lon);
+    metadata.write_to_file(path).map_err(|e| e.to_string())
+}
+
+pub fn write_embedded_orientation(path: &Path, orientation: u16) -> Result<(), String> {
+    let mut metadata = Metadata::new_from_path(path).map_err(|e| e.to_string())?;
+    metadata.set_tag(ExifTag::Orientation(vec![orientation]));
+    metadata.write_to_file(path).map_err(|e| e.to_string())
+}
+
+const XMP_APP1_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// Walks the JPEG header segments up to the start of scan, returning `(marker, start, end)`
+/// byte ranges of each segment including its marker.
+fn jpeg_header_segments(bytes: &[u8]) -> Option<Vec<(u8, usize, usize)>> {
+    if bytes.len() < 4 || bytes[0] != 0xFF || bytes[1] != 0xD8 {
+        return None;
+    }
+    let mut segments = Vec::new();
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        let marker = bytes[pos + 1];
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let length = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let end = pos + 2 + length;
+        if length < 2 || end > bytes.len() {
+            return None;
+        }
+        segments.push((marker, pos, end));
+        pos = end;
+    }
+    Some(segments)
+}
+
+fn find_xmp_segment(bytes: &[u8]) -> Option<(usize, usize)> {
+    jpeg_header_segments(bytes)?
+        .into_iter()
+        .find(|(marker, start, end)| {
+            *marker == 0xE1 && bytes[start + 4..*end].starts_with(XMP_APP1_HEADER)
+        })
+        .map(|(_, start, end)| (start, end))
+}
+
+/// Returns the XMP packet embedded in a JPEG, if any. Only the header segments are read.
+pub fn read_embedded_xmp(path: &Path) -> Option<String> {
+    let is_jpeg = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| matches!(e.to_lowercase().as_str(), "jpg" | "jpeg"));
+    if !is_jpeg {
+        return None;
+    }
+
+    let mut header = Vec::new();
+    fs::File::open(path)
+        .ok()?
+        .take(512 * 1024)
+        .read_to_end(&mut header)
+        .ok()?;
+    let (start, end) = find_xmp_segment(&header)?;
+    let payload = &header[start + 4 + XMP_APP1_HEADER.len()..end];
+    Some(String::from_utf8_lossy(payload).into_owned())
+}
+
+pub fn capture_datetime(exif: &HashMap<String, String>) -> Option<DateTime<Utc>> {
+    ["DateTimeOriginal", "CreateDate", "DateTime"]
+        .iter()
+        .find_map(|key| parse_raw_creation_date(exif.get(*key).map(String::as_str)))
+}
+
 pub fn get_creation_date_from_path(path: &Path) -> DateTime<Utc> {
     if let Some(map) = read_rrexif_sidecar(path)
         && let Some(dt_str) = map.get("DateTimeOriginal").or(map.get("CreateDate"))
@@ -794,21 +1041,268 @@ pub fn get_creation_date_from_bytes(path_hint: &str, file_bytes: &[u8]) -> DateT
     Utc::now()
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct MetadataStamp {
+    pub artist: Option<String>,
+    pub copyright: Option<String>,
+    pub contact_url: Option<String>,
+}
+
+impl MetadataStamp {
+    fn field(value: &Option<String>) -> Option<&str> {
+        value.as_deref().map(str::trim).filter(|s| !s.is_empty())
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        Self::field(&self.artist)
+    }
+
+    pub fn copyright(&self) -> Option<&str> {
+        Self::field(&self.copyright)
+    }
+
+    pub fn contact_url(&self) -> Option<&str> {
+        Self::field(&self.contact_url)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.artist().is_none() && self.copyright().is_none() && self.contact_url().is_none()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DngSourceInfo {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub capture_date: Option<String>,
+    pub artist: Option<String>,
+    pub copyright: Option<String>,
+}
+
+pub fn read_dng_source_info(
+    original_path_str: &str,
+    keep_metadata: bool,
+    stamp: &MetadataStamp,
+) -> DngSourceInfo {
+    let mut info = DngSourceInfo {
+        artist: stamp.artist().map(str::to_string),
+        copyright: stamp.copyright().map(str::to_string),
+        ..Default::default()
+    };
+    if !keep_metadata {
+        return info;
+    }
+
+    let original_path = Path::new(original_path_str);
+    let map = read_rrexif_sidecar(original_path).or_else(|| {
+        fs::read(original_path)
+            .ok()
+            .map(|bytes| read_exif_data_from_bytes(original_path_str, &bytes))
+    });
+    if let Some(map) = map {
+        let clean = |key: &str| {
+            map.get(key)
+                .map(|s| s.replace('"', "").trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+        info.make = clean("Make");
+        info.model = clean("Model");
+        info.capture_date = map
+            .get("DateTimeOriginal")
+            .or(map.get("CreateDate"))
+            .and_then(|s| parse_creation_datetime(s))
+            .map(|dt| dt.format("%Y:%m:%d %H:%M:%S").to_string());
+        if info.artist.is_none() {
+            info.artist = clean("Artist");
+        }
+        if info.copyright.is_none() {
+            info.copyright = clean("Copyright");
+        }
+    }
+    info
+}
+
+/// Explains why `stamp` cannot be written into an export of `output_format`, so the export
+/// can be refused up front instead of silently shipping files without it.
+pub fn metadata_stamp_error(output_format: &str, stamp: &MetadataStamp) -> Option<String> {
+    if stamp.is_empty() {
+        return None;
+    }
+    match output_format.to_lowercase().as_str() {
+        "jpg" | "jpeg" | "png" | "webp" | "jxl" => None,
+        // LUT exports carry no image metadata at all.
+        "cube" => None,
+        "dng" if stamp.contact_url().is_none() => None,
+        "dng" => Some(
+            "A contact URL cannot be written into DNG exports. Clear it or choose another format."
+                .to_string(),
+        ),
+        other => Some(format!(
+            "Artist, copyright and contact details cannot be written into {} exports. Clear them or choose JPEG, PNG, WebP or JPEG XL.",
+            other.to_uppercase()
+        )),
+    }
+}
+
+fn escape_xmp_text(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// XMP packet carrying the stamp as Dublin Core creator/rights and the IPTC Core work URL.
+fn stamp_xmp_packet(stamp: &MetadataStamp) -> String {
+    let mut body = String::new();
+    if let Some(artist) = stamp.artist() {
+        body.push_str(&format!(
+            "   <dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>\n",
+            escape_xmp_text(artist)
+        ));
+    }
+    if let Some(copyright) = stamp.copyright() {
+        body.push_str(&format!(
+            "   <dc:rights><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:rights>\n",
+            escape_xmp_text(copyright)
+        ));
+    }
+    if let Some(contact_url) = stamp.contact_url() {
+        body.push_str(&format!(
+            "   <Iptc4xmpCore:CreatorContactInfo rdf:parseType=\"Resource\">\n    <Iptc4xmpCore:CiUrlWork>{}</Iptc4xmpCore:CiUrlWork>\n   </Iptc4xmpCore:CreatorContactInfo>\n",
+            escape_xmp_text(contact_url)
+        ));
+    }
+    format!(
+        r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="RapidRAW">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:Iptc4xmpCore="http://iptc.org/std/Iptc4xmpCore/1.0/xmlns/">
+{}  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        body
+    )
+}
+
+/// Replaces the JPEG's XMP segment with `packet`, or adds one after the other APPn segments.
+fn embed_xmp_in_jpeg(bytes: &mut Vec<u8>, packet: &str) -> Result<(), String> {
+    let payload_len = XMP_APP1_HEADER.len() + packet.len();
+    if payload_len + 2 > u16::MAX as usize {
+        return Err("XMP packet is too large for a JPEG segment".to_string());
+    }
+    let mut segment = vec![0xFF, 0xE1];
+    segment.extend_from_slice(&((payload_len + 2) as u16).to_be_bytes());
+    segment.extend_from_slice(XMP_APP1_HEADER);
+    segment.extend_from_slice(packet.as_bytes());
+
+    let segments = jpeg_header_segments(bytes).ok_or("Output is not a valid JPEG")?;
+    if let Some((start, end)) = find_xmp_segment(bytes) {
+        bytes.splice(start..end, segment);
+    } else {
+        let insert_at = segments
+            .iter()
+            .take_while(|(marker, _, _)| (0xE0..=0xEF).contains(marker))
+            .last()
+            .map_or(2, |(_, _, end)| *end);
+        bytes.splice(insert_at..insert_at, segment);
+    }
+    Ok(())
+}
+
+/// EXIF UserComment with the 8-byte ASCII character code header. Non-ASCII bytes are
+/// percent-encoded, which keeps URLs valid.
+fn ascii_user_comment(text: &str) -> Vec<u8> {
+    let mut comment = b"ASCII\0\0\0".to_vec();
+    for byte in text.bytes() {
+        if byte.is_ascii() && !byte.is_ascii_control() {
+            comment.push(byte);
+        } else {
+            comment.extend_from_slice(format!("%{:02X}", byte).as_bytes());
+        }
+    }
+    comment
+}
+
 pub fn write_image_with_metadata(
     image_bytes: &mut Vec<u8>,
     original_path_str: &str,
     output_format: &str,
     keep_metadata: bool,
     strip_gps: bool,
+    stamp: &MetadataStamp,
+    is_srgb: bool,
 ) -> Result<(), String> {
     // FIXME: temporary solution until I find a way to write metadata to TIFF
-    if !keep_metadata || output_format.to_lowercase() == "tiff" {
+    if (!keep_metadata && stamp.is_empty()) || output_format.to_lowercase() == "tiff" {
         return Ok(());
     }
 
+    let is_jpeg = matches!(output_format.to_lowercase().as_str(), "jpg" | "jpeg");
+    let file_type = match output_format.to_lowercase().as_str() {
+        "jpg" | "jpeg" => FileExtension::JPEG,
+        "png" => FileExtension::PNG {
+            as_zTXt_chunk: true,
+        },
+        "webp" => FileExtension::WEBP,
+        "jxl" => FileExtension::JXL,
+        _ => return Ok(()),
+    };
+
+    let mut metadata = Metadata::new();
+    let copied_user_comment =
+        keep_metadata && copy_source_metadata(&mut metadata, original_path_str, strip_gps);
+
+    if let Some(artist) = stamp.artist() {
+        metadata.set_tag(ExifTag::Artist(artist.to_string()));
+    }
+    if let Some(copyright) = stamp.copyright() {
+        metadata.set_tag(ExifTag::Copyright(copyright.to_string()));
+    }
+    // JPEGs carry the contact URL in XMP below; other formats only get EXIF, where the
+    // UserComment is the closest fit. A comment copied from the source is kept.
+    if !is_jpeg && let Some(contact_url) = stamp.contact_url() {
+        if copied_user_comment {
+            log::warn!(
+                "Not writing contact URL into {}: the source's UserComment was kept",
+                original_path_str
+            );
+        } else {
+            metadata.set_tag(ExifTag::UserComment(ascii_user_comment(&format!(
+                "Contact: {}",
+                contact_url
+            ))));
+        }
+    }
+
+    metadata.set_tag(ExifTag::Software("RapidRAW".to_string()));
+    metadata.set_tag(ExifTag::Orientation(vec![1u16]));
+    // 0xFFFF marks the file as "uncalibrated" so readers fall back to the ICC profile.
+    metadata.set_tag(ExifTag::ColorSpace(vec![if is_srgb {
+        1u16
+    } else {
+        0xFFFF
+    }]));
+
+    if let Err(e) = metadata.write_to_vec(image_bytes, file_type) {
+        log::warn!("Failed to write metadata: {}", e);
+    }
+
+    if is_jpeg && !stamp.is_empty() {
+        embed_xmp_in_jpeg(image_bytes, &stamp_xmp_packet(stamp))?;
+    }
+
+    Ok(())
+}
+
+/// Copies the source's EXIF into `metadata`. Returns whether a UserComment was copied.
+fn copy_source_metadata(metadata: &mut Metadata, original_path_str: &str, strip_gps: bool) -> bool {
     let original_path = Path::new(original_path_str);
     if !original_path.exists() {
-        return Ok(());
+        return false;
     }
 
     // Skip TIFF sources to avoid potential tag corruption issues
@@ -818,20 +1312,11 @@ pub fn write_image_with_metadata(
         .unwrap_or("")
         .to_lowercase();
     if original_ext == "tiff" || original_ext == "tif" {
-        return Ok(());
+        return false;
     }
 
-    let file_type = match output_format.to_lowercase().as_str() {
-        "jpg" | "jpeg" => FileExtension::JPEG,
-        "png" => FileExtension::PNG {
-            as_zTXt_chunk: true,
-        },
-        "tiff" => FileExtension::TIFF,
-        _ => return Ok(()),
-    };
-
-    let mut metadata = Metadata::new();
     let mut source_read_success = false;
+    let mut copied_user_comment = false;
 
     if let Some(map) = read_rrexif_sidecar(original_path) {
         source_read_success = true;
@@ -885,6 +1370,7 @@ pub fn write_image_with_metadata(
         }
         if let Some(val) = map.get("UserComment") {
             metadata.set_tag(ExifTag::UserComment(clean_s(val).into_bytes()));
+            copied_user_comment = true;
         }
         if let Some(val) = map.get("ImageDescription") {
             metadata.set_tag(ExifTag::ImageDescription(clean_s(val)));
@@ -922,6 +1408,9 @@ pub fn write_image_with_metadata(
         {
             metadata.set_tag(ExifTag::ISO(vec![iso]));
         }
+        if !strip_gps && let Some((lat, lon)) = gps_coordinates(&map) {
+            set_gps_tags(metadata, lat, lon);
+        }
     }
 
     if !source_read_success && let Ok(file) = std::fs::File::open(original_path) {
@@ -1187,16 +1676,7 @@ pub fn write_image_with_metadata(
             }
         }
     }
-
-    metadata.set_tag(ExifTag::Software("RapidRAW".to_string()));
-    metadata.set_tag(ExifTag::Orientation(vec![1u16]));
-    metadata.set_tag(ExifTag::ColorSpace(vec![1u16]));
-
-    if let Err(e) = metadata.write_to_vec(image_bytes, file_type) {
-        log::warn!("Failed to write metadata: {}", e);
-    }
-
-    Ok(())
+    copied_user_comment
 }
 
 pub fn get_primary_sidecar_path(image_path: &Path) -> PathBuf {
@@ -7,7 +7,12 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use image::codecs::jpeg::JpegEncoder;
-use image::{DynamicImage, GenericImageView, GrayImage, ImageBuffer, ImageFormat, Luma, imageops};
+use image::codecs::png::PngEncoder;
+use image::codecs::tiff::TiffEncoder;
+use image::{
+    DynamicImage, GenericImageView, GrayImage, ImageBuffer, ImageEncoder, ImageFormat, Luma,
+    imageops,
+};
 use jxl_encoder::{
     LosslessConfig, LossyConfig, PixelLayout,
     api::{calibrated_jxl_quality, quality_to_distance},
@@ -18,6 +23,9 @@ use tauri::Emitter;
 use tauri::Manager;
 
 use crate::AppState;
+use crate::app_settings::AppSettings;
+use crate::color_management::{self, OutputColorSpace};
+use crate::dng_writer;
 use crate::exif_processing;
 use crate::file_management::{
     generate_filename_from_template, parse_virtual_path, read_file_mapped,
@@ -28,15 +36,15 @@ use crate::image_loader::{
 };
 use crate::image_processing::{
     AllAdjustments, Crop, GpuContext, RenderRequest, downscale_f32_image,
-    get_all_adjustments_from_json, get_or_init_gpu_context, process_and_get_dynamic_image,
+    get_all_adjustments_from_json, get_gpu_context_or_fallback, process_and_get_dynamic_image,
     resolve_tonemapper_override_from_handle,
 };
 use crate::lut_processing::{
-    convert_image_to_cube_lut, generate_identity_lut_image, get_or_load_lut,
+    convert_image_to_cube_lut, generate_identity_lut_image, resolve_lut_for_adjustments,
 };
 use crate::mask_generation::{MaskDefinition, generate_mask_bitmap};
 
-use crate::cache_utils::{calculate_full_job_hash, calculate_transform_hash};
+use crate::cache_utils::{RenderCache, calculate_full_job_hash, calculate_transform_hash};
 use crate::{
     apply_all_transformations, generate_transformed_preview, get_cached_or_generate_mask,
     hydrate_adjustments, load_settings, resolve_warped_image_for_masks,
@@ -59,6 +67,45 @@ pub struct ResizeOptions {
     pub dont_enlarge: bool,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputSharpeningMedium {
+    #[default]
+    None,
+    Screen,
+    PrintMatte,
+    PrintGlossy,
+}
+
+impl OutputSharpeningMedium {
+    // (blur sigma in output pixels, strength at amount 50). Matte paper spreads ink more
+    // than glossy, so it gets a wider radius and a stronger push.
+    fn parameters(self) -> Option<(f32, f32)> {
+        match self {
+            OutputSharpeningMedium::None => None,
+            OutputSharpeningMedium::Screen => Some((0.6, 0.5)),
+            OutputSharpeningMedium::PrintGlossy => Some((1.0, 0.8)),
+            OutputSharpeningMedium::PrintMatte => Some((1.4, 1.1)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputSharpening {
+    pub medium: OutputSharpeningMedium,
+    pub amount: f32,
+}
+
+impl Default for OutputSharpening {
+    fn default() -> Self {
+        Self {
+            medium: OutputSharpeningMedium::None,
+            amount: 50.0,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ExportSettings {
@@ -74,6 +121,34 @@ pub struct ExportSettings {
     pub export_masks: bool,
     #[serde(default)]
     pub preserve_folders: bool,
+    #[serde(default)]
+    pub artist: Option<String>,
+    #[serde(default)]
+    pub copyright: Option<String>,
+    #[serde(default)]
+    pub contact_url: Option<String>,
+    #[serde(default)]
+    pub write_xmp_sidecar: bool,
+    #[serde(default)]
+    pub output_icc_profile: Option<String>,
+    #[serde(default)]
+    pub output_color_space: OutputColorSpace,
+    #[serde(default)]
+    pub output_sharpening: OutputSharpening,
+    #[serde(default)]
+    pub skip_existing: bool,
+    #[serde(default)]
+    pub skip_existing_only_if_newer: bool,
+}
+
+impl ExportSettings {
+    fn metadata_stamp(&self) -> exif_processing::MetadataStamp {
+        exif_processing::MetadataStamp {
+            artist: self.artist.clone(),
+            copyright: self.copyright.clone(),
+            contact_url: self.contact_url.clone(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -257,6 +332,42 @@ fn relative_export_dir_for_preserved_folders(
         })
 }
 
+/// Unsharp mask on luminance, sized for the final output pixels. Runs independently of
+/// the sharpening in the develop adjustments.
+fn apply_output_sharpening(image: DynamicImage, settings: &OutputSharpening) -> DynamicImage {
+    let Some((sigma, base_strength)) = settings.medium.parameters() else {
+        return image;
+    };
+    let strength = base_strength * (settings.amount.clamp(0.0, 100.0) / 50.0);
+    if strength <= 0.0 {
+        return image;
+    }
+    const THRESHOLD: f32 = 0.004;
+
+    let mut sharpened = image.to_rgba32f();
+    let blurred = imageops::blur(&sharpened, sigma);
+    let luma = |p: &image::Rgba<f32>| 0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2];
+    for (pixel, blurred_pixel) in sharpened.pixels_mut().zip(blurred.pixels()) {
+        let detail = luma(pixel) - luma(blurred_pixel);
+        if detail.abs() <= THRESHOLD {
+            continue;
+        }
+        for c in 0..3 {
+            pixel[c] = (pixel[c] + detail * strength).clamp(0.0, 1.0);
+        }
+    }
+
+    let sharpened = DynamicImage::ImageRgba32F(sharpened);
+    match image {
+        DynamicImage::ImageRgb8(_) => DynamicImage::ImageRgb8(sharpened.to_rgb8()),
+        DynamicImage::ImageRgba8(_) => DynamicImage::ImageRgba8(sharpened.to_rgba8()),
+        DynamicImage::ImageRgb16(_) => DynamicImage::ImageRgb16(sharpened.to_rgb16()),
+        DynamicImage::ImageRgba16(_) => DynamicImage::ImageRgba16(sharpened.to_rgba16()),
+        DynamicImage::ImageRgb32F(_) => DynamicImage::ImageRgb32F(sharpened.to_rgb32f()),
+        _ => sharpened,
+    }
+}
+
 fn apply_export_resize_and_watermark(
     mut image: DynamicImage,
     export_settings: &ExportSettings,
@@ -270,26 +381,65 @@ fn apply_export_resize_and_watermark(
         }
     }
 
+    image = apply_output_sharpening(image, &export_settings.output_sharpening);
+
     if let Some(watermark_settings) = &export_settings.watermark {
         apply_watermark(&mut image, watermark_settings)?;
     }
     Ok(image)
 }
 
+fn check_export_cancelled(state: &tauri::State<AppState>) -> Result<(), String> {
+    if state.export_cancellation_token.load(Ordering::SeqCst) {
+        return Err("cancelled".to_string());
+    }
+    Ok(())
+}
+
+fn emit_export_progress(app_handle: &tauri::AppHandle, path: &str, stage: &str, percent: u8) {
+    let _ = app_handle.emit(
+        "export-progress",
+        serde_json::json!({
+            "path": path,
+            "stage": stage,
+            "percent": percent,
+        }),
+    );
+}
+
+/// Renders `base_image` with its adjustments at full resolution. `on_stage` is called after
+/// each stage with a progress percentage and may return an error to abort.
 #[allow(clippy::too_many_arguments)]
-fn process_image_for_export_pipeline(
+pub fn process_image_for_export_pipeline(
     path: &str,
     base_image: &DynamicImage,
     js_adjustments: &Value,
-    context: &GpuContext,
+    context: Option<&GpuContext>,
     state: &tauri::State<AppState>,
     is_raw: bool,
     debug_tag: &str,
     app_handle: &tauri::AppHandle,
+    on_stage: &dyn Fn(&str, u8) -> Result<(), String>,
+    fit_within: Option<(u32, u32)>,
 ) -> Result<DynamicImage, String> {
     let (transformed_image, unscaled_crop_offset) =
         apply_all_transformations(Cow::Borrowed(base_image), js_adjustments);
+    let full_w = transformed_image.width();
+    let transformed_image = match fit_within {
+        Some((max_w, max_h)) => Cow::Owned(downscale_f32_image(
+            transformed_image.as_ref(),
+            max_w,
+            max_h,
+        )),
+        None => transformed_image,
+    };
     let (img_w, img_h) = transformed_image.dimensions();
+    let scale = img_w as f32 / full_w.max(1) as f32;
+    let scaled_crop_offset = (
+        unscaled_crop_offset.0 * scale,
+        unscaled_crop_offset.1 * scale,
+    );
+    on_stage("transformed", 20)?;
 
     let mask_definitions: Vec<MaskDefinition> = js_adjustments
         .get("masks")
@@ -304,23 +454,26 @@ fn process_image_for_export_pipeline(
                 def,
                 img_w,
                 img_h,
-                1.0,
-                unscaled_crop_offset,
+                scale,
+                scaled_crop_offset,
                 warped_image.as_deref(),
             )
         })
         .collect();
+    on_stage("masksGenerated", 35)?;
 
     let tm_override = resolve_tonemapper_override_from_handle(app_handle, is_raw);
     let mut all_adjustments = get_all_adjustments_from_json(js_adjustments, is_raw, tm_override);
     all_adjustments.global.show_clipping = 0;
+    all_adjustments
+        .global
+        .set_grain_frame(scale, unscaled_crop_offset);
 
-    let lut_path = js_adjustments["lutPath"].as_str();
-    let lut = lut_path.and_then(|p| get_or_load_lut(state, p).ok());
+    let lut = resolve_lut_for_adjustments(state, js_adjustments);
 
     let unique_hash = calculate_full_job_hash(path, js_adjustments);
 
-    process_and_get_dynamic_image(
+    let processed = process_and_get_dynamic_image(
         context,
         state,
         transformed_image.as_ref(),
@@ -332,6 +485,56 @@ fn process_image_for_export_pipeline(
             roi: None,
         },
         debug_tag,
+    )?;
+    on_stage("gpuProcessed", 75)?;
+
+    // The GPU always writes RGBA. Only keep the alpha when the source had transparency,
+    // so straightened edges of opaque photos do not come out see-through.
+    if base_image.color().has_alpha() {
+        Ok(processed)
+    } else {
+        Ok(DynamicImage::ImageRgb8(processed.to_rgb8()))
+    }
+}
+
+/// Loads `path` with the adjustments stored in its sidecar and renders it, at full resolution
+/// unless `fit_within` asks for a smaller size.
+pub fn render_path_from_sidecar(
+    path: &str,
+    context: Option<&GpuContext>,
+    settings: &AppSettings,
+    state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
+    debug_tag: &str,
+    fit_within: Option<(u32, u32)>,
+) -> Result<DynamicImage, String> {
+    let (source_path, sidecar_path) = parse_virtual_path(path);
+    let source_path_str = source_path.to_string_lossy().to_string();
+    let mut js_adjustments = exif_processing::load_sidecar(&sidecar_path).adjustments;
+    hydrate_adjustments(state, &mut js_adjustments);
+
+    let bytes = fs::read(&source_path).map_err(|e| e.to_string())?;
+    let base_image = load_and_composite(
+        &bytes,
+        &source_path_str,
+        &js_adjustments,
+        false,
+        settings,
+        None,
+    )
+    .map_err(|e| e.to_string())?;
+
+    process_image_for_export_pipeline(
+        &source_path_str,
+        &base_image,
+        &js_adjustments,
+        context,
+        state,
+        is_raw_file(&source_path_str),
+        debug_tag,
+        app_handle,
+        &|_, _| Ok(()),
+        fit_within,
     )
 }
 
@@ -351,14 +554,61 @@ fn save_image_with_metadata(
     output_path: &std::path::Path,
     source_path_str: &str,
     export_settings: &ExportSettings,
+    app_handle: Option<&tauri::AppHandle>,
 ) -> Result<(), String> {
+    if let Some(app_handle) = app_handle {
+        emit_export_progress(app_handle, source_path_str, "encoding", 80);
+    }
+
     let extension = output_path
         .extension()
         .and_then(|s| s.to_str())
         .unwrap_or("")
         .to_lowercase();
 
-    let mut image_bytes = encode_image_to_bytes(image, &extension, export_settings.jpeg_quality)?;
+    // A custom ICC profile takes precedence over the built-in output color spaces. Formats
+    // that cannot carry a profile keep the pipeline's sRGB pixels, which is what viewers
+    // assume for untagged images.
+    let can_embed_icc = matches!(extension.as_str(), "jpg" | "jpeg" | "png" | "tiff");
+    let (output_profile, is_srgb) = match export_settings.output_icc_profile.as_deref() {
+        _ if extension == "dng" => (None, true),
+        _ if !can_embed_icc => (None, true),
+        Some(path) if !path.is_empty() => {
+            let profile = color_management::load_icc_profile(path)?;
+            let icc_bytes = fs::read(path).map_err(|e| e.to_string())?;
+            (Some((profile, icc_bytes)), false)
+        }
+        _ => {
+            let space = export_settings.output_color_space;
+            let profile = space.profile()?;
+            let icc_bytes = profile.icc().map_err(|e| e.to_string())?;
+            (Some((profile, icc_bytes)), space == OutputColorSpace::Srgb)
+        }
+    };
+
+    let mut image_bytes = if extension == "dng" {
+        let info = exif_processing::read_dng_source_info(
+            source_path_str,
+            export_settings.keep_metadata,
+            &export_settings.metadata_stamp(),
+        );
+        dng_writer::encode_linear_dng(image, &info)?
+    } else if let Some((profile, icc_bytes)) = &output_profile {
+        // Pipeline output is already sRGB, so only the profile tag needs to be added.
+        let converted = if is_srgb {
+            Cow::Borrowed(image)
+        } else {
+            Cow::Owned(color_management::convert_to_profile(image, profile)?)
+        };
+        encode_image_with_icc(
+            &converted,
+            &extension,
+            export_settings.jpeg_quality,
+            Some(icc_bytes),
+        )?
+    } else {
+        encode_image_to_bytes(image, &extension, export_settings.jpeg_quality)?
+    };
 
     exif_processing::write_image_with_metadata(
         &mut image_bytes,
@@ -366,8 +616,16 @@ fn save_image_with_metadata(
         &extension,
         export_settings.keep_metadata,
         export_settings.strip_gps,
+        &export_settings.metadata_stamp(),
+        is_srgb,
     )?;
 
+    if let Some(app_handle) = app_handle {
+        // Encoding can take a while; bail out before anything lands on disk.
+        check_export_cancelled(&app_handle.state::<AppState>())?;
+        emit_export_progress(app_handle, source_path_str, "writing", 95);
+    }
+
     #[cfg(target_os = "android")]
     {
         let file_name = output_path
@@ -382,7 +640,35 @@ fn save_image_with_metadata(
     }
 
     #[cfg(not(target_os = "android"))]
-    fs::write(output_path, image_bytes).map_err(|e| e.to_string())?;
+    write_file_atomically(output_path, &image_bytes)?;
+
+    if let Some(app_handle) = app_handle {
+        emit_export_progress(app_handle, source_path_str, "done", 100);
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "android"))]
+fn write_file_atomically(output_path: &Path, bytes: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+
+    let parent_dir = output_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+
+    // The temp file lives next to the target so the final rename stays on one filesystem.
+    // If anything fails before `persist`, dropping the NamedTempFile removes it.
+    let mut temp_file = tempfile::Builder::new()
+        .prefix(".rapidraw-export-")
+        .suffix(".tmp")
+        .tempfile_in(parent_dir)
+        .map_err(|e| e.to_string())?;
+    temp_file.write_all(bytes).map_err(|e| e.to_string())?;
+    temp_file.as_file().sync_all().map_err(|e| e.to_string())?;
+    temp_file
+        .persist(output_path)
+        .map_err(|e| e.error.to_string())?;
 
     Ok(())
 }
@@ -397,35 +683,11 @@ pub fn mime_type_for_extension(extension: &str) -> &'static str {
         "gif" => "image/gif",
         "tif" | "tiff" => "image/tiff",
         "jxl" => "image/jxl",
+        "dng" => "image/x-adobe-dng",
         _ => "application/octet-stream",
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-fn process_image_for_export(
-    path: &str,
-    base_image: &DynamicImage,
-    js_adjustments: &Value,
-    export_settings: &ExportSettings,
-    context: &GpuContext,
-    state: &tauri::State<AppState>,
-    is_raw: bool,
-    app_handle: &tauri::AppHandle,
-) -> Result<DynamicImage, String> {
-    let processed_image = process_image_for_export_pipeline(
-        path,
-        base_image,
-        js_adjustments,
-        context,
-        state,
-        is_raw,
-        "process_image_for_export",
-        app_handle,
-    )?;
-
-    apply_export_resize_and_watermark(processed_image, export_settings)
-}
-
 fn build_single_mask_adjustments(all: &AllAdjustments, mask_index: usize) -> AllAdjustments {
     let mut single = AllAdjustments {
         global: all.global,
@@ -434,6 +696,10 @@ fn build_single_mask_adjustments(all: &AllAdjustments, mask_index: usize) -> All
         tile_offset_x: all.tile_offset_x,
         tile_offset_y: all.tile_offset_y,
         mask_atlas_cols: all.mask_atlas_cols,
+        image_offset_x: all.image_offset_x,
+        image_offset_y: all.image_offset_y,
+        image_width: all.image_width,
+        image_height: all.image_height,
     };
     single.mask_adjustments[0] = all.mask_adjustments[mask_index];
     for i in 1..single.mask_adjustments.len() {
@@ -455,6 +721,15 @@ fn encode_image_to_bytes(
     image: &DynamicImage,
     output_format: &str,
     jpeg_quality: u8,
+) -> Result<Vec<u8>, String> {
+    encode_image_with_icc(image, output_format, jpeg_quality, None)
+}
+
+fn encode_image_with_icc(
+    image: &DynamicImage,
+    output_format: &str,
+    jpeg_quality: u8,
+    icc_profile: Option<&[u8]>,
 ) -> Result<Vec<u8>, String> {
     let mut image_bytes = Vec::new();
     let mut cursor = Cursor::new(&mut image_bytes);
@@ -496,14 +771,25 @@ fn encode_image_to_bytes(
             return Ok(jxl_data);
         }
         "webp" => {
-            let encoder = webp::Encoder::from_image(image)
+            // The WebP encoder only accepts 8-bit RGB or RGBA.
+            let image_to_encode = if image.color().has_alpha() {
+                DynamicImage::ImageRgba8(image.to_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(image.to_rgb8())
+            };
+            let encoder = webp::Encoder::from_image(&image_to_encode)
                 .map_err(|_| "Failed to create WebP encoder".to_string())?;
             let webp_mem = encoder.encode(jpeg_quality as f32);
             return Ok(webp_mem.to_vec());
         }
         "jpg" | "jpeg" => {
             let rgb_image = image.to_rgb8();
-            let encoder = JpegEncoder::new_with_quality(&mut cursor, jpeg_quality);
+            let mut encoder = JpegEncoder::new_with_quality(&mut cursor, jpeg_quality);
+            if let Some(icc) = icc_profile {
+                encoder
+                    .set_icc_profile(icc.to_vec())
+                    .map_err(|e| e.to_string())?;
+            }
             rgb_image
                 .write_with_encoder(encoder)
                 .map_err(|e| e.to_string())?;
@@ -511,17 +797,36 @@ fn encode_image_to_bytes(
         "png" => {
             let image_to_encode = if image.as_rgb32f().is_some() {
                 DynamicImage::ImageRgb16(image.to_rgb16())
+            } else if image.as_rgba32f().is_some() {
+                DynamicImage::ImageRgba16(image.to_rgba16())
             } else {
                 image.clone()
             };
 
+            let mut encoder = PngEncoder::new(&mut cursor);
+            if let Some(icc) = icc_profile {
+                encoder
+                    .set_icc_profile(icc.to_vec())
+                    .map_err(|e| e.to_string())?;
+            }
             image_to_encode
-                .write_to(&mut cursor, image::ImageFormat::Png)
+                .write_with_encoder(encoder)
                 .map_err(|e| e.to_string())?;
         }
         "tiff" => {
-            DynamicImage::ImageRgb16(image.to_rgb16())
-                .write_to(&mut cursor, image::ImageFormat::Tiff)
+            let mut encoder = TiffEncoder::new(&mut cursor);
+            if let Some(icc) = icc_profile
+                && let Err(e) = encoder.set_icc_profile(icc.to_vec())
+            {
+                log::warn!("Could not embed ICC profile in TIFF: {}", e);
+            }
+            let image_to_encode = if image.color().has_alpha() {
+                DynamicImage::ImageRgba16(image.to_rgba16())
+            } else {
+                DynamicImage::ImageRgb16(image.to_rgb16())
+            };
+            image_to_encode
+                .write_with_encoder(encoder)
                 .map_err(|e| e.to_string())?;
         }
         "avif" => {
@@ -529,6 +834,12 @@ fn encode_image_to_bytes(
                 .write_to(&mut cursor, image::ImageFormat::Avif)
                 .map_err(|e| e.to_string())?;
         }
+        "dng" => {
+            return dng_writer::encode_linear_dng(
+                image,
+                &exif_processing::DngSourceInfo::default(),
+            );
+        }
         _ => return Err(format!("Unsupported file format: {}", output_format)),
     };
     Ok(image_bytes)
@@ -541,7 +852,7 @@ fn export_masks_for_image(
     export_settings: &ExportSettings,
     output_path_obj: &std::path::Path,
     source_path_str: &str,
-    context: &Arc<GpuContext>,
+    context: Option<&GpuContext>,
     state: &tauri::State<AppState>,
     is_raw: bool,
     app_handle: &tauri::AppHandle,
@@ -572,8 +883,7 @@ fn export_masks_for_image(
     if !mask_bitmaps.is_empty() {
         let tm_override = resolve_tonemapper_override_from_handle(app_handle, is_raw);
         let all_adjustments = get_all_adjustments_from_json(js_adjustments, is_raw, tm_override);
-        let lut_path = js_adjustments["lutPath"].as_str();
-        let lut = lut_path.and_then(|p| get_or_load_lut(state, p).ok());
+        let lut = resolve_lut_for_adjustments(state, js_adjustments);
         let unique_hash = calculate_full_job_hash(source_path_str, js_adjustments);
         let output_dir = output_path_obj.parent().unwrap_or(output_path_obj);
         let stem = output_path_obj
@@ -618,11 +928,13 @@ fn export_masks_for_image(
                 output_dir.join(format!("{}_mask_{}_image.{}", stem, i, extension));
             let mask_alpha_path = output_dir.join(format!("{}_mask_{}_alpha.png", stem, i));
 
+            check_export_cancelled(state)?;
             save_image_with_metadata(
                 &with_options,
                 &mask_image_path,
                 source_path_str,
                 export_settings,
+                None,
             )?;
 
             if export_settings.preserve_timestamps {
@@ -653,7 +965,7 @@ fn export_masks_for_image(
 fn export_adjustments_as_lut(
     js_adjustments: &Value,
     source_path_str: &str,
-    context: &Arc<GpuContext>,
+    context: Option<&GpuContext>,
     state: &tauri::State<AppState>,
     app_handle: &tauri::AppHandle,
 ) -> Result<Vec<u8>, String> {
@@ -679,8 +991,7 @@ fn export_adjustments_as_lut(
     all_adjustments.global.chromatic_aberration_red_cyan = 0.0;
     all_adjustments.global.chromatic_aberration_blue_yellow = 0.0;
 
-    let lut_path = js_adjustments["lutPath"].as_str();
-    let lut = lut_path.and_then(|p| get_or_load_lut(state, p).ok());
+    let lut = resolve_lut_for_adjustments(state, js_adjustments);
     let unique_hash = calculate_full_job_hash(source_path_str, js_adjustments);
 
     let processed_lut = process_and_get_dynamic_image(
@@ -700,6 +1011,25 @@ fn export_adjustments_as_lut(
     convert_image_to_cube_lut(&processed_lut, lut_size)
 }
 
+/// Whether a previous export at `output_path` can be kept when resuming a batch. With
+/// `require_newer` the output must also be newer than the source and its sidecar.
+fn is_existing_export_current(
+    output_path: &Path,
+    source_path: &Path,
+    sidecar_path: &Path,
+    require_newer: bool,
+) -> bool {
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    let Some(output_modified) = modified(output_path) else {
+        return false;
+    };
+    !require_newer
+        || [source_path, sidecar_path]
+            .into_iter()
+            .filter_map(modified)
+            .all(|m| m <= output_modified)
+}
+
 struct ExportHandleGuard {
     app_handle: tauri::AppHandle,
 }
@@ -736,9 +1066,16 @@ pub async fn export_images(
     if state.export_task_handle.lock().unwrap().is_some() {
         return Err("An export is already in progress.".to_string());
     }
+    if let Some(error) =
+        exif_processing::metadata_stamp_error(&output_format, &export_settings.metadata_stamp())
+    {
+        return Err(error);
+    }
 
-    let context = get_or_init_gpu_context(&state, &app_handle)?;
-    let context = Arc::new(context);
+    state
+        .export_cancellation_token
+        .store(false, Ordering::SeqCst);
+    let context = get_gpu_context_or_fallback(&state, &app_handle);
     let progress_counter = Arc::new(AtomicUsize::new(0));
 
     let available_cores = std::thread::available_parallelism()
@@ -772,6 +1109,7 @@ pub async fn export_images(
         let output_folder_path = std::path::Path::new(&output_folder_or_file);
         let total_paths = paths.len();
         let settings = load_settings(app_handle.clone()).unwrap_or_default();
+        let render_cache = Arc::new(RenderCache::open(&app_handle, &settings));
 
         let mut base_path_counts: HashMap<String, usize> = HashMap::new();
         let mut export_items = Vec::with_capacity(total_paths);
@@ -811,7 +1149,7 @@ pub async fn export_images(
             let permit = semaphore.clone().acquire_owned().await.unwrap();
 
             let app_handle_clone = app_handle.clone();
-            let context_clone = Arc::clone(&context);
+            let context_clone = context.clone();
             let progress_counter_clone = Arc::clone(&progress_counter);
             let output_folder_path = output_folder_path.to_path_buf();
             let base_origin_folders = base_origin_folders.clone();
@@ -820,6 +1158,8 @@ pub async fn export_images(
             let current_edit_path = current_edit_path.clone();
             let current_edit_adjustments = current_edit_adjustments.clone();
             let settings = settings.clone();
+            let render_cache = Arc::clone(&render_cache);
+            let summary_path = image_path_str.clone();
 
             let handle = tokio::task::spawn_blocking(move || {
                 if app_handle_clone
@@ -861,6 +1201,7 @@ pub async fn export_images(
                     global_index + 1,
                     total_paths,
                     &file_date,
+                    exif_processing::read_rrexif_sidecar(original_path).as_ref(),
                 );
 
                 if let Some(vc_id) = explicit_vc {
@@ -890,13 +1231,24 @@ pub async fn export_images(
                 };
 
                 let extension = output_format.to_lowercase();
+                let skipped = export_settings.skip_existing
+                    && is_existing_export_current(
+                        &output_path,
+                        &source_path,
+                        &sidecar_path,
+                        export_settings.skip_existing_only_if_newer,
+                    );
 
                 let result: Result<(), String> = (|| {
+                    if skipped {
+                        return Ok(());
+                    }
+
                     if extension == "cube" {
                         let cube_bytes = export_adjustments_as_lut(
                             &js_adjustments,
                             &source_path_str,
-                            &context_clone,
+                            context_clone.as_ref(),
                             &state,
                             &app_handle_clone,
                         )?;
@@ -917,51 +1269,55 @@ pub async fn export_images(
                         return Ok(());
                     }
 
-                    let base_image = if is_current_edit {
-                        match crate::get_original_image(&state) {
-                            Ok((orig_data_arc, _)) => {
-                                composite_patches_on_image(&orig_data_arc, &js_adjustments)
-                                    .map_err(|e| format!("Failed to composite AI patches: {}", e))?
+                    let load_base_image = || -> Result<DynamicImage, String> {
+                        Ok(if is_current_edit {
+                            match crate::get_original_image(&state) {
+                                Ok((orig_data_arc, _)) => {
+                                    composite_patches_on_image(&orig_data_arc, &js_adjustments)
+                                        .map_err(|e| {
+                                            format!("Failed to composite AI patches: {}", e)
+                                        })?
+                                }
+                                Err(_) => {
+                                    let bytes =
+                                        fs::read(&source_path_str).map_err(|e| e.to_string())?;
+                                    load_and_composite(
+                                        &bytes,
+                                        &source_path_str,
+                                        &js_adjustments,
+                                        false,
+                                        &settings,
+                                        None,
+                                    )
+                                    .map_err(|e| format!("Failed to load fallback image: {}", e))?
+                                }
                             }
-                            Err(_) => {
-                                let bytes =
-                                    fs::read(&source_path_str).map_err(|e| e.to_string())?;
-                                load_and_composite(
-                                    &bytes,
+                        } else {
+                            match read_file_mapped(Path::new(&source_path_str)) {
+                                Ok(mmap) => load_and_composite(
+                                    &mmap,
                                     &source_path_str,
                                     &js_adjustments,
                                     false,
                                     &settings,
                                     None,
                                 )
-                                .map_err(|e| format!("Failed to load fallback image: {}", e))?
+                                .map_err(|e| format!("Failed to load from mmap: {}", e))?,
+                                Err(_) => {
+                                    let bytes =
+                                        fs::read(&source_path_str).map_err(|e| e.to_string())?;
+                                    load_and_composite(
+                                        &bytes,
+                                        &source_path_str,
+                                        &js_adjustments,
+                                        false,
+                                        &settings,
+                                        None,
+                                    )
+                                    .map_err(|e| format!("Failed to load from bytes: {}", e))?
+                                }
                             }
-                        }
-                    } else {
-                        match read_file_mapped(Path::new(&source_path_str)) {
-                            Ok(mmap) => load_and_composite(
-                                &mmap,
-                                &source_path_str,
-                                &js_adjustments,
-                                false,
-                                &settings,
-                                None,
-                            )
-                            .map_err(|e| format!("Failed to load from mmap: {}", e))?,
-                            Err(_) => {
-                                let bytes =
-                                    fs::read(&source_path_str).map_err(|e| e.to_string())?;
-                                load_and_composite(
-                                    &bytes,
-                                    &source_path_str,
-                                    &js_adjustments,
-                                    false,
-                                    &settings,
-                                    None,
-                                )
-                                .map_err(|e| format!("Failed to load from bytes: {}", e))?
-                            }
-                        }
+                        })
                     };
 
                     let mut main_export_adjustments = js_adjustments.clone();
@@ -971,35 +1327,99 @@ pub async fn export_images(
                         obj.insert("masks".to_string(), serde_json::json!([]));
                     }
 
-                    let final_image = process_image_for_export(
-                        &source_path_str,
-                        &base_image,
-                        &main_export_adjustments,
-                        &export_settings,
-                        &context_clone,
-                        &state,
-                        is_raw,
-                        &app_handle_clone,
-                    )?;
+                    let cache_key = render_cache.as_ref().as_ref().map(|_| {
+                        RenderCache::key(
+                            &source_path_str,
+                            &main_export_adjustments,
+                            &settings,
+                            context_clone.is_some(),
+                        )
+                    });
+                    let cached_render = render_cache
+                        .as_ref()
+                        .as_ref()
+                        .zip(cache_key.as_deref())
+                        .and_then(|(cache, key)| cache.get(key));
+
+                    let mut base_image = None;
+                    let processed_image = match cached_render {
+                        Some(cached) => {
+                            log::info!("Using cached render for {}", source_path_str);
+                            cached
+                        }
+                        None => {
+                            let base = base_image.insert(load_base_image()?);
+                            let processed = process_image_for_export_pipeline(
+                                &source_path_str,
+                                base,
+                                &main_export_adjustments,
+                                context_clone.as_ref(),
+                                &state,
+                                is_raw,
+                                "process_image_for_export",
+                                &app_handle_clone,
+                                &|stage, percent| {
+                                    check_export_cancelled(&state)?;
+                                    emit_export_progress(
+                                        &app_handle_clone,
+                                        &source_path_str,
+                                        stage,
+                                        percent,
+                                    );
+                                    Ok(())
+                                },
+                                None,
+                            )?;
+                            if let (Some(cache), Some(key)) = (render_cache.as_ref(), &cache_key) {
+                                cache.put(key, &processed);
+                            }
+                            processed
+                        }
+                    };
+                    if base_image.is_none()
+                        && (export_settings.write_xmp_sidecar || export_settings.export_masks)
+                    {
+                        base_image = Some(load_base_image()?);
+                    }
+                    let final_image =
+                        apply_export_resize_and_watermark(processed_image, &export_settings)?;
                     save_image_with_metadata(
                         &final_image,
                         &output_path,
                         &source_path_str,
                         &export_settings,
+                        Some(&app_handle_clone),
                     )?;
 
                     if export_settings.preserve_timestamps {
                         set_timestamps_from_exif(Path::new(&source_path_str), &output_path);
                     }
 
-                    if export_settings.export_masks {
+                    if export_settings.write_xmp_sidecar {
+                        let mut metadata = exif_processing::load_sidecar(&sidecar_path);
+                        metadata.adjustments = js_adjustments.clone();
+                        let dims = base_image.as_ref().map(|b| (b.width(), b.height()));
+                        if let Err(e) =
+                            crate::xmp_sidecar::write_xmp_for_source(&source_path, &metadata, dims)
+                        {
+                            log::warn!(
+                                "Failed to write XMP sidecar for {}: {}",
+                                source_path_str,
+                                e
+                            );
+                        }
+                    }
+
+                    if export_settings.export_masks
+                        && let Some(base_image) = &base_image
+                    {
                         export_masks_for_image(
-                            &base_image,
+                            base_image,
                             &js_adjustments,
                             &export_settings,
                             &output_path,
                             &source_path_str,
-                            &context_clone,
+                            context_clone.as_ref(),
                             &state,
                             is_raw,
                             &app_handle_clone,
@@ -1009,6 +1429,17 @@ pub async fn export_images(
                     Ok(())
                 })();
 
+                let _ = app_handle_clone.emit(
+                    "batch-export-file-done",
+                    serde_json::json!({
+                        "path": &image_path_str,
+                        "outputPath": output_path.to_string_lossy(),
+                        "success": result.is_ok(),
+                        "skipped": skipped,
+                        "error": result.as_ref().err(),
+                    }),
+                );
+
                 let current_progress = progress_counter_clone.fetch_add(1, Ordering::SeqCst) + 1;
                 let _ = app_handle_clone.emit(
                     "batch-export-progress",
@@ -1020,44 +1451,58 @@ pub async fn export_images(
                 );
 
                 drop(permit);
-                result
+                result.map(|()| skipped)
             });
 
-            join_handles.push(handle);
+            join_handles.push((summary_path, handle));
         }
 
         let mut results = Vec::new();
-        for handle in join_handles {
+        for (path, handle) in join_handles {
             match handle.await {
-                Ok(res) => results.push(res),
-                Err(e) => results.push(Err(format!("Thread crashed: {}", e))),
+                Ok(res) => results.push((path, res)),
+                Err(e) => results.push((path, Err(format!("Thread crashed: {}", e)))),
             }
         }
 
         tokio::time::sleep(std::time::Duration::from_millis(150)).await;
 
-        let mut error_count = 0;
-        for result in results {
-            if let Err(e) = result {
-                error_count += 1;
-                log::error!("Export error: {}", e);
-                if total_paths == 1 {
-                    let _ = app_handle.emit("export-error", e);
+        let mut failed_paths = Vec::new();
+        let mut skipped_count = 0;
+        for (path, result) in results {
+            match result {
+                Ok(true) => skipped_count += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    log::error!("Export error for {}: {}", path, e);
+                    failed_paths.push(path);
+                    if total_paths == 1 {
+                        let _ = app_handle.emit("export-error", e);
+                    }
                 }
             }
         }
+        let error_count = failed_paths.len();
 
         if error_count > 0 && total_paths > 1 {
             let _ = app_handle.emit(
                 "export-complete-with-errors",
-                serde_json::json!({ "errors": error_count, "total": total_paths }),
+                serde_json::json!({
+                    "errors": error_count,
+                    "total": total_paths,
+                    "skipped": skipped_count,
+                    "failedPaths": failed_paths,
+                }),
             );
         } else if error_count == 0 {
             let _ = app_handle.emit(
                 "batch-export-progress",
                 serde_json::json!({ "current": total_paths, "total": total_paths, "path": "" }),
             );
-            let _ = app_handle.emit("export-complete", ());
+            let _ = app_handle.emit(
+                "export-complete",
+                serde_json::json!({ "skipped": skipped_count }),
+            );
         }
     });
 
@@ -1067,6 +1512,9 @@ pub async fn export_images(
 
 #[tauri::command]
 pub fn cancel_export(state: tauri::State<AppState>) -> Result<(), String> {
+    state
+        .export_cancellation_token
+        .store(true, Ordering::SeqCst);
     match state.export_task_handle.lock().unwrap().take() {
         Some(handle) => {
             handle.abort();
@@ -1101,7 +1549,7 @@ pub async fn estimate_export_sizes(
     let (source_path, sidecar_path) = parse_virtual_path(first_path);
     let source_path_str = source_path.to_string_lossy().to_string();
 
-    let context = get_or_init_gpu_context(&state, &app_handle)?;
+    let context = get_gpu_context_or_fallback(&state, &app_handle);
     let is_current_edit = Some(&source_path_str) == current_edit_path.as_ref();
     let is_raw = is_raw_file(&source_path_str);
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
@@ -1176,15 +1624,16 @@ pub async fn estimate_export_sizes(
         let mut all_adjustments =
             get_all_adjustments_from_json(&adjustments_clone, is_raw, tm_override);
         all_adjustments.global.show_clipping = 0;
+        all_adjustments
+            .global
+            .set_grain_frame(scale, unscaled_crop_offset);
 
-        let lut = adjustments_clone["lutPath"]
-            .as_str()
-            .and_then(|p| get_or_load_lut(&state, p).ok());
+        let lut = resolve_lut_for_adjustments(&state, &adjustments_clone);
         let unique_hash =
             calculate_full_job_hash(&loaded_image.path, &adjustments_clone).wrapping_add(1);
 
         let processed_preview = process_and_get_dynamic_image(
-            &context,
+            context.as_ref(),
             &state,
             &preview_image,
             unique_hash,
@@ -1314,15 +1763,16 @@ pub async fn estimate_export_sizes(
         let mut all_adjustments =
             get_all_adjustments_from_json(&js_adjustments, is_raw, tm_override);
         all_adjustments.global.show_clipping = 0;
+        all_adjustments
+            .global
+            .set_grain_frame(total_scale, unscaled_crop_offset);
 
-        let lut = js_adjustments["lutPath"]
-            .as_str()
-            .and_then(|p| get_or_load_lut(&state, p).ok());
+        let lut = resolve_lut_for_adjustments(&state, &js_adjustments);
         let unique_hash =
             calculate_full_job_hash(&source_path_str, &js_adjustments).wrapping_add(1);
 
         let processed_preview = process_and_get_dynamic_image(
-            &context,
+            context.as_ref(),
             &state,
             &preview_base,
             unique_hash,
@@ -1364,3 +1814,126 @@ pub async fn estimate_export_sizes(
 
     Ok(single_image_extrapolated_size * paths.len())
 }
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedPreview {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractPreviewsResult {
+    pub written: Vec<String>,
+    pub skipped: Vec<SkippedPreview>,
+}
+
+fn create_unique_output_file(folder: &Path, stem: &str) -> Result<(PathBuf, fs::File), String> {
+    let mut counter = 0;
+    loop {
+        let file_name = if counter == 0 {
+            format!("{}.jpg", stem)
+        } else {
+            format!("{}_{}.jpg", stem, counter)
+        };
+        let candidate = folder.join(file_name);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(file) => return Ok((candidate, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => counter += 1,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+fn extract_preview_to_folder(path: &str, output_folder: &Path) -> Result<String, String> {
+    use std::io::Write;
+
+    let (source_path, _) = parse_virtual_path(path);
+    let source_path_str = source_path.to_string_lossy().to_string();
+    if !is_raw_file(&source_path_str) {
+        return Err("Not a raw file".to_string());
+    }
+
+    let bytes = read_file_mapped(&source_path).map_err(|e| e.to_string())?;
+    let stem = source_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| "Invalid file name".to_string())?;
+
+    if let Some(jpeg) = crate::image_loader::largest_embedded_jpeg_bytes(&bytes) {
+        let (output_path, mut file) = create_unique_output_file(output_folder, stem)?;
+        if let Err(e) = file.write_all(jpeg) {
+            drop(file);
+            let _ = fs::remove_file(&output_path);
+            return Err(e.to_string());
+        }
+        drop(file);
+
+        if let Some(orientation) = crate::image_loader::raw_exif_orientation(&bytes)
+            && orientation > 1
+            && let Err(e) =
+                exif_processing::write_embedded_orientation(&output_path, orientation as u16)
+        {
+            log::warn!(
+                "Failed to write orientation to preview of {}: {}",
+                source_path_str,
+                e
+            );
+        }
+        return Ok(output_path.to_string_lossy().into_owned());
+    }
+
+    let preview = crate::image_loader::extract_embedded_preview(&bytes, &source_path_str, 0)
+        .ok_or_else(|| "No embedded preview found".to_string())?;
+    let (output_path, file) = create_unique_output_file(output_folder, stem)?;
+    let result = JpegEncoder::new_with_quality(std::io::BufWriter::new(file), 95)
+        .encode_image(&DynamicImage::ImageRgb8(preview.to_rgb8()))
+        .map_err(|e| e.to_string());
+    if let Err(e) = result {
+        let _ = fs::remove_file(&output_path);
+        return Err(e);
+    }
+    Ok(output_path.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+pub async fn extract_previews(
+    paths: Vec<String>,
+    output_folder: String,
+) -> Result<ExtractPreviewsResult, String> {
+    let output_folder = PathBuf::from(output_folder);
+    fs::create_dir_all(&output_folder).map_err(|e| e.to_string())?;
+
+    tokio::task::spawn_blocking(move || {
+        use rayon::prelude::*;
+
+        let outcomes: Vec<(String, Result<String, String>)> = paths
+            .par_iter()
+            .map(|path| {
+                (
+                    path.clone(),
+                    extract_preview_to_folder(path, &output_folder),
+                )
+            })
+            .collect();
+
+        let mut result = ExtractPreviewsResult {
+            written: Vec::new(),
+            skipped: Vec::new(),
+        };
+        for (path, outcome) in outcomes {
+            match outcome {
+                Ok(output_path) => result.written.push(output_path),
+                Err(reason) => result.skipped.push(SkippedPreview { path, reason }),
+            }
+        }
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
@@ -1,14 +1,12 @@
 use memmap2::{Mmap, MmapOptions};
 use std::borrow::Cow;
-use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fs;
-use std::hash::{Hash, Hasher};
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
 
 use anyhow::Result;
@@ -28,16 +26,17 @@ use crate::PendingMetadata;
 #[cfg(target_os = "android")]
 use crate::android_integration::*;
 use crate::app_settings::*;
-use crate::cache_utils::calculate_geometry_hash;
+use crate::cache_utils::{calculate_full_job_hash, calculate_geometry_hash};
 use crate::exif_processing;
 use crate::formats::{is_raw_file, is_supported_image_file};
 use crate::gpu_processing;
 use crate::image_loader;
 use crate::image_processing::GpuContext;
 use crate::image_processing::{
-    Crop, ImageMetadata, apply_coarse_rotation, apply_cpu_default_raw_processing, apply_crop,
-    apply_flip, apply_geometry_warp, apply_rotation, auto_results_to_json,
-    get_all_adjustments_from_json, perform_auto_analysis,
+    AutoAdjustOptions, Crop, EditHistory, EditSnapshot, ImageMetadata, LabStatistics,
+    ReferenceMatchDelta, apply_coarse_rotation, apply_cpu_default_raw_processing, apply_crop,
+    apply_flip, apply_geometry_warp, apply_rotation, auto_results_to_json, compute_lab_statistics,
+    get_all_adjustments_from_json, match_lab_statistics, perform_auto_analysis_with_options,
 };
 use crate::mask_generation::MaskDefinition;
 use crate::preset_converter;
@@ -62,21 +61,24 @@ fn emit_thumbnail_cache_setup_error(app_handle: &AppHandle, path: &str, reason:
     );
 }
 
+/// Thumbnails are keyed on the source file's size and modification time plus the sidecar's
+/// adjustments, so an external edit to either yields a new key instead of a stale hit.
 fn compute_thumbnail_cache_hash(path_str: &str, adjustments_bytes: &[u8]) -> Option<String> {
     let (source_path, _) = parse_virtual_path(path_str);
 
-    let img_mod_time = fs::metadata(&source_path)
-        .ok()?
+    let source_meta = fs::metadata(&source_path).ok()?;
+    let img_mod_time = source_meta
         .modified()
         .ok()?
         .duration_since(std::time::UNIX_EPOCH)
         .ok()?
-        .as_secs();
+        .as_nanos();
 
     let mut hasher = blake3::Hasher::new();
     hasher.update(path_str.as_bytes());
     hasher.update(&img_mod_time.to_le_bytes());
-    hasher.update(adjustments_bytes);
+    hasher.update(&source_meta.len().to_le_bytes());
+    hasher.update(blake3::hash(adjustments_bytes).as_bytes());
     Some(hasher.finalize().to_hex().to_string())
 }
 
@@ -89,7 +91,7 @@ fn resolve_image_metadata(
     let mut metadata = crate::exif_processing::load_sidecar(sidecar_path);
 
     if enable_xmp_sync
-        && sync_metadata_from_xmp(image_path, &mut metadata)
+        && sync_metadata_from_xmp(image_path, sidecar_path, &mut metadata, false)
         && let Ok(json) = serde_json::to_string_pretty(&metadata)
     {
         let _ = fs::write(sidecar_path, json);
@@ -261,6 +263,40 @@ pub struct ImageFile {
     exif: Option<HashMap<String, String>>,
     is_virtual_copy: bool,
     is_cloud_placeholder: bool,
+    color_label: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageListFilter {
+    pub min_rating: Option<u8>,
+    pub color_labels: Option<Vec<String>>,
+    pub has_edits: Option<bool>,
+}
+
+impl ImageListFilter {
+    fn matches(&self, is_edited: bool, rating: u8, color_label: Option<&str>) -> bool {
+        if self.min_rating.is_some_and(|min| rating < min) {
+            return false;
+        }
+        if self.has_edits.is_some_and(|wanted| wanted != is_edited) {
+            return false;
+        }
+        match &self.color_labels {
+            Some(labels) if !labels.is_empty() => {
+                let label = color_label.unwrap_or("none");
+                labels.iter().any(|l| l.eq_ignore_ascii_case(label))
+            }
+            _ => true,
+        }
+    }
+}
+
+fn color_label_from_tags(tags: Option<&[String]>) -> Option<String> {
+    tags?
+        .iter()
+        .find_map(|t| t.strip_prefix(COLOR_TAG_PREFIX))
+        .map(str::to_string)
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -304,34 +340,53 @@ pub fn parse_virtual_path(virtual_path: &str) -> (PathBuf, PathBuf) {
     (source_path, sidecar_path)
 }
 
+pub fn read_exif_for_path(virtual_path: &str) -> HashMap<String, String> {
+    let (source_path, _) = parse_virtual_path(virtual_path);
+    let source_path_str = source_path.to_string_lossy().to_string();
+
+    if let Some(sidecar_exif) = crate::exif_processing::read_rrexif_sidecar(&source_path) {
+        sidecar_exif
+    } else if is_cloud_placeholder(&source_path) {
+        HashMap::new()
+    } else if let Ok(mmap) = read_file_mapped(&source_path) {
+        crate::exif_processing::read_exif_data(&source_path_str, &mmap)
+    } else if let Ok(bytes) = fs::read(&source_path) {
+        crate::exif_processing::read_exif_data(&source_path_str, &bytes)
+    } else {
+        HashMap::new()
+    }
+}
+
 #[tauri::command]
 pub async fn read_exif_for_paths(
     paths: Vec<String>,
+    app_handle: AppHandle,
 ) -> Result<HashMap<String, HashMap<String, String>>, String> {
+    let lens_db = app_handle
+        .state::<AppState>()
+        .lens_db
+        .lock()
+        .unwrap()
+        .clone();
+
     tauri::async_runtime::spawn_blocking(move || {
         let exif_data: HashMap<String, HashMap<String, String>> = paths
             .par_iter()
             .filter_map(|virtual_path| {
-                let (source_path, _) = parse_virtual_path(virtual_path);
-                let source_path_str = source_path.to_string_lossy().to_string();
-
-                let map = if let Some(sidecar_exif) =
-                    crate::exif_processing::read_rrexif_sidecar(&source_path)
-                {
-                    sidecar_exif
-                } else if is_cloud_placeholder(&source_path) {
-                    HashMap::new()
-                } else if let Ok(mmap) = read_file_mapped(&source_path) {
-                    crate::exif_processing::read_exif_data(&source_path_str, &mmap)
-                } else if let Ok(bytes) = fs::read(&source_path) {
-                    crate::exif_processing::read_exif_data(&source_path_str, &bytes)
-                } else {
-                    HashMap::new()
-                };
+                let mut map = read_exif_for_path(virtual_path);
 
                 if map.is_empty() {
                     None
                 } else {
+                    let crop_factor = lens_db.as_deref().and_then(|db| {
+                        crate::lens_correction::find_camera_crop_factor(
+                            db,
+                            map.get("Make").map_or("", String::as_str),
+                            map.get("Model").map_or("", String::as_str),
+                        )
+                    });
+                    let computed = crate::exif_processing::computed_exif_fields(&map, crop_factor);
+                    map.extend(computed);
                     Some((virtual_path.clone(), map))
                 }
             })
@@ -389,7 +444,11 @@ pub async fn update_exif_fields(
 }
 
 #[tauri::command]
-pub fn list_images_in_dir(path: String, app_handle: AppHandle) -> Result<Vec<ImageFile>, String> {
+pub fn list_images_in_dir(
+    path: String,
+    filter: Option<ImageListFilter>,
+    app_handle: AppHandle,
+) -> Result<Vec<ImageFile>, String> {
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
     let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
 
@@ -469,20 +528,30 @@ pub fn list_images_in_dir(path: String, app_handle: AppHandle) -> Result<Vec<Ima
                     && resolve_xmp_path(&path_buf)
                         .is_some_and(|p| crate::file_management::is_cloud_placeholder(&p));
 
-                let (is_edited, tags, rating) =
-                    if crate::file_management::is_cloud_placeholder(&sidecar_path)
-                        || xmp_is_placeholder
-                    {
-                        enqueue_metadata(
-                            &app_handle,
-                            virtual_path.clone(),
-                            path_buf.clone(),
-                            sidecar_path.clone(),
-                        );
-                        (false, None, 0)
-                    } else {
-                        resolve_image_metadata(&path_buf, &sidecar_path, enable_xmp_sync, &settings)
-                    };
+                let metadata_pending = crate::file_management::is_cloud_placeholder(&sidecar_path)
+                    || xmp_is_placeholder;
+
+                let (is_edited, tags, rating) = if metadata_pending {
+                    enqueue_metadata(
+                        &app_handle,
+                        virtual_path.clone(),
+                        path_buf.clone(),
+                        sidecar_path.clone(),
+                    );
+                    (false, None, 0)
+                } else {
+                    resolve_image_metadata(&path_buf, &sidecar_path, enable_xmp_sync, &settings)
+                };
+
+                let color_label = color_label_from_tags(tags.as_deref());
+                // Images whose metadata is still being hydrated are kept so they are not
+                // hidden for good once their real rating and label arrive.
+                if !metadata_pending
+                    && let Some(filter) = &filter
+                    && !filter.matches(is_edited, rating, color_label.as_deref())
+                {
+                    continue;
+                }
 
                 file_results.push(ImageFile {
                     path: virtual_path,
@@ -493,6 +562,7 @@ pub fn list_images_in_dir(path: String, app_handle: AppHandle) -> Result<Vec<Ima
                     is_virtual_copy,
                     rating,
                     is_cloud_placeholder,
+                    color_label,
                 });
             }
 
@@ -506,6 +576,7 @@ pub fn list_images_in_dir(path: String, app_handle: AppHandle) -> Result<Vec<Ima
 #[tauri::command]
 pub fn list_images_recursive(
     path: String,
+    filter: Option<ImageListFilter>,
     app_handle: AppHandle,
 ) -> Result<Vec<ImageFile>, String> {
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
@@ -593,20 +664,30 @@ pub fn list_images_recursive(
                     && resolve_xmp_path(&path_buf)
                         .is_some_and(|p| crate::file_management::is_cloud_placeholder(&p));
 
-                let (is_edited, tags, rating) =
-                    if crate::file_management::is_cloud_placeholder(&sidecar_path)
-                        || xmp_is_placeholder
-                    {
-                        enqueue_metadata(
-                            &app_handle,
-                            virtual_path.clone(),
-                            path_buf.clone(),
-                            sidecar_path.clone(),
-                        );
-                        (false, None, 0)
-                    } else {
-                        resolve_image_metadata(&path_buf, &sidecar_path, enable_xmp_sync, &settings)
-                    };
+                let metadata_pending = crate::file_management::is_cloud_placeholder(&sidecar_path)
+                    || xmp_is_placeholder;
+
+                let (is_edited, tags, rating) = if metadata_pending {
+                    enqueue_metadata(
+                        &app_handle,
+                        virtual_path.clone(),
+                        path_buf.clone(),
+                        sidecar_path.clone(),
+                    );
+                    (false, None, 0)
+                } else {
+                    resolve_image_metadata(&path_buf, &sidecar_path, enable_xmp_sync, &settings)
+                };
+
+                let color_label = color_label_from_tags(tags.as_deref());
+                // Images whose metadata is still being hydrated are kept so they are not
+                // hidden for good once their real rating and label arrive.
+                if !metadata_pending
+                    && let Some(filter) = &filter
+                    && !filter.matches(is_edited, rating, color_label.as_deref())
+                {
+                    continue;
+                }
 
                 file_results.push(ImageFile {
                     path: virtual_path,
@@ -617,6 +698,7 @@ pub fn list_images_recursive(
                     is_virtual_copy,
                     rating,
                     is_cloud_placeholder,
+                    color_label,
                 });
             }
 
@@ -872,6 +954,8 @@ pub fn get_album_images(
                 resolve_image_metadata(&source_path, &sidecar_path, enable_xmp_sync, &settings)
             };
 
+            let color_label = color_label_from_tags(tags.as_deref());
+
             Some(ImageFile {
                 path: virtual_path,
                 modified,
@@ -881,6 +965,7 @@ pub fn get_album_images(
                 is_virtual_copy,
                 rating,
                 is_cloud_placeholder,
+                color_label,
             })
         })
         .collect();
@@ -1205,11 +1290,13 @@ pub fn generate_thumbnail_data(
     path_str: &str,
     gpu_context: Option<&GpuContext>,
     preloaded_image: Option<&DynamicImage>,
+    target_res: Option<u32>,
     app_handle: &AppHandle,
 ) -> anyhow::Result<DynamicImage> {
     let (source_path, sidecar_path) = parse_virtual_path(path_str);
     let source_path_str = source_path.to_string_lossy().to_string();
     let is_raw = is_raw_file(&source_path_str);
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
 
     let metadata: Option<ImageMetadata> = if is_cloud_placeholder(&sidecar_path) {
         enqueue_metadata(
@@ -1219,22 +1306,25 @@ pub fn generate_thumbnail_data(
             sidecar_path.clone(),
         );
         None
-    } else {
+    } else if thumbnails_show_edits(&settings) {
         fs::read_to_string(&sidecar_path)
             .ok()
             .and_then(|content| serde_json::from_str(&content).ok())
+    } else {
+        None
     };
 
     let adjustments = metadata
         .as_ref()
         .map_or(serde_json::Value::Null, |m| m.adjustments.clone());
 
-    if let (Some(context), Some(meta)) = (gpu_context, metadata)
+    // Edited images go through the same pipeline as the editor, on the CPU when no GPU is
+    // available, so the grid matches the loaded preview.
+    if let Some(meta) = metadata
         && !meta.adjustments.is_null()
     {
         let state = app_handle.state::<AppState>();
-        let settings = load_settings(app_handle.clone()).unwrap_or_default();
-        let target_res = settings.thumbnail_resolution.unwrap_or(720);
+        let target_res = target_res.unwrap_or_else(|| settings.thumbnail_resolution.unwrap_or(720));
 
         let geometry_hash = calculate_geometry_hash(&meta.adjustments);
 
@@ -1268,7 +1358,6 @@ pub fn generate_thumbnail_data(
         let (processing_base, total_scale) = if let Some(hit) = cached_base {
             hit
         } else {
-            let settings = load_settings(app_handle.clone()).unwrap_or_default();
             let mut raw_scale_factor = 1.0f32;
 
             let composite_image = if let Some(img) = preloaded_image {
@@ -1419,47 +1508,49 @@ pub fn generate_thumbnail_data(
             .collect();
 
         let tm_override = crate::image_processing::resolve_tonemapper_override(&settings, is_raw);
-        let gpu_adjustments = get_all_adjustments_from_json(&meta.adjustments, is_raw, tm_override);
-        let lut_path = meta.adjustments["lutPath"].as_str();
-        let lut = lut_path.and_then(|p| {
-            let mut cache = state.lut_cache.lock().unwrap();
-            if let Some(cached_lut) = cache.get(p) {
-                return Some(cached_lut.clone());
-            }
-            if let Ok(loaded_lut) = crate::lut_processing::parse_lut_file(p) {
-                let arc_lut = Arc::new(loaded_lut);
-                cache.insert(p.to_string(), arc_lut.clone());
-                return Some(arc_lut);
-            }
-            None
-        });
+        let mut gpu_adjustments =
+            get_all_adjustments_from_json(&meta.adjustments, is_raw, tm_override);
+        gpu_adjustments
+            .global
+            .set_grain_frame(total_scale, unscaled_crop_offset);
+        let lut = crate::lut_processing::resolve_lut_for_adjustments(&state, &meta.adjustments);
 
-        let mut hasher = DefaultHasher::new();
-        path_str.hash(&mut hasher);
-        meta.adjustments.to_string().hash(&mut hasher);
-        let unique_hash = hasher.finish();
+        let unique_hash = calculate_full_job_hash(path_str, &meta.adjustments);
 
-        if let Ok(processed_image) = gpu_processing::process_and_get_dynamic_image(
-            context,
+        let processed = gpu_processing::process_and_get_dynamic_image(
+            gpu_context,
             &state,
             cropped_preview.as_ref(),
             unique_hash,
             gpu_processing::RenderRequest {
                 adjustments: gpu_adjustments,
                 mask_bitmaps: &mask_bitmaps,
-                lut,
+                lut: lut.clone(),
                 roi: None,
             },
             "generate_thumbnail_data",
-        ) {
-            return Ok(processed_image);
-        } else {
-            return Ok(cropped_preview.into_owned());
-        }
+        );
+        return Ok(match processed {
+            Ok(processed_image) => processed_image,
+            Err(e) => {
+                log::warn!(
+                    "GPU thumbnail render failed for {}, using CPU: {}",
+                    path_str,
+                    e
+                );
+                crate::cpu_processing::process_image_cpu(
+                    cropped_preview.as_ref(),
+                    &gpu_processing::RenderRequest {
+                        adjustments: gpu_adjustments,
+                        mask_bitmaps: &mask_bitmaps,
+                        lut,
+                        roi: None,
+                    },
+                )
+            }
+        });
     }
 
-    let settings = load_settings(app_handle.clone()).unwrap_or_default();
-
     let mut final_image = if let Some(img) = preloaded_image {
         image_loader::composite_patches_on_image(img, &adjustments)?
     } else {
@@ -1510,12 +1601,94 @@ pub fn generate_thumbnail_data(
     Ok(apply_coarse_rotation(Cow::Owned(final_image), fallback_orientation_steps).into_owned())
 }
 
-fn encode_thumbnail(image: &DynamicImage, target_width: u32) -> Result<Vec<u8>> {
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ThumbnailFormat {
+    Jpeg,
+    WebP,
+}
+
+impl ThumbnailFormat {
+    fn from_settings(settings: &AppSettings) -> Self {
+        match settings.thumbnail_format.as_deref() {
+            Some("webp") => ThumbnailFormat::WebP,
+            _ => ThumbnailFormat::Jpeg,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpg",
+            ThumbnailFormat::WebP => "webp",
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            ThumbnailFormat::Jpeg => ThumbnailFormat::WebP,
+            ThumbnailFormat::WebP => ThumbnailFormat::Jpeg,
+        }
+    }
+}
+
+fn thumbnail_cache_path(thumb_cache_dir: &Path, stem: &str, format: ThumbnailFormat) -> PathBuf {
+    thumb_cache_dir.join(format!("{}.{}", stem, format.extension()))
+}
+
+/// Finds a cached thumbnail in either format, preferring the configured one, so switching
+/// the setting does not throw away an existing cache.
+fn find_cached_thumbnail(
+    thumb_cache_dir: &Path,
+    stem: &str,
+    preferred: ThumbnailFormat,
+) -> Option<PathBuf> {
+    [preferred, preferred.other()]
+        .into_iter()
+        .map(|format| thumbnail_cache_path(thumb_cache_dir, stem, format))
+        .find(|path| path.exists())
+}
+
+fn encode_thumbnail_rgb(thumbnail: &DynamicImage, format: ThumbnailFormat) -> Result<Vec<u8>> {
+    let rgb = thumbnail.to_rgb8();
+    match format {
+        ThumbnailFormat::Jpeg => {
+            let mut buf = Cursor::new(Vec::new());
+            let mut encoder = JpegEncoder::new_with_quality(&mut buf, 75);
+            encoder.encode_image(&rgb)?;
+            Ok(buf.into_inner())
+        }
+        ThumbnailFormat::WebP => {
+            let encoder = webp::Encoder::from_rgb(rgb.as_raw(), rgb.width(), rgb.height());
+            Ok(encoder.encode(75.0).to_vec())
+        }
+    }
+}
+
+fn encode_thumbnail(
+    image: &DynamicImage,
+    target_width: u32,
+    format: ThumbnailFormat,
+) -> Result<Vec<u8>> {
     let thumbnail = crate::image_processing::downscale_f32_image(image, target_width, target_width);
-    let mut buf = Cursor::new(Vec::new());
-    let mut encoder = JpegEncoder::new_with_quality(&mut buf, 75);
-    encoder.encode_image(&thumbnail.to_rgb8())?;
-    Ok(buf.into_inner())
+    encode_thumbnail_rgb(&thumbnail, format)
+}
+
+fn encode_embedded_raw_thumbnail(
+    source_path: &Path,
+    target_width: u32,
+    format: ThumbnailFormat,
+) -> Option<Vec<u8>> {
+    let source_path_str = source_path.to_string_lossy();
+    let mmap = read_file_mapped(source_path).ok()?;
+    // Small previews are upscaled only slightly; tiny EXIF thumbnails are rejected.
+    let preview =
+        image_loader::extract_embedded_preview(&mmap, &source_path_str, target_width / 2)?;
+    let thumbnail = preview.thumbnail(target_width, target_width);
+    encode_thumbnail_rgb(&thumbnail, format).ok()
+}
+
+/// Whether thumbnails render the sidecar edits or the original with default processing.
+fn thumbnails_show_edits(settings: &AppSettings) -> bool {
+    settings.thumbnail_show_edits.unwrap_or(true)
 }
 
 fn generate_single_thumbnail_and_cache(
@@ -1542,10 +1715,15 @@ fn generate_single_thumbnail_and_cache(
             let is_raw = crate::formats::is_raw_file(path_str);
             let tm = crate::image_processing::resolve_tonemapper_override(settings, is_raw);
 
+            let adjustments_bytes = if thumbnails_show_edits(settings) {
+                serde_json::to_vec(&meta.adjustments).unwrap_or_default()
+            } else {
+                Vec::new()
+            };
             (
                 meta.rating,
                 crate::image_processing::is_image_edited(&meta.adjustments, is_raw, tm),
-                serde_json::to_vec(&meta.adjustments).unwrap_or_default(),
+                adjustments_bytes,
             )
         } else {
             (0, false, Vec::new())
@@ -1556,12 +1734,13 @@ fn generate_single_thumbnail_and_cache(
 
     let cache_hash = compute_thumbnail_cache_hash(path_str, &adjustments_bytes)?;
 
-    let cache_filename = format!("{}.jpg", cache_hash);
-    let cache_path = thumb_cache_dir.join(cache_filename);
-
-    if !force_regenerate && cache_path.exists() {
-        return Some((cache_path.to_string_lossy().into_owned(), rating, is_edited));
+    let format = ThumbnailFormat::from_settings(settings);
+    if !force_regenerate
+        && let Some(existing) = find_cached_thumbnail(thumb_cache_dir, &cache_hash, format)
+    {
+        return Some((existing.to_string_lossy().into_owned(), rating, is_edited));
     }
+    let cache_path = thumbnail_cache_path(thumb_cache_dir, &cache_hash, format);
 
     if is_cloud_placeholder(&source_path) {
         return None;
@@ -1569,9 +1748,24 @@ fn generate_single_thumbnail_and_cache(
 
     let target_width = settings.thumbnail_resolution.unwrap_or(720);
 
+    if preloaded_image.is_none()
+        && (!is_edited || !thumbnails_show_edits(settings))
+        && settings.use_embedded_raw_thumbnails.unwrap_or(true)
+        && crate::formats::is_raw_file(path_str)
+        && let Some(thumb_data) = encode_embedded_raw_thumbnail(&source_path, target_width, format)
+        && fs::write(&cache_path, &thumb_data).is_ok()
+    {
+        let cache_path_str = cache_path.to_string_lossy().into_owned();
+        if !settings.refine_embedded_raw_thumbnails.unwrap_or(false) {
+            return Some((cache_path_str, rating, is_edited));
+        }
+        // Show the embedded preview right away, then overwrite it with a real decode.
+        emit_thumbnail_generated(app_handle, path_str, &cache_path_str, rating, is_edited);
+    }
+
     if let Ok(thumb_image) =
-        generate_thumbnail_data(path_str, gpu_context, preloaded_image, app_handle)
-        && let Ok(thumb_data) = encode_thumbnail(&thumb_image, target_width)
+        generate_thumbnail_data(path_str, gpu_context, preloaded_image, None, app_handle)
+        && let Ok(thumb_data) = encode_thumbnail(&thumb_image, target_width, format)
     {
         let _ = fs::write(&cache_path, &thumb_data);
         return Some((cache_path.to_string_lossy().into_owned(), rating, is_edited));
@@ -1579,11 +1773,123 @@ fn generate_single_thumbnail_and_cache(
     None
 }
 
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailSizeEntry {
+    pub size: u32,
+    pub thumbnail_path: String,
+}
+
+/// Ensures a cached thumbnail exists for each of `sizes` (sorted largest first). Only the
+/// largest missing size is rendered; the smaller ones are downscaled from it.
+fn generate_thumbnail_sizes(
+    path_str: &str,
+    sizes: &[u32],
+    thumb_cache_dir: &Path,
+    settings: &AppSettings,
+    gpu_context: Option<&GpuContext>,
+    app_handle: &AppHandle,
+) -> Vec<ThumbnailSizeEntry> {
+    let format = ThumbnailFormat::from_settings(settings);
+    let Some(cache_hash) = get_cache_key_hash(path_str, thumbnails_show_edits(settings)) else {
+        return Vec::new();
+    };
+    let stem_for = |size: u32| format!("{}_{}", cache_hash, size);
+
+    let missing: Vec<u32> = sizes
+        .iter()
+        .copied()
+        .filter(|&size| find_cached_thumbnail(thumb_cache_dir, &stem_for(size), format).is_none())
+        .collect();
+
+    if let Some(&largest) = missing.first()
+        && !is_cloud_placeholder(&parse_virtual_path(path_str).0)
+    {
+        match generate_thumbnail_data(path_str, gpu_context, None, Some(largest), app_handle) {
+            Ok(image) => {
+                for &size in &missing {
+                    match encode_thumbnail(&image, size, format) {
+                        Ok(data) => {
+                            let cache_path =
+                                thumbnail_cache_path(thumb_cache_dir, &stem_for(size), format);
+                            if let Err(e) = fs::write(cache_path, data) {
+                                log::warn!(
+                                    "Failed to cache {}px thumbnail for {}: {}",
+                                    size,
+                                    path_str,
+                                    e
+                                );
+                            }
+                        }
+                        Err(e) => log::warn!(
+                            "Failed to encode {}px thumbnail for {}: {}",
+                            size,
+                            path_str,
+                            e
+                        ),
+                    }
+                }
+            }
+            Err(e) => log::warn!("Failed to generate thumbnail for {}: {}", path_str, e),
+        }
+    }
+
+    sizes
+        .iter()
+        .filter_map(|&size| {
+            let cache_path = find_cached_thumbnail(thumb_cache_dir, &stem_for(size), format)?;
+            Some(ThumbnailSizeEntry {
+                size,
+                thumbnail_path: cache_path.to_string_lossy().into_owned(),
+            })
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub async fn generate_thumbnails(
+    paths: Vec<String>,
+    sizes: Vec<u32>,
+    app_handle: AppHandle,
+) -> Result<HashMap<String, Vec<ThumbnailSizeEntry>>, String> {
+    let mut sizes: Vec<u32> = sizes.into_iter().filter(|&size| size > 0).collect();
+    sizes.sort_unstable_by(|a, b| b.cmp(a));
+    sizes.dedup();
+    if sizes.is_empty() {
+        return Err("At least one thumbnail size is required".to_string());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let thumb_cache_dir = resolve_thumbnail_cache_dir(&app_handle)?;
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
+        let state = app_handle.state::<AppState>();
+        let gpu_context = gpu_processing::get_or_init_gpu_context(&state, &app_handle).ok();
+
+        Ok(paths
+            .par_iter()
+            .map(|path_str| {
+                let entries = generate_thumbnail_sizes(
+                    path_str,
+                    &sizes,
+                    &thumb_cache_dir,
+                    &settings,
+                    gpu_context.as_ref(),
+                    &app_handle,
+                );
+                (path_str.clone(), entries)
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 pub fn start_thumbnail_workers(app_handle: tauri::AppHandle) {
     let state = app_handle.state::<crate::AppState>();
     let manager = state.thumbnail_manager.clone();
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
     let thread_count = settings.thumbnail_worker_threads.unwrap_or(4).clamp(1, 16);
+    manager.set_max_concurrency(settings.thumbnail_max_concurrency);
 
     for _ in 0..thread_count {
         let app_clone = app_handle.clone();
@@ -1592,21 +1898,10 @@ pub fn start_thumbnail_workers(app_handle: tauri::AppHandle) {
 
         std::thread::spawn(move || {
             loop {
-                let path_to_process: String = {
-                    let mut queue = manager_clone.queue.lock().unwrap();
-                    while queue.is_empty() {
-                        queue = manager_clone.cvar.wait(queue).unwrap();
-                    }
-                    let path = queue.pop_back().unwrap();
-
-                    let mut processing = manager_clone.processing_now.lock().unwrap();
-                    if processing.contains(&path) {
-                        let state = app_clone.state::<crate::AppState>();
-                        increment_thumbnail_progress(&state, &app_clone);
-                        continue;
-                    }
-                    processing.insert(path.clone());
-                    path
+                let Some(path_to_process) = manager_clone.wait_for_next() else {
+                    let state = app_clone.state::<crate::AppState>();
+                    increment_thumbnail_progress(&state, &app_clone);
+                    continue;
                 };
 
                 let state = app_clone.state::<crate::AppState>();
@@ -1635,11 +1930,7 @@ pub fn start_thumbnail_workers(app_handle: tauri::AppHandle) {
                     }
                     increment_thumbnail_progress(&state, &app_clone);
                 }
-                manager_clone
-                    .processing_now
-                    .lock()
-                    .unwrap()
-                    .remove(&path_to_process);
+                manager_clone.finish(&path_to_process);
             }
         });
     }
@@ -1706,6 +1997,12 @@ pub fn update_thumbnail_queue(
     Ok(())
 }
 
+#[tauri::command]
+pub fn prioritize_thumbnails(paths: Vec<String>, app_handle: AppHandle) -> Result<usize, String> {
+    let state = app_handle.state::<crate::AppState>();
+    Ok(state.thumbnail_manager.prioritize(&paths))
+}
+
 pub fn add_to_thumbnail_queue(state: &AppState, count: usize, app_handle: &AppHandle) {
     let mut tracker = state.thumbnail_progress.lock().unwrap();
     tracker.total += count;
@@ -2051,6 +2348,26 @@ fn find_all_associated_files(source_image_path: &Path) -> Result<Vec<PathBuf>, S
     Ok(associated_files)
 }
 
+/// Returns the `.xmp` sidecar next to an image, and whether another image in the folder
+/// shares the same stem (e.g. a RAW+JPEG pair) and therefore also relies on it.
+fn find_xmp_companion(source_image_path: &Path) -> Option<(PathBuf, bool)> {
+    let xmp_path = resolve_xmp_path(source_image_path)?;
+    let stem = source_image_path.file_stem()?;
+    let shared = source_image_path
+        .parent()
+        .and_then(|parent| fs::read_dir(parent).ok())
+        .map(|entries| {
+            entries.filter_map(Result::ok).any(|entry| {
+                let path = entry.path();
+                path != source_image_path
+                    && path.file_stem() == Some(stem)
+                    && is_supported_image_file(&path)
+            })
+        })
+        .unwrap_or(false);
+    Some((xmp_path, shared))
+}
+
 #[tauri::command]
 pub fn copy_files(source_paths: Vec<String>, destination_folder: String) -> Result<(), String> {
     let dest_path = Path::new(&destination_folder);
@@ -2103,11 +2420,31 @@ pub fn copy_files(source_paths: Vec<String>, destination_folder: String) -> Resu
                 fs::copy(&original_file, &final_dest_path).map_err(|e| e.to_string())?;
             }
         } else {
-            for file_to_copy in all_files_to_copy {
-                if let Some(file_name) = file_to_copy.file_name() {
-                    let dest_file_path = dest_path.join(file_name);
-                    fs::copy(&file_to_copy, &dest_file_path).map_err(|e| e.to_string())?;
-                }
+            let mut files_to_copy: Vec<(PathBuf, PathBuf)> = all_files_to_copy
+                .into_iter()
+                .filter_map(|file| {
+                    let dest_file_path = dest_path.join(file.file_name()?);
+                    Some((file, dest_file_path))
+                })
+                .collect();
+
+            if let Some(existing) = files_to_copy.iter().find(|(_, dest)| dest.exists()) {
+                return Err(format!(
+                    "File already exists at destination: {}",
+                    existing.1.display()
+                ));
+            }
+
+            if let Some((xmp_path, _)) = find_xmp_companion(&source_image_path)
+                && let Some(file_name) = xmp_path.file_name()
+                && !dest_path.join(file_name).exists()
+            {
+                let dest_xmp = dest_path.join(file_name);
+                files_to_copy.push((xmp_path, dest_xmp));
+            }
+
+            for (file_to_copy, dest_file_path) in files_to_copy {
+                fs::copy(&file_to_copy, &dest_file_path).map_err(|e| e.to_string())?;
             }
         }
     }
@@ -2120,7 +2457,46 @@ pub fn move_files(
     destination_folder: String,
     app_handle: AppHandle,
 ) -> Result<(), String> {
-    let dest_path = Path::new(&destination_folder);
+    let (all_files_to_trash, renames) = copy_images_for_move(&source_paths, &destination_folder)?;
+
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+    if !all_files_to_trash.is_empty()
+        && let Err(trash_error) = trash::delete_all(&all_files_to_trash)
+    {
+        log::warn!(
+            "Failed to move source files to trash: {}. Falling back to permanent delete.",
+            trash_error
+        );
+        for path in all_files_to_trash {
+            if path.is_file() {
+                fs::remove_file(&path).map_err(|e| {
+                    format!("Failed to delete source file {}: {}", path.display(), e)
+                })?;
+            }
+        }
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    for path in all_files_to_trash {
+        if path.is_file() {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to delete source file {}: {}", path.display(), e))?;
+        }
+    }
+
+    sync_album_path_changes(&app_handle, Some(&renames), None, None);
+
+    Ok(())
+}
+
+/// Copies each image with its sidecars, virtual copies and XMP companion into the
+/// destination. Returns the source files to remove afterwards and the old to new image
+/// paths.
+fn copy_images_for_move(
+    source_paths: &[String],
+    destination_folder: &str,
+) -> Result<(Vec<PathBuf>, HashMap<String, String>), String> {
+    let dest_path = Path::new(destination_folder);
     if !dest_path.is_dir() {
         return Err(format!(
             "Destination is not a folder: {}",
@@ -2165,6 +2541,18 @@ pub fn move_files(
             }
         }
 
+        if let Some((xmp_path, shared)) = find_xmp_companion(&source_image_path)
+            && let Some(file_name) = xmp_path.file_name()
+        {
+            let dest_xmp = dest_path.join(file_name);
+            if !dest_xmp.exists() {
+                fs::copy(&xmp_path, &dest_xmp).map_err(|e| e.to_string())?;
+                if !shared {
+                    all_files_to_trash.push(xmp_path);
+                }
+            }
+        }
+
         let dest_image_path = dest_path.join(source_image_path.file_name().unwrap());
         renames.insert(
             source_image_path.to_string_lossy().into_owned(),
@@ -2174,34 +2562,7 @@ pub fn move_files(
         all_files_to_trash.extend(files_to_move);
     }
 
-    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
-    if !all_files_to_trash.is_empty()
-        && let Err(trash_error) = trash::delete_all(&all_files_to_trash)
-    {
-        log::warn!(
-            "Failed to move source files to trash: {}. Falling back to permanent delete.",
-            trash_error
-        );
-        for path in all_files_to_trash {
-            if path.is_file() {
-                fs::remove_file(&path).map_err(|e| {
-                    format!("Failed to delete source file {}: {}", path.display(), e)
-                })?;
-            }
-        }
-    }
-
-    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
-    for path in all_files_to_trash {
-        if path.is_file() {
-            fs::remove_file(&path)
-                .map_err(|e| format!("Failed to delete source file {}: {}", path.display(), e))?;
-        }
-    }
-
-    sync_album_path_changes(&app_handle, Some(&renames), None, None);
-
-    Ok(())
+    Ok((all_files_to_trash, renames))
 }
 
 #[tauri::command]
@@ -2211,7 +2572,7 @@ pub fn save_metadata_and_update_thumbnail(
     app_handle: AppHandle,
     state: tauri::State<AppState>,
 ) -> Result<(), String> {
-    let (source_path, sidecar_path) = parse_virtual_path(&path);
+    let (_, sidecar_path) = parse_virtual_path(&path);
 
     let mut metadata = crate::exif_processing::load_sidecar(&sidecar_path);
 
@@ -2225,16 +2586,80 @@ pub fn save_metadata_and_update_thumbnail(
         );
     }
 
-    metadata.adjustments = final_adjustments;
+    let previous_adjustments = std::mem::replace(&mut metadata.adjustments, final_adjustments);
+    let history_limit = load_settings(app_handle.clone())
+        .ok()
+        .and_then(|s| s.edit_history_limit)
+        .unwrap_or(DEFAULT_EDIT_HISTORY_LIMIT);
+    record_edit_history(&mut metadata, previous_adjustments, history_limit as usize);
 
-    let json_string = serde_json::to_string_pretty(&metadata).map_err(|e| e.to_string())?;
-    std::fs::write(&sidecar_path, json_string).map_err(|e| e.to_string())?;
+    write_metadata_and_refresh_thumbnail(&path, &metadata, app_handle, &state)
+}
 
-    if let Ok(settings) = load_settings(app_handle.clone())
+/// Pushes the current adjustments onto the sidecar's undo stack. Anything past the
+/// current position (the redo tail) is discarded, as in any linear undo model.
+fn record_edit_history(metadata: &mut ImageMetadata, previous: Value, limit: usize) {
+    if limit == 0 {
+        metadata.history = None;
+        return;
+    }
+
+    let history = metadata.history.get_or_insert_with(EditHistory::default);
+    history
+        .entries
+        .truncate((history.position + 1).min(history.entries.len()));
+    // The sidecar may have been changed outside of this command (paste, presets, sync),
+    // so make sure the state being replaced is reachable with undo.
+    if !previous.is_null() && history.entries.last() != Some(&previous) {
+        history.entries.push(previous);
+    }
+    if history.entries.last() != Some(&metadata.adjustments) {
+        history.entries.push(metadata.adjustments.clone());
+    }
+
+    let overflow = history.entries.len().saturating_sub(limit);
+    history.entries.drain(..overflow);
+    history.position = history.entries.len().saturating_sub(1);
+}
+
+/// Replaces an image's adjustments the way an edit in the editor would: the replaced state
+/// is recorded for undo, the sidecar is written and XMP is synced when enabled.
+pub fn save_adjustments_with_history(
+    path: &str,
+    adjustments: Value,
+    settings: &AppSettings,
+) -> Result<(), String> {
+    let (source_path, sidecar_path) = parse_virtual_path(path);
+    let mut metadata = crate::exif_processing::load_sidecar(&sidecar_path);
+
+    let previous_adjustments = std::mem::replace(&mut metadata.adjustments, adjustments);
+    let history_limit = settings
+        .edit_history_limit
+        .unwrap_or(DEFAULT_EDIT_HISTORY_LIMIT);
+    record_edit_history(&mut metadata, previous_adjustments, history_limit as usize);
+    write_sidecar(&sidecar_path, &metadata)?;
+
+    if settings.enable_xmp_sync.unwrap_or(false) {
+        let create_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+        sync_metadata_to_xmp(&source_path, &metadata, create_if_missing);
+    }
+    Ok(())
+}
+
+fn write_metadata_and_refresh_thumbnail(
+    path: &str,
+    metadata: &ImageMetadata,
+    app_handle: AppHandle,
+    state: &tauri::State<AppState>,
+) -> Result<(), String> {
+    let (source_path, sidecar_path) = parse_virtual_path(path);
+    write_sidecar(&sidecar_path, metadata)?;
+
+    if let Ok(settings) = load_settings(app_handle.clone())
         && settings.enable_xmp_sync.unwrap_or(false)
     {
         let create_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
-        sync_metadata_to_xmp(&source_path, &metadata, create_if_missing);
+        sync_metadata_to_xmp(&source_path, metadata, create_if_missing);
     }
 
     let loaded_image_lock = state.original_image.lock().unwrap();
@@ -2249,11 +2674,11 @@ pub fn save_metadata_and_update_thumbnail(
     };
     drop(loaded_image_lock);
 
-    let gpu_context = gpu_processing::get_or_init_gpu_context(&state, &app_handle).ok();
+    let gpu_context = gpu_processing::get_or_init_gpu_context(state, &app_handle).ok();
     let app_handle_clone = app_handle.clone();
-    let path_clone = path.clone();
+    let path_clone = path.to_string();
 
-    add_to_thumbnail_queue(&state, 1, &app_handle);
+    add_to_thumbnail_queue(state, 1, &app_handle);
 
     thread::spawn(move || {
         let state = app_handle_clone.state::<AppState>();
@@ -2379,7 +2804,7 @@ pub async fn apply_adjustments_to_paths(
                 &thumb_cache_dir,
                 gpu_context.as_ref(),
                 None,
-                true,
+                false,
                 &app_handle,
                 &settings,
             );
@@ -2395,6 +2820,67 @@ pub async fn apply_adjustments_to_paths(
     Ok(())
 }
 
+/// Returns the develop settings stored in an image's sidecar, ready to be pasted with
+/// `paste_adjustments`. Non-adjustment metadata such as rating and tags is left out.
+#[tauri::command]
+pub fn copy_adjustments(source_path: String) -> Result<Value, String> {
+    let (_, sidecar_path) = parse_virtual_path(&source_path);
+    let metadata = crate::exif_processing::load_sidecar(&sidecar_path);
+    let known = all_available_adjustments();
+
+    let copied: serde_json::Map<String, Value> = metadata
+        .adjustments
+        .as_object()
+        .map(|adjustments| {
+            adjustments
+                .iter()
+                .filter(|(key, _)| known.contains(key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Value::Object(copied))
+}
+
+/// Pastes copied adjustments onto `paths`. With `sections`, only the keys belonging to
+/// those sections (see `adjustment_section_keys`) are written.
+#[tauri::command]
+pub async fn paste_adjustments(
+    paths: Vec<String>,
+    adjustments: Value,
+    sections: Option<Vec<String>>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let source = adjustments
+        .as_object()
+        .ok_or("Adjustments to paste must be an object")?;
+
+    let allowed: HashSet<&str> = match &sections {
+        Some(sections) => {
+            let mut allowed = HashSet::new();
+            for section in sections {
+                let keys = adjustment_section_keys(section)
+                    .ok_or_else(|| format!("Unknown adjustment section '{}'", section))?;
+                allowed.extend(keys.iter().copied());
+            }
+            allowed
+        }
+        None => source.keys().map(|k| k.as_str()).collect(),
+    };
+    let known = all_available_adjustments();
+
+    let filtered: serde_json::Map<String, Value> = source
+        .iter()
+        .filter(|(key, _)| allowed.contains(key.as_str()) && known.contains(key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if filtered.is_empty() {
+        return Ok(());
+    }
+
+    apply_adjustments_to_paths(paths, Value::Object(filtered), app_handle).await
+}
+
 #[tauri::command]
 pub async fn reset_adjustments_for_paths(
     paths: Vec<String>,
@@ -2448,7 +2934,7 @@ pub async fn reset_adjustments_for_paths(
                 &thumb_cache_dir,
                 gpu_context.as_ref(),
                 None,
-                true,
+                false,
                 &app_handle,
                 &settings,
             );
@@ -2464,13 +2950,22 @@ pub async fn reset_adjustments_for_paths(
     Ok(())
 }
 
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoAdjustSummary {
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
 #[tauri::command]
 pub async fn apply_auto_adjustments_to_paths(
     paths: Vec<String>,
+    options: Option<AutoAdjustOptions>,
     app_handle: AppHandle,
-) -> Result<(), String> {
+) -> Result<AutoAdjustSummary, String> {
     let state = app_handle.state::<AppState>();
     add_to_thumbnail_queue(&state, paths.len(), &app_handle);
+    let options = options.unwrap_or_default();
 
     tauri::async_runtime::spawn_blocking(move || {
         let settings = load_settings(app_handle.clone()).unwrap_or_default();
@@ -2488,11 +2983,12 @@ pub async fn apply_auto_adjustments_to_paths(
                 for _ in 0..paths.len() {
                     increment_thumbnail_progress(&state, &app_handle);
                 }
-                return;
+                return Err(e);
             }
         };
 
         let gpu_context = gpu_processing::get_or_init_gpu_context(&state, &app_handle).ok();
+        let succeeded = AtomicUsize::new(0);
 
         paths.par_iter().for_each(|path| {
             let loaded_image: Option<DynamicImage> = (|| -> Result<DynamicImage, String> {
@@ -2509,8 +3005,16 @@ pub async fn apply_auto_adjustments_to_paths(
                 )
                 .map_err(|e| e.to_string())?;
 
-                let auto_results = perform_auto_analysis(&image);
-                let auto_adjustments_json = auto_results_to_json(&auto_results);
+                let auto_results = perform_auto_analysis_with_options(
+                    &image,
+                    is_raw_file(&source_path_str),
+                    &options,
+                );
+                let mut auto_adjustments_json = auto_results_to_json(&auto_results);
+                if options.adjust_wb {
+                    auto_adjustments_json["temperature"] = auto_results.temperature.into();
+                    auto_adjustments_json["tint"] = auto_results.tint.into();
+                }
 
                 let mut existing_metadata = crate::exif_processing::load_sidecar(&sidecar_path);
 
@@ -2541,9 +3045,9 @@ pub async fn apply_auto_adjustments_to_paths(
                     }
                 }
 
-                if let Ok(json_string) = serde_json::to_string_pretty(&existing_metadata) {
-                    let _ = std::fs::write(&sidecar_path, json_string);
-                }
+                let json_string =
+                    serde_json::to_string_pretty(&existing_metadata).map_err(|e| e.to_string())?;
+                std::fs::write(&sidecar_path, json_string).map_err(|e| e.to_string())?;
 
                 if enable_xmp_sync {
                     sync_metadata_to_xmp(&source_path, &existing_metadata, create_xmp_if_missing);
@@ -2552,6 +3056,9 @@ pub async fn apply_auto_adjustments_to_paths(
             })()
             .map_err(|e| eprintln!("Failed to apply auto adjustments to {}: {}", path, e))
             .ok();
+            if loaded_image.is_some() {
+                succeeded.fetch_add(1, Ordering::Relaxed);
+            }
 
             let result = generate_single_thumbnail_and_cache(
                 path,
@@ -2569,9 +3076,139 @@ pub async fn apply_auto_adjustments_to_paths(
 
             increment_thumbnail_progress(&state, &app_handle);
         });
-    });
 
-    Ok(())
+        let succeeded = succeeded.into_inner();
+        Ok(AutoAdjustSummary {
+            succeeded,
+            failed: paths.len() - succeeded,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceMatchResult {
+    pub path: String,
+    pub delta: Option<ReferenceMatchDelta>,
+    pub error: Option<String>,
+}
+
+fn load_lab_statistics(
+    path: &str,
+    settings: &AppSettings,
+) -> std::result::Result<LabStatistics, String> {
+    let (source_path, _) = parse_virtual_path(path);
+    let source_path_str = source_path.to_string_lossy().to_string();
+    let file_bytes = fs::read(&source_path).map_err(|e| e.to_string())?;
+    let image = image_loader::load_base_image_from_bytes(
+        &file_bytes,
+        &source_path_str,
+        true,
+        settings,
+        None,
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(compute_lab_statistics(
+        &image,
+        is_raw_file(&source_path_str),
+    ))
+}
+
+/// Evens out exposure, white balance and contrast across `target_paths` by matching the
+/// Lab statistics of each unedited image to the reference. The reference's own edits are
+/// carried over as the base. With `dry_run` only the deltas are returned.
+#[tauri::command]
+pub async fn match_to_reference(
+    reference_path: String,
+    target_paths: Vec<String>,
+    dry_run: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<Vec<ReferenceMatchResult>, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    let handle = app_handle.clone();
+
+    let results = tauri::async_runtime::spawn_blocking(move || {
+        let settings = load_settings(handle.clone()).unwrap_or_default();
+        let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
+        let create_xmp_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+
+        let reference_stats = load_lab_statistics(&reference_path, &settings)
+            .map_err(|e| format!("Failed to analyze reference image: {}", e))?;
+        let (_, reference_sidecar) = parse_virtual_path(&reference_path);
+        let reference_adjustments =
+            crate::exif_processing::load_sidecar(&reference_sidecar).adjustments;
+        let reference_value = |key: &str| reference_adjustments[key].as_f64().unwrap_or(0.0);
+
+        let results: Vec<ReferenceMatchResult> = target_paths
+            .par_iter()
+            .map(|path| {
+                let outcome = (|| -> std::result::Result<ReferenceMatchDelta, String> {
+                    let target_stats = load_lab_statistics(path, &settings)?;
+                    let delta = match_lab_statistics(&reference_stats, &target_stats);
+                    if dry_run {
+                        return Ok(delta);
+                    }
+
+                    let (source_path, sidecar_path) = parse_virtual_path(path);
+                    let mut metadata = crate::exif_processing::load_sidecar(&sidecar_path);
+                    if !metadata.adjustments.is_object() {
+                        metadata.adjustments = serde_json::json!({});
+                    }
+                    let adjustments = &mut metadata.adjustments;
+                    adjustments["exposure"] = (reference_value("exposure") + delta.exposure).into();
+                    adjustments["contrast"] = (reference_value("contrast") + delta.contrast)
+                        .clamp(-100.0, 100.0)
+                        .into();
+                    adjustments["temperature"] = (reference_value("temperature")
+                        + delta.temperature)
+                        .clamp(-100.0, 100.0)
+                        .into();
+                    adjustments["tint"] = (reference_value("tint") + delta.tint)
+                        .clamp(-100.0, 100.0)
+                        .into();
+
+                    let json_string =
+                        serde_json::to_string_pretty(&metadata).map_err(|e| e.to_string())?;
+                    fs::write(&sidecar_path, json_string).map_err(|e| e.to_string())?;
+                    if enable_xmp_sync {
+                        sync_metadata_to_xmp(&source_path, &metadata, create_xmp_if_missing);
+                    }
+                    Ok(delta)
+                })();
+
+                match outcome {
+                    Ok(delta) => ReferenceMatchResult {
+                        path: path.clone(),
+                        delta: Some(delta),
+                        error: None,
+                    },
+                    Err(e) => {
+                        log::warn!("Failed to match '{}' to reference: {}", path, e);
+                        ReferenceMatchResult {
+                            path: path.clone(),
+                            delta: None,
+                            error: Some(e),
+                        }
+                    }
+                }
+            })
+            .collect();
+        Ok::<_, String>(results)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    if !dry_run {
+        let updated: Vec<String> = results
+            .iter()
+            .filter(|r| r.delta.is_some())
+            .map(|r| r.path.clone())
+            .collect();
+        update_thumbnail_queue(updated, app_handle)?;
+    }
+    Ok(results)
 }
 
 #[tauri::command]
@@ -2583,6 +3220,7 @@ pub fn set_color_label_for_paths(
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
     let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
     let create_xmp_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+    let write_rating_to_file = settings.write_rating_to_file.unwrap_or(false);
 
     paths.par_iter().for_each(|path| {
         let (_, sidecar_path) = parse_virtual_path(path);
@@ -2608,9 +3246,15 @@ pub fn set_color_label_for_paths(
             let _ = std::fs::write(&sidecar_path, json_string);
         }
 
-        if enable_xmp_sync {
+        // Ratings for other DAMs go into the .xmp sidecar; originals are never rewritten.
+        let write_to_file = write_rating_to_file && !path.contains("?vc=");
+        if enable_xmp_sync || write_to_file {
             let source_path = parse_virtual_path(path).0;
-            sync_metadata_to_xmp(&source_path, &metadata, create_xmp_if_missing);
+            sync_metadata_to_xmp(
+                &source_path,
+                &metadata,
+                create_xmp_if_missing || write_to_file,
+            );
         }
     });
 
@@ -2626,6 +3270,7 @@ pub fn set_rating_for_paths(
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
     let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
     let create_xmp_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+    let write_rating_to_file = settings.write_rating_to_file.unwrap_or(false);
 
     paths.par_iter().for_each(|path| {
         let (_, sidecar_path) = parse_virtual_path(path);
@@ -2638,9 +3283,15 @@ pub fn set_rating_for_paths(
             let _ = std::fs::write(&sidecar_path, json_string);
         }
 
-        if enable_xmp_sync {
+        // Ratings for other DAMs go into the .xmp sidecar; originals are never rewritten.
+        let write_to_file = write_rating_to_file && !path.contains("?vc=");
+        if enable_xmp_sync || write_to_file {
             let source_path = parse_virtual_path(path).0;
-            sync_metadata_to_xmp(&source_path, &metadata, create_xmp_if_missing);
+            sync_metadata_to_xmp(
+                &source_path,
+                &metadata,
+                create_xmp_if_missing || write_to_file,
+            );
         }
     });
 
@@ -2656,7 +3307,7 @@ pub fn load_metadata(path: String, app_handle: AppHandle) -> Result<ImageMetadat
     let mut metadata = crate::exif_processing::load_sidecar(&sidecar_path);
 
     if enable_xmp_sync
-        && sync_metadata_from_xmp(&source_path, &mut metadata)
+        && sync_metadata_from_xmp(&source_path, &sidecar_path, &mut metadata, true)
         && let Ok(json) = serde_json::to_string_pretty(&metadata)
     {
         let _ = fs::write(&sidecar_path, json);
@@ -2786,21 +3437,40 @@ pub fn handle_import_legacy_presets_from_file(
     let content = fs::read_to_string(&file_path)
         .map_err(|e| format!("Failed to read legacy preset file: {}", e))?;
 
-    let xmp_content = if file_path.to_lowercase().ends_with(".lrtemplate") {
-        let re = Regex::new(r#"(?s)s.xmp = "(.*)""#).unwrap();
-        if let Some(caps) = re.captures(&content) {
-            caps.get(1)
-                .map(|m| m.as_str().replace(r#"\""#, r#"""#))
-                .unwrap_or(content)
-        } else {
-            content
-        }
-    } else {
-        content
-    };
+    let converted_preset = preset_converter::convert_external_preset(
+        &content,
+        file_path.to_lowercase().ends_with(".lrtemplate"),
+    )?;
+
+    add_converted_preset(converted_preset, app_handle)
+}
+
+#[tauri::command]
+pub fn import_external_preset(
+    path: String,
+    app_handle: AppHandle,
+) -> Result<Vec<PresetItem>, String> {
+    let extension = Path::new(&path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    if extension != "xmp" && extension != "lrtemplate" {
+        return Err(format!("Unsupported preset format: .{}", extension));
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read preset file: {}", e))?;
+    let converted_preset =
+        preset_converter::convert_external_preset(&content, extension == "lrtemplate")?;
 
-    let converted_preset = preset_converter::convert_xmp_to_preset(&xmp_content)?;
+    add_converted_preset(converted_preset, app_handle)
+}
 
+fn add_converted_preset(
+    converted_preset: Preset,
+    app_handle: AppHandle,
+) -> Result<Vec<PresetItem>, String> {
     let mut current_presets = load_presets(app_handle.clone())?;
 
     let current_names: HashSet<String> = current_presets
@@ -2993,8 +3663,42 @@ pub fn show_in_finder(path: String) -> Result<(), String> {
     Ok(())
 }
 
+/// Sends `paths` to the OS trash, or removes them for good when `use_trash` is false. A failed
+/// trash operation is reported instead of silently falling back to a permanent delete.
+fn remove_paths(paths: Vec<PathBuf>, use_trash: bool) -> Result<(), String> {
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+    if use_trash {
+        return trash::delete_all(&paths).map_err(|e| {
+            format!(
+                "Failed to move files to the trash: {}. Use permanent delete to remove them.",
+                e
+            )
+        });
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    if use_trash {
+        log::info!("No system trash on this platform; deleting files permanently.");
+    }
+
+    for path in paths {
+        if path.is_file() {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to delete file {}: {}", path.display(), e))?;
+        } else if path.is_dir() {
+            fs::remove_dir_all(&path)
+                .map_err(|e| format!("Failed to delete directory {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
 #[tauri::command]
-pub fn delete_files_from_disk(paths: Vec<String>, app_handle: AppHandle) -> Result<(), String> {
+pub fn delete_files_from_disk(
+    paths: Vec<String>,
+    use_trash: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
     let mut files_to_trash = HashSet::new();
 
     let mut deletions = HashSet::new();
@@ -3032,33 +3736,7 @@ pub fn delete_files_from_disk(paths: Vec<String>, app_handle: AppHandle) -> Resu
     }
 
     let final_paths_to_delete: Vec<PathBuf> = files_to_trash.into_iter().collect();
-    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
-    if let Err(trash_error) = trash::delete_all(&final_paths_to_delete) {
-        log::warn!(
-            "Failed to move files to trash: {}. Falling back to permanent delete.",
-            trash_error
-        );
-        for path in final_paths_to_delete {
-            if path.is_file() {
-                fs::remove_file(&path)
-                    .map_err(|e| format!("Failed to delete file {}: {}", path.display(), e))?;
-            } else if path.is_dir() {
-                fs::remove_dir_all(&path)
-                    .map_err(|e| format!("Failed to delete directory {}: {}", path.display(), e))?;
-            }
-        }
-    }
-
-    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
-    for path in final_paths_to_delete {
-        if path.is_file() {
-            fs::remove_file(&path)
-                .map_err(|e| format!("Failed to delete file {}: {}", path.display(), e))?;
-        } else if path.is_dir() {
-            fs::remove_dir_all(&path)
-                .map_err(|e| format!("Failed to delete directory {}: {}", path.display(), e))?;
-        }
-    }
+    remove_paths(final_paths_to_delete, use_trash.unwrap_or(true))?;
 
     sync_album_path_changes(&app_handle, None, Some(&deletions), None);
 
@@ -3068,6 +3746,7 @@ pub fn delete_files_from_disk(paths: Vec<String>, app_handle: AppHandle) -> Resu
 #[tauri::command]
 pub fn delete_files_with_associated(
     paths: Vec<String>,
+    use_trash: Option<bool>,
     app_handle: AppHandle,
 ) -> Result<(), String> {
     if paths.is_empty() {
@@ -3112,7 +3791,8 @@ pub fn delete_files_with_associated(
                     && stems_to_delete.contains(base_stem)
                     && (is_supported_image_file(entry_filename_str.as_ref())
                         || entry_filename_str.ends_with(".rrdata")
-                        || entry_filename_str.ends_with(".rrexif"))
+                        || entry_filename_str.ends_with(".rrexif")
+                        || entry_filename_str.to_lowercase().ends_with(".xmp"))
                 {
                     files_to_trash.insert(entry_path);
                 }
@@ -3125,27 +3805,7 @@ pub fn delete_files_with_associated(
     }
 
     let final_paths_to_delete: Vec<PathBuf> = files_to_trash.into_iter().collect();
-    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
-    if let Err(trash_error) = trash::delete_all(&final_paths_to_delete) {
-        log::warn!(
-            "Failed to move files to trash: {}. Falling back to permanent delete.",
-            trash_error
-        );
-        for path in final_paths_to_delete {
-            if path.is_file() {
-                fs::remove_file(&path)
-                    .map_err(|e| format!("Failed to delete file {}: {}", path.display(), e))?;
-            }
-        }
-    }
-
-    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
-    for path in final_paths_to_delete {
-        if path.is_file() {
-            fs::remove_file(&path)
-                .map_err(|e| format!("Failed to delete file {}: {}", path.display(), e))?;
-        }
-    }
+    remove_paths(final_paths_to_delete, use_trash.unwrap_or(true))?;
 
     sync_album_path_changes(&app_handle, None, Some(&deletions), None);
 
@@ -3164,10 +3824,12 @@ pub fn get_thumb_cache_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
     Ok(thumb_cache_dir)
 }
 
-pub fn get_cache_key_hash(path_str: &str) -> Option<String> {
+pub fn get_cache_key_hash(path_str: &str, show_edits: bool) -> Option<String> {
     let (_, sidecar_path) = parse_virtual_path(path_str);
 
-    let adjustments_bytes = if let Ok(content) = fs::read_to_string(&sidecar_path) {
+    let adjustments_bytes = if !show_edits {
+        Vec::new()
+    } else if let Ok(content) = fs::read_to_string(&sidecar_path) {
         if let Ok(meta) = serde_json::from_str::<ImageMetadata>(&content) {
             serde_json::to_vec(&meta.adjustments).unwrap_or_default()
         } else {
@@ -3189,27 +3851,45 @@ pub fn get_cached_or_generate_thumbnail_image(
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
     let target_width = settings.thumbnail_resolution.unwrap_or(720);
 
-    if let Some(cache_hash) = get_cache_key_hash(path_str) {
-        let cache_filename = format!("{}.jpg", cache_hash);
-        let cache_path = thumb_cache_dir.join(cache_filename);
+    if let Some(cache_hash) = get_cache_key_hash(path_str, thumbnails_show_edits(&settings)) {
+        let format = ThumbnailFormat::from_settings(&settings);
 
-        if cache_path.exists() {
-            if let Ok(image) = image::open(&cache_path) {
+        if let Some(cached_path) = find_cached_thumbnail(&thumb_cache_dir, &cache_hash, format) {
+            // Decode by content rather than extension; the cache may hold JPEG or WebP.
+            if let Some(image) = image::ImageReader::open(&cached_path)
+                .and_then(|reader| reader.with_guessed_format())
+                .ok()
+                .and_then(|reader| reader.decode().ok())
+            {
                 return Ok(image);
             }
             eprintln!(
                 "Could not open cached thumbnail, regenerating: {:?}",
-                cache_path
+                cached_path
             );
         }
 
-        let thumb_image = generate_thumbnail_data(path_str, gpu_context, None, app_handle)?;
-        let thumb_data = encode_thumbnail(&thumb_image, target_width)?;
-        fs::write(&cache_path, &thumb_data)?;
+        let thumb_image = generate_thumbnail_data(path_str, gpu_context, None, None, app_handle)?;
+        let thumb_data = encode_thumbnail(&thumb_image, target_width, format)?;
+        fs::write(
+            thumbnail_cache_path(&thumb_cache_dir, &cache_hash, format),
+            &thumb_data,
+        )?;
 
         Ok(thumb_image)
     } else {
-        generate_thumbnail_data(path_str, gpu_context, None, app_handle)
+        generate_thumbnail_data(path_str, gpu_context, None, None, app_handle)
+    }
+}
+
+/// Picks up the rating and label other DAMs embedded in an imported file, so they are not
+/// lost when the library is first opened without a `.xmp` sidecar.
+fn import_embedded_rating(image_path: &Path, sidecar_path: &Path) {
+    let mut metadata = crate::exif_processing::load_sidecar(sidecar_path);
+    if sync_metadata_from_xmp(image_path, sidecar_path, &mut metadata, true)
+        && let Ok(json) = serde_json::to_string_pretty(&metadata)
+    {
+        let _ = fs::write(sidecar_path, json);
     }
 }
 
@@ -3261,6 +3941,7 @@ pub async fn import_files(
                         i + 1,
                         total_files,
                         &file_date,
+                        None,
                     );
                     let extension = source_name_path
                         .extension()
@@ -3315,6 +3996,7 @@ pub async fn import_files(
                     i + 1,
                     total_files,
                     &file_date,
+                    None,
                 );
                 let extension = source_path
                     .extension()
@@ -3331,11 +4013,12 @@ pub async fn import_files(
                 }
 
                 fs::copy(&source_path, &dest_file_path).map_err(|e| e.to_string())?;
-                if source_sidecar.exists()
-                    && let Some(dest_str) = dest_file_path.to_str()
-                {
+                if let Some(dest_str) = dest_file_path.to_str() {
                     let (_, dest_sidecar) = parse_virtual_path(dest_str);
-                    fs::copy(&source_sidecar, &dest_sidecar).map_err(|e| e.to_string())?;
+                    if source_sidecar.exists() {
+                        fs::copy(&source_sidecar, &dest_sidecar).map_err(|e| e.to_string())?;
+                    }
+                    import_embedded_rating(&dest_file_path, &dest_sidecar);
                 }
 
                 let mut source_rrexif_name = source_path.file_name().unwrap().to_os_string();
@@ -3414,6 +4097,7 @@ pub fn generate_filename_from_template(
     sequence: usize,
     total: usize,
     file_date: &DateTime<Utc>,
+    exif: Option<&HashMap<String, String>>,
 ) -> String {
     let stem = original_path
         .file_stem()
@@ -3434,10 +4118,91 @@ pub fn generate_filename_from_template(
     result = result.replace("{DD}", &local_date.format("%d").to_string());
     result = result.replace("{hh}", &local_date.format("%H").to_string());
     result = result.replace("{mm}", &local_date.format("%M").to_string());
+    result = result.replace("{ss}", &local_date.format("%S").to_string());
+
+    if let Some(exif) = exif {
+        let value = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| exif.get(*key))
+                .map(|v| sanitize_filename_component(v))
+                .unwrap_or_default()
+        };
+        result = result.replace("{make}", &value(&["Make"]));
+        result = result.replace("{camera}", &value(&["Model"]));
+        result = result.replace("{lens}", &value(&["LensModel"]));
+        result = result.replace(
+            "{iso}",
+            &value(&["PhotographicSensitivity", "ISOSpeed", "ISOSpeedRatings"]),
+        );
+    }
 
     result
 }
 
+fn sanitize_filename_component(value: &str) -> String {
+    value
+        .trim()
+        .trim_matches('"')
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Lists the `.rrdata` sidecars (including virtual copies) and the `.rrexif` cache that
+/// belong to `original_path`, paired with their names after a rename to `new_path`.
+fn collect_sidecar_renames(
+    original_path: &Path,
+    new_path: &Path,
+) -> Result<HashMap<PathBuf, PathBuf>, String> {
+    let mut sidecar_operations: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let parent = original_path
+        .parent()
+        .ok_or("Could not get parent directory")?;
+    let original_filename_str = original_path.file_name().unwrap().to_string_lossy();
+    let new_filename_str = new_path.file_name().unwrap().to_string_lossy();
+
+    if let Ok(entries) = fs::read_dir(parent) {
+        for entry in entries.filter_map(Result::ok) {
+            let entry_path = entry.path();
+            let entry_os_filename = entry.file_name();
+            let entry_filename = entry_os_filename.to_string_lossy();
+
+            if entry_filename.starts_with(&format!("{}.", original_filename_str))
+                && entry_filename.ends_with(".rrdata")
+            {
+                let new_sidecar_filename =
+                    entry_filename.replacen(&*original_filename_str, &new_filename_str, 1);
+                let new_sidecar_path = parent.join(new_sidecar_filename);
+                sidecar_operations.insert(entry_path, new_sidecar_path);
+            } else if entry_filename == format!("{}.rrdata", original_filename_str) {
+                let mut new_sidecar_name = new_path.file_name().unwrap().to_os_string();
+                new_sidecar_name.push(".rrdata");
+                let new_sidecar_path = new_path.with_file_name(new_sidecar_name);
+
+                sidecar_operations.insert(entry_path, new_sidecar_path);
+            }
+        }
+    }
+
+    let mut old_rrexif_name = original_path.file_name().unwrap().to_os_string();
+    old_rrexif_name.push(".rrexif");
+    let old_rrexif = original_path.with_file_name(old_rrexif_name);
+
+    if old_rrexif.exists() {
+        let mut new_rrexif_name = new_path.file_name().unwrap().to_os_string();
+        new_rrexif_name.push(".rrexif");
+        let new_rrexif = new_path.with_file_name(new_rrexif_name);
+        sidecar_operations.insert(old_rrexif, new_rrexif);
+    }
+    Ok(sidecar_operations)
+}
+
 #[tauri::command]
 pub fn rename_files(
     paths: Vec<String>,
@@ -3474,6 +4239,7 @@ pub fn rename_files(
             i + 1,
             paths.len(),
             &file_date,
+            None,
         );
         let new_filename = format!("{}.{}", new_stem, extension);
         let new_path = parent.join(new_filename);
@@ -3490,45 +4256,7 @@ pub fn rename_files(
 
     let mut sidecar_operations: HashMap<PathBuf, PathBuf> = HashMap::new();
     for (original_path, new_path) in &operations {
-        let parent = original_path
-            .parent()
-            .ok_or("Could not get parent directory")?;
-        let original_filename_str = original_path.file_name().unwrap().to_string_lossy();
-        let new_filename_str = new_path.file_name().unwrap().to_string_lossy();
-
-        if let Ok(entries) = fs::read_dir(parent) {
-            for entry in entries.filter_map(Result::ok) {
-                let entry_path = entry.path();
-                let entry_os_filename = entry.file_name();
-                let entry_filename = entry_os_filename.to_string_lossy();
-
-                if entry_filename.starts_with(&format!("{}.", original_filename_str))
-                    && entry_filename.ends_with(".rrdata")
-                {
-                    let new_sidecar_filename =
-                        entry_filename.replacen(&*original_filename_str, &new_filename_str, 1);
-                    let new_sidecar_path = parent.join(new_sidecar_filename);
-                    sidecar_operations.insert(entry_path, new_sidecar_path);
-                } else if entry_filename == format!("{}.rrdata", original_filename_str) {
-                    let mut new_sidecar_name = new_path.file_name().unwrap().to_os_string();
-                    new_sidecar_name.push(".rrdata");
-                    let new_sidecar_path = new_path.with_file_name(new_sidecar_name);
-
-                    sidecar_operations.insert(entry_path, new_sidecar_path);
-                }
-            }
-        }
-
-        let mut old_rrexif_name = original_path.file_name().unwrap().to_os_string();
-        old_rrexif_name.push(".rrexif");
-        let old_rrexif = original_path.with_file_name(old_rrexif_name);
-
-        if old_rrexif.exists() {
-            let mut new_rrexif_name = new_path.file_name().unwrap().to_os_string();
-            new_rrexif_name.push(".rrexif");
-            let new_rrexif = new_path.with_file_name(new_rrexif_name);
-            sidecar_operations.insert(old_rrexif, new_rrexif);
-        }
+        sidecar_operations.extend(collect_sidecar_renames(original_path, new_path)?);
     }
     operations.extend(sidecar_operations);
 
@@ -3557,6 +4285,160 @@ pub fn rename_files(
     Ok(final_new_paths)
 }
 
+/// Renames originals in place from an export-style template that can also use EXIF tokens
+/// such as `{camera}` or `{iso}`. Existing files are never overwritten: a clashing name gets a
+/// numeric suffix instead. Sidecars and virtual copies move with their image.
+#[tauri::command]
+pub fn rename_files_templated(
+    paths: Vec<String>,
+    template: String,
+    app_handle: AppHandle,
+) -> Result<Vec<String>, String> {
+    if template.trim().is_empty() {
+        return Err("The rename template is empty.".to_string());
+    }
+
+    let mut planned: HashSet<PathBuf> = HashSet::new();
+    let mut operations: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut seen_sources: HashSet<PathBuf> = HashSet::new();
+
+    for (i, path_str) in paths.iter().enumerate() {
+        let (original_path, _) = parse_virtual_path(path_str);
+        if !seen_sources.insert(original_path.clone()) {
+            continue;
+        }
+        if !original_path.exists() {
+            return Err(format!("File not found: {}", path_str));
+        }
+
+        let parent = original_path
+            .parent()
+            .ok_or("Could not get parent directory")?;
+        let extension = original_path
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("");
+        let file_date = exif_processing::get_creation_date_from_path(&original_path);
+        // Files that were never opened have no cached EXIF, so read it from the file.
+        let exif = read_exif_for_path(path_str);
+
+        let stem = generate_filename_from_template(
+            &template,
+            &original_path,
+            i + 1,
+            paths.len(),
+            &file_date,
+            (!exif.is_empty()).then_some(&exif),
+        );
+        let stem = sanitize_filename_component(&stem);
+        if stem.is_empty() {
+            return Err(format!(
+                "The template produced an empty name for {}",
+                original_path.display()
+            ));
+        }
+
+        let with_extension = |name: String| {
+            if extension.is_empty() {
+                parent.join(name)
+            } else {
+                parent.join(format!("{}.{}", name, extension))
+            }
+        };
+        let mut new_path = with_extension(stem.clone());
+        let mut counter = 1;
+        while new_path != original_path && (new_path.exists() || planned.contains(&new_path)) {
+            new_path = with_extension(format!("{}_{}", stem, counter));
+            counter += 1;
+        }
+
+        if new_path != original_path {
+            planned.insert(new_path.clone());
+            operations.push((original_path, new_path));
+        }
+    }
+
+    let mut renames = HashMap::new();
+    let mut final_new_paths = Vec::with_capacity(operations.len());
+    let mut result = Ok(());
+    for (original_path, new_path) in operations {
+        match rename_with_sidecars(&original_path, &new_path) {
+            Ok(renamed) => {
+                renames.extend(renamed);
+                final_new_paths.push(new_path.to_string_lossy().into_owned());
+            }
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+
+    // Albums must follow the files that were renamed even when a later file failed.
+    sync_album_path_changes(&app_handle, Some(&renames), None, None);
+
+    result.map(|_| final_new_paths)
+}
+
+/// Renames an image together with its sidecars. If any of them fails, the ones already
+/// renamed are moved back so the image never ends up split from its edits.
+fn rename_with_sidecars(
+    original_path: &Path,
+    new_path: &Path,
+) -> Result<HashMap<String, String>, String> {
+    let sidecars = collect_sidecar_renames(original_path, new_path)?;
+    if let Some((_, existing)) = sidecars.iter().find(|(_, target)| target.exists()) {
+        return Err(format!(
+            "Refusing to overwrite existing sidecar {}",
+            existing.display()
+        ));
+    }
+
+    let mut done: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for (old_path, target) in
+        std::iter::once((original_path.to_path_buf(), new_path.to_path_buf())).chain(sidecars)
+    {
+        let renamed = if target.exists() {
+            Err(format!(
+                "Refusing to overwrite existing file {}",
+                target.display()
+            ))
+        } else {
+            fs::rename(&old_path, &target).map_err(|e| {
+                format!(
+                    "Failed to rename {} to {}: {}",
+                    old_path.display(),
+                    target.display(),
+                    e
+                )
+            })
+        };
+        if let Err(e) = renamed {
+            for (done_old, done_target) in done.iter().rev() {
+                if let Err(rollback_error) = fs::rename(done_target, done_old) {
+                    log::error!(
+                        "Failed to restore {} after a failed rename: {}",
+                        done_old.display(),
+                        rollback_error
+                    );
+                }
+            }
+            return Err(e);
+        }
+        done.push((old_path, target));
+    }
+
+    Ok(done
+        .into_iter()
+        .map(|(old_path, target)| {
+            (
+                old_path.to_string_lossy().into_owned(),
+                target.to_string_lossy().into_owned(),
+            )
+        })
+        .collect())
+}
+
 #[tauri::command]
 pub fn create_virtual_copy(
     source_virtual_path: String,
@@ -3586,6 +4468,72 @@ pub fn create_virtual_copy(
     Ok(new_virtual_path)
 }
 
+fn write_sidecar(sidecar_path: &Path, metadata: &ImageMetadata) -> Result<(), String> {
+    let json_string = serde_json::to_string_pretty(metadata).map_err(|e| e.to_string())?;
+    fs::write(sidecar_path, json_string).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn save_edit_snapshot(path: String, name: String) -> Result<EditSnapshot, String> {
+    let (_, sidecar_path) = parse_virtual_path(&path);
+    let mut metadata = crate::exif_processing::load_sidecar(&sidecar_path);
+
+    let name = name.trim();
+    let snapshot = EditSnapshot {
+        id: Uuid::new_v4().to_string(),
+        name: if name.is_empty() {
+            format!("Snapshot {}", metadata.snapshots.len() + 1)
+        } else {
+            name.to_string()
+        },
+        created_at: Utc::now().to_rfc3339(),
+        adjustments: metadata.adjustments.clone(),
+    };
+    metadata.snapshots.push(snapshot.clone());
+    write_sidecar(&sidecar_path, &metadata)?;
+    Ok(snapshot)
+}
+
+#[tauri::command]
+pub fn list_edit_snapshots(path: String) -> Result<Vec<EditSnapshot>, String> {
+    let (_, sidecar_path) = parse_virtual_path(&path);
+    Ok(crate::exif_processing::load_sidecar(&sidecar_path).snapshots)
+}
+
+/// Makes a snapshot the active edit again and returns its adjustments. The snapshot itself
+/// is kept, so the user can switch back and forth between looks.
+#[tauri::command]
+pub fn restore_edit_snapshot(
+    path: String,
+    snapshot_id: String,
+    app_handle: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<Value, String> {
+    let (_, sidecar_path) = parse_virtual_path(&path);
+    let metadata = crate::exif_processing::load_sidecar(&sidecar_path);
+    let adjustments = metadata
+        .snapshots
+        .iter()
+        .find(|s| s.id == snapshot_id)
+        .map(|s| s.adjustments.clone())
+        .ok_or_else(|| format!("Snapshot '{}' not found", snapshot_id))?;
+
+    save_metadata_and_update_thumbnail(path, adjustments.clone(), app_handle, state)?;
+    Ok(adjustments)
+}
+
+#[tauri::command]
+pub fn delete_edit_snapshot(path: String, snapshot_id: String) -> Result<(), String> {
+    let (_, sidecar_path) = parse_virtual_path(&path);
+    let mut metadata = crate::exif_processing::load_sidecar(&sidecar_path);
+    let before = metadata.snapshots.len();
+    metadata.snapshots.retain(|s| s.id != snapshot_id);
+    if metadata.snapshots.len() == before {
+        return Err(format!("Snapshot '{}' not found", snapshot_id));
+    }
+    write_sidecar(&sidecar_path, &metadata)
+}
+
 pub fn extract_xmp_rating(content: &str) -> Option<u8> {
     if let Some(idx) = content.find("xmp:Rating=\"") {
         let start = idx + 12;
@@ -3646,17 +4594,45 @@ pub fn resolve_xmp_path(image_path: &Path) -> Option<PathBuf> {
     }
 }
 
-pub fn sync_metadata_from_xmp(source_path: &Path, metadata: &mut ImageMetadata) -> bool {
-    let actual_xmp = resolve_xmp_path(source_path);
+fn modified_time(path: &Path) -> Option<std::time::SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Merges rating, label and keywords from the `.xmp` sidecar into `metadata`. A JPEG's
+/// embedded XMP is only consulted with `read_embedded`, which is meant for import and editor
+/// load rather than folder listings. When both sides carry a rating or label, whichever file
+/// was modified last wins.
+pub fn sync_metadata_from_xmp(
+    source_path: &Path,
+    sidecar_path: &Path,
+    metadata: &mut ImageMetadata,
+    read_embedded: bool,
+) -> bool {
+    let external = match resolve_xmp_path(source_path) {
+        Some(xmp_file) => fs::read_to_string(&xmp_file)
+            .ok()
+            .map(|content| (content, modified_time(&xmp_file))),
+        None if read_embedded => crate::exif_processing::read_embedded_xmp(source_path)
+            .map(|content| (content, modified_time(source_path))),
+        None => None,
+    };
+    let external_is_newer = |external_modified: Option<std::time::SystemTime>| match (
+        external_modified,
+        modified_time(sidecar_path),
+    ) {
+        (Some(external), Some(sidecar)) => external > sidecar,
+        (_, None) => true,
+        _ => false,
+    };
 
     let mut changed = false;
 
-    if let Some(xmp_file) = actual_xmp
-        && let Ok(content) = fs::read_to_string(&xmp_file)
-    {
-        if metadata.rating == 0
-            && let Some(rating) = extract_xmp_rating(&content)
-            && rating != 0
+    if let Some((content, external_modified)) = external {
+        let prefer_external = external_is_newer(external_modified);
+
+        if let Some(rating) = extract_xmp_rating(&content)
+            && rating != metadata.rating
+            && ((metadata.rating == 0 && rating != 0) || prefer_external)
         {
             metadata.rating = rating;
             if let Some(obj) = metadata.adjustments.as_object_mut() {
@@ -3673,6 +4649,7 @@ pub fn sync_metadata_from_xmp(source_path: &Path, metadata: &mut ImageMetadata)
         let mut current_tags = metadata.tags.clone().unwrap_or_default();
         let original_len = current_tags.len();
         let had_no_tags = metadata.tags.is_none();
+        let had_label = current_tags.iter().any(|t| t.starts_with(COLOR_TAG_PREFIX));
 
         for tag in xmp_tags {
             if !current_tags.contains(&tag) {
@@ -3680,15 +4657,22 @@ pub fn sync_metadata_from_xmp(source_path: &Path, metadata: &mut ImageMetadata)
             }
         }
 
-        if let Some(label) = xmp_label {
+        let mut label_changed = false;
+        if let Some(label) = xmp_label
+            && (!had_label || prefer_external)
+        {
             let label_tag = format!("{}{}", COLOR_TAG_PREFIX, label.to_lowercase());
             if !current_tags.contains(&label_tag) {
                 current_tags.retain(|t| !t.starts_with(COLOR_TAG_PREFIX));
                 current_tags.push(label_tag);
+                label_changed = true;
             }
         }
 
-        if current_tags.len() != original_len || (had_no_tags && !current_tags.is_empty()) {
+        if label_changed
+            || current_tags.len() != original_len
+            || (had_no_tags && !current_tags.is_empty())
+        {
             metadata.tags = Some(current_tags);
             changed = true;
         }
@@ -3696,6 +4680,74 @@ pub fn sync_metadata_from_xmp(source_path: &Path, metadata: &mut ImageMetadata)
     changed
 }
 
+const XMP_SKELETON: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="RapidRAW">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/">
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+
+/// The color label stored in the tags, capitalized the way other DAMs write `xmp:Label`.
+fn xmp_color_label(metadata: &ImageMetadata) -> Option<String> {
+    metadata.tags.as_ref()?.iter().rev().find_map(|t| {
+        let color = t.strip_prefix(COLOR_TAG_PREFIX)?;
+        let mut c = color.chars();
+        Some(match c.next() {
+            None => String::new(),
+            Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
+        })
+    })
+}
+
+/// Sets `xmp:Rating` and `xmp:Label` in an XMP packet, adding them when missing and removing
+/// the label when `label` is `None`.
+fn apply_rating_and_label_to_xmp(content: &str, rating: u8, label: Option<&str>) -> String {
+    let mut content = content.to_string();
+    let rating_str = rating.to_string();
+    let re_rating_attr = Regex::new(r#"xmp:Rating\s*=\s*"[^"]*""#).unwrap();
+    let re_rating_tag = Regex::new(r#"<xmp:Rating\s*>[^<]*</xmp:Rating>"#).unwrap();
+
+    if re_rating_attr.is_match(&content) {
+        content = re_rating_attr
+            .replace(&content, format!("xmp:Rating=\"{}\"", rating_str))
+            .to_string();
+    } else if re_rating_tag.is_match(&content) {
+        content = re_rating_tag
+            .replace(&content, format!("<xmp:Rating>{}</xmp:Rating>", rating_str))
+            .to_string();
+    } else if let Some(last_index) = content.rfind("</rdf:Description>") {
+        let (start, end) = content.split_at(last_index);
+        content = format!("{} <xmp:Rating>{}</xmp:Rating>\n{}", start, rating_str, end);
+    }
+
+    if let Some(lbl) = label {
+        let re_label_attr = Regex::new(r#"xmp:Label\s*=\s*"[^"]*""#).unwrap();
+        let re_label_tag = Regex::new(r#"<xmp:Label\s*>[^<]*</xmp:Label>"#).unwrap();
+
+        if re_label_attr.is_match(&content) {
+            content = re_label_attr
+                .replace(&content, format!("xmp:Label=\"{}\"", lbl))
+                .to_string();
+        } else if re_label_tag.is_match(&content) {
+            content = re_label_tag
+                .replace(&content, format!("<xmp:Label>{}</xmp:Label>", lbl))
+                .to_string();
+        } else if let Some(last_index) = content.rfind("</rdf:Description>") {
+            let (start, end) = content.split_at(last_index);
+            content = format!("{} <xmp:Label>{}</xmp:Label>\n{}", start, lbl, end);
+        }
+    } else {
+        let re_label_attr = Regex::new(r#"\s*xmp:Label\s*=\s*"[^"]*""#).unwrap();
+        let re_label_tag = Regex::new(r#"\s*<xmp:Label\s*>[^<]*</xmp:Label>"#).unwrap();
+        content = re_label_attr.replace_all(&content, "").to_string();
+        content = re_label_tag.replace_all(&content, "").to_string();
+    }
+    content
+}
+
 pub fn sync_metadata_to_xmp(source_path: &Path, metadata: &ImageMetadata, create_if_missing: bool) {
     let xmp_path = source_path.with_extension("xmp");
     let xmp_path_upper = source_path.with_extension("XMP");
@@ -3712,16 +4764,7 @@ pub fn sync_metadata_to_xmp(source_path: &Path, metadata: &ImageMetadata, create
         if !create_if_missing {
             return;
         }
-        let skeleton = r#"<?xml version="1.0" encoding="UTF-8"?>
-<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="RapidRAW">
- <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
-  <rdf:Description rdf:about=""
-    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
-    xmlns:dc="http://purl.org/dc/elements/1.1/">
-  </rdf:Description>
- </rdf:RDF>
-</x:xmpmeta>"#;
-        if let Err(e) = fs::write(&xmp_path, skeleton) {
+        if let Err(e) = fs::write(&xmp_path, XMP_SKELETON) {
             log::error!("Failed to create skeleton XMP: {}", e);
             return;
         }
@@ -3729,64 +4772,19 @@ pub fn sync_metadata_to_xmp(source_path: &Path, metadata: &ImageMetadata, create
     }
 
     if let Some(xmp_file) = actual_xmp
-        && let Ok(mut content) = fs::read_to_string(&xmp_file)
+        && let Ok(content) = fs::read_to_string(&xmp_file)
     {
-        let rating_str = metadata.rating.to_string();
-        let re_rating_attr = Regex::new(r#"xmp:Rating\s*=\s*"[^"]*""#).unwrap();
-        let re_rating_tag = Regex::new(r#"<xmp:Rating\s*>[^<]*</xmp:Rating>"#).unwrap();
-
-        if re_rating_attr.is_match(&content) {
-            content = re_rating_attr
-                .replace(&content, format!("xmp:Rating=\"{}\"", rating_str))
-                .to_string();
-        } else if re_rating_tag.is_match(&content) {
-            content = re_rating_tag
-                .replace(&content, format!("<xmp:Rating>{}</xmp:Rating>", rating_str))
-                .to_string();
-        } else if let Some(last_index) = content.rfind("</rdf:Description>") {
-            let (start, end) = content.split_at(last_index);
-            content = format!("{} <xmp:Rating>{}</xmp:Rating>\n{}", start, rating_str, end);
-        }
-
-        let current_tags = metadata.tags.clone().unwrap_or_default();
-        let mut label = None;
-        let mut normal_tags = Vec::new();
-
-        for t in current_tags {
-            if let Some(color) = t.strip_prefix(COLOR_TAG_PREFIX) {
-                let mut c = color.chars();
-                let cap_color = match c.next() {
-                    None => String::new(),
-                    Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
-                };
-                label = Some(cap_color);
-            } else {
-                normal_tags.push(t);
-            }
-        }
-
-        if let Some(lbl) = label {
-            let re_label_attr = Regex::new(r#"xmp:Label\s*=\s*"[^"]*""#).unwrap();
-            let re_label_tag = Regex::new(r#"<xmp:Label\s*>[^<]*</xmp:Label>"#).unwrap();
-
-            if re_label_attr.is_match(&content) {
-                content = re_label_attr
-                    .replace(&content, format!("xmp:Label=\"{}\"", lbl))
-                    .to_string();
-            } else if re_label_tag.is_match(&content) {
-                content = re_label_tag
-                    .replace(&content, format!("<xmp:Label>{}</xmp:Label>", lbl))
-                    .to_string();
-            } else if let Some(last_index) = content.rfind("</rdf:Description>") {
-                let (start, end) = content.split_at(last_index);
-                content = format!("{} <xmp:Label>{}</xmp:Label>\n{}", start, lbl, end);
-            }
-        } else {
-            let re_label_attr = Regex::new(r#"\s*xmp:Label\s*=\s*"[^"]*""#).unwrap();
-            let re_label_tag = Regex::new(r#"\s*<xmp:Label\s*>[^<]*</xmp:Label>"#).unwrap();
-            content = re_label_attr.replace_all(&content, "").to_string();
-            content = re_label_tag.replace_all(&content, "").to_string();
-        }
+        let label = xmp_color_label(metadata);
+        let mut content =
+            apply_rating_and_label_to_xmp(&content, metadata.rating, label.as_deref());
+
+        let normal_tags: Vec<String> = metadata
+            .tags
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|t| !t.starts_with(COLOR_TAG_PREFIX))
+            .collect();
 
         let re_subject =
             Regex::new(r#"(?s)<dc:subject>\s*<rdf:Bag>.*?</rdf:Bag>\s*</dc:subject>"#).unwrap();
@@ -3810,3 +4808,206 @@ pub fn sync_metadata_to_xmp(source_path: &Path, metadata: &ImageMetadata, create
         let _ = fs::write(&xmp_file, content);
     }
 }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditHistoryState {
+    pub position: usize,
+    pub length: usize,
+    pub can_undo: bool,
+    pub can_redo: bool,
+}
+
+impl EditHistoryState {
+    fn from_history(history: Option<&EditHistory>) -> Self {
+        let (position, length) = history.map_or((0, 0), |h| (h.position, h.entries.len()));
+        Self {
+            position,
+            length,
+            can_undo: position > 0,
+            can_redo: position + 1 < length,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_edit_history_state(path: String) -> Result<EditHistoryState, String> {
+    let (_, sidecar_path) = parse_virtual_path(&path);
+    let metadata = crate::exif_processing::load_sidecar(&sidecar_path);
+    Ok(EditHistoryState::from_history(metadata.history.as_ref()))
+}
+
+fn step_edit_history(
+    path: String,
+    forward: bool,
+    app_handle: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<Value, String> {
+    let (_, sidecar_path) = parse_virtual_path(&path);
+    let mut metadata = crate::exif_processing::load_sidecar(&sidecar_path);
+    let history = metadata
+        .history
+        .as_mut()
+        .ok_or_else(|| "No edit history for this image".to_string())?;
+
+    let target = if forward {
+        history.position + 1
+    } else {
+        history
+            .position
+            .checked_sub(1)
+            .ok_or_else(|| "Nothing to undo".to_string())?
+    };
+    let adjustments = history
+        .entries
+        .get(target)
+        .cloned()
+        .ok_or_else(|| "Nothing to redo".to_string())?;
+    history.position = target;
+    metadata.adjustments = adjustments.clone();
+
+    write_metadata_and_refresh_thumbnail(&path, &metadata, app_handle, &state)?;
+    Ok(adjustments)
+}
+
+#[tauri::command]
+pub fn undo_edit(
+    path: String,
+    app_handle: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<Value, String> {
+    step_edit_history(path, false, app_handle, state)
+}
+
+#[tauri::command]
+pub fn redo_edit(
+    path: String,
+    app_handle: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<Value, String> {
+    step_edit_history(path, true, app_handle, state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write_sidecar(path: &Path, adjustments: Value) {
+        let metadata = ImageMetadata {
+            rating: 4,
+            adjustments,
+            ..ImageMetadata::default()
+        };
+        fs::write(path, serde_json::to_string(&metadata).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn moved_image_keeps_its_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src_dir, dest_dir) = (dir.path().join("src"), dir.path().join("dest"));
+        fs::create_dir_all(&src_dir).unwrap();
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let image_path = src_dir.join("photo.jpg");
+        fs::write(&image_path, b"not really a jpeg").unwrap();
+        let image_str = image_path.to_string_lossy().to_string();
+        let virtual_str = format!("{}?vc=2", image_str);
+        let (_, sidecar_path) = parse_virtual_path(&image_str);
+        let (_, virtual_sidecar_path) = parse_virtual_path(&virtual_str);
+        write_sidecar(&sidecar_path, json!({"exposure": 1.25}));
+        write_sidecar(&virtual_sidecar_path, json!({"exposure": -0.5}));
+        fs::write(src_dir.join("photo.xmp"), b"<x:xmpmeta/>").unwrap();
+
+        let (to_remove, renames) =
+            copy_images_for_move(&[image_str.clone()], &dest_dir.to_string_lossy()).unwrap();
+        for path in to_remove {
+            fs::remove_file(path).unwrap();
+        }
+
+        let moved_str = dest_dir.join("photo.jpg").to_string_lossy().to_string();
+        assert_eq!(renames.get(&image_str), Some(&moved_str));
+        assert!(!image_path.exists());
+        assert!(!sidecar_path.exists());
+        assert!(!virtual_sidecar_path.exists());
+        assert!(dest_dir.join("photo.xmp").exists());
+
+        let (_, moved_sidecar) = parse_virtual_path(&moved_str);
+        let moved = crate::exif_processing::load_sidecar(&moved_sidecar);
+        assert_eq!(moved.rating, 4);
+        assert_eq!(moved.adjustments, json!({"exposure": 1.25}));
+
+        let (_, moved_virtual_sidecar) = parse_virtual_path(&format!("{}?vc=2", moved_str));
+        let moved_virtual = crate::exif_processing::load_sidecar(&moved_virtual_sidecar);
+        assert_eq!(moved_virtual.adjustments, json!({"exposure": -0.5}));
+    }
+
+    #[test]
+    fn move_refuses_to_overwrite_existing_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src_dir, dest_dir) = (dir.path().join("src"), dir.path().join("dest"));
+        fs::create_dir_all(&src_dir).unwrap();
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let image_path = src_dir.join("photo.jpg");
+        fs::write(&image_path, b"not really a jpeg").unwrap();
+        let image_str = image_path.to_string_lossy().to_string();
+        write_sidecar(&parse_virtual_path(&image_str).1, json!({"exposure": 1.0}));
+        fs::write(dest_dir.join("photo.jpg.rrdata"), b"{}").unwrap();
+
+        assert!(copy_images_for_move(&[image_str], &dest_dir.to_string_lossy()).is_err());
+        assert!(!dest_dir.join("photo.jpg").exists());
+        assert_eq!(fs::read(dest_dir.join("photo.jpg.rrdata")).unwrap(), b"{}");
+    }
+
+    #[test]
+    fn thumbnail_regenerates_after_sidecar_edit() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumb_dir = dir.path().join("thumbnails");
+        fs::create_dir_all(&thumb_dir).unwrap();
+        let image_path = dir.path().join("photo.jpg");
+        fs::write(&image_path, b"not really a jpeg").unwrap();
+        let image_str = image_path.to_string_lossy().to_string();
+        let (_, sidecar_path) = parse_virtual_path(&image_str);
+        write_sidecar(&sidecar_path, json!({"exposure": 0.5}));
+
+        let format = ThumbnailFormat::Jpeg;
+        let key = get_cache_key_hash(&image_str, true).unwrap();
+        fs::write(thumbnail_cache_path(&thumb_dir, &key, format), b"thumb").unwrap();
+        assert!(find_cached_thumbnail(&thumb_dir, &key, format).is_some());
+
+        // A rating change leaves the rendered pixels alone and keeps the cached thumbnail.
+        let mut metadata = crate::exif_processing::load_sidecar(&sidecar_path);
+        metadata.rating = 5;
+        fs::write(&sidecar_path, serde_json::to_string(&metadata).unwrap()).unwrap();
+        assert_eq!(get_cache_key_hash(&image_str, true).unwrap(), key);
+
+        write_sidecar(&sidecar_path, json!({"exposure": 1.5}));
+        let edited_key = get_cache_key_hash(&image_str, true).unwrap();
+        assert_ne!(edited_key, key);
+        assert!(find_cached_thumbnail(&thumb_dir, &edited_key, format).is_none());
+
+        // Thumbnails that ignore edits are unaffected by the sidecar.
+        assert_eq!(
+            get_cache_key_hash(&image_str, false),
+            compute_thumbnail_cache_hash(&image_str, &[])
+        );
+    }
+
+    #[test]
+    fn thumbnail_regenerates_after_external_source_edit() {
+        let dir = tempfile::tempdir().unwrap();
+        let image_path = dir.path().join("photo.jpg");
+        fs::write(&image_path, b"original pixels").unwrap();
+        let image_str = image_path.to_string_lossy().to_string();
+        let key = get_cache_key_hash(&image_str, true).unwrap();
+
+        fs::write(&image_path, b"retouched elsewhere").unwrap();
+        filetime::set_file_mtime(
+            &image_path,
+            filetime::FileTime::from_unix_time(2_000_000_000, 0),
+        )
+        .unwrap();
+        assert_ne!(get_cache_key_hash(&image_str, true).unwrap(), key);
+    }
+}
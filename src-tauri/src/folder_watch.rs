@@ -0,0 +1,156 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, channel};
+use std::time::{Duration, Instant};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::AppState;
+use crate::file_management::add_to_thumbnail_queue;
+use crate::formats::is_supported_image_file;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const SETTLE_DURATION: Duration = Duration::from_millis(1000);
+
+pub struct FolderWatch {
+    pub path: String,
+    _watcher: RecommendedWatcher,
+    stop_flag: Arc<AtomicBool>,
+}
+
+impl Drop for FolderWatch {
+    fn drop(&mut self) {
+        self.stop_flag.store(true, Ordering::SeqCst);
+    }
+}
+
+struct PendingFile {
+    last_size: u64,
+    last_change: Instant,
+}
+
+fn is_candidate_file(path: &Path) -> bool {
+    let is_hidden_or_temp = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_none_or(|n| n.starts_with('.') || n.starts_with('~') || n.ends_with(".tmp"));
+    !is_hidden_or_temp && is_supported_image_file(path)
+}
+
+fn queue_thumbnail(app_handle: &AppHandle, path: &str) {
+    let state = app_handle.state::<AppState>();
+    {
+        let mut queue = state.thumbnail_manager.queue.lock().unwrap();
+        queue.retain(|p| p != path);
+        queue.push_back(path.to_string());
+    }
+    add_to_thumbnail_queue(&state, 1, app_handle);
+    state.thumbnail_manager.cvar.notify_all();
+}
+
+/// Returns the files whose size has stayed unchanged for `SETTLE_DURATION`, so
+/// half-written files from a tethered camera are not picked up early.
+fn collect_settled_files(pending: &mut HashMap<PathBuf, PendingFile>) -> Vec<PathBuf> {
+    let now = Instant::now();
+    let mut settled = Vec::new();
+
+    pending.retain(|path, entry| {
+        let Ok(size) = fs::metadata(path).map(|m| m.len()) else {
+            return false;
+        };
+        if size != entry.last_size {
+            entry.last_size = size;
+            entry.last_change = now;
+            return true;
+        }
+        if size > 0 && now.duration_since(entry.last_change) >= SETTLE_DURATION {
+            settled.push(path.clone());
+            return false;
+        }
+        true
+    });
+
+    settled
+}
+
+#[tauri::command]
+pub fn start_folder_watch(path: String, app_handle: AppHandle) -> Result<(), String> {
+    let state = app_handle.state::<AppState>();
+    let watch_root = PathBuf::from(&path);
+    if !watch_root.is_dir() {
+        return Err(format!("Cannot watch '{}': not a directory", path));
+    }
+
+    // Replacing the handle drops the previous watcher and stops its thread.
+    *state.folder_watch.lock().unwrap() = None;
+
+    let (tx, rx) = channel::<notify::Result<notify::Event>>();
+    let mut watcher = notify::recommended_watcher(tx).map_err(|e| e.to_string())?;
+    watcher
+        .watch(&watch_root, RecursiveMode::NonRecursive)
+        .map_err(|e| e.to_string())?;
+
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let thread_stop_flag = stop_flag.clone();
+    let thread_app_handle = app_handle.clone();
+
+    std::thread::spawn(move || {
+        let mut pending: HashMap<PathBuf, PendingFile> = HashMap::new();
+        let mut announced: HashSet<PathBuf> = HashSet::new();
+
+        while !thread_stop_flag.load(Ordering::SeqCst) {
+            match rx.recv_timeout(POLL_INTERVAL) {
+                Ok(Ok(event))
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) =>
+                {
+                    for event_path in event.paths {
+                        if announced.contains(&event_path) || !is_candidate_file(&event_path) {
+                            continue;
+                        }
+                        pending
+                            .entry(event_path)
+                            .and_modify(|entry| entry.last_change = Instant::now())
+                            .or_insert(PendingFile {
+                                last_size: u64::MAX,
+                                last_change: Instant::now(),
+                            });
+                    }
+                }
+                Ok(Ok(_)) | Err(RecvTimeoutError::Timeout) => {}
+                Ok(Err(e)) => log::warn!("Folder watch error: {}", e),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            for settled_path in collect_settled_files(&mut pending) {
+                let path_str = settled_path.to_string_lossy().into_owned();
+                log::info!("Folder watch picked up new file: {}", path_str);
+                let _ =
+                    thread_app_handle.emit("file-added", serde_json::json!({ "path": &path_str }));
+                queue_thumbnail(&thread_app_handle, &path_str);
+                announced.insert(settled_path);
+            }
+        }
+        log::info!("Folder watch thread stopped");
+    });
+
+    log::info!("Started watching folder: {}", path);
+    *state.folder_watch.lock().unwrap() = Some(FolderWatch {
+        path,
+        _watcher: watcher,
+        stop_flag,
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_folder_watch(app_handle: AppHandle) -> Result<(), String> {
+    let state = app_handle.state::<AppState>();
+    if let Some(watch) = state.folder_watch.lock().unwrap().take() {
+        log::info!("Stopped watching folder: {}", watch.path);
+    }
+    Ok(())
+}
@@ -0,0 +1,606 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rayon::prelude::*;
+use regex::Regex;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::app_settings::load_settings;
+use crate::exif_processing::{
+    capture_datetime, get_primary_sidecar_path, gps_coordinates, gps_exif_entries, load_sidecar,
+    write_embedded_gps,
+};
+use crate::file_management::{
+    parse_virtual_path, read_exif_for_path, resolve_xmp_path, sync_metadata_to_xmp,
+};
+use crate::formats::is_raw_file;
+use crate::tagging::{USER_TAG_PREFIX, add_tag_for_paths};
+
+// GeoNames "cities1000" and "countryInfo" dumps (tab separated). Downloaded on request into
+// the app data directory; a copy shipped with the resources is used as a fallback.
+const GEONAMES_DIR: &str = "geonames";
+const GEONAMES_CITIES_FILE: &str = "cities1000.txt";
+const GEONAMES_COUNTRIES_FILE: &str = "countryInfo.txt";
+const GEONAMES_BASE_URL: &str = "https://download.geonames.org/export/dump/";
+const DEFAULT_GEOCODING_ADDRESS: &str = "https://nominatim.openstreetmap.org";
+const MAX_OFFLINE_DISTANCE_KM: f64 = 50.0;
+const EARTH_RADIUS_KM: f64 = 6371.0;
+// Public Nominatim instances allow at most one request per second.
+const ONLINE_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+const XMP_EXIF_NAMESPACE: &str = "http://ns.adobe.com/exif/1.0/";
+const EMBEDDED_GPS_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedLocation {
+    pub city: Option<String>,
+    pub country: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GeotagResult {
+    pub path: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub location: Option<ResolvedLocation>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GpsPoint {
+    pub path: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub timestamp: Option<i64>,
+}
+
+struct City {
+    name: String,
+    country_code: String,
+    lat: f64,
+    lon: f64,
+}
+
+#[derive(Deserialize)]
+struct NominatimAddress {
+    city: Option<String>,
+    town: Option<String>,
+    village: Option<String>,
+    municipality: Option<String>,
+    country: Option<String>,
+    country_code: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NominatimResponse {
+    address: Option<NominatimAddress>,
+}
+
+// Two decimals is roughly a kilometre, close enough to share a city lookup.
+fn cache_key(lat: f64, lon: f64) -> String {
+    format!("{:.2},{:.2}", lat, lon)
+}
+
+fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+fn geonames_data_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .map(|dir| dir.join(GEONAMES_DIR))
+        .map_err(|e| e.to_string())
+}
+
+fn resolve_geonames_file(app_handle: &AppHandle, file_name: &str) -> Option<PathBuf> {
+    let downloaded = geonames_data_dir(app_handle).ok()?.join(file_name);
+    if downloaded.exists() {
+        return Some(downloaded);
+    }
+    app_handle
+        .path()
+        .resolve(
+            format!("resources/{}/{}", GEONAMES_DIR, file_name),
+            tauri::path::BaseDirectory::Resource,
+        )
+        .ok()
+}
+
+fn load_offline_cities(app_handle: &AppHandle) -> Vec<City> {
+    let Some(path) = resolve_geonames_file(app_handle, GEONAMES_CITIES_FILE) else {
+        return Vec::new();
+    };
+    let Ok(content) = fs::read_to_string(&path) else {
+        log::info!("No offline city database at {:?}", path);
+        return Vec::new();
+    };
+
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 9 {
+                return None;
+            }
+            Some(City {
+                name: fields[1].to_string(),
+                lat: fields[4].parse().ok()?,
+                lon: fields[5].parse().ok()?,
+                country_code: fields[8].to_string(),
+            })
+        })
+        .collect()
+}
+
+/// ISO 3166 alpha-2 code to English country name.
+fn load_country_names(app_handle: &AppHandle) -> HashMap<String, String> {
+    let Some(path) = resolve_geonames_file(app_handle, GEONAMES_COUNTRIES_FILE) else {
+        return HashMap::new();
+    };
+    let Ok(content) = fs::read_to_string(&path) else {
+        log::info!("No offline country database at {:?}", path);
+        return HashMap::new();
+    };
+    parse_country_names(&content)
+}
+
+fn parse_country_names(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let (code, name) = (fields.first()?, fields.get(4)?);
+            (!code.is_empty() && !name.is_empty()).then(|| (code.to_string(), name.to_string()))
+        })
+        .collect()
+}
+
+/// Offline lookups only know the country code and Nominatim answers in whatever language
+/// it picks, so both are mapped to the GeoNames English name to produce the same tag.
+/// Falls back to `fallback` when the code is unknown.
+fn country_name(
+    countries: &HashMap<String, String>,
+    code: Option<&str>,
+    fallback: Option<String>,
+) -> Option<String> {
+    code.filter(|c| c.len() == 2)
+        .and_then(|c| countries.get(&c.to_ascii_uppercase()).cloned())
+        .or(fallback)
+        .filter(|name| !name.is_empty())
+}
+
+fn lookup_offline(
+    cities: &[City],
+    countries: &HashMap<String, String>,
+    lat: f64,
+    lon: f64,
+) -> Option<ResolvedLocation> {
+    let (distance, city) = cities
+        .par_iter()
+        .map(|c| (haversine_km(lat, lon, c.lat, c.lon), c))
+        .min_by(|a, b| a.0.total_cmp(&b.0))?;
+    (distance <= MAX_OFFLINE_DISTANCE_KM).then(|| ResolvedLocation {
+        city: Some(city.name.clone()),
+        country: country_name(
+            countries,
+            Some(&city.country_code),
+            Some(city.country_code.clone()),
+        ),
+    })
+}
+
+async fn lookup_online(
+    client: &Client,
+    address: &str,
+    countries: &HashMap<String, String>,
+    lat: f64,
+    lon: f64,
+) -> Result<Option<ResolvedLocation>, String> {
+    let url = format!(
+        "{}/reverse?format=jsonv2&zoom=10&accept-language=en&lat={}&lon={}",
+        address.trim_end_matches('/'),
+        lat,
+        lon
+    );
+    let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Geocoding service returned {}", response.status()));
+    }
+    let body: NominatimResponse = response.json().await.map_err(|e| e.to_string())?;
+    Ok(body.address.map(|a| ResolvedLocation {
+        city: a.city.or(a.town).or(a.village).or(a.municipality),
+        country: country_name(countries, a.country_code.as_deref(), a.country),
+    }))
+}
+
+fn get_geocode_cache_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(data_dir.join("geocode_cache.json"))
+}
+
+fn load_geocode_cache(app_handle: &AppHandle) -> HashMap<String, ResolvedLocation> {
+    get_geocode_cache_path(app_handle)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_geocode_cache(
+    app_handle: &AppHandle,
+    cache: &HashMap<String, ResolvedLocation>,
+) -> Result<(), String> {
+    let path = get_geocode_cache_path(app_handle)?;
+    let json_string = serde_json::to_string_pretty(cache).map_err(|e| e.to_string())?;
+    fs::write(path, json_string).map_err(|e| e.to_string())
+}
+
+fn write_geonames_file(dest: &Path, bytes: &[u8]) -> Result<(), String> {
+    let temp_path = dest.with_extension("part");
+    fs::write(&temp_path, bytes).map_err(|e| e.to_string())?;
+    fs::rename(&temp_path, dest).map_err(|e| e.to_string())
+}
+
+async fn fetch_geonames_file(client: &Client, file_name: &str) -> Result<Vec<u8>, String> {
+    let response = client
+        .get(format!("{}{}", GEONAMES_BASE_URL, file_name))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to download {}: {}", file_name, e))?;
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    Ok(bytes.to_vec())
+}
+
+/// Downloads the GeoNames city and country lists used for offline reverse geocoding.
+#[tauri::command]
+pub async fn download_geonames_data(app_handle: AppHandle) -> Result<(), String> {
+    let dir = geonames_data_dir(&app_handle)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let client = Client::new();
+
+    let _ = app_handle.emit("ai-model-download-start", "GeoNames");
+    let result: Result<(), String> = async {
+        let countries = fetch_geonames_file(&client, GEONAMES_COUNTRIES_FILE).await?;
+        let archive = fetch_geonames_file(&client, "cities1000.zip").await?;
+
+        tokio::task::spawn_blocking(move || {
+            let mut archive = zip::ZipArchive::new(std::io::Cursor::new(archive))
+                .map_err(|e| format!("Invalid GeoNames archive: {}", e))?;
+            let mut entry = archive
+                .by_name(GEONAMES_CITIES_FILE)
+                .map_err(|e| format!("Invalid GeoNames archive: {}", e))?;
+            let mut cities = Vec::new();
+            std::io::Read::read_to_end(&mut entry, &mut cities).map_err(|e| e.to_string())?;
+
+            write_geonames_file(&dir.join(GEONAMES_COUNTRIES_FILE), &countries)?;
+            write_geonames_file(&dir.join(GEONAMES_CITIES_FILE), &cities)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+    .await;
+    let _ = app_handle.emit("ai-model-download-finish", "GeoNames");
+    result
+}
+
+/// Whether offline reverse geocoding data is available, downloaded or bundled.
+#[tauri::command]
+pub fn has_geonames_data(app_handle: AppHandle) -> bool {
+    resolve_geonames_file(&app_handle, GEONAMES_CITIES_FILE).is_some_and(|path| path.exists())
+}
+
+#[tauri::command]
+pub async fn geotag_to_location(
+    paths: Vec<String>,
+    app_handle: AppHandle,
+) -> Result<Vec<GeotagResult>, String> {
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let online_address = settings.enable_online_geocoding.unwrap_or(false).then(|| {
+        settings
+            .geocoding_service_address
+            .clone()
+            .filter(|a| !a.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_GEOCODING_ADDRESS.to_string())
+    });
+
+    let handle = app_handle.clone();
+    let (coordinates, cities, countries) = tokio::task::spawn_blocking(move || {
+        let coordinates: Vec<(String, (f64, f64))> = paths
+            .par_iter()
+            .filter_map(|path| {
+                gps_coordinates(&read_exif_for_path(path)).map(|coords| (path.clone(), coords))
+            })
+            .collect();
+        let (cities, countries) = if coordinates.is_empty() {
+            (Vec::new(), HashMap::new())
+        } else {
+            (load_offline_cities(&handle), load_country_names(&handle))
+        };
+        (coordinates, cities, countries)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if cities.is_empty() && online_address.is_none() && !coordinates.is_empty() {
+        log::warn!("No offline city database and online geocoding is disabled");
+    }
+
+    let client = match &online_address {
+        Some(_) => Some(
+            Client::builder()
+                .user_agent(concat!("RapidRAW/", env!("CARGO_PKG_VERSION")))
+                .build()
+                .map_err(|e| e.to_string())?,
+        ),
+        None => None,
+    };
+
+    let mut cache = load_geocode_cache(&app_handle);
+    let mut cache_dirty = false;
+    let mut made_online_request = false;
+    let mut paths_by_tag: HashMap<String, Vec<String>> = HashMap::new();
+    let mut results = Vec::with_capacity(coordinates.len());
+
+    for (path, (lat, lon)) in coordinates {
+        let key = cache_key(lat, lon);
+        let location = match cache.get(&key) {
+            // Entries cached before country names were normalised may still hold a code.
+            Some(cached) => Some(ResolvedLocation {
+                city: cached.city.clone(),
+                country: country_name(
+                    &countries,
+                    cached.country.as_deref(),
+                    cached.country.clone(),
+                ),
+            }),
+            None => {
+                let mut resolved = lookup_offline(&cities, &countries, lat, lon);
+                if let (Some(client), Some(address)) = (&client, &online_address) {
+                    if made_online_request {
+                        tokio::time::sleep(ONLINE_REQUEST_INTERVAL).await;
+                    }
+                    made_online_request = true;
+                    match lookup_online(client, address, &countries, lat, lon).await {
+                        Ok(Some(online)) => resolved = Some(online),
+                        Ok(None) => {}
+                        Err(e) => log::warn!("Reverse geocoding failed for {}: {}", path, e),
+                    }
+                }
+                if let Some(location) = &resolved {
+                    cache.insert(key, location.clone());
+                    cache_dirty = true;
+                }
+                resolved
+            }
+        };
+
+        if let Some(location) = &location {
+            for name in [&location.city, &location.country].into_iter().flatten() {
+                paths_by_tag
+                    .entry(format!("{}{}", USER_TAG_PREFIX, name.to_lowercase()))
+                    .or_default()
+                    .push(path.clone());
+            }
+        }
+
+        results.push(GeotagResult {
+            path,
+            latitude: lat,
+            longitude: lon,
+            location,
+        });
+    }
+
+    if cache_dirty && let Err(e) = save_geocode_cache(&app_handle, &cache) {
+        log::warn!("Failed to save geocode cache: {}", e);
+    }
+
+    for (tag, tag_paths) in paths_by_tag {
+        add_tag_for_paths(tag_paths, tag, app_handle.clone())?;
+    }
+
+    Ok(results)
+}
+
+/// Coordinates of every image that has GPS data, ordered by capture time so the
+/// frontend can draw a track. Images without a timestamp come last.
+#[tauri::command]
+pub async fn get_gps_points(paths: Vec<String>) -> Result<Vec<GpsPoint>, String> {
+    tokio::task::spawn_blocking(move || {
+        let mut points: Vec<GpsPoint> = paths
+            .par_iter()
+            .filter_map(|path| {
+                let exif = read_exif_for_path(path);
+                let (lat, lon) = gps_coordinates(&exif)?;
+                Some(GpsPoint {
+                    path: path.clone(),
+                    lat,
+                    lon,
+                    timestamp: capture_datetime(&exif).map(|dt| dt.timestamp()),
+                })
+            })
+            .collect();
+        points.sort_by_key(|p| (p.timestamp.is_none(), p.timestamp));
+        points
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// XMP stores GPS as "DDD,MM.mmmmk", e.g. "48,51.4000N".
+fn xmp_gps_value(value: f64, positive: char, negative: char) -> String {
+    let abs = value.abs();
+    let degrees = abs.trunc();
+    let minutes = (abs - degrees) * 60.0;
+    let hemisphere = if value < 0.0 { negative } else { positive };
+    format!("{},{:.6}{}", degrees as u32, minutes, hemisphere)
+}
+
+fn set_xmp_property(content: &str, name: &str, value: &str) -> String {
+    let re_attr = Regex::new(&format!(r#"{}\s*=\s*"[^"]*""#, regex::escape(name))).unwrap();
+    let re_tag = Regex::new(&format!(r#"<{0}\s*>[^<]*</{0}>"#, regex::escape(name))).unwrap();
+
+    if re_attr.is_match(content) {
+        re_attr
+            .replace(content, format!("{}=\"{}\"", name, value))
+            .to_string()
+    } else if re_tag.is_match(content) {
+        re_tag
+            .replace(content, format!("<{0}>{1}</{0}>", name, value))
+            .to_string()
+    } else if let Some(last_index) = content.rfind("</rdf:Description>") {
+        let (start, end) = content.split_at(last_index);
+        format!("{} <{1}>{2}</{1}>\n{3}", start, name, value, end)
+    } else {
+        content.to_string()
+    }
+}
+
+fn write_xmp_gps(xmp_path: &Path, lat: f64, lon: f64) -> Result<(), String> {
+    let mut content = fs::read_to_string(xmp_path).map_err(|e| e.to_string())?;
+    if !content.contains(XMP_EXIF_NAMESPACE) {
+        content = content.replacen(
+            "<rdf:Description ",
+            &format!("<rdf:Description xmlns:exif=\"{}\" ", XMP_EXIF_NAMESPACE),
+            1,
+        );
+    }
+    content = set_xmp_property(&content, "exif:GPSLatitude", &xmp_gps_value(lat, 'N', 'S'));
+    content = set_xmp_property(&content, "exif:GPSLongitude", &xmp_gps_value(lon, 'E', 'W'));
+    fs::write(xmp_path, content).map_err(|e| e.to_string())
+}
+
+fn assign_gps(
+    source_path: &Path,
+    lat: f64,
+    lon: f64,
+    enable_xmp_sync: bool,
+    create_xmp_if_missing: bool,
+) -> Result<(), String> {
+    let source_path_str = source_path.to_string_lossy();
+
+    // Reading first makes sure the sidecar's EXIF cache exists before it is amended.
+    let mut exif = read_exif_for_path(&source_path_str);
+    exif.extend(gps_exif_entries(lat, lon));
+    let primary_path = get_primary_sidecar_path(source_path);
+    let mut metadata = load_sidecar(&primary_path);
+    metadata.exif = Some(exif);
+    let json_string = serde_json::to_string_pretty(&metadata).map_err(|e| e.to_string())?;
+    fs::write(&primary_path, json_string).map_err(|e| e.to_string())?;
+
+    let extension = source_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    if is_raw_file(&source_path_str) {
+        // Raw files are never modified, the location goes to the XMP sidecar instead.
+        if enable_xmp_sync {
+            sync_metadata_to_xmp(source_path, &metadata, create_xmp_if_missing);
+            if let Some(xmp_path) = resolve_xmp_path(source_path) {
+                write_xmp_gps(&xmp_path, lat, lon)?;
+            }
+        }
+    } else if EMBEDDED_GPS_EXTENSIONS.contains(&extension.as_str()) {
+        write_embedded_gps(source_path, lat, lon)?;
+    }
+    Ok(())
+}
+
+/// Assigns a location to images that lack one. Returns the paths that could not be
+/// updated.
+#[tauri::command]
+pub async fn set_gps_for_paths(
+    paths: Vec<String>,
+    lat: f64,
+    lon: f64,
+    app_handle: AppHandle,
+) -> Result<Vec<String>, String> {
+    if !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+        return Err(format!("Latitude {} is out of range (-90 to 90)", lat));
+    }
+    if !lon.is_finite() || !(-180.0..=180.0).contains(&lon) {
+        return Err(format!("Longitude {} is out of range (-180 to 180)", lon));
+    }
+
+    let settings = load_settings(app_handle).unwrap_or_default();
+    let enable_xmp_sync = settings.enable_xmp_sync.unwrap_or(false);
+    let create_xmp_if_missing = settings.create_xmp_if_missing.unwrap_or(false);
+
+    tokio::task::spawn_blocking(move || {
+        // Virtual copies share their source file, so each file is only written once.
+        let mut source_paths: Vec<PathBuf> = paths
+            .iter()
+            .map(|path| parse_virtual_path(path).0)
+            .collect();
+        source_paths.sort();
+        source_paths.dedup();
+
+        source_paths
+            .par_iter()
+            .filter_map(|source_path| {
+                assign_gps(
+                    source_path,
+                    lat,
+                    lon,
+                    enable_xmp_sync,
+                    create_xmp_if_missing,
+                )
+                .err()
+                .map(|e| {
+                    log::warn!("Failed to set GPS for {}: {}", source_path.display(), e);
+                    source_path.to_string_lossy().into_owned()
+                })
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_and_online_lookups_produce_the_same_country() {
+        let countries = parse_country_names(
+            "#ISO\tISO3\tISO-Numeric\tfips\tCountry\n\
+             DE\tDEU\t276\tGM\tGermany\n\
+             CH\tCHE\t756\tSZ\tSwitzerland\n",
+        );
+
+        let offline = country_name(&countries, Some("DE"), Some("DE".to_string()));
+        let online = country_name(&countries, Some("de"), Some("Deutschland".to_string()));
+        assert_eq!(offline.as_deref(), Some("Germany"));
+        assert_eq!(offline, online);
+
+        assert_eq!(
+            country_name(&countries, Some("xx"), Some("Atlantis".to_string())).as_deref(),
+            Some("Atlantis")
+        );
+        assert_eq!(
+            country_name(&HashMap::new(), Some("CH"), Some("CH".to_string())).as_deref(),
+            Some("CH")
+        );
+    }
+}
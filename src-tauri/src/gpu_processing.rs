@@ -1,10 +1,14 @@
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use std::time::Instant;
 
 use half::f16;
 use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, Rgba};
+use serde::Serialize;
 use std::num::NonZero;
 
+use tauri::Emitter;
 #[cfg(not(any(target_os = "android", target_os = "linux")))]
 use tauri::Manager;
 use wgpu::util::{DeviceExt, TextureDataOrder};
@@ -135,13 +139,84 @@ impl WgpuDisplay {
     }
 }
 
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GpuAdapterInfo {
+    pub index: usize,
+    pub name: String,
+    pub backend: String,
+    pub device_type: String,
+}
+
+fn find_preferred_adapter(
+    instance: &wgpu::Instance,
+    backends: wgpu::Backends,
+    name: &str,
+    compatible_surface: Option<&wgpu::Surface>,
+) -> Option<wgpu::Adapter> {
+    pollster::block_on(instance.enumerate_adapters(backends))
+        .into_iter()
+        .find(|adapter| {
+            adapter.get_info().name == name
+                && compatible_surface.is_none_or(|surface| adapter.is_surface_supported(surface))
+        })
+}
+
+#[tauri::command]
+pub fn list_gpu_adapters() -> Result<Vec<GpuAdapterInfo>, String> {
+    #[allow(unused_mut)]
+    let mut instance_desc = wgpu::InstanceDescriptor::new_without_display_handle_from_env();
+
+    #[cfg(target_os = "windows")]
+    if std::env::var("WGPU_BACKEND").is_err() {
+        instance_desc.backends = wgpu::Backends::PRIMARY;
+    }
+
+    let backends = instance_desc.backends;
+    let instance = wgpu::Instance::new(instance_desc);
+    let adapters = pollster::block_on(instance.enumerate_adapters(backends))
+        .into_iter()
+        .enumerate()
+        .map(|(index, adapter)| {
+            let info = adapter.get_info();
+            GpuAdapterInfo {
+                index,
+                name: info.name,
+                backend: format!("{:?}", info.backend),
+                device_type: format!("{:?}", info.device_type),
+            }
+        })
+        .collect();
+
+    Ok(adapters)
+}
+
+pub fn get_gpu_context_or_fallback(
+    state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
+) -> Option<GpuContext> {
+    if state.gpu_degraded_mode.load(Ordering::SeqCst) {
+        return None;
+    }
+
+    match get_or_init_gpu_context(state, app_handle) {
+        Ok(context) => Some(context),
+        Err(e) => {
+            log::error!(
+                "GPU initialization failed, falling back to CPU processing: {}",
+                e
+            );
+            state.gpu_degraded_mode.store(true, Ordering::SeqCst);
+            let _ = app_handle.emit("gpu-degraded-mode", serde_json::json!({ "reason": e }));
+            None
+        }
+    }
+}
+
 pub fn get_or_init_gpu_context(
     state: &tauri::State<AppState>,
-    _app_handle: &tauri::AppHandle,
+    app_handle: &tauri::AppHandle,
 ) -> Result<GpuContext, String> {
-    #[cfg(not(any(target_os = "android", target_os = "linux")))]
-    let app_handle = _app_handle;
-
     let mut context_lock = state.gpu_context.lock().unwrap();
     if let Some(context) = &*context_lock {
         return Ok(context.clone());
@@ -163,11 +238,12 @@ pub fn get_or_init_gpu_context(
         let _ = std::fs::write(p, "initializing_gpu");
     }
 
+    let settings = crate::app_settings::load_settings(app_handle.clone()).unwrap_or_default();
+    let backends = instance_desc.backends;
     let instance = wgpu::Instance::new(instance_desc);
 
     #[cfg(not(any(target_os = "android", target_os = "linux")))]
     let surface_opt = {
-        let settings = crate::app_settings::load_settings(app_handle.clone()).unwrap_or_default();
         let use_wgpu_renderer = settings.use_wgpu_renderer.unwrap_or(true);
 
         if use_wgpu_renderer {
@@ -196,17 +272,36 @@ pub fn get_or_init_gpu_context(
     #[cfg(any(target_os = "android", target_os = "linux"))]
     let surface_opt: Option<wgpu::Surface> = None;
 
-    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::HighPerformance,
-        compatible_surface: surface_opt.as_ref(),
-        ..Default::default()
-    }))
-    .map_err(|e| {
-        if let Some(p) = &flag_path {
-            let _ = std::fs::remove_file(p);
-        }
-        format!("Failed to find a wgpu adapter: {}", e)
-    })?;
+    let preferred_adapter = settings
+        .preferred_gpu_adapter
+        .as_deref()
+        .filter(|name| !name.is_empty() && *name != "auto")
+        .and_then(|name| {
+            let found = find_preferred_adapter(&instance, backends, name, surface_opt.as_ref());
+            if found.is_none() {
+                log::warn!(
+                    "Preferred GPU adapter '{}' is not available, falling back to default.",
+                    name
+                );
+            }
+            found
+        });
+
+    let adapter = match preferred_adapter {
+        Some(adapter) => adapter,
+        None => pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: surface_opt.as_ref(),
+            ..Default::default()
+        }))
+        .map_err(|e| {
+            if let Some(p) = &flag_path {
+                let _ = std::fs::remove_file(p);
+            }
+            format!("Failed to find a wgpu adapter: {}", e)
+        })?,
+    };
+    log::info!("Using GPU adapter: {}", adapter.get_info().name);
 
     let mut required_features = wgpu::Features::empty();
     if adapter
@@ -217,6 +312,10 @@ pub fn get_or_init_gpu_context(
     }
 
     let limits = adapter.limits();
+    log::info!(
+        "GPU max texture dimension: {}px, larger images are processed in regions",
+        limits.max_texture_dimension_2d
+    );
 
     let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
         label: Some("Processing Device"),
@@ -412,13 +511,23 @@ pub fn get_or_init_gpu_context(
     Ok(new_context)
 }
 
-fn read_texture_data_roi(
+/// A tile copy that has been recorded and submitted but not read yet. Keeping it
+/// pending lets the CPU unpack one tile while the GPU is already working on the next.
+struct PendingTileReadback {
+    buffer: wgpu::Buffer,
+    submission: wgpu::SubmissionIndex,
+    receiver: std::sync::mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+    padded_bytes_per_row: u32,
+    unpadded_bytes_per_row: u32,
+}
+
+fn record_texture_readback(
     device: &wgpu::Device,
-    queue: &wgpu::Queue,
+    encoder: &mut wgpu::CommandEncoder,
     texture: &wgpu::Texture,
     origin: wgpu::Origin3d,
     size: wgpu::Extent3d,
-) -> Result<Vec<u8>, String> {
+) -> (wgpu::Buffer, u32, u32) {
     let unpadded_bytes_per_row = 4 * size.width;
     let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
     let padded_bytes_per_row = (unpadded_bytes_per_row + align - 1) & !(align - 1);
@@ -431,8 +540,6 @@ fn read_texture_data_roi(
         mapped_at_creation: false,
     });
 
-    let mut encoder =
-        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
     encoder.copy_texture_to_buffer(
         wgpu::TexelCopyTextureInfo {
             texture,
@@ -451,37 +558,95 @@ fn read_texture_data_roi(
         size,
     );
 
-    queue.submit(Some(encoder.finish()));
-    let buffer_slice = output_buffer.slice(..);
-    let (tx, rx) = std::sync::mpsc::channel();
-    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-        let _ = tx.send(result);
-    });
-    device
-        .poll(wgpu::PollType::Wait {
-            submission_index: None,
-            timeout: Some(std::time::Duration::from_secs(60)),
-        })
-        .map_err(|e| format!("Failed while polling mapped GPU buffer: {}", e))?;
-    let map_result = rx
-        .recv()
-        .map_err(|e| format!("Failed receiving GPU map result: {}", e))?;
-    map_result.map_err(|e| e.to_string())?;
-
-    let padded_data = buffer_slice
-        .get_mapped_range()
-        .map_err(|e| format!("Failed to get mapped GPU buffer range: {}", e))?
-        .to_vec();
-    output_buffer.unmap();
-
-    if padded_bytes_per_row == unpadded_bytes_per_row {
-        Ok(padded_data)
-    } else {
-        let mut unpadded_data = Vec::with_capacity((unpadded_bytes_per_row * size.height) as usize);
-        for chunk in padded_data.chunks(padded_bytes_per_row as usize) {
-            unpadded_data.extend_from_slice(&chunk[..unpadded_bytes_per_row as usize]);
+    (output_buffer, padded_bytes_per_row, unpadded_bytes_per_row)
+}
+
+impl PendingTileReadback {
+    fn start(
+        buffer: wgpu::Buffer,
+        submission: wgpu::SubmissionIndex,
+        padded_bytes_per_row: u32,
+        unpadded_bytes_per_row: u32,
+    ) -> Self {
+        let (tx, rx) = std::sync::mpsc::channel();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = tx.send(result);
+            });
+        Self {
+            buffer,
+            submission,
+            receiver: rx,
+            padded_bytes_per_row,
+            unpadded_bytes_per_row,
+        }
+    }
+
+    /// Waits for this tile's submission only, so later tiles keep running on the GPU.
+    fn finish(self, device: &wgpu::Device) -> Result<Vec<u8>, String> {
+        device
+            .poll(wgpu::PollType::Wait {
+                submission_index: Some(self.submission),
+                timeout: Some(std::time::Duration::from_secs(60)),
+            })
+            .map_err(|e| format!("Failed while polling mapped GPU buffer: {}", e))?;
+        let map_result = self
+            .receiver
+            .recv()
+            .map_err(|e| format!("Failed receiving GPU map result: {}", e))?;
+        map_result.map_err(|e| e.to_string())?;
+
+        let buffer_slice = self.buffer.slice(..);
+        let padded_data = buffer_slice
+            .get_mapped_range()
+            .map_err(|e| format!("Failed to get mapped GPU buffer range: {}", e))?
+            .to_vec();
+        self.buffer.unmap();
+
+        if self.padded_bytes_per_row == self.unpadded_bytes_per_row {
+            Ok(padded_data)
+        } else {
+            let row_count = padded_data.len() / self.padded_bytes_per_row as usize;
+            let mut unpadded_data =
+                Vec::with_capacity(self.unpadded_bytes_per_row as usize * row_count);
+            for chunk in padded_data.chunks(self.padded_bytes_per_row as usize) {
+                unpadded_data.extend_from_slice(&chunk[..self.unpadded_bytes_per_row as usize]);
+            }
+            Ok(unpadded_data)
         }
-        Ok(unpadded_data)
+    }
+}
+
+struct TilePlacement {
+    x_start: u32,
+    y_start: u32,
+    crop_x_start: u32,
+    crop_y_start: u32,
+    input_width: u32,
+    tile_width: u32,
+    tile_height: u32,
+}
+
+fn copy_tile_into_output(
+    final_pixels: &mut [u8],
+    tile_data: &[u8],
+    placement: &TilePlacement,
+    bounds_x: u32,
+    bounds_y: u32,
+    out_width: u32,
+) {
+    let copy_bytes = (placement.tile_width * 4) as usize;
+    for row in 0..placement.tile_height {
+        let final_y = placement.y_start + row - bounds_y;
+        let final_x = placement.x_start - bounds_x;
+        let final_row_offset = (final_y * out_width + final_x) as usize * 4;
+        let source_y = placement.crop_y_start + row;
+        let source_row_offset =
+            (source_y * placement.input_width + placement.crop_x_start) as usize * 4;
+
+        final_pixels[final_row_offset..final_row_offset + copy_bytes]
+            .copy_from_slice(&tile_data[source_row_offset..source_row_offset + copy_bytes]);
     }
 }
 
@@ -554,6 +719,51 @@ pub struct GpuProcessor {
 }
 
 const FLARE_MAP_SIZE: u32 = 512;
+/// Widest blur the main pass reads, at the 1080 px reference size.
+const STRUCTURE_BLUR_RADIUS: f32 = 40.0;
+
+fn input_texture_bytes(width: u32, height: u32) -> u64 {
+    width as u64 * height as u64 * 8
+}
+
+fn processor_texture_bytes(width: u32, height: u32) -> u64 {
+    let pixels = width as u64 * height as u64;
+    let flare_pixels = FLARE_MAP_SIZE as u64 * FLARE_MAP_SIZE as u64;
+    // Five Rgba16Float blur/scratch targets, three Rgba8 outputs and three flare maps.
+    pixels * (5 * 8 + 3 * 4) + flare_pixels * 3 * 8
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GpuMemoryStats {
+    pub texture_bytes: u64,
+    pub image_cache_bytes: u64,
+    pub processor_bytes: u64,
+    pub budget_bytes: u64,
+}
+
+#[tauri::command]
+pub fn get_gpu_memory_stats(state: tauri::State<AppState>) -> Result<GpuMemoryStats, String> {
+    let processor_bytes = state
+        .gpu_processor
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map_or(0, |p| p.size_bytes);
+    let image_cache_bytes = state
+        .gpu_image_cache
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map_or(0, |c| c.size_bytes);
+
+    Ok(GpuMemoryStats {
+        texture_bytes: processor_bytes + image_cache_bytes,
+        image_cache_bytes,
+        processor_bytes,
+        budget_bytes: state.gpu_memory_budget.load(Ordering::SeqCst),
+    })
+}
 
 impl GpuProcessor {
     pub fn new(context: GpuContext, max_width: u32, max_height: u32) -> Result<Self, String> {
@@ -1085,10 +1295,20 @@ impl GpuProcessor {
         request: RenderRequest,
         skip_cpu_readback: bool,
         output_to_display: bool,
+        flare_source: Option<&DynamicImage>,
     ) -> Result<(Vec<u8>, u32, u32, u32, u32), String> {
         let device = &self.context.device;
         let queue = &self.context.queue;
-        let scale = (width.min(height) as f32) / 1080.0;
+        let (full_width, full_height) =
+            if request.adjustments.image_width > 0 && request.adjustments.image_height > 0 {
+                (
+                    request.adjustments.image_width,
+                    request.adjustments.image_height,
+                )
+            } else {
+                (width, height)
+            };
+        let scale = (full_width.min(full_height) as f32) / 1080.0;
         const MAX_MASK_BINDINGS: u32 = 1;
 
         let bounds = request.roi.unwrap_or(Roi {
@@ -1184,8 +1404,8 @@ impl GpuProcessor {
         if adjustments.global.flare_amount > 0.0 {
             let mut encoder = device.create_command_encoder(&Default::default());
 
-            let aspect_ratio = if height > 0 {
-                width as f32 / height as f32
+            let aspect_ratio = if full_height > 0 {
+                full_width as f32 / full_height as f32
             } else {
                 1.0
             };
@@ -1201,13 +1421,42 @@ impl GpuProcessor {
             };
             queue.write_buffer(&self.flare_params_buffer, 0, bytemuck::bytes_of(&f_params));
 
+            // A region of an oversized image only sees part of the frame, so the flare map
+            // is built from a downscaled copy of the whole image instead.
+            let flare_source_view = flare_source.map(|image| {
+                let (source_width, source_height) = image.dimensions();
+                device
+                    .create_texture_with_data(
+                        queue,
+                        &wgpu::TextureDescriptor {
+                            label: Some("Flare Source Texture"),
+                            size: wgpu::Extent3d {
+                                width: source_width,
+                                height: source_height,
+                                depth_or_array_layers: 1,
+                            },
+                            mip_level_count: 1,
+                            sample_count: 1,
+                            dimension: wgpu::TextureDimension::D2,
+                            format: wgpu::TextureFormat::Rgba16Float,
+                            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                                | wgpu::TextureUsages::COPY_DST,
+                            view_formats: &[],
+                        },
+                        TextureDataOrder::MipMajor,
+                        bytemuck::cast_slice(&to_rgba_f16(image)),
+                    )
+                    .create_view(&Default::default())
+            });
+            let flare_input_view = flare_source_view.as_ref().unwrap_or(input_texture_view);
+
             let bg0 = device.create_bind_group(&wgpu::BindGroupDescriptor {
                 label: Some("Flare BG0"),
                 layout: &self.flare_bgl_0,
                 entries: &[
                     wgpu::BindGroupEntry {
                         binding: 0,
-                        resource: wgpu::BindingResource::TextureView(input_texture_view),
+                        resource: wgpu::BindingResource::TextureView(flare_input_view),
                     },
                     wgpu::BindGroupEntry {
                         binding: 1,
@@ -1237,7 +1486,7 @@ impl GpuProcessor {
                 entries: &[
                     wgpu::BindGroupEntry {
                         binding: 0,
-                        resource: wgpu::BindingResource::TextureView(input_texture_view),
+                        resource: wgpu::BindingResource::TextureView(flare_input_view),
                     },
                     wgpu::BindGroupEntry {
                         binding: 1,
@@ -1292,6 +1541,8 @@ impl GpuProcessor {
             }
         ];
 
+        let mut pending_tile: Option<(PendingTileReadback, TilePlacement)> = None;
+
         let start_tile_x = bounds.x / TILE_SIZE;
         let start_tile_y = bounds.y / TILE_SIZE;
         let end_tile_x = (bounds.x + bounds.width).div_ceil(TILE_SIZE);
@@ -1406,7 +1657,8 @@ impl GpuProcessor {
                 let did_create_sharpness_blur = run_blur(1.0, &self.sharpness_blur_view);
                 let did_create_tonal_blur = run_blur(3.5, &self.tonal_blur_view);
                 let did_create_clarity_blur = run_blur(8.0, &self.clarity_blur_view);
-                let did_create_structure_blur = run_blur(40.0, &self.structure_blur_view);
+                let did_create_structure_blur =
+                    run_blur(STRUCTURE_BLUR_RADIUS, &self.structure_blur_view);
 
                 let mut main_encoder = device.create_command_encoder(&Default::default());
 
@@ -1545,42 +1797,72 @@ impl GpuProcessor {
                     );
                 }
 
-                queue.submit(Some(main_encoder.finish()));
+                if skip_cpu_readback {
+                    queue.submit(Some(main_encoder.finish()));
+                    continue;
+                }
 
-                if !skip_cpu_readback {
-                    let processed_tile_data = read_texture_data_roi(
-                        device,
-                        queue,
-                        &self.tile_output_texture,
-                        wgpu::Origin3d::ZERO,
-                        input_texture_size,
-                    )?;
-
-                    for row in 0..tile_height {
-                        let final_y = y_start + row - bounds.y;
-                        let final_x = x_start - bounds.x;
-                        let final_row_offset = (final_y * out_width + final_x) as usize * 4;
-                        let source_y = crop_y_start + row;
-                        let source_row_offset =
-                            (source_y * input_width + crop_x_start) as usize * 4;
-                        let copy_bytes = (tile_width * 4) as usize;
-
-                        final_pixels[final_row_offset..final_row_offset + copy_bytes]
-                            .copy_from_slice(
-                                &processed_tile_data
-                                    [source_row_offset..source_row_offset + copy_bytes],
-                            );
-                    }
+                let (readback_buffer, padded_bpr, unpadded_bpr) = record_texture_readback(
+                    device,
+                    &mut main_encoder,
+                    &self.tile_output_texture,
+                    wgpu::Origin3d::ZERO,
+                    input_texture_size,
+                );
+                let submission = queue.submit(Some(main_encoder.finish()));
+                let readback = PendingTileReadback::start(
+                    readback_buffer,
+                    submission,
+                    padded_bpr,
+                    unpadded_bpr,
+                );
+
+                // Unpack the previous tile while the GPU processes this one.
+                if let Some((previous, placement)) = pending_tile.take() {
+                    let tile_data = previous.finish(device)?;
+                    copy_tile_into_output(
+                        &mut final_pixels,
+                        &tile_data,
+                        &placement,
+                        bounds.x,
+                        bounds.y,
+                        out_width,
+                    );
                 }
+
+                pending_tile = Some((
+                    readback,
+                    TilePlacement {
+                        x_start,
+                        y_start,
+                        crop_x_start,
+                        crop_y_start,
+                        input_width,
+                        tile_width,
+                        tile_height,
+                    },
+                ));
             }
         }
 
+        if let Some((previous, placement)) = pending_tile.take() {
+            let tile_data = previous.finish(device)?;
+            copy_tile_into_output(
+                &mut final_pixels,
+                &tile_data,
+                &placement,
+                bounds.x,
+                bounds.y,
+                out_width,
+            );
+        }
+
         Ok((final_pixels, out_width, out_height, bounds.x, bounds.y))
     }
 }
 
 pub fn process_and_get_dynamic_image(
-    context: &GpuContext,
+    context: Option<&GpuContext>,
     state: &tauri::State<AppState>,
     base_image: &DynamicImage,
     transform_hash: u64,
@@ -1596,12 +1878,13 @@ pub fn process_and_get_dynamic_image(
         caller_id,
         false,
         None,
+        None,
     )
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn process_and_get_dynamic_image_with_analytics(
-    context: &GpuContext,
+    context: Option<&GpuContext>,
     state: &tauri::State<AppState>,
     base_image: &DynamicImage,
     transform_hash: u64,
@@ -1619,20 +1902,167 @@ pub fn process_and_get_dynamic_image_with_analytics(
         caller_id,
         output_to_display,
         analytics_config,
+        None,
     )
 }
 
+const MIN_OVERSIZED_REGION_OVERLAP: u32 = 256;
+/// Long edge of the downscaled whole frame that the flare map of every region is built
+/// from.
+const OVERSIZED_FLARE_SOURCE_SIZE: u32 = 2048;
+
+/// Overlap between regions of an oversized image. The blurs behind clarity, structure
+/// and local contrast scale with the image, so each region needs at least the widest
+/// blur radius of context on every side to avoid seams.
+fn oversized_region_overlap(width: u32, height: u32, max_dim: u32) -> u32 {
+    let scale = width.min(height) as f32 / 1080.0;
+    let blur_radius = (STRUCTURE_BLUR_RADIUS * scale).ceil() as u32;
+    (blur_radius + 16)
+        .max(MIN_OVERSIZED_REGION_OVERLAP)
+        .min(max_dim / 4)
+}
+
+/// Renders an image that exceeds the GPU texture limit as overlapping regions that each
+/// fit on the GPU, then stitches the centres of those regions back together. Each region
+/// carries its offset in the full image so vignettes and other position-dependent effects
+/// line up across region borders, and the flare map is built once from the whole frame.
 #[allow(clippy::too_many_arguments)]
-fn process_and_get_dynamic_image_inner(
+fn process_oversized_image(
     context: &GpuContext,
     state: &tauri::State<AppState>,
     base_image: &DynamicImage,
     transform_hash: u64,
     request: RenderRequest,
     caller_id: &str,
+    max_dim: u32,
+) -> Result<DynamicImage, String> {
+    let start_time = Instant::now();
+    let (width, height) = base_image.dimensions();
+    let overlap = oversized_region_overlap(width, height, max_dim);
+    let region_size = max_dim.saturating_sub(2 * overlap).max(overlap);
+    let flare_source = (request.adjustments.global.flare_amount > 0.0).then(|| {
+        base_image.resize(
+            OVERSIZED_FLARE_SOURCE_SIZE,
+            OVERSIZED_FLARE_SOURCE_SIZE,
+            image::imageops::FilterType::Triangle,
+        )
+    });
+    let bounds = request.roi.unwrap_or(Roi {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    });
+    let bounds_x_end = bounds.x + bounds.width;
+    let bounds_y_end = bounds.y + bounds.height;
+
+    let mut stitched = image::RgbaImage::new(bounds.width, bounds.height);
+    let mut region_count = 0;
+
+    for region_y in 0..height.div_ceil(region_size) {
+        for region_x in 0..width.div_ceil(region_size) {
+            let x_start = (region_x * region_size).max(bounds.x);
+            let y_start = (region_y * region_size).max(bounds.y);
+            let x_end = ((region_x + 1) * region_size).min(width).min(bounds_x_end);
+            let y_end = ((region_y + 1) * region_size).min(height).min(bounds_y_end);
+            if x_start >= x_end || y_start >= y_end {
+                continue;
+            }
+
+            let input_x = x_start.saturating_sub(overlap);
+            let input_y = y_start.saturating_sub(overlap);
+            let input_width = (x_end + overlap).min(width) - input_x;
+            let input_height = (y_end + overlap).min(height) - input_y;
+
+            let region_image = base_image.crop_imm(input_x, input_y, input_width, input_height);
+            let region_masks: Vec<ImageBuffer<Luma<u8>, Vec<u8>>> = request
+                .mask_bitmaps
+                .iter()
+                .map(|mask| {
+                    image::imageops::crop_imm(mask, input_x, input_y, input_width, input_height)
+                        .to_image()
+                })
+                .collect();
+
+            let mut region_adjustments = request.adjustments;
+            region_adjustments.image_offset_x = input_x;
+            region_adjustments.image_offset_y = input_y;
+            region_adjustments.image_width = width;
+            region_adjustments.image_height = height;
+
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            (transform_hash, input_x, input_y, input_width, input_height).hash(&mut hasher);
+
+            let rendered = process_and_get_dynamic_image_inner(
+                Some(context),
+                state,
+                &region_image,
+                hasher.finish(),
+                RenderRequest {
+                    adjustments: region_adjustments,
+                    mask_bitmaps: &region_masks,
+                    lut: request.lut.clone(),
+                    roi: Some(Roi {
+                        x: x_start - input_x,
+                        y: y_start - input_y,
+                        width: x_end - x_start,
+                        height: y_end - y_start,
+                    }),
+                },
+                caller_id,
+                false,
+                None,
+                flare_source.as_ref(),
+            )?;
+
+            image::imageops::replace(
+                &mut stitched,
+                &rendered.to_rgba8(),
+                (x_start - bounds.x) as i64,
+                (y_start - bounds.y) as i64,
+            );
+            region_count += 1;
+        }
+    }
+
+    log::info!(
+        "[{}] {}x{} processed in {} GPU regions in {:?}",
+        caller_id,
+        width,
+        height,
+        region_count,
+        start_time.elapsed()
+    );
+    Ok(DynamicImage::ImageRgba8(stitched))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_and_get_dynamic_image_inner(
+    context: Option<&GpuContext>,
+    state: &tauri::State<AppState>,
+    base_image: &DynamicImage,
+    transform_hash: u64,
+    request: RenderRequest,
+    caller_id: &str,
     output_to_display: bool,
     analytics_config: Option<crate::AnalyticsConfig>,
+    flare_source: Option<&DynamicImage>,
 ) -> Result<DynamicImage, String> {
+    let Some(context) = context else {
+        log::info!("[{}] GPU unavailable, using CPU fallback", caller_id);
+        let processed = crate::cpu_processing::process_image_cpu(base_image, &request);
+        if let Some(analytics) = analytics_config {
+            let _ = analytics.sender.send(crate::AnalyticsJob {
+                path: analytics.path,
+                image: Arc::new(processed.clone()),
+                compute_waveform: analytics.compute_waveform,
+                active_waveform_channel: analytics.active_waveform_channel,
+                histogram_region: analytics.histogram_region,
+            });
+        }
+        return Ok(processed);
+    };
+
     let start_time = Instant::now();
     let (width, height) = base_image.dimensions();
     let device = &context.device;
@@ -1640,24 +2070,48 @@ fn process_and_get_dynamic_image_inner(
 
     let max_dim = context.limits.max_texture_dimension_2d;
     if width > max_dim || height > max_dim {
-        log::warn!(
-            "Image dimensions ({}x{}) exceed GPU limits ({}). Bypassing GPU processing and returning unprocessed image to prevent a crash. Try upgrading your GPU :)",
+        log::info!(
+            "[{}] Image dimensions ({}x{}) exceed GPU limits ({}), processing in regions",
+            caller_id,
             width,
             height,
             max_dim
         );
-        return Ok(base_image.clone());
+        let processed = process_oversized_image(
+            context,
+            state,
+            base_image,
+            transform_hash,
+            request,
+            caller_id,
+            max_dim,
+        )?;
+        if let Some(analytics) = analytics_config {
+            let _ = analytics.sender.send(crate::AnalyticsJob {
+                path: analytics.path,
+                image: Arc::new(processed.clone()),
+                compute_waveform: analytics.compute_waveform,
+                active_waveform_channel: analytics.active_waveform_channel,
+                histogram_region: analytics.histogram_region,
+            });
+        }
+        return Ok(processed);
     }
 
     let mut reallocated = false;
 
+    let budget_bytes = state.gpu_memory_budget.load(Ordering::SeqCst);
+    let input_bytes = input_texture_bytes(width, height);
+
     let mut processor_lock = state.gpu_processor.lock().unwrap();
     let mut needs_new_processor = false;
     let new_width = (width + 255) & !255;
     let new_height = (height + 255) & !255;
 
     if let Some(p) = processor_lock.as_ref() {
-        if p.width < width || p.height < height {
+        let oversized = p.width > new_width || p.height > new_height;
+        let over_budget = budget_bytes > 0 && p.size_bytes + input_bytes > budget_bytes;
+        if p.width < width || p.height < height || (oversized && over_budget) {
             needs_new_processor = true;
         }
     } else {
@@ -1671,14 +2125,37 @@ fn process_and_get_dynamic_image_inner(
             new_height
         );
 
-        if let Ok(mut display_lock) = context.display.lock()
-            && let Some(display) = display_lock.as_mut()
-        {
-            display.current_bind_group = None;
-        }
-
+        let display_bound = context
+            .display
+            .lock()
+            .ok()
+            .and_then(|mut display_lock| {
+                display_lock
+                    .as_mut()
+                    .map(|display| display.current_bind_group.take().is_some())
+            })
+            .unwrap_or(false);
+
+        // The native display samples the old processor's output texture, so keep it until
+        // its frame has been copied over; otherwise a render that only grows the processor
+        // (export, full resolution scopes) would leave the view blank.
         let old_processor = processor_lock.take();
-        drop(old_processor);
+        let retained_processor = if display_bound {
+            old_processor
+        } else {
+            drop(old_processor);
+            None
+        };
+
+        let new_processor_bytes = processor_texture_bytes(new_width, new_height);
+        if budget_bytes > 0 {
+            let mut cache_lock = state.gpu_image_cache.lock().unwrap();
+            let cached_bytes = cache_lock.as_ref().map_or(0, |c| c.size_bytes);
+            if new_processor_bytes + cached_bytes > budget_bytes {
+                log::info!("Evicting cached GPU input texture to stay within VRAM budget");
+                drop(cache_lock.take());
+            }
+        }
 
         let _ = context.device.poll(wgpu::PollType::Wait {
             submission_index: None,
@@ -1687,10 +2164,27 @@ fn process_and_get_dynamic_image_inner(
 
         let new_processor = GpuProcessor::new(context.clone(), new_width, new_height)?;
 
+        if let Some(old) = retained_processor {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Display Migration Encoder"),
+            });
+            encoder.copy_texture_to_texture(
+                old.processor.output_texture.as_image_copy(),
+                new_processor.output_texture.as_image_copy(),
+                wgpu::Extent3d {
+                    width: old.width.min(new_width),
+                    height: old.height.min(new_height),
+                    depth_or_array_layers: 1,
+                },
+            );
+            queue.submit(Some(encoder.finish()));
+        }
+
         *processor_lock = Some(crate::GpuProcessorState {
             processor: new_processor,
             width: new_width,
             height: new_height,
+            size_bytes: new_processor_bytes,
         });
         reallocated = true;
     }
@@ -1747,6 +2241,16 @@ fn process_and_get_dynamic_image_inner(
         let old_cache = cache_lock.take();
         drop(old_cache);
 
+        if budget_bytes > 0 && processor_state.size_bytes + input_bytes > budget_bytes {
+            log::warn!(
+                "GPU textures for {}x{} ({} MB) exceed the configured VRAM budget ({} MB)",
+                width,
+                height,
+                (processor_state.size_bytes + input_bytes) / (1024 * 1024),
+                budget_bytes / (1024 * 1024)
+            );
+        }
+
         let _ = context.device.poll(wgpu::PollType::Wait {
             submission_index: None,
             timeout: Some(std::time::Duration::from_millis(500)),
@@ -1781,6 +2285,7 @@ fn process_and_get_dynamic_image_inner(
             width,
             height,
             transform_hash,
+            size_bytes: input_bytes,
         });
     }
 
@@ -1795,6 +2300,7 @@ fn process_and_get_dynamic_image_inner(
         request,
         skip_readback,
         output_to_display,
+        flare_source,
     )?;
 
     let mut final_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -1939,6 +2445,7 @@ fn process_and_get_dynamic_image_inner(
                             image: std::sync::Arc::new(dynamic_img),
                             compute_waveform: analytics.compute_waveform,
                             active_waveform_channel: analytics.active_waveform_channel,
+                            histogram_region: analytics.histogram_region,
                         });
                     }
                 }
@@ -1955,6 +2462,7 @@ fn process_and_get_dynamic_image_inner(
                         image: std::sync::Arc::new(dynamic_img),
                         compute_waveform: analytics.compute_waveform,
                         active_waveform_channel: analytics.active_waveform_channel,
+                        histogram_region: analytics.histogram_region,
                     });
                 }
             });
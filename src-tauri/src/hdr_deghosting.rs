@@ -1,25 +1,40 @@
 use crate::app_settings::AppSettings;
 use crate::exif_processing::{read_exposure_time_secs, read_iso};
+use crate::file_management::read_file_mapped;
 use crate::formats::is_raw_file;
 use crate::image_loader::load_base_image_from_bytes;
 use crate::image_processing::{
     apply_cpu_default_raw_processing, apply_linear_to_srgb, apply_srgb_to_linear,
+    srgb_to_linear_scalar,
 };
-use crate::panorama_stitching::{Feature, KeyPoint, Match};
+use crate::panorama_stitching::{Feature, KeyPoint, Match, emit_stage_progress};
 use crate::panorama_utils::{processing, stitching};
-use image::{DynamicImage, GenericImageView, Rgb32FImage};
+use image::{DynamicImage, GenericImageView, GrayImage, Rgb32FImage};
 use nalgebra::{Matrix2, Matrix3, Point2};
+use rayon::prelude::*;
 use std::fs;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use tauri::{AppHandle, Emitter};
+use tauri::AppHandle;
 
 pub type HdrFrame = (String, DynamicImage, Duration, f32);
 
+pub const HDR_CANCELLED: &str = "HDR merge cancelled.";
+
+// Cumulative percentages at which each merge stage ends.
+pub const HDR_LOAD_STAGE_END: f32 = 50.0;
+pub const HDR_ALIGN_STAGE_END: f32 = 75.0;
+pub const HDR_MERGE_STAGE_END: f32 = 95.0;
+
 const DEGHOST_FAST_THRESHOLD: u8 = 8;
 const DEGHOST_NON_MAXIMA_SUPPRESSION_RADIUS: f32 = 8.0;
 const DEGHOST_MAX_PROCESSING_DIMENSION: u32 = 3200;
 const DEGHOST_IDENTITY_MAX_DISPLACEMENT: f64 = 1.0;
+const BLEND_MATCH_SAMPLE_TARGET: u64 = 65_536;
+// Linear luminance range of source pixels trusted when matching brightness to the merge.
+const BLEND_MATCH_MIN_LUMA: f32 = 0.02;
+const BLEND_MATCH_MAX_LUMA: f32 = 0.9;
 
 enum AlignmentOutcome {
     Warped(Rgb32FImage),
@@ -33,17 +48,31 @@ struct FrameDetection {
     scale_factor: f64,
 }
 
+pub fn check_hdr_cancelled(cancel_token: &AtomicBool) -> Result<(), String> {
+    if cancel_token.load(Ordering::SeqCst) {
+        Err(HDR_CANCELLED.to_string())
+    } else {
+        Ok(())
+    }
+}
+
 pub fn load_hdr_frames(
     paths: &[String],
     app_handle: &AppHandle,
     settings: &AppSettings,
+    cancel_token: &AtomicBool,
 ) -> Result<Vec<HdrFrame>, String> {
     assert!(paths.len() >= 2, "hdr merge requires at least two paths");
     paths
         .iter()
-        .map(|path| {
-            let _ = app_handle.emit(
+        .enumerate()
+        .map(|(index, path)| {
+            check_hdr_cancelled(cancel_token)?;
+            emit_stage_progress(
+                app_handle,
                 "hdr-progress",
+                "loading",
+                HDR_LOAD_STAGE_END * index as f32 / paths.len() as f32,
                 format!(
                     "Processing '{}'",
                     Path::new(path)
@@ -52,19 +81,30 @@ pub fn load_hdr_frames(
                         .to_string_lossy()
                 ),
             );
-            let file_bytes =
-                fs::read(path).map_err(|e| format!("Failed to read image {}: {}", path, e))?;
+            let file_slice: Vec<u8>;
+            let mmap_guard;
+            let file_bytes: &[u8] = match read_file_mapped(Path::new(path)) {
+                Ok(mmap) => {
+                    mmap_guard = Some(mmap);
+                    mmap_guard.as_ref().unwrap()
+                }
+                Err(_) => {
+                    file_slice = fs::read(path)
+                        .map_err(|e| format!("Failed to read image {}: {}", path, e))?;
+                    &file_slice
+                }
+            };
             let mut dynamic_image =
-                load_base_image_from_bytes(&file_bytes, path, false, settings, None)
+                load_base_image_from_bytes(file_bytes, path, false, settings, None)
                     .map_err(|e| format!("Failed to load image {}: {}", path, e))?;
             if !is_raw_file(path) {
                 dynamic_image = apply_srgb_to_linear(dynamic_image);
             }
-            let gains = match read_iso(path, &file_bytes) {
+            let gains = match read_iso(path, file_bytes) {
                 None => return Err(format!("Image {} is missing ISO/Sensitivity data", path)),
                 Some(gains) => gains as f32,
             };
-            let exposure = match read_exposure_time_secs(path, &file_bytes) {
+            let exposure = match read_exposure_time_secs(path, file_bytes) {
                 None => return Err(format!("Image {} is missing ExposureTime data", path)),
                 Some(exp) => Duration::from_secs_f32(exp),
             };
@@ -103,9 +143,21 @@ pub fn assert_uniform_dimensions(frames: &[HdrFrame]) -> Result<(), String> {
     Ok(())
 }
 
-pub fn align_hdr_frames(frames: &mut [HdrFrame], app_handle: &AppHandle) {
+/// Stops early when cancelled, leaving the remaining frames unaligned; callers are expected
+/// to check the token afterwards.
+pub fn align_hdr_frames(
+    frames: &mut [HdrFrame],
+    app_handle: &AppHandle,
+    cancel_token: &AtomicBool,
+) {
     assert!(!frames.is_empty(), "alignment requires at least one frame");
-    let _ = app_handle.emit("hdr-progress", "Deghosting...");
+    emit_stage_progress(
+        app_handle,
+        "hdr-progress",
+        "aligning",
+        HDR_LOAD_STAGE_END,
+        "Deghosting...",
+    );
     let brief_pairs = processing::generate_brief_pairs();
     let reference_index = frames.len() / 2;
     let detections: Vec<FrameDetection> = frames
@@ -113,6 +165,9 @@ pub fn align_hdr_frames(frames: &mut [HdrFrame], app_handle: &AppHandle) {
         .map(|frame| detect_frame_features(&frame.1, &brief_pairs, is_raw_file(&frame.0)))
         .collect();
     for index in 0..frames.len() {
+        if cancel_token.load(Ordering::SeqCst) {
+            return;
+        }
         if index == reference_index {
             continue;
         }
@@ -121,7 +176,15 @@ pub fn align_hdr_frames(frames: &mut [HdrFrame], app_handle: &AppHandle) {
             .unwrap_or_default()
             .to_string_lossy()
             .into_owned();
-        let _ = app_handle.emit("hdr-progress", format!("Aligning '{}'...", file_name));
+        let align_progress = HDR_LOAD_STAGE_END
+            + (HDR_ALIGN_STAGE_END - HDR_LOAD_STAGE_END) * index as f32 / frames.len() as f32;
+        emit_stage_progress(
+            app_handle,
+            "hdr-progress",
+            "aligning",
+            align_progress,
+            format!("Aligning '{}'...", file_name),
+        );
         let outcome = align_frame_to_reference(
             &frames[index].1,
             &detections[index],
@@ -133,8 +196,11 @@ pub fn align_hdr_frames(frames: &mut [HdrFrame], app_handle: &AppHandle) {
             }
             AlignmentOutcome::AlreadyAligned => {}
             AlignmentOutcome::Failed => {
-                let _ = app_handle.emit(
+                emit_stage_progress(
+                    app_handle,
                     "hdr-progress",
+                    "aligning",
+                    align_progress,
                     format!("Could not align '{}', using as-is", file_name),
                 );
             }
@@ -142,17 +208,106 @@ pub fn align_hdr_frames(frames: &mut [HdrFrame], app_handle: &AppHandle) {
     }
 }
 
+/// Renders a linear HDR source frame as it would look on its own, without merging.
+fn tone_map_frame(image: &DynamicImage, source_is_raw: bool) -> DynamicImage {
+    let mut display = image.clone();
+    if source_is_raw {
+        apply_cpu_default_raw_processing(&mut display);
+        display
+    } else {
+        apply_linear_to_srgb(display)
+    }
+}
+
+fn linear_luma(p: &[f32]) -> f32 {
+    0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2]
+}
+
+/// Brings a linear source frame to the brightness of the merged result and encodes it the
+/// same way. The merge is stretched after combining exposures, so the frame is scaled by
+/// the median ratio between the two over its well-exposed pixels.
+pub fn match_frame_to_result(
+    frame: &DynamicImage,
+    result: &DynamicImage,
+) -> Result<DynamicImage, String> {
+    if frame.dimensions() != result.dimensions() {
+        return Err("Source exposure does not match the merged image size.".to_string());
+    }
+    let mut linear = frame.to_rgb32f();
+    let merged = result.to_rgb32f();
+    let pixel_count = linear.width() as u64 * linear.height() as u64;
+    let stride = (pixel_count / BLEND_MATCH_SAMPLE_TARGET).max(1) as usize;
+
+    let mut log_ratios: Vec<f32> = linear
+        .as_raw()
+        .chunks_exact(3)
+        .zip(merged.as_raw().chunks_exact(3))
+        .step_by(stride)
+        .filter_map(|(src, dst)| {
+            let src_luma = linear_luma(src);
+            if !(BLEND_MATCH_MIN_LUMA..=BLEND_MATCH_MAX_LUMA).contains(&src_luma) {
+                return None;
+            }
+            let dst_luma = linear_luma(&[
+                srgb_to_linear_scalar(dst[0]),
+                srgb_to_linear_scalar(dst[1]),
+                srgb_to_linear_scalar(dst[2]),
+            ]);
+            (dst_luma > 1e-4).then(|| (dst_luma / src_luma).ln())
+        })
+        .collect();
+
+    let gain = if log_ratios.is_empty() {
+        log::warn!("No well-exposed pixels to match the HDR source frame, using it unscaled");
+        1.0
+    } else {
+        let mid = log_ratios.len() / 2;
+        let (_, median, _) = log_ratios.select_nth_unstable_by(mid, f32::total_cmp);
+        median.exp()
+    };
+
+    linear.par_iter_mut().for_each(|v| *v *= gain);
+    Ok(apply_linear_to_srgb(DynamicImage::ImageRgb32F(linear)))
+}
+
+/// Replaces the merged result with `source` wherever `mask` is painted, feathered by the
+/// mask's grey levels. The mask is stretched to the result size if needed.
+pub fn blend_frame_into_result(
+    result: &DynamicImage,
+    source: &DynamicImage,
+    mask: &GrayImage,
+) -> Result<DynamicImage, String> {
+    let (width, height) = result.dimensions();
+    if source.dimensions() != (width, height) {
+        return Err("Source exposure does not match the merged image size.".to_string());
+    }
+    let mask = if mask.dimensions() != (width, height) {
+        image::imageops::resize(mask, width, height, image::imageops::FilterType::Triangle)
+    } else {
+        mask.clone()
+    };
+
+    let mut blended = result.to_rgb32f();
+    let source = source.to_rgb32f();
+    blended
+        .par_chunks_mut(3)
+        .zip(source.par_chunks(3))
+        .zip(mask.as_raw().par_iter())
+        .for_each(|((dst, src), &m)| {
+            let weight = m as f32 / 255.0;
+            for (d, s) in dst.iter_mut().zip(src) {
+                *d += (s - *d) * weight;
+            }
+        });
+    Ok(DynamicImage::ImageRgb32F(blended))
+}
+
 fn detect_frame_features(
     image: &DynamicImage,
     brief_pairs: &[(Point2<i32>, Point2<i32>)],
     source_is_raw: bool,
 ) -> FrameDetection {
-    let mut detection_proxy = image.clone();
-    if source_is_raw {
-        apply_cpu_default_raw_processing(&mut detection_proxy);
-    } else {
-        detection_proxy = apply_linear_to_srgb(detection_proxy);
-    }
+    let detection_proxy = tone_map_frame(image, source_is_raw);
     let gray_full = image::imageops::colorops::grayscale(&detection_proxy.to_rgb8());
     let (width, height) = gray_full.dimensions();
     let (small_width, small_height, scale_factor) =
@@ -35,6 +35,8 @@ pub struct LoadImageResult {
     pub metadata: ImageMetadata,
     pub exif: HashMap<String, String>,
     pub is_raw: bool,
+    pub has_alpha: bool,
+    pub clipped_highlight_percentage: Option<f32>,
 }
 
 #[derive(Deserialize)]
@@ -72,11 +74,61 @@ pub fn load_and_composite(
     settings: &AppSettings,
     cancel_token: Option<(Arc<AtomicUsize>, usize)>,
 ) -> Result<DynamicImage> {
-    let base_image =
-        load_base_image_from_bytes(base_image, path, use_fast_raw_dev, settings, cancel_token)?;
+    let base_image = load_base_image_with_wb(
+        base_image,
+        path,
+        use_fast_raw_dev,
+        settings,
+        raw_overrides_from_adjustments(adjustments),
+        cancel_token,
+    )?;
     composite_patches_on_image(&base_image, adjustments)
 }
 
+/// Per-image options applied while developing a raw, read from its adjustments.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RawOverrides {
+    pub wb: Option<[f32; 4]>,
+    /// Opt-in (`applyCalibrationFrames`) for the dark/flat frames configured in settings.
+    pub calibrate: bool,
+}
+
+pub fn raw_overrides_from_adjustments(adjustments: &Value) -> RawOverrides {
+    RawOverrides {
+        wb: raw_wb_override_from_adjustments(adjustments),
+        calibrate: adjustments["applyCalibrationFrames"]
+            .as_bool()
+            .unwrap_or(false),
+    }
+}
+
+/// Reads the demosaic-stage white balance override (`rawWbMultipliers`, as R/G/B or
+/// R/G/B/G2 multipliers) from adjustments.
+pub fn raw_wb_override_from_adjustments(adjustments: &Value) -> Option<[f32; 4]> {
+    let values: Vec<f32> = adjustments["rawWbMultipliers"]
+        .as_array()?
+        .iter()
+        .filter_map(|v| v.as_f64().map(|f| f as f32))
+        .collect();
+    if values.iter().any(|v| !v.is_finite() || *v <= 0.0) {
+        return None;
+    }
+    match values.as_slice() {
+        [r, g, b] => Some([*r, *g, *b, *g]),
+        [r, g, b, g2] => Some([*r, *g, *b, *g2]),
+        _ => None,
+    }
+}
+
+/// Frames merged with each other (HDR, panoramas) must share the same upright geometry
+/// before dimension checks and alignment, regardless of the user's auto-orient preference.
+pub fn upright_merge_settings(settings: AppSettings) -> AppSettings {
+    AppSettings {
+        auto_orient_from_exif: Some(true),
+        ..settings
+    }
+}
+
 pub fn load_base_image_from_bytes(
     bytes: &[u8],
     path_for_ext_check: &str,
@@ -84,7 +136,53 @@ pub fn load_base_image_from_bytes(
     settings: &AppSettings,
     cancel_token: Option<(Arc<AtomicUsize>, usize)>,
 ) -> Result<DynamicImage> {
-    let highlight_compression = settings.raw_highlight_compression.unwrap_or(2.5);
+    load_base_image_with_wb(
+        bytes,
+        path_for_ext_check,
+        use_fast_raw_dev,
+        settings,
+        RawOverrides::default(),
+        cancel_token,
+    )
+}
+
+pub fn load_base_image_with_wb(
+    bytes: &[u8],
+    path_for_ext_check: &str,
+    use_fast_raw_dev: bool,
+    settings: &AppSettings,
+    overrides: RawOverrides,
+    cancel_token: Option<(Arc<AtomicUsize>, usize)>,
+) -> Result<DynamicImage> {
+    load_base_image_with_clipping(
+        bytes,
+        path_for_ext_check,
+        use_fast_raw_dev,
+        settings,
+        overrides,
+        cancel_token,
+    )
+    .map(|(image, _)| image)
+}
+
+/// Like `load_base_image_with_wb`, additionally returning the percentage of clipped sensor
+/// samples for raws that went through the demosaic (`None` for everything else).
+pub fn load_base_image_with_clipping(
+    bytes: &[u8],
+    path_for_ext_check: &str,
+    use_fast_raw_dev: bool,
+    settings: &AppSettings,
+    overrides: RawOverrides,
+    cancel_token: Option<(Arc<AtomicUsize>, usize)>,
+) -> Result<(DynamicImage, Option<f32>)> {
+    let scene_referred = settings.linear_scene_referred.unwrap_or(false);
+    // Scene-referred output keeps the developed values untouched, so the highlight roll-off
+    // and the detail enhancement below are both disabled.
+    let highlight_compression = if scene_referred {
+        f32::MAX
+    } else {
+        settings.raw_highlight_compression.unwrap_or(2.5)
+    };
     let linear_mode = settings.linear_raw_mode.clone();
     let color_nr_setting = settings.raw_preprocessing_color_nr.unwrap_or(0.5);
     let color_nr_amount = if color_nr_setting <= 0.0 {
@@ -95,6 +193,9 @@ pub fn load_base_image_from_bytes(
     };
     let sharpening_amount = settings.raw_preprocessing_sharpening.unwrap_or(0.35);
     let apply_to_non_raws = settings.apply_preprocessing_to_non_raws.unwrap_or(false);
+    let auto_orient = settings.auto_orient_from_exif.unwrap_or(true);
+    let demosaic_quality = settings.demosaic_quality.unwrap_or_default();
+    let half_res = use_fast_raw_dev && settings.half_res_raw_preview.unwrap_or(false);
 
     crate::exif_processing::persist_exif_if_missing(
         Path::new(path_for_ext_check),
@@ -103,17 +204,38 @@ pub fn load_base_image_from_bytes(
     );
 
     if is_raw_file(path_for_ext_check) {
+        let calibration = if overrides.calibrate {
+            match crate::raw_processing::cached_calibration_frames(
+                settings.calibration_dark_frame.as_deref(),
+                settings.calibration_flat_field.as_deref(),
+            ) {
+                Ok(frames) => frames,
+                Err(e) => {
+                    log::warn!("Ignoring calibration frames: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
         match panic::catch_unwind(move || {
             crate::raw_processing::develop_raw_image(
                 bytes,
                 use_fast_raw_dev,
                 highlight_compression,
                 linear_mode,
+                demosaic_quality,
+                half_res,
+                overrides.wb,
+                calibration,
                 cancel_token,
             )
         }) {
-            Ok(Ok(mut image)) => {
-                if !use_fast_raw_dev && (color_nr_amount > 0.0 || sharpening_amount > 0.0) {
+            Ok(Ok((mut image, clipped_percentage))) => {
+                if !use_fast_raw_dev
+                    && !scene_referred
+                    && (color_nr_amount > 0.0 || sharpening_amount > 0.0)
+                {
                     let start = Instant::now();
                     remove_raw_artifacts_and_enhance(
                         &mut image,
@@ -127,7 +249,7 @@ pub fn load_base_image_from_bytes(
                         duration
                     );
                 }
-                Ok(image)
+                Ok((image, Some(clipped_percentage)))
             }
             Ok(Err(e)) => {
                 let classified = classify_raw_develop_error(path_for_ext_check, e);
@@ -149,7 +271,7 @@ pub fn load_base_image_from_bytes(
                         preview.height()
                     );
 
-                    return Ok(linearize_embedded_preview(preview));
+                    return Ok((linearize_embedded_preview(preview), None));
                 }
                 Err(classified)
             }
@@ -163,7 +285,7 @@ pub fn load_base_image_from_bytes(
                         preview.height()
                     );
 
-                    return Ok(linearize_embedded_preview(preview));
+                    return Ok((linearize_embedded_preview(preview), None));
                 }
                 Err(anyhow!(
                     "Failed to process RAW file: {}",
@@ -172,7 +294,7 @@ pub fn load_base_image_from_bytes(
             }
         }
     } else {
-        let mut image = load_image_with_orientation(bytes, cancel_token)?;
+        let mut image = load_image_with_orientation(bytes, auto_orient, cancel_token)?;
 
         if apply_to_non_raws
             && !use_fast_raw_dev
@@ -188,7 +310,7 @@ pub fn load_base_image_from_bytes(
             );
         }
 
-        Ok(image)
+        Ok((image, None))
     }
 }
 
@@ -209,7 +331,7 @@ fn classify_raw_develop_error(path: &str, err: anyhow::Error) -> anyhow::Error {
     err
 }
 
-fn largest_tiff_jpeg_preview(buf: &[u8]) -> Option<DynamicImage> {
+fn tiff_jpeg_candidates(buf: &[u8]) -> Option<Vec<&[u8]>> {
     let le = match buf.get(..4)? {
         [0x49, 0x49, 0x2A, 0x00] => true,
         [0x4D, 0x4D, 0x00, 0x2A] => false,
@@ -291,15 +413,38 @@ fn largest_tiff_jpeg_preview(buf: &[u8]) -> Option<DynamicImage> {
 
     candidates.sort_by_key(|&(_, len)| std::cmp::Reverse(len));
 
-    for (off, len) in candidates {
-        if let Some(bytes) = buf.get(off as usize..(off + len) as usize)
-            && let Ok(img) = image::load_from_memory_with_format(bytes, image::ImageFormat::Jpeg)
-        {
-            return Some(img);
-        }
-    }
+    Some(
+        candidates
+            .into_iter()
+            .filter_map(|(off, len)| buf.get(off as usize..off.checked_add(len)? as usize))
+            .collect(),
+    )
+}
 
-    None
+fn largest_tiff_jpeg_preview(buf: &[u8]) -> Option<DynamicImage> {
+    tiff_jpeg_candidates(buf)?
+        .into_iter()
+        .find_map(|bytes| image::load_from_memory_with_format(bytes, image::ImageFormat::Jpeg).ok())
+}
+
+pub fn largest_embedded_jpeg_bytes(buf: &[u8]) -> Option<&[u8]> {
+    tiff_jpeg_candidates(buf)?.into_iter().find(|bytes| {
+        bytes.starts_with(&[0xFF, 0xD8])
+            && ImageReader::with_format(Cursor::new(*bytes), image::ImageFormat::Jpeg)
+                .into_dimensions()
+                .is_ok()
+    })
+}
+
+pub fn raw_exif_orientation(bytes: &[u8]) -> Option<u32> {
+    ExifReader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(Tag::Orientation, exif::In::PRIMARY)?
+                .value
+                .get_uint(0)
+        })
 }
 
 fn embedded_preview_fallback(bytes: &[u8], path: &str) -> Option<DynamicImage> {
@@ -312,16 +457,7 @@ fn embedded_preview_fallback(bytes: &[u8], path: &str) -> Option<DynamicImage> {
         .ok()?,
     };
 
-    let orientation = ExifReader::new()
-        .read_from_container(&mut Cursor::new(bytes))
-        .ok()
-        .and_then(|exif| {
-            exif.get_field(Tag::Orientation, exif::In::PRIMARY)?
-                .value
-                .get_uint(0)
-        });
-
-    Some(match orientation {
+    Some(match raw_exif_orientation(bytes) {
         Some(o) if o > 1 => apply_orientation(img, Orientation::from_u16(o as u16)),
         _ => img,
     })
@@ -339,6 +475,16 @@ fn safe_embedded_preview_fallback(bytes: &[u8], path: &str) -> Option<DynamicIma
     }
 }
 
+/// Returns the camera's embedded JPEG preview (display-referred sRGB, EXIF
+/// orientation applied) if it is at least `min_dim` pixels on its long edge.
+pub fn extract_embedded_preview(bytes: &[u8], path: &str, min_dim: u32) -> Option<DynamicImage> {
+    let preview = safe_embedded_preview_fallback(bytes, path)?;
+    if preview.width().max(preview.height()) < min_dim {
+        return None;
+    }
+    Some(preview)
+}
+
 fn linearize_embedded_preview(preview: DynamicImage) -> DynamicImage {
     let preview = DynamicImage::ImageRgb32F(preview.to_rgb32f());
     let mut linear_preview = apply_srgb_to_linear(preview).into_rgb32f();
@@ -352,6 +498,7 @@ fn linearize_embedded_preview(preview: DynamicImage) -> DynamicImage {
 
 pub fn load_image_with_orientation(
     bytes: &[u8],
+    apply_exif_orientation: bool,
     cancel_token: Option<(Arc<AtomicUsize>, usize)>,
 ) -> Result<DynamicImage> {
     let check_cancel = || -> Result<()> {
@@ -364,7 +511,7 @@ pub fn load_image_with_orientation(
     };
 
     let cursor = Cursor::new(bytes);
-    let mut reader = ImageReader::new(cursor.clone())
+    let mut reader = ImageReader::new(cursor)
         .with_guessed_format()
         .context("Failed to guess image format")?;
 
@@ -375,24 +522,28 @@ pub fn load_image_with_orientation(
     let image = reader.decode().context("Failed to decode image")?;
     check_cancel()?;
 
-    let oriented_image = {
-        let exif_reader = ExifReader::new();
-        if let Ok(exif) = exif_reader.read_from_container(&mut cursor.clone()) {
-            if let Some(orientation) = exif
-                .get_field(Tag::Orientation, exif::In::PRIMARY)
+    let orientation = if apply_exif_orientation {
+        exif_processing::read_exif(bytes).and_then(|exif| {
+            exif.get_field(Tag::Orientation, exif::In::PRIMARY)
                 .and_then(|f| f.value.get_uint(0))
-            {
-                check_cancel()?;
-                apply_orientation(image, Orientation::from_u16(orientation as u16))
-            } else {
-                image
-            }
-        } else {
-            image
+        })
+    } else {
+        None
+    };
+
+    let oriented_image = match orientation {
+        Some(o) if o > 1 => {
+            check_cancel()?;
+            apply_orientation(image, Orientation::from_u16(o as u16))
         }
+        _ => image,
     };
 
-    Ok(DynamicImage::ImageRgb32F(oriented_image.to_rgb32f()))
+    if oriented_image.color().has_alpha() {
+        Ok(DynamicImage::ImageRgba32F(oriented_image.to_rgba32f()))
+    } else {
+        Ok(DynamicImage::ImageRgb32F(oriented_image.to_rgb32f()))
+    }
 }
 
 pub fn composite_patches_on_image(
@@ -766,27 +917,48 @@ pub async fn load_image(
         state.geometry_cache.lock().unwrap().clear();
 
         *state.denoise_result.lock().unwrap() = None;
+        *state.expand_result.lock().unwrap() = None;
+        *state.upscale_result.lock().unwrap() = None;
         *state.hdr_result.lock().unwrap() = None;
+        *state.hdr_frames.lock().unwrap() = None;
         *state.panorama_result.lock().unwrap() = None;
     }
 
     let (source_path, sidecar_path) = parse_virtual_path(&path);
     let source_path_str = source_path.to_string_lossy().to_string();
 
-    let metadata: ImageMetadata = crate::exif_processing::load_sidecar(&sidecar_path);
+    let mut metadata: ImageMetadata = crate::exif_processing::load_sidecar(&sidecar_path);
 
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
 
     let path_clone = source_path_str.clone();
+    let raw_overrides = raw_overrides_from_adjustments(&metadata.adjustments);
+    let mut cache_key = match raw_overrides.wb {
+        Some(wb) => format!(
+            "{}#wb={:.4},{:.4},{:.4},{:.4}",
+            source_path_str, wb[0], wb[1], wb[2], wb[3]
+        ),
+        None => source_path_str.clone(),
+    };
+    if raw_overrides.calibrate {
+        cache_key.push_str(&format!(
+            "#calibrated:{}",
+            crate::raw_processing::calibration_cache_tag(
+                settings.calibration_dark_frame.as_deref(),
+                settings.calibration_flat_field.as_deref(),
+            )
+        ));
+    }
 
-    let cached_data = state
-        .decoded_image_cache
-        .lock()
-        .unwrap()
-        .get(&source_path_str);
+    let cached_data = state.decoded_image_cache.lock().unwrap().get(&cache_key);
 
-    let (pristine_arc, exif_data) = if let Some((cached_img, cached_exif)) = cached_data {
-        (cached_img, cached_exif)
+    let (pristine_arc, exif_data, clipped_highlight_percentage) = if let Some((
+        cached_img,
+        cached_exif,
+        cached_clipping,
+    )) = cached_data
+    {
+        (cached_img, cached_exif, cached_clipping)
     } else {
         if crate::file_management::is_cloud_placeholder(&source_path) {
             return Err(format!(
@@ -795,28 +967,29 @@ pub async fn load_image(
             ));
         }
 
-        let (pristine_img, exif_data_loaded) = tokio::task::spawn_blocking(move || {
+        let (pristine_img, exif_data_loaded, clipping) = tokio::task::spawn_blocking(move || {
             if generation_tracker.load(Ordering::SeqCst) != my_generation {
                 return Err("Load cancelled".to_string());
             }
 
-            let result: Result<(DynamicImage, HashMap<String, String>), String> =
+            let result: Result<(DynamicImage, HashMap<String, String>, Option<f32>), String> =
                 (|| match read_file_mapped(Path::new(&path_clone)) {
                     Ok(mmap) => {
                         if generation_tracker.load(Ordering::SeqCst) != my_generation {
                             return Err("Load cancelled".to_string());
                         }
 
-                        let img = load_base_image_from_bytes(
+                        let (img, clipped) = load_base_image_with_clipping(
                             &mmap,
                             &path_clone,
                             false,
                             &settings,
+                            raw_overrides,
                             cancel_token.clone(),
                         )
                         .map_err(|e| e.to_string())?;
                         let exif = exif_processing::read_exif_data(&path_clone, &mmap);
-                        Ok((img, exif))
+                        Ok((img, exif, clipped))
                     }
                     Err(e) => {
                         log::warn!(
@@ -832,16 +1005,17 @@ pub async fn load_image(
                             return Err("Load cancelled".to_string());
                         }
 
-                        let img = load_base_image_from_bytes(
+                        let (img, clipped) = load_base_image_with_clipping(
                             &bytes,
                             &path_clone,
                             false,
                             &settings,
+                            raw_overrides,
                             cancel_token.clone(),
                         )
                         .map_err(|e| e.to_string())?;
                         let exif = exif_processing::read_exif_data(&path_clone, &bytes);
-                        Ok((img, exif))
+                        Ok((img, exif, clipped))
                     }
                 })();
             result
@@ -852,12 +1026,13 @@ pub async fn load_image(
         let arc_img = Arc::new(pristine_img);
 
         state.decoded_image_cache.lock().unwrap().insert(
-            source_path_str.clone(),
+            cache_key,
             arc_img.clone(),
             exif_data_loaded.clone(),
+            clipping,
         );
 
-        (arc_img, exif_data_loaded)
+        (arc_img, exif_data_loaded, clipping)
     };
 
     if state.load_image_generation.load(Ordering::SeqCst) != my_generation {
@@ -871,6 +1046,33 @@ pub async fn load_image(
     }
 
     let (orig_width, orig_height) = pristine_arc.dimensions();
+    let has_alpha = pristine_arc.color().has_alpha();
+
+    if is_raw
+        && !crate::xmp_sidecar::has_native_adjustments(&metadata.adjustments)
+        && let Some(xmp_path) = crate::file_management::resolve_xmp_path(&source_path)
+        && let Ok(content) = fs::read_to_string(&xmp_path)
+    {
+        let orientation_steps = metadata.adjustments["orientationSteps"]
+            .as_u64()
+            .unwrap_or(0);
+        match crate::xmp_sidecar::adjustments_from_xmp(
+            &content,
+            Some((orig_width, orig_height)),
+            orientation_steps,
+        ) {
+            Ok(mut adjustments) => {
+                if let (Some(obj), Some(existing)) = (
+                    adjustments.as_object_mut(),
+                    metadata.adjustments.get("rating"),
+                ) {
+                    obj.insert("rating".to_string(), existing.clone());
+                }
+                metadata.adjustments = adjustments;
+            }
+            Err(e) => log::warn!("Failed to seed adjustments from {:?}: {}", xmp_path, e),
+        }
+    }
 
     *state.original_image.lock().unwrap() = Some(LoadedImage {
         path,
@@ -884,5 +1086,76 @@ pub async fn load_image(
         metadata,
         exif: exif_data,
         is_raw,
+        has_alpha,
+        clipped_highlight_percentage,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgb, RgbImage};
+    use little_exif::exif_tag::ExifTag;
+    use little_exif::filetype::FileExtension;
+    use little_exif::metadata::Metadata;
+
+    fn quadrant_image() -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(64, 32, |x, y| match (x < 32, y < 16) {
+            (true, true) => Rgb([220, 30, 30]),
+            (false, true) => Rgb([30, 220, 30]),
+            (true, false) => Rgb([30, 30, 220]),
+            (false, false) => Rgb([220, 220, 30]),
+        }))
+    }
+
+    fn encode_jpeg(image: &DynamicImage) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Jpeg)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn merge_inputs_are_upright_despite_rotated_exif() {
+        let upright = quadrant_image();
+        // Orientation 6 asks viewers to rotate 90 degrees clockwise, so the stored pixels
+        // are the upright frame turned the other way.
+        let mut rotated_bytes = encode_jpeg(&upright.rotate270());
+        let mut exif = Metadata::new();
+        exif.set_tag(ExifTag::Orientation(vec![6u16]));
+        exif.write_to_vec(&mut rotated_bytes, FileExtension::JPEG)
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let upright_path = dir.path().join("upright.jpg");
+        let rotated_path = dir.path().join("rotated.jpg");
+        let upright_bytes = encode_jpeg(&upright);
+        fs::write(&upright_path, &upright_bytes).unwrap();
+        fs::write(&rotated_path, &rotated_bytes).unwrap();
+
+        let user_settings = AppSettings {
+            auto_orient_from_exif: Some(false),
+            ..AppSettings::default()
+        };
+        let settings = upright_merge_settings(user_settings);
+        let load = |bytes: &[u8], path: &Path| {
+            load_base_image_from_bytes(bytes, &path.to_string_lossy(), false, &settings, None)
+                .unwrap()
+                .to_rgb8()
+        };
+        let a = load(&upright_bytes, &upright_path);
+        let b = load(&rotated_bytes, &rotated_path);
+
+        assert_eq!(a.dimensions(), b.dimensions());
+        for (x, y) in [(8, 4), (56, 4), (8, 28), (56, 28)] {
+            let (pa, pb) = (a.get_pixel(x, y), b.get_pixel(x, y));
+            for c in 0..3 {
+                assert!(
+                    (pa[c] as i32 - pb[c] as i32).abs() < 24,
+                    "pixel ({x}, {y}) differs: {pa:?} vs {pb:?}"
+                );
+            }
+        }
+    }
+}
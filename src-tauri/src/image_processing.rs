@@ -1,7 +1,7 @@
 use crate::gpu_processing::WgpuDisplay;
 use bytemuck::{Pod, Zeroable};
 use glam::{Mat3, Vec2, Vec3};
-use image::{DynamicImage, GenericImageView, Rgb32FImage, Rgba};
+use image::{DynamicImage, GenericImageView, Rgb32FImage, Rgba, Rgba32FImage};
 use imageproc::geometric_transformations::{Border, Interpolation, rotate_about_center};
 use nalgebra::{Matrix3 as NaMatrix3, Vector3 as NaVector3};
 use rawler::decoders::Orientation;
@@ -13,9 +13,10 @@ use std::borrow::Cow;
 use std::f32::consts::PI;
 use std::sync::Arc;
 
+use crate::color_management;
 pub use crate::gpu_processing::{
-    RenderRequest, get_or_init_gpu_context, process_and_get_dynamic_image,
-    process_and_get_dynamic_image_with_analytics,
+    RenderRequest, get_gpu_context_or_fallback, get_or_init_gpu_context,
+    process_and_get_dynamic_image, process_and_get_dynamic_image_with_analytics,
 };
 use crate::{AppState, mask_generation::MaskDefinition};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
@@ -48,6 +49,22 @@ impl<'a> IntoCowImage<'a> for &'a std::sync::Arc<DynamicImage> {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EditSnapshot {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+    pub adjustments: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EditHistory {
+    pub entries: Vec<Value>,
+    pub position: usize,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ImageMetadata {
     pub version: u32,
@@ -57,6 +74,10 @@ pub struct ImageMetadata {
     pub tags: Option<Vec<String>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub exif: Option<std::collections::HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub snapshots: Vec<EditSnapshot>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<EditHistory>,
 }
 
 impl Default for ImageMetadata {
@@ -67,6 +88,8 @@ impl Default for ImageMetadata {
             adjustments: Value::Null,
             tags: None,
             exif: None,
+            snapshots: Vec::new(),
+            history: None,
         }
     }
 }
@@ -104,6 +127,14 @@ pub struct GeometryParams {
     pub vig_k1: f32,
     pub vig_k2: f32,
     pub vig_k3: f32,
+    #[serde(default)]
+    pub manual_ca_red: f32,
+    #[serde(default)]
+    pub manual_ca_blue: f32,
+    /// The pixels are display-referred sRGB while the vignette gain is meant for linear
+    /// data, as with the processed raw geometry preview.
+    #[serde(default)]
+    pub vignette_on_display_referred: bool,
 }
 
 impl Default for GeometryParams {
@@ -132,10 +163,16 @@ impl Default for GeometryParams {
             vig_k1: 0.0,
             vig_k2: 0.0,
             vig_k3: 0.0,
+            manual_ca_red: 0.0,
+            manual_ca_blue: 0.0,
+            vignette_on_display_referred: false,
         }
     }
 }
 
+// Slider range is -100..100, which maps to a +/-0.2% radial scale of the channel.
+const MANUAL_CA_SCALE: f32 = 0.00002;
+
 pub fn get_geometry_params_from_json(adjustments: &serde_json::Value) -> GeometryParams {
     let lens_params = adjustments
         .get("lensDistortionParams")
@@ -191,6 +228,9 @@ pub fn get_geometry_params_from_json(adjustments: &serde_json::Value) -> Geometr
         vig_k3: lens_params
             .and_then(|p| p.get("vig_k3").and_then(|k| k.as_f64()))
             .unwrap_or(0.0) as f32,
+        manual_ca_red: adjustments["manualCaRed"].as_f64().unwrap_or(0.0) as f32,
+        manual_ca_blue: adjustments["manualCaBlue"].as_f64().unwrap_or(0.0) as f32,
+        vignette_on_display_referred: false,
     }
 }
 
@@ -642,8 +682,57 @@ fn compute_lens_auto_crop_scale(params: &GeometryParams, width: f32, height: f32
     }
 }
 
+pub fn srgb_to_linear_scalar(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb_scalar(v: f32) -> f32 {
+    let v = v.max(0.0);
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
 pub fn warp_image_geometry(image: &DynamicImage, params: GeometryParams) -> DynamicImage {
-    let src_img = image.to_rgb32f();
+    let warped = warp_rgb_geometry(&image.to_rgb32f(), params);
+    if !image.color().has_alpha() {
+        return DynamicImage::ImageRgb32F(warped);
+    }
+
+    // Alpha goes through the same warp as a grey image, without the per-channel and
+    // brightness corrections that only make sense for colour.
+    let alpha_params = GeometryParams {
+        lens_tca_enabled: false,
+        lens_vignette_enabled: false,
+        manual_ca_red: 0.0,
+        manual_ca_blue: 0.0,
+        ..params
+    };
+    let rgba = image.to_rgba32f();
+    let alpha_as_rgb = Rgb32FImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let a = rgba.get_pixel(x, y)[3];
+        image::Rgb([a, a, a])
+    });
+    let warped_alpha = warp_rgb_geometry(&alpha_as_rgb, alpha_params);
+    reattach_alpha(warped, |x, y| warped_alpha.get_pixel(x, y)[0])
+}
+
+/// Combines an RGB result with an alpha plane sampled per pixel.
+fn reattach_alpha(rgb: Rgb32FImage, alpha_at: impl Fn(u32, u32) -> f32) -> DynamicImage {
+    let out = Rgba32FImage::from_fn(rgb.width(), rgb.height(), |x, y| {
+        let p = rgb.get_pixel(x, y);
+        Rgba([p[0], p[1], p[2], alpha_at(x, y).clamp(0.0, 1.0)])
+    });
+    DynamicImage::ImageRgba32F(out)
+}
+
+fn warp_rgb_geometry(src_img: &Rgb32FImage, params: GeometryParams) -> Rgb32FImage {
     let (width, height) = src_img.dimensions();
     let mut out_buffer = vec![0.0f32; (width * height * 3) as usize];
 
@@ -676,17 +765,20 @@ pub fn warp_image_geometry(image: &DynamicImage, params: GeometryParams) -> Dyna
         1.0
     };
 
-    let vr = if (params.tca_vr - 1.0).abs() > 1e-5 {
+    let profile_vr = if params.lens_tca_enabled && (params.tca_vr - 1.0).abs() > 1e-5 {
         params.tca_vr + (1.0 - params.tca_vr) * (1.0 - params.lens_tca_amount)
     } else {
         1.0
     };
-    let vb = if (params.tca_vb - 1.0).abs() > 1e-5 {
+    let profile_vb = if params.lens_tca_enabled && (params.tca_vb - 1.0).abs() > 1e-5 {
         params.tca_vb + (1.0 - params.tca_vb) * (1.0 - params.lens_tca_amount)
     } else {
         1.0
     };
-    let has_tca = params.lens_tca_enabled && ((vr - 1.0).abs() > 1e-5 || (vb - 1.0).abs() > 1e-5);
+    // Manual CA applies on top of the profile, so it also works for uncatalogued lenses.
+    let vr = profile_vr * (1.0 + params.manual_ca_red * MANUAL_CA_SCALE);
+    let vb = profile_vb * (1.0 + params.manual_ca_blue * MANUAL_CA_SCALE);
+    let has_tca = (vr - 1.0).abs() > 1e-7 || (vb - 1.0).abs() > 1e-7;
 
     let vk1 = params.vig_k1 as f64;
     let vk2 = params.vig_k2 as f64;
@@ -695,6 +787,7 @@ pub fn warp_image_geometry(image: &DynamicImage, params: GeometryParams) -> Dyna
     let has_vignetting = params.lens_vignette_enabled
         && (vk1.abs() > 1e-6 || vk2.abs() > 1e-6 || vk3.abs() > 1e-6)
         && lens_vig_amt > 0.01;
+    let display_referred_vignette = params.vignette_on_display_referred;
 
     let src_raw = src_img.as_raw();
     let width_usize = width as usize;
@@ -788,9 +881,16 @@ pub fn warp_image_geometry(image: &DynamicImage, params: GeometryParams) -> Dyna
                             let correction_gain = 1.0 / v_factor;
                             let final_gain = 1.0 + (correction_gain - 1.0) * lens_vig_amt;
 
-                            pixel[0] *= final_gain as f32;
-                            pixel[1] *= final_gain as f32;
-                            pixel[2] *= final_gain as f32;
+                            let gain = final_gain as f32;
+                            if display_referred_vignette {
+                                for v in pixel.iter_mut() {
+                                    *v = linear_to_srgb_scalar(srgb_to_linear_scalar(*v) * gain);
+                                }
+                            } else {
+                                pixel[0] *= gain;
+                                pixel[1] *= gain;
+                                pixel[2] *= gain;
+                            }
                         }
                     }
                 }
@@ -798,8 +898,7 @@ pub fn warp_image_geometry(image: &DynamicImage, params: GeometryParams) -> Dyna
             }
         });
 
-    let out_img = Rgb32FImage::from_vec(width, height, out_buffer).unwrap();
-    DynamicImage::ImageRgb32F(out_img)
+    Rgb32FImage::from_vec(width, height, out_buffer).unwrap()
 }
 
 pub fn unwarp_image_geometry(warped_image: &DynamicImage, params: GeometryParams) -> DynamicImage {
@@ -1318,10 +1417,12 @@ pub fn is_geometry_identity(params: &GeometryParams) -> bool {
             && params.lens_dist_k2.abs() < 1e-6
             && params.lens_dist_k3.abs() < 1e-6);
 
-    let tca_identity = !params.lens_tca_enabled
+    let tca_identity = (!params.lens_tca_enabled
         || ((params.lens_tca_amount - 1.0).abs() < 1e-4
             && (params.tca_vr - 1.0).abs() < 1e-6
-            && (params.tca_vb - 1.0).abs() < 1e-6);
+            && (params.tca_vb - 1.0).abs() < 1e-6))
+        && params.manual_ca_red == 0.0
+        && params.manual_ca_blue == 0.0;
 
     let vig_identity = !params.lens_vignette_enabled
         || ((params.lens_vignette_amount - 1.0).abs() < 1e-4
@@ -1363,8 +1464,8 @@ pub struct AutoAdjustmentResults {
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Pod, Zeroable, Default)]
 #[repr(C)]
 pub struct Point {
-    x: f32,
-    y: f32,
+    pub x: f32,
+    pub y: f32,
     _pad1: f32,
     _pad2: f32,
 }
@@ -1372,9 +1473,9 @@ pub struct Point {
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Pod, Zeroable, Default)]
 #[repr(C)]
 pub struct HslColor {
-    hue: f32,
-    saturation: f32,
-    luminance: f32,
+    pub hue: f32,
+    pub saturation: f32,
+    pub luminance: f32,
     _pad: f32,
 }
 
@@ -1433,9 +1534,9 @@ pub struct GlobalAdjustments {
     pub tint: f32,
     pub vibrance: f32,
     pub hue: f32,
-    _pad_color1: f32,
-    _pad_color2: f32,
-    _pad_color3: f32,
+    pub grain_seed: f32,
+    pub grain_offset_x: f32,
+    pub grain_offset_y: f32,
 
     pub sharpness: f32,
     pub luma_noise_reduction: f32,
@@ -1456,18 +1557,18 @@ pub struct GlobalAdjustments {
     pub chromatic_aberration_blue_yellow: f32,
     pub show_clipping: u32,
     pub is_raw_image: u32,
-    _pad_ca1: f32,
+    pub grain_scale: f32,
 
     pub has_lut: u32,
     pub lut_intensity: f32,
     pub tonemapper_mode: u32,
-    _pad_lut2: f32,
-    _pad_lut3: f32,
-    _pad_lut4: f32,
-    _pad_lut5: f32,
+    pub defringe_purple_amount: f32,
+    pub defringe_purple_hue: f32,
+    pub defringe_green_amount: f32,
+    pub defringe_green_hue: f32,
 
-    _pad_agx1: f32,
-    _pad_agx2: f32,
+    pub defringe_purple_range: f32,
+    pub defringe_green_range: f32,
     _pad_agx3: f32,
     pub agx_pipe_to_rendering_matrix: GpuMat3,
     pub agx_rendering_to_pipe_matrix: GpuMat3,
@@ -1496,8 +1597,8 @@ pub struct GlobalAdjustments {
     pub red_curve_count: u32,
     pub green_curve_count: u32,
     pub blue_curve_count: u32,
-    _pad_end1: f32,
-    _pad_end2: f32,
+    pub vignette_highlight_priority: f32,
+    pub texture_amount: f32,
     _pad_end3: f32,
     _pad_end4: f32,
 
@@ -1505,6 +1606,35 @@ pub struct GlobalAdjustments {
     pub halation_amount: f32,
     pub flare_amount: f32,
     pub sharpness_threshold: f32,
+
+    pub clip_highlight_color: [f32; 4],
+    pub clip_shadow_color: [f32; 4],
+    pub clip_highlight_threshold: f32,
+    pub clip_shadow_threshold: f32,
+    pub clip_zebra: u32,
+    _pad_clip: f32,
+}
+
+impl GlobalAdjustments {
+    /// Anchors grain to full-resolution pixels of the uncropped image, so a preview
+    /// rendered at `scale` shows the same grain pattern as the full-size export.
+    pub fn set_grain_frame(&mut self, scale: f32, unscaled_crop_offset: (f32, f32)) {
+        self.grain_scale = scale.max(1e-4);
+        self.grain_offset_x = unscaled_crop_offset.0;
+        self.grain_offset_y = unscaled_crop_offset.1;
+    }
+
+    /// Applies the user's clipping warning colors and thresholds. Thresholds are 8-bit
+    /// output levels, so a highlight threshold of 250 also flags near-white pixels.
+    pub fn set_clipping_overlay(&mut self, overlay: &crate::app_settings::ClippingOverlaySettings) {
+        let highlight = overlay.highlight_rgb();
+        let shadow = overlay.shadow_rgb();
+        self.clip_highlight_color = [highlight[0], highlight[1], highlight[2], 1.0];
+        self.clip_shadow_color = [shadow[0], shadow[1], shadow[2], 1.0];
+        self.clip_highlight_threshold = (overlay.highlight_threshold as f32 - 0.5) / 255.0;
+        self.clip_shadow_threshold = (overlay.shadow_threshold as f32 + 0.5) / 255.0;
+        self.clip_zebra = if overlay.zebra { 1 } else { 0 };
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Pod, Zeroable, Default)]
@@ -1535,7 +1665,7 @@ pub struct MaskAdjustments {
     pub sharpness_threshold: f32,
 
     pub hue: f32,
-    _pad_cg1: f32,
+    pub texture_amount: f32,
     _pad_cg2: f32,
     pub color_grading_shadows: ColorGradeSettings,
     pub color_grading_midtones: ColorGradeSettings,
@@ -1572,6 +1702,10 @@ pub struct AllAdjustments {
     pub tile_offset_x: u32,
     pub tile_offset_y: u32,
     pub mask_atlas_cols: u32,
+    pub image_offset_x: u32,
+    pub image_offset_y: u32,
+    pub image_width: u32,
+    pub image_height: u32,
 }
 
 struct AdjustmentScales {
@@ -1594,6 +1728,7 @@ struct AdjustmentScales {
     clarity: f32,
     dehaze: f32,
     structure: f32,
+    texture: f32,
     centré: f32,
 
     vignette_amount: f32,
@@ -1643,6 +1778,7 @@ const SCALES: AdjustmentScales = AdjustmentScales {
     clarity: 125.0,
     dehaze: 750.0,
     structure: 125.0,
+    texture: 125.0,
     centré: 250.0,
 
     vignette_amount: 100.0,
@@ -1716,6 +1852,22 @@ fn parse_color_grade_settings(js_cg: &serde_json::Value) -> ColorGradeSettings {
     }
 }
 
+/// Drops malformed points, orders the rest by x and keeps at most the 16 points the
+/// shader can hold, so the GPU and CPU curve evaluators always see a valid spline.
+fn sanitize_curve_points(points: Vec<serde_json::Value>) -> Vec<serde_json::Value> {
+    let mut points: Vec<serde_json::Value> = points
+        .into_iter()
+        .filter(|p| p["x"].as_f64().is_some() && p["y"].as_f64().is_some())
+        .collect();
+    points.sort_by(|a, b| {
+        let ax = a["x"].as_f64().unwrap_or(0.0);
+        let bx = b["x"].as_f64().unwrap_or(0.0);
+        ax.total_cmp(&bx)
+    });
+    points.truncate(16);
+    points
+}
+
 fn convert_points_to_aligned(frontend_points: Vec<serde_json::Value>) -> [Point; 16] {
     let mut aligned_points = [Point::default(); 16];
     for (i, point) in frontend_points.iter().enumerate().take(16) {
@@ -1828,7 +1980,16 @@ fn calculate_agx_matrices() -> (GpuMat3, GpuMat3) {
     )
 }
 
+/// Flat rendering for `linear_scene_referred` raws. The linear, white-balanced camera RGB
+/// (black level at 0.0, sensor white at 1.0, no highlight roll-off) is clamped to [0, 1]
+/// and encoded with the plain sRGB OETF: `12.92 * x` below 0.0031308, otherwise
+/// `1.055 * x^(1/2.4) - 0.055`. No brightness gamma, contrast curve or AgX is applied.
+pub const TONEMAPPER_FLAT: u32 = 2;
+
 pub fn resolve_tonemapper_override(settings: &crate::AppSettings, is_raw: bool) -> Option<u32> {
+    if is_raw && settings.linear_scene_referred.unwrap_or(false) {
+        return Some(TONEMAPPER_FLAT);
+    }
     if !settings.tonemapper_override_enabled.unwrap_or(false) {
         return None;
     }
@@ -2106,6 +2267,11 @@ fn get_global_adjustments_from_json(
         Vec::new()
     };
 
+    let luma_points = sanitize_curve_points(luma_points);
+    let red_points = sanitize_curve_points(red_points);
+    let green_points = sanitize_curve_points(green_points);
+    let blue_points = sanitize_curve_points(blue_points);
+
     let cg_obj = js_adjustments
         .get("colorGrading")
         .cloned()
@@ -2142,14 +2308,20 @@ fn get_global_adjustments_from_json(
     let (pipe_to_rendering, rendering_to_pipe) = calculate_agx_matrices();
 
     let (has_lut, lut_intensity) = if is_visible("effects") {
-        (
-            if js_adjustments["lutPath"].is_string() {
-                1
-            } else {
-                0
-            },
-            js_adjustments["lutIntensity"].as_f64().unwrap_or(100.0) as f32 / 100.0,
-        )
+        if crate::lut_processing::has_lut_stack(js_adjustments) {
+            // Stack intensities are baked into the composed LUT.
+            (1, 1.0)
+        } else {
+            (
+                if js_adjustments["lutPath"].is_string() {
+                    1
+                } else {
+                    0
+                },
+                (js_adjustments["lutIntensity"].as_f64().unwrap_or(100.0) as f32 / 100.0)
+                    .clamp(0.0, 1.0),
+            )
+        }
     } else {
         (0, 1.0)
     };
@@ -2168,9 +2340,9 @@ fn get_global_adjustments_from_json(
         tint: get_val("color", "tint", SCALES.tint, None),
         vibrance: get_val("color", "vibrance", SCALES.vibrance, None),
         hue: get_val("color", "hue", 1.0, None),
-        _pad_color1: 0.0,
-        _pad_color2: 0.0,
-        _pad_color3: 0.0,
+        grain_seed: (js_adjustments["grainSeed"].as_u64().unwrap_or(0) % 1024) as f32,
+        grain_offset_x: 0.0,
+        grain_offset_y: 0.0,
 
         sharpness: get_val("details", "sharpness", SCALES.sharpness, None),
         luma_noise_reduction: get_val(
@@ -2236,20 +2408,20 @@ fn get_global_adjustments_from_json(
             0
         },
         is_raw_image: if is_raw { 1 } else { 0 },
-        _pad_ca1: 0.0,
+        grain_scale: 1.0,
 
         has_lut,
         lut_intensity,
 
         tonemapper_mode: tonemapper_override
             .unwrap_or_else(|| if tone_mapper == "agx" { 1 } else { 0 }),
-        _pad_lut2: 0.0,
-        _pad_lut3: 0.0,
-        _pad_lut4: 0.0,
-        _pad_lut5: 0.0,
+        defringe_purple_amount: get_val("details", "defringePurpleAmount", 100.0, None),
+        defringe_purple_hue: get_val("details", "defringePurpleHue", 1.0, Some(290.0)),
+        defringe_green_amount: get_val("details", "defringeGreenAmount", 100.0, None),
+        defringe_green_hue: get_val("details", "defringeGreenHue", 1.0, Some(110.0)),
 
-        _pad_agx1: 0.0,
-        _pad_agx2: 0.0,
+        defringe_purple_range: get_val("details", "defringePurpleRange", 1.0, Some(40.0)),
+        defringe_green_range: get_val("details", "defringeGreenRange", 1.0, Some(40.0)),
         _pad_agx3: 0.0,
         agx_pipe_to_rendering_matrix: pipe_to_rendering,
         agx_rendering_to_pipe_matrix: rendering_to_pipe,
@@ -2306,8 +2478,13 @@ fn get_global_adjustments_from_json(
         red_curve_count: red_points.len() as u32,
         green_curve_count: green_points.len() as u32,
         blue_curve_count: blue_points.len() as u32,
-        _pad_end1: 0.0,
-        _pad_end2: 0.0,
+        vignette_highlight_priority: get_val(
+            "effects",
+            "vignetteHighlightPriority",
+            SCALES.vignette_amount,
+            None,
+        ),
+        texture_amount: get_val("details", "texture", SCALES.texture, None),
         _pad_end3: 0.0,
         _pad_end4: 0.0,
 
@@ -2320,6 +2497,13 @@ fn get_global_adjustments_from_json(
             SCALES.sharpness_threshold,
             Some(15.0),
         ),
+
+        clip_highlight_color: [1.0, 0.0, 0.0, 1.0],
+        clip_shadow_color: [0.0, 0.0, 1.0, 1.0],
+        clip_highlight_threshold: 0.998,
+        clip_shadow_threshold: 0.002,
+        clip_zebra: 0,
+        _pad_clip: 0.0,
     }
 }
 
@@ -2365,6 +2549,10 @@ fn get_mask_adjustments_from_json(adj: &serde_json::Value) -> MaskAdjustments {
     } else {
         Vec::new()
     };
+    let luma_points = sanitize_curve_points(luma_points);
+    let red_points = sanitize_curve_points(red_points);
+    let green_points = sanitize_curve_points(green_points);
+    let blue_points = sanitize_curve_points(blue_points);
     let cg_obj = adj.get("colorGrading").cloned().unwrap_or_default();
 
     MaskAdjustments {
@@ -2399,7 +2587,7 @@ fn get_mask_adjustments_from_json(adj: &serde_json::Value) -> MaskAdjustments {
         sharpness_threshold: get_val("details", "sharpnessThreshold", SCALES.sharpness_threshold),
 
         hue: get_val("color", "hue", 1.0),
-        _pad_cg1: 0.0,
+        texture_amount: get_val("details", "texture", SCALES.texture),
         _pad_cg2: 0.0,
         color_grading_shadows: if is_visible("color") {
             parse_color_grade_settings(&cg_obj["shadows"])
@@ -2485,6 +2673,10 @@ pub fn get_all_adjustments_from_json(
         tile_offset_x: 0,
         tile_offset_y: 0,
         mask_atlas_cols: 1,
+        image_offset_x: 0,
+        image_offset_y: 0,
+        image_width: 0,
+        image_height: 0,
     }
 }
 
@@ -2517,6 +2709,7 @@ pub fn remove_raw_artifacts_and_enhance(
     color_nr_inv_sigma: f32,
     sharpening_amount: f32,
 ) {
+    let source_alpha = image.color().has_alpha().then(|| image.to_rgba32f());
     let mut buffer = image.to_rgb32f();
     let w = buffer.width() as usize;
     let h = buffer.height() as usize;
@@ -2623,7 +2816,10 @@ pub fn remove_raw_artifacts_and_enhance(
         apply_gentle_detail_enhance(&mut buffer, &ycbcr_buffer, sharpening_amount);
     }
 
-    *image = DynamicImage::ImageRgb32F(buffer);
+    *image = match source_alpha {
+        Some(alpha) => reattach_alpha(buffer, |x, y| alpha.get_pixel(x, y)[3]),
+        None => DynamicImage::ImageRgb32F(buffer),
+    };
 }
 
 fn apply_gentle_detail_enhance(
@@ -2726,7 +2922,38 @@ pub struct HistogramData {
     luma: Vec<f32>,
 }
 
-pub fn calculate_histogram_from_image(image: &DynamicImage) -> Result<HistogramData, String> {
+/// Converts a normalized `(x, y, width, height)` rect into a pixel rect inside `image`.
+/// Returns `None` when the region covers nothing.
+fn normalized_region_to_pixels(
+    image: &DynamicImage,
+    region: (f32, f32, f32, f32),
+) -> Option<(u32, u32, u32, u32)> {
+    let (width, height) = image.dimensions();
+    let (rx, ry, rw, rh) = region;
+    let x0 = (rx.clamp(0.0, 1.0) * width as f32).floor() as u32;
+    let y0 = (ry.clamp(0.0, 1.0) * height as f32).floor() as u32;
+    let x1 = ((rx + rw).clamp(0.0, 1.0) * width as f32).ceil() as u32;
+    let y1 = ((ry + rh).clamp(0.0, 1.0) * height as f32).ceil() as u32;
+    if x1 <= x0 || y1 <= y0 {
+        return None;
+    }
+    Some((x0, y0, x1 - x0, y1 - y0))
+}
+
+/// Builds the histogram of `image`, or of just the normalized `region` of it when given.
+pub fn calculate_histogram_from_image(
+    image: &DynamicImage,
+    region: Option<(f32, f32, f32, f32)>,
+) -> Result<HistogramData, String> {
+    let cropped;
+    let image = match region.and_then(|r| normalized_region_to_pixels(image, r)) {
+        Some((x, y, w, h)) if (w, h) != image.dimensions() => {
+            cropped = image.crop_imm(x, y, w, h);
+            &cropped
+        }
+        _ => image,
+    };
+
     let init_hist = || ([0u32; 256], [0u32; 256], [0u32; 256], [0u32; 256]);
 
     let reduce_hist = |mut a: ([u32; 256], [u32; 256], [u32; 256], [u32; 256]),
@@ -3165,14 +3392,45 @@ pub fn calculate_waveform_from_image(
     })
 }
 
+/// Tuning knobs for batch auto adjustments. The defaults reproduce `perform_auto_analysis`.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AutoAdjustOptions {
+    /// Target median luma on a 0-255 scale.
+    pub exposure_target: f64,
+    /// Multiplier for the computed contrast.
+    pub contrast_strength: f64,
+    /// Percentage of pixels ignored at each end when finding the black and white points.
+    pub clip_percentage: f64,
+    pub adjust_wb: bool,
+}
+
+impl Default for AutoAdjustOptions {
+    fn default() -> Self {
+        Self {
+            exposure_target: 128.0,
+            contrast_strength: 1.0,
+            clip_percentage: 1.0,
+            adjust_wb: false,
+        }
+    }
+}
+
 pub fn perform_auto_analysis(image: &DynamicImage) -> AutoAdjustmentResults {
+    perform_auto_analysis_with_options(image, false, &AutoAdjustOptions::default())
+}
+
+pub fn perform_auto_analysis_with_options(
+    image: &DynamicImage,
+    is_raw: bool,
+    options: &AutoAdjustOptions,
+) -> AutoAdjustmentResults {
     const ANALYSIS_MAX_DIM: u32 = 1024;
 
     const LUMA_R: f32 = 0.2126;
     const LUMA_G: f32 = 0.7152;
     const LUMA_B: f32 = 0.0722;
 
-    const EXPOSURE_MIDPOINT: f64 = 128.0;
     const EXPOSURE_SCALE: f64 = 0.125;
     const WHITE_POINT_HARD_LIMIT: usize = 245;
     const HIGHLIGHT_LUMA_THRESHOLD: usize = 240;
@@ -3274,9 +3532,10 @@ pub fn perform_auto_analysis(image: &DynamicImage) -> AutoAdjustmentResults {
         255
     };
 
-    let p1 = percentile(&luma_hist, 0.01);
+    let clip = options.clip_percentage.clamp(0.0, 10.0) / 100.0;
+    let p1 = percentile(&luma_hist, clip);
     let p50 = percentile(&luma_hist, 0.50);
-    let p99 = percentile(&luma_hist, 0.99);
+    let p99 = percentile(&luma_hist, 1.0 - clip);
 
     let black_point = p1;
     let white_point = p99;
@@ -3287,7 +3546,7 @@ pub fn perform_auto_analysis(image: &DynamicImage) -> AutoAdjustmentResults {
     let clipped_percent =
         luma_hist[CLIPPED_LUMA_THRESHOLD..256].iter().sum::<u32>() as f64 / total_pixels;
 
-    let mut exposure = (EXPOSURE_MIDPOINT - p50 as f64) * EXPOSURE_SCALE;
+    let mut exposure = (options.exposure_target.clamp(0.0, 255.0) - p50 as f64) * EXPOSURE_SCALE;
 
     if white_point > WHITE_POINT_HARD_LIMIT
         || highlight_percent > HIGHLIGHT_PERCENT_THRESHOLD
@@ -3307,6 +3566,7 @@ pub fn perform_auto_analysis(image: &DynamicImage) -> AutoAdjustmentResults {
     if highlight_percent > HIGHLIGHT_PERCENT_THRESHOLD {
         contrast *= HIGHLIGHT_CONTRAST_REDUCE;
     }
+    contrast *= options.contrast_strength.max(0.0);
 
     let shadow_percent = luma_hist[0..SHADOW_LUMA_MAX].iter().sum::<u32>() as f64 / total_pixels;
 
@@ -3363,12 +3623,23 @@ pub fn perform_auto_analysis(image: &DynamicImage) -> AutoAdjustmentResults {
         adjusted_luma_hist[luma.clamp(0.0, 255.0).round() as usize] += 1;
     }
 
-    let adj_p1 = percentile(&adjusted_luma_hist, 0.01);
+    let adj_p1 = percentile(&adjusted_luma_hist, clip);
     let adj_p50 = percentile(&adjusted_luma_hist, 0.50);
-    let adj_p99 = percentile(&adjusted_luma_hist, 0.99);
+    let adj_p99 = percentile(&adjusted_luma_hist, 1.0 - clip);
     let blacks: f64 = -(adj_p1 as f64 * BLACKS_SCALE);
     let whites: f64 = (adj_p99 as f64 - 255.0) * WHITES_SCALE;
-    let brightness: f64 = (MID_GRAY - adj_p50 as f64) * BRIGHTNESS_SCALE;
+    let brightness: f64 =
+        (options.exposure_target.clamp(0.0, 255.0) - adj_p50 as f64) * BRIGHTNESS_SCALE;
+
+    // Gray world: neutralize the average color of the whole frame.
+    let (temperature, tint) = if options.adjust_wb {
+        let radius = analysis_preview.width().max(analysis_preview.height());
+        sample_linear_average(&analysis_preview, 0.5, 0.5, radius, is_raw)
+            .and_then(white_balance_to_neutralize)
+            .unwrap_or((0.0, 0.0))
+    } else {
+        (0.0, 0.0)
+    };
 
     AutoAdjustmentResults {
         exposure: (exposure / EXPOSURE_OUTPUT_SCALE).clamp(-5.0, 5.0),
@@ -3378,8 +3649,8 @@ pub fn perform_auto_analysis(image: &DynamicImage) -> AutoAdjustmentResults {
         shadows: shadows.clamp(-100.0, 100.0),
         vibrancy: vibrancy.clamp(-100.0, 100.0),
         vignette_amount: vignette_amount.clamp(-100.0, 100.0),
-        temperature: 0.0,
-        tint: 0.0,
+        temperature,
+        tint,
         dehaze: dehaze.clamp(-100.0, 100.0),
         clarity: clarity.clamp(-100.0, 100.0),
         centre: centre.clamp(-100.0, 100.0),
@@ -3388,6 +3659,88 @@ pub fn perform_auto_analysis(image: &DynamicImage) -> AutoAdjustmentResults {
     }
 }
 
+/// Averages the linear RGB of a disc around the normalized point `(x, y)`.
+pub fn sample_linear_average(
+    image: &DynamicImage,
+    x: f32,
+    y: f32,
+    radius: u32,
+    is_raw: bool,
+) -> Option<[f32; 3]> {
+    let (width, height) = image.dimensions();
+    if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) || width == 0 || height == 0 {
+        return None;
+    }
+    let cx = ((x * width as f32) as u32).min(width - 1);
+    let cy = ((y * height as f32) as u32).min(height - 1);
+    let r = radius as i64;
+    let read = |px: u32, py: u32| -> [f32; 3] {
+        match image {
+            DynamicImage::ImageRgb32F(img) => img.get_pixel(px, py).0,
+            DynamicImage::ImageRgba32F(img) => {
+                let p = img.get_pixel(px, py);
+                [p[0], p[1], p[2]]
+            }
+            _ => {
+                let p = image.get_pixel(px, py);
+                [p[0], p[1], p[2]].map(|v| v as f32 / 255.0)
+            }
+        }
+    };
+    let to_linear = |v: f32| {
+        if is_raw {
+            v
+        } else if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    let mut sum = [0.0f64; 3];
+    let mut count = 0usize;
+    for dy in -r..=r {
+        for dx in -r..=r {
+            if dx * dx + dy * dy > r * r {
+                continue;
+            }
+            let px = cx as i64 + dx;
+            let py = cy as i64 + dy;
+            if px < 0 || py < 0 || px >= width as i64 || py >= height as i64 {
+                continue;
+            }
+            let p = read(px as u32, py as u32);
+            for (acc, v) in sum.iter_mut().zip(p) {
+                *acc += to_linear(v) as f64;
+            }
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return None;
+    }
+    Some(sum.map(|v| (v / count as f64) as f32))
+}
+
+/// Solves the shader's `apply_white_balance` multipliers for the temperature and tint
+/// slider values that turn `rgb` neutral. Returns `None` for unusable samples.
+pub fn white_balance_to_neutralize(rgb: [f32; 3]) -> Option<(f64, f64)> {
+    let [r, g, b] = rgb.map(|v| v as f64);
+    if r <= 1e-4 || g <= 1e-4 || b <= 1e-4 {
+        return None;
+    }
+
+    // Red and blue share the tint multiplier, so their ratio fixes temperature alone.
+    let temp = (b - r) / (0.2 * (r + b));
+    let green = g * (1.0 + 0.05 * temp);
+    let red_blue = r * (1.0 + 0.2 * temp);
+    let tint = (green - red_blue) / (0.25 * (green + red_blue));
+
+    let temperature = (temp * SCALES.temperature as f64).clamp(-100.0, 100.0);
+    let tint = (tint * SCALES.tint as f64).clamp(-100.0, 100.0);
+    Some((temperature, tint))
+}
+
 pub fn auto_results_to_json(results: &AutoAdjustmentResults) -> serde_json::Value {
     json!({
         "exposure": results.exposure,
@@ -3411,20 +3764,285 @@ pub fn auto_results_to_json(results: &AutoAdjustmentResults) -> serde_json::Valu
     })
 }
 
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AutoAdjustMode {
+    #[default]
+    All,
+    Tone,
+    Color,
+}
+
+const AUTO_COLOR_DEFAULT_CLIP_PERCENT: f64 = 0.1;
+const AUTO_COLOR_MIN_RANGE: usize = 16;
+
+/// Per-channel black and white points (0-255, display-referred) for an auto color
+/// stretch. `clip_percent` of the pixels at each end of every channel is ignored.
+pub fn compute_auto_color_levels(
+    image: &DynamicImage,
+    is_raw: bool,
+    clip_percent: f64,
+) -> [(usize, usize); 3] {
+    const ANALYSIS_MAX_DIM: u32 = 1024;
+
+    let mut analysis_preview = downscale_f32_image(image, ANALYSIS_MAX_DIM, ANALYSIS_MAX_DIM);
+    if is_raw {
+        apply_cpu_default_raw_processing(&mut analysis_preview);
+    }
+    let rgb_image = analysis_preview.to_rgb8();
+    let total_pixels = (rgb_image.width() as u64 * rgb_image.height() as u64).max(1);
+
+    let mut hists = [[0u64; 256]; 3];
+    for pixel in rgb_image.pixels() {
+        for (hist, &v) in hists.iter_mut().zip(pixel.0.iter()) {
+            hist[v as usize] += 1;
+        }
+    }
+
+    let clip = (clip_percent.clamp(0.0, 10.0) / 100.0 * total_pixels as f64) as u64;
+    hists.map(|hist| {
+        let mut cumulative = 0u64;
+        let low = hist
+            .iter()
+            .position(|&v| {
+                cumulative += v;
+                cumulative > clip
+            })
+            .unwrap_or(0);
+        cumulative = 0;
+        let high = 255
+            - hist
+                .iter()
+                .rev()
+                .position(|&v| {
+                    cumulative += v;
+                    cumulative > clip
+                })
+                .unwrap_or(0);
+
+        if high <= low || high - low < AUTO_COLOR_MIN_RANGE {
+            (0, 255)
+        } else {
+            (low, high)
+        }
+    })
+}
+
+/// Expresses the per-channel stretch as red/green/blue curves so it stays editable in the
+/// normal pipeline. The returned `curves` replaces the whole object on the frontend, so the
+/// luma curve (and anything else) is carried over from `current_curves`.
+pub fn auto_color_levels_to_json(
+    levels: &[(usize, usize); 3],
+    current_curves: Option<&serde_json::Value>,
+) -> serde_json::Value {
+    let curve = |(low, high): (usize, usize)| {
+        json!([
+            { "x": low as f64, "y": 0.0 },
+            { "x": high as f64, "y": 255.0 }
+        ])
+    };
+    let mut curves = current_curves
+        .and_then(|c| c.as_object())
+        .cloned()
+        .unwrap_or_default();
+    curves.insert("red".to_string(), curve(levels[0]));
+    curves.insert("green".to_string(), curve(levels[1]));
+    curves.insert("blue".to_string(), curve(levels[2]));
+    json!({
+        "curves": curves,
+        "sectionVisibility": {
+            "curves": true
+        }
+    })
+}
+
+pub struct LabStatistics {
+    pub mean: [f32; 3],
+    pub std_dev: [f32; 3],
+}
+
+/// Per-channel mean and standard deviation of the default (unedited) rendering in Lab.
+pub fn compute_lab_statistics(image: &DynamicImage, is_raw: bool) -> LabStatistics {
+    const ANALYSIS_MAX_DIM: u32 = 512;
+
+    let mut analysis_preview = downscale_f32_image(image, ANALYSIS_MAX_DIM, ANALYSIS_MAX_DIM);
+    if is_raw {
+        apply_cpu_default_raw_processing(&mut analysis_preview);
+    }
+    let rgb_image = analysis_preview.to_rgb32f();
+    let total = (rgb_image.width() as f64 * rgb_image.height() as f64).max(1.0);
+
+    let mut sum = [0.0f64; 3];
+    let mut sum_sq = [0.0f64; 3];
+    for pixel in rgb_image.pixels() {
+        let lab = color_management::srgb_to_lab(pixel.0.map(|v| v.clamp(0.0, 1.0)));
+        for ((s, sq), v) in sum.iter_mut().zip(sum_sq.iter_mut()).zip(lab) {
+            *s += v as f64;
+            *sq += v as f64 * v as f64;
+        }
+    }
+
+    let mean = sum.map(|v| v / total);
+    let std_dev =
+        std::array::from_fn(|i| (sum_sq[i] / total - mean[i] * mean[i]).max(0.0).sqrt() as f32);
+    LabStatistics {
+        mean: mean.map(|v| v as f32),
+        std_dev,
+    }
+}
+
+/// Slider offsets that move a target image towards the reference statistics.
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceMatchDelta {
+    pub exposure: f64,
+    pub temperature: f64,
+    pub tint: f64,
+    pub contrast: f64,
+}
+
+pub fn match_lab_statistics(
+    reference: &LabStatistics,
+    target: &LabStatistics,
+) -> ReferenceMatchDelta {
+    // Mean L* to linear luminance, so exposure can be solved as a ratio of stops.
+    let luminance = |l: f32| color_management::lab_to_linear_srgb([l, 0.0, 0.0])[1].max(1e-4);
+    let exposure_stops = (luminance(reference.mean[0]) / luminance(target.mean[0])).log2();
+    let exposure = (exposure_stops * SCALES.exposure) as f64;
+
+    // The neutralizing white balance of each mean color; their difference carries the
+    // target's cast over to the reference's.
+    let neutralize = |stats: &LabStatistics| {
+        white_balance_to_neutralize(color_management::lab_to_linear_srgb(stats.mean))
+            .unwrap_or((0.0, 0.0))
+    };
+    let (ref_temp, ref_tint) = neutralize(reference);
+    let (target_temp, target_tint) = neutralize(target);
+
+    let contrast = if target.std_dev[0] > 0.5 && reference.std_dev[0] > 0.5 {
+        // Contrast strength is 2^(1.25 * slider) around the perceptual midpoint.
+        let ratio = reference.std_dev[0] / target.std_dev[0];
+        (ratio.log2() / 1.25 * SCALES.contrast) as f64
+    } else {
+        0.0
+    };
+
+    ReferenceMatchDelta {
+        exposure: exposure.clamp(-5.0, 5.0),
+        temperature: (target_temp - ref_temp).clamp(-100.0, 100.0),
+        tint: (target_tint - ref_tint).clamp(-100.0, 100.0),
+        contrast: contrast.clamp(-100.0, 100.0),
+    }
+}
+
+pub fn auto_tone_to_json(results: &AutoAdjustmentResults) -> serde_json::Value {
+    json!({
+        "exposure": results.exposure,
+        "brightness": results.brightness,
+        "contrast": results.contrast,
+        "highlights": results.highlights,
+        "shadows": results.shadows,
+        "whites": results.whites,
+        "blacks": results.blacks,
+        "sectionVisibility": {
+            "basic": true
+        }
+    })
+}
+
 #[tauri::command]
 pub fn calculate_auto_adjustments(
+    mode: Option<AutoAdjustMode>,
+    clip_percent: Option<f64>,
+    current_adjustments: Option<serde_json::Value>,
     state: tauri::State<AppState>,
 ) -> Result<serde_json::Value, String> {
-    let original_image = state
-        .original_image
-        .lock()
-        .unwrap()
-        .as_ref()
-        .ok_or("No image loaded for auto adjustments")?
-        .image
-        .clone();
-
-    let results = perform_auto_analysis(&original_image);
-
-    Ok(auto_results_to_json(&results))
+    let (original_image, is_raw) = {
+        let lock = state.original_image.lock().unwrap();
+        let loaded = lock
+            .as_ref()
+            .ok_or("No image loaded for auto adjustments")?;
+        (loaded.image.clone(), loaded.is_raw)
+    };
+
+    match mode.unwrap_or_default() {
+        AutoAdjustMode::All => Ok(auto_results_to_json(&perform_auto_analysis(
+            &original_image,
+        ))),
+        AutoAdjustMode::Tone => Ok(auto_tone_to_json(&perform_auto_analysis(&original_image))),
+        AutoAdjustMode::Color => {
+            let clip = clip_percent.unwrap_or(AUTO_COLOR_DEFAULT_CLIP_PERCENT);
+            let levels = compute_auto_color_levels(&original_image, is_raw, clip);
+            let current_curves = current_adjustments.as_ref().and_then(|a| a.get("curves"));
+            Ok(auto_color_levels_to_json(&levels, current_curves))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_color_keeps_the_luma_curve() {
+        let luma = json!([
+            { "x": 0.0, "y": 10.0 },
+            { "x": 128.0, "y": 140.0 },
+            { "x": 255.0, "y": 255.0 }
+        ]);
+        let current = json!({ "luma": luma, "red": [{ "x": 0.0, "y": 0.0 }] });
+        let result = auto_color_levels_to_json(&[(10, 240), (5, 250), (0, 255)], Some(&current));
+        assert_eq!(result["curves"]["luma"], luma);
+        assert_eq!(result["curves"]["red"][0]["x"], json!(10.0));
+        assert_eq!(result["curves"]["blue"][1]["x"], json!(255.0));
+    }
+
+    #[test]
+    fn raw_preview_vignette_matches_full_res_on_flat_gray() {
+        let (width, height) = (96, 64);
+        let linear_gray = 0.18f32;
+        let params = GeometryParams {
+            vig_k1: -0.35,
+            vig_k2: 0.05,
+            lens_vignette_amount: 1.0,
+            ..GeometryParams::default()
+        };
+
+        // Export path: the gain is applied to linear sensor data, then the result is encoded.
+        let linear = Rgb32FImage::from_pixel(width, height, image::Rgb([linear_gray; 3]));
+        let full_res = warp_rgb_geometry(&linear, params);
+
+        // Preview path: the same frame arrives already encoded for display.
+        let encoded = Rgb32FImage::from_pixel(
+            width,
+            height,
+            image::Rgb([linear_to_srgb_scalar(linear_gray); 3]),
+        );
+        let preview = warp_rgb_geometry(
+            &encoded,
+            GeometryParams {
+                vignette_on_display_referred: true,
+                ..params
+            },
+        );
+
+        let corner = full_res.get_pixel(0, 0)[0];
+        let center = full_res.get_pixel(width / 2, height / 2)[0];
+        assert!(
+            corner > center,
+            "vignette correction should brighten corners"
+        );
+
+        for (full, prev) in full_res.pixels().zip(preview.pixels()) {
+            for c in 0..3 {
+                let expected = linear_to_srgb_scalar(full[c]);
+                assert!(
+                    (expected - prev[c]).abs() < 1e-3,
+                    "preview {} differs from export {}",
+                    prev[c],
+                    expected
+                );
+            }
+        }
+    }
 }
@@ -1,16 +1,21 @@
 use std::io::Cursor;
 
 use base64::{Engine as _, engine::general_purpose};
-use image::{DynamicImage, GenericImageView, Rgb, RgbImage, RgbaImage};
+use image::{DynamicImage, GenericImageView, GrayImage, Rgb, RgbImage, Rgba, RgbaImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tauri::Emitter;
 
 use crate::ai_connector;
 use crate::ai_processing;
-use crate::app_settings::load_settings;
+use crate::app_settings::{AppSettings, load_settings};
 use crate::app_state::AppState;
 use crate::image_loader::composite_patches_on_image;
 use crate::image_processing::apply_linear_to_srgb;
-use crate::mask_generation::{AiPatchDefinition, MaskDefinition, generate_mask_bitmap};
+use crate::mask_generation::{
+    AiPatchDefinition, MaskDefinition, decode_base64_mask, generate_mask_bitmap,
+};
 use crate::resolve_warped_image_for_masks;
 
 #[tauri::command]
@@ -28,32 +33,8 @@ pub async fn generate_manual_cleanup_patch(
         patches.retain(|p| p.get("id").and_then(|id| id.as_str()) != Some(&patch_definition.id));
     }
 
-    let is_raw = {
-        let guard = state.original_image.lock().unwrap();
-        guard.as_ref().map(|img| img.is_raw).unwrap_or(false)
-    };
-
-    let (base_image, _) = crate::get_original_image(&state)?;
-    let composited = composite_patches_on_image(&base_image, &source_image_adjustments)
-        .map_err(|e| format!("Failed to prepare source image: {}", e))?;
-
-    let source_image = if is_raw {
-        apply_linear_to_srgb(composited)
-    } else {
-        composited
-    };
-
-    let (img_w, img_h) = source_image.dimensions();
-
-    let orientation_steps = current_adjustments
-        .get("orientationSteps")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0) as u8;
-    let (trans_w, trans_h) = if orientation_steps % 2 == 1 {
-        (img_h, img_w)
-    } else {
-        (img_w, img_h)
-    };
+    let (source_image, is_raw) = prepare_cleanup_source(&state, &source_image_adjustments)?;
+    let (trans_w, trans_h) = oriented_dimensions(&source_image, &current_adjustments);
 
     let mask_def_for_generation = MaskDefinition {
         id: patch_definition.id.clone(),
@@ -81,8 +62,84 @@ pub async fn generate_manual_cleanup_patch(
     )
     .ok_or("Failed to generate mask bitmap for manual cleanup")?;
 
+    let sub_masks_val = serde_json::to_value(&patch_definition.sub_masks).unwrap_or(Value::Null);
+    let mut is_heal = false;
+    if let Some(arr) = sub_masks_val.as_array() {
+        for sm in arr {
+            if let Some(t) = sm.get("type").and_then(|v| v.as_str())
+                && t.eq_ignore_ascii_case("heal")
+            {
+                is_heal = true;
+                break;
+            }
+        }
+    }
+    if !is_heal && patch_definition.name.to_lowercase().contains("heal") {
+        is_heal = true;
+    }
+
+    build_cleanup_patch(
+        &source_image,
+        mask_bitmap,
+        source_point,
+        &current_adjustments,
+        is_heal,
+        is_raw,
+    )
+}
+
+/// Loads the original with every other patch composited, in the encoding patches are
+/// stored in.
+fn prepare_cleanup_source(
+    state: &tauri::State<'_, AppState>,
+    adjustments: &Value,
+) -> Result<(DynamicImage, bool), String> {
+    let is_raw = {
+        let guard = state.original_image.lock().unwrap();
+        guard.as_ref().map(|img| img.is_raw).unwrap_or(false)
+    };
+
+    let (base_image, _) = crate::get_original_image(state)?;
+    let composited = composite_patches_on_image(&base_image, adjustments)
+        .map_err(|e| format!("Failed to prepare source image: {}", e))?;
+
+    let source_image = if is_raw {
+        apply_linear_to_srgb(composited)
+    } else {
+        composited
+    };
+    Ok((source_image, is_raw))
+}
+
+/// Size of the image after the orientation steps, which is the space masks are drawn in.
+fn oriented_dimensions(image: &DynamicImage, adjustments: &Value) -> (u32, u32) {
+    let (img_w, img_h) = image.dimensions();
+    let orientation_steps = adjustments
+        .get("orientationSteps")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u8;
+    if orientation_steps % 2 == 1 {
+        (img_h, img_w)
+    } else {
+        (img_w, img_h)
+    }
+}
+
+/// Maps a mask and source point drawn on the oriented image back onto the original pixels
+/// and clones (or heals, with Poisson blending) the source into the masked region.
+fn build_cleanup_patch(
+    source_image: &DynamicImage,
+    mask_bitmap: GrayImage,
+    source_point: (f64, f64),
+    current_adjustments: &Value,
+    is_heal: bool,
+    is_raw: bool,
+) -> Result<String, String> {
+    let (img_w, img_h) = source_image.dimensions();
+    let (trans_w, trans_h) = (mask_bitmap.width(), mask_bitmap.height());
+
     let mask_bitmap =
-        crate::image_processing::inverse_transform_mask(mask_bitmap, &current_adjustments);
+        crate::image_processing::inverse_transform_mask(mask_bitmap, current_adjustments);
 
     let mask_raw = mask_bitmap.as_raw();
     let img_w_usize = img_w as usize;
@@ -142,7 +199,7 @@ pub async fn generate_manual_cleanup_patch(
         source_point.1,
         trans_w as f64,
         trans_h as f64,
-        &current_adjustments,
+        current_adjustments,
     );
 
     let offset_x = (source_point_untransformed.0 - center_x).round() as i32;
@@ -153,22 +210,6 @@ pub async fn generate_manual_cleanup_patch(
     let crop_w = (max_x - min_x + 1) as u32;
     let crop_h = (max_y - min_y + 1) as u32;
 
-    let sub_masks_val = serde_json::to_value(&patch_definition.sub_masks).unwrap_or(Value::Null);
-    let mut is_heal = false;
-    if let Some(arr) = sub_masks_val.as_array() {
-        for sm in arr {
-            if let Some(t) = sm.get("type").and_then(|v| v.as_str())
-                && t.eq_ignore_ascii_case("heal")
-            {
-                is_heal = true;
-                break;
-            }
-        }
-    }
-    if !is_heal && patch_definition.name.to_lowercase().contains("heal") {
-        is_heal = true;
-    }
-
     let mut color_image = RgbImage::new(crop_w, crop_h);
 
     if !is_heal {
@@ -188,102 +229,139 @@ pub async fn generate_manual_cleanup_patch(
             }
         }
     } else {
-        let bw = max_x - min_x + 3;
-        let bh = max_y - min_y + 3;
-
-        let mut v_r = vec![0.0f32; bw * bh];
-        let mut v_g = vec![0.0f32; bw * bh];
-        let mut v_b = vec![0.0f32; bw * bh];
-
-        let mut region = vec![0u8; bw * bh];
+        poisson_clone_into(
+            source_image,
+            &mask_bitmap,
+            (min_x, min_y, max_x, max_y),
+            (offset_x, offset_y),
+            &mut color_image,
+        );
+    }
 
-        for y in 0..bh {
-            for x in 0..bw {
-                let img_x = min_x as i32 + x as i32 - 1;
-                let img_y = min_y as i32 + y as i32 - 1;
+    encode_patch_json(
+        &color_image,
+        &mask_bitmap,
+        (min_x_u32, min_y_u32),
+        100,
+        is_raw,
+    )
+}
 
-                if img_x >= 0
-                    && img_x < img_w as i32
-                    && img_y >= 0
-                    && img_y < img_h as i32
-                    && mask_bitmap.get_pixel(img_x as u32, img_y as u32)[0] > 0
-                {
-                    region[y * bw + x] = 1;
-                }
+/// Copies the masked region from `offset` away and solves the Poisson equation so the
+/// copied gradients blend seamlessly into the destination boundary. Writes the result into
+/// `color_image`, which covers the inclusive `bounds` of the mask.
+fn poisson_clone_into(
+    source_image: &DynamicImage,
+    mask_bitmap: &GrayImage,
+    (min_x, min_y, max_x, max_y): (usize, usize, usize, usize),
+    (offset_x, offset_y): (i32, i32),
+    color_image: &mut RgbImage,
+) {
+    let (img_w, img_h) = source_image.dimensions();
+    let crop_w = color_image.width();
+    let crop_h = color_image.height();
+    let bw = max_x - min_x + 3;
+    let bh = max_y - min_y + 3;
+
+    let mut v_r = vec![0.0f32; bw * bh];
+    let mut v_g = vec![0.0f32; bw * bh];
+    let mut v_b = vec![0.0f32; bw * bh];
+
+    let mut region = vec![0u8; bw * bh];
+
+    for y in 0..bh {
+        for x in 0..bw {
+            let img_x = min_x as i32 + x as i32 - 1;
+            let img_y = min_y as i32 + y as i32 - 1;
+
+            if img_x >= 0
+                && img_x < img_w as i32
+                && img_y >= 0
+                && img_y < img_h as i32
+                && mask_bitmap.get_pixel(img_x as u32, img_y as u32)[0] > 0
+            {
+                region[y * bw + x] = 1;
             }
         }
+    }
 
-        let mut omega_coords = Vec::with_capacity(bw * bh);
+    let mut omega_coords = Vec::with_capacity(bw * bh);
 
-        for y in 1..(bh - 1) {
-            for x in 1..(bw - 1) {
-                if region[y * bw + x] == 0 {
-                    if region[(y - 1) * bw + x] == 1
-                        || region[(y + 1) * bw + x] == 1
-                        || region[y * bw + x - 1] == 1
-                        || region[y * bw + x + 1] == 1
-                    {
-                        region[y * bw + x] = 2;
+    for y in 1..(bh - 1) {
+        for x in 1..(bw - 1) {
+            if region[y * bw + x] == 0 {
+                if region[(y - 1) * bw + x] == 1
+                    || region[(y + 1) * bw + x] == 1
+                    || region[y * bw + x - 1] == 1
+                    || region[y * bw + x + 1] == 1
+                {
+                    region[y * bw + x] = 2;
 
-                        let img_x = (min_x as i32 + x as i32 - 1) as u32;
-                        let img_y = (min_y as i32 + y as i32 - 1) as u32;
+                    let img_x = (min_x as i32 + x as i32 - 1) as u32;
+                    let img_y = (min_y as i32 + y as i32 - 1) as u32;
 
-                        let src_x = (img_x as i32 + offset_x).clamp(0, img_w as i32 - 1) as u32;
-                        let src_y = (img_y as i32 + offset_y).clamp(0, img_h as i32 - 1) as u32;
+                    let src_x = (img_x as i32 + offset_x).clamp(0, img_w as i32 - 1) as u32;
+                    let src_y = (img_y as i32 + offset_y).clamp(0, img_h as i32 - 1) as u32;
 
-                        let dest_px = source_image.get_pixel(img_x, img_y);
-                        let src_px = source_image.get_pixel(src_x, src_y);
+                    let dest_px = source_image.get_pixel(img_x, img_y);
+                    let src_px = source_image.get_pixel(src_x, src_y);
 
-                        v_r[y * bw + x] = dest_px[0] as f32 - src_px[0] as f32;
-                        v_g[y * bw + x] = dest_px[1] as f32 - src_px[1] as f32;
-                        v_b[y * bw + x] = dest_px[2] as f32 - src_px[2] as f32;
-                    }
-                } else if region[y * bw + x] == 1 {
-                    omega_coords.push((x, y));
+                    v_r[y * bw + x] = dest_px[0] as f32 - src_px[0] as f32;
+                    v_g[y * bw + x] = dest_px[1] as f32 - src_px[1] as f32;
+                    v_b[y * bw + x] = dest_px[2] as f32 - src_px[2] as f32;
                 }
+            } else if region[y * bw + x] == 1 {
+                omega_coords.push((x, y));
             }
         }
+    }
 
-        let omega = 1.6f32;
-        let iterations = 400;
-
-        for _ in 0..iterations {
-            for &(x, y) in &omega_coords {
-                let idx = y * bw + x;
-                let sum_r = v_r[idx - bw] + v_r[idx + bw] + v_r[idx - 1] + v_r[idx + 1];
-                let sum_g = v_g[idx - bw] + v_g[idx + bw] + v_g[idx - 1] + v_g[idx + 1];
-                let sum_b = v_b[idx - bw] + v_b[idx + bw] + v_b[idx - 1] + v_b[idx + 1];
+    let omega = 1.6f32;
+    let iterations = 400;
 
-                v_r[idx] = (1.0 - omega) * v_r[idx] + omega * 0.25 * sum_r;
-                v_g[idx] = (1.0 - omega) * v_g[idx] + omega * 0.25 * sum_g;
-                v_b[idx] = (1.0 - omega) * v_b[idx] + omega * 0.25 * sum_b;
-            }
-        }
+    for _ in 0..iterations {
         for &(x, y) in &omega_coords {
-            let img_x = (min_x as i32 + x as i32 - 1) as u32;
-            let img_y = (min_y as i32 + y as i32 - 1) as u32;
-
-            let src_x = (img_x as i32 + offset_x).clamp(0, img_w as i32 - 1) as u32;
-            let src_y = (img_y as i32 + offset_y).clamp(0, img_h as i32 - 1) as u32;
-            let src_px = source_image.get_pixel(src_x, src_y);
-
             let idx = y * bw + x;
-            let out_r = (src_px[0] as f32 + v_r[idx]).clamp(0.0, 255.0) as u8;
-            let out_g = (src_px[1] as f32 + v_g[idx]).clamp(0.0, 255.0) as u8;
-            let out_b = (src_px[2] as f32 + v_b[idx]).clamp(0.0, 255.0) as u8;
-
-            let out_x = img_x as i32 - min_x as i32;
-            let out_y = img_y as i32 - min_y as i32;
-            if out_x >= 0 && out_x < crop_w as i32 && out_y >= 0 && out_y < crop_h as i32 {
-                color_image.put_pixel(out_x as u32, out_y as u32, Rgb([out_r, out_g, out_b]));
-            }
+            let sum_r = v_r[idx - bw] + v_r[idx + bw] + v_r[idx - 1] + v_r[idx + 1];
+            let sum_g = v_g[idx - bw] + v_g[idx + bw] + v_g[idx - 1] + v_g[idx + 1];
+            let sum_b = v_b[idx - bw] + v_b[idx + bw] + v_b[idx - 1] + v_b[idx + 1];
+
+            v_r[idx] = (1.0 - omega) * v_r[idx] + omega * 0.25 * sum_r;
+            v_g[idx] = (1.0 - omega) * v_g[idx] + omega * 0.25 * sum_g;
+            v_b[idx] = (1.0 - omega) * v_b[idx] + omega * 0.25 * sum_b;
         }
     }
+    for &(x, y) in &omega_coords {
+        let img_x = (min_x as i32 + x as i32 - 1) as u32;
+        let img_y = (min_y as i32 + y as i32 - 1) as u32;
+
+        let src_x = (img_x as i32 + offset_x).clamp(0, img_w as i32 - 1) as u32;
+        let src_y = (img_y as i32 + offset_y).clamp(0, img_h as i32 - 1) as u32;
+        let src_px = source_image.get_pixel(src_x, src_y);
+
+        let idx = y * bw + x;
+        let out_r = (src_px[0] as f32 + v_r[idx]).clamp(0.0, 255.0) as u8;
+        let out_g = (src_px[1] as f32 + v_g[idx]).clamp(0.0, 255.0) as u8;
+        let out_b = (src_px[2] as f32 + v_b[idx]).clamp(0.0, 255.0) as u8;
+
+        let out_x = img_x as i32 - min_x as i32;
+        let out_y = img_y as i32 - min_y as i32;
+        if out_x >= 0 && out_x < crop_w as i32 && out_y >= 0 && out_y < crop_h as i32 {
+            color_image.put_pixel(out_x as u32, out_y as u32, Rgb([out_r, out_g, out_b]));
+        }
+    }
+}
 
-    let quality = 100;
-
+fn encode_patch_json(
+    color_image: &RgbImage,
+    mask_bitmap: &GrayImage,
+    (min_x_u32, min_y_u32): (u32, u32),
+    quality: u8,
+    is_srgb_encoded: bool,
+) -> Result<String, String> {
+    let (crop_w, crop_h) = color_image.dimensions();
     let output_mask =
-        image::imageops::crop_imm(&mask_bitmap, min_x_u32, min_y_u32, crop_w, crop_h).to_image();
+        image::imageops::crop_imm(mask_bitmap, min_x_u32, min_y_u32, crop_w, crop_h).to_image();
 
     let mut color_buf = Cursor::new(Vec::with_capacity(32768));
     color_image
@@ -310,13 +388,55 @@ pub async fn generate_manual_cleanup_patch(
         "offsetY": min_y_u32,
         "width": crop_w,
         "height": crop_h,
-        "isSrgbEncoded": is_raw
+        "isSrgbEncoded": is_srgb_encoded
     })
     .to_string();
 
     Ok(result_json)
 }
 
+/// Heals the masked region from `source_offset` away. The mask and offset are given on
+/// the oriented image, like mask sub-masks, and go through the same crop, rotation and
+/// orientation handling as the manual cleanup patches.
+#[tauri::command]
+pub async fn heal_region(
+    mask_base64: String,
+    source_offset: (i32, i32),
+    current_adjustments: Value,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let (source_image, is_raw) = prepare_cleanup_source(&state, &current_adjustments)?;
+    let (trans_w, trans_h) = oriented_dimensions(&source_image, &current_adjustments);
+
+    let mask = decode_base64_mask(&mask_base64).ok_or("Failed to decode heal mask.")?;
+    let mask_bitmap = if mask.dimensions() != (trans_w, trans_h) {
+        image::imageops::resize(
+            &mask,
+            trans_w,
+            trans_h,
+            image::imageops::FilterType::Nearest,
+        )
+    } else {
+        mask
+    };
+
+    let (min_x, min_y, max_x, max_y) =
+        mask_bounds(&mask_bitmap).ok_or_else(|| "Mask is empty.".to_string())?;
+    let source_point = (
+        (min_x + max_x) as f64 / 2.0 + source_offset.0 as f64,
+        (min_y + max_y) as f64 / 2.0 + source_offset.1 as f64,
+    );
+
+    build_cleanup_patch(
+        &source_image,
+        mask_bitmap,
+        source_point,
+        &current_adjustments,
+        true,
+        is_raw,
+    )
+}
+
 #[tauri::command]
 pub async fn invoke_generative_replace_with_mask_def(
     path: String,
@@ -393,199 +513,774 @@ pub async fn invoke_generative_replace_with_mask_def(
     let mask_bitmap =
         crate::image_processing::inverse_transform_mask(mask_bitmap, &current_adjustments);
 
+    let (min_x, min_y, max_x, max_y) =
+        mask_bounds(&mask_bitmap).ok_or_else(|| "Mask is empty.".to_string())?;
+    let region_rect @ (region_x, region_y, region_w, region_h) =
+        context_region((min_x, min_y, max_x, max_y), (img_w, img_h));
+
+    let region_image = source_image.crop_imm(region_x, region_y, region_w, region_h);
+    let region_mask =
+        image::imageops::crop_imm(&mask_bitmap, region_x, region_y, region_w, region_h).to_image();
+
+    let generation_params = ai_connector::GenerationParams {
+        prompt: patch_definition.prompt.clone(),
+        negative_prompt: patch_definition.negative_prompt.clone(),
+        guidance_scale: patch_definition.guidance_scale,
+        steps: patch_definition.steps,
+        seed: patch_definition.seed,
+    };
+
     let patch_rgba = if use_fast_inpaint {
-        let lama_model = ai_processing::get_or_init_lama_model(
+        match ai_processing::get_or_init_lama_model(
             &app_handle,
             &state.ai_state,
             &state.ai_init_lock,
         )
         .await
-        .map_err(|e| e.to_string())?;
+        {
+            Ok(lama_model) => {
+                ai_processing::run_lama_inpainting(&region_image, &region_mask, &lama_model)
+                    .map_err(|e| e.to_string())?
+            }
+            Err(e) => {
+                log::warn!(
+                    "Inpainting model unavailable ({}), falling back to PatchMatch fill",
+                    e
+                );
+                perform_fast_inpaint(
+                    &region_image,
+                    &region_mask,
+                    PATCH_MATCH_RADIUS,
+                    PATCH_MATCH_ITERATIONS,
+                )
+            }
+        }
+    } else {
+        generative_fill_region(
+            &path,
+            region_rect,
+            &region_image,
+            &region_mask,
+            generation_params,
+            false,
+            token,
+            &settings,
+            &app_handle,
+        )
+        .await?
+    };
 
-        ai_processing::run_lama_inpainting(&source_image, &mask_bitmap, &lama_model)
-            .map_err(|e| e.to_string())?
-    } else if settings.ai_provider.as_deref() == Some("cloud")
-        && let Some(auth_token) = token
-    {
-        let base_url = "https://getrapidraw.com/api";
-
-        let mut rgba_mask = RgbaImage::new(img_w, img_h);
-        for (src_val, dst_chunk) in mask_bitmap.as_raw().iter().zip(rgba_mask.chunks_mut(4)) {
-            let intensity = *src_val;
-            dst_chunk[0] = intensity;
-            dst_chunk[1] = intensity;
-            dst_chunk[2] = intensity;
-            dst_chunk[3] = 255;
+    let (patch_w, patch_h) = patch_rgba.dimensions();
+    let final_patch = if patch_w != region_w || patch_h != region_h {
+        image::imageops::resize(
+            &patch_rgba,
+            region_w,
+            region_h,
+            image::imageops::FilterType::Lanczos3,
+        )
+    } else {
+        patch_rgba
+    };
+
+    let crop_w = max_x - min_x + 1;
+    let crop_h = max_y - min_y + 1;
+    let mut color_image = RgbImage::new(crop_w, crop_h);
+
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            if mask_bitmap.get_pixel(x, y)[0] > 0 {
+                let patch_pixel = final_patch.get_pixel(x - region_x, y - region_y);
+                color_image.put_pixel(
+                    x - min_x,
+                    y - min_y,
+                    Rgb([patch_pixel[0], patch_pixel[1], patch_pixel[2]]),
+                );
+            }
         }
-        let mask_image_dynamic = DynamicImage::ImageRgba8(rgba_mask);
+    }
 
-        let (real_path_buf, _) = crate::file_management::parse_virtual_path(&path);
+    encode_patch_json(&color_image, &mask_bitmap, (min_x, min_y), 95, is_raw)
+}
 
-        ai_connector::process_inpainting(
-            base_url,
-            &real_path_buf.to_string_lossy(),
-            &source_image,
-            &mask_image_dynamic,
-            patch_definition.prompt,
-            Some(&auth_token),
-        )
-        .await
-        .map_err(|e| e.to_string())?
-    } else if settings.ai_provider.as_deref() == Some("ai-connector")
-        && let Some(address) = settings.ai_connector_address
-    {
-        let base_url = format!("http://{}", address);
-
-        let mut rgba_mask = RgbaImage::new(img_w, img_h);
-        for (src_val, dst_chunk) in mask_bitmap.as_raw().iter().zip(rgba_mask.chunks_mut(4)) {
-            let intensity = *src_val;
-            dst_chunk[0] = intensity;
-            dst_chunk[1] = intensity;
-            dst_chunk[2] = intensity;
-            dst_chunk[3] = 255;
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ExpandDirection {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Horizontal,
+    Vertical,
+    All,
+}
+
+impl ExpandDirection {
+    /// Padding added to the left, top, right and bottom edges.
+    fn padding(self, amount: u32) -> (u32, u32, u32, u32) {
+        match self {
+            Self::Left => (amount, 0, 0, 0),
+            Self::Right => (0, 0, amount, 0),
+            Self::Top => (0, amount, 0, 0),
+            Self::Bottom => (0, 0, 0, amount),
+            Self::Horizontal => (amount, 0, amount, 0),
+            Self::Vertical => (0, amount, 0, amount),
+            Self::All => (amount, amount, amount, amount),
         }
-        let mask_image_dynamic = DynamicImage::ImageRgba8(rgba_mask);
+    }
+}
 
-        let (real_path_buf, _) = crate::file_management::parse_virtual_path(&path);
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpandResult {
+    pub width: u32,
+    pub height: u32,
+    pub offset_x: u32,
+    pub offset_y: u32,
+    pub preview: String,
+}
 
-        ai_connector::process_inpainting(
-            &base_url,
-            &real_path_buf.to_string_lossy(),
-            &source_image,
-            &mask_image_dynamic,
-            patch_definition.prompt,
-            None,
-        )
-        .await
-        .map_err(|e| e.to_string())?
+/// How far the generated area reaches back into the original image so the seam is redrawn.
+const EXPAND_SEAM_OVERLAP: u32 = 16;
+const EXPAND_PREVIEW_SIZE: u32 = 1920;
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn generative_expand(
+    path: String,
+    direction: ExpandDirection,
+    amount: u32,
+    prompt: String,
+    current_adjustments: Value,
+    token: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<ExpandResult, String> {
+    if amount == 0 {
+        return Err("Expansion amount must be greater than zero.".to_string());
+    }
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+
+    let is_raw = {
+        let guard = state.original_image.lock().unwrap();
+        guard.as_ref().map(|img| img.is_raw).unwrap_or(false)
+    };
+
+    let (base_image, _) = crate::get_original_image(&state)?;
+    let composited = composite_patches_on_image(&base_image, &current_adjustments)
+        .map_err(|e| format!("Failed to prepare source image: {}", e))?;
+    let source = if is_raw {
+        apply_linear_to_srgb(composited)
     } else {
-        return Err(
-            "No generative backend configured or connection invalid. Please check your AI settings."
-                .to_string(),
-        );
+        composited
+    }
+    .to_rgb8();
+
+    let (img_w, img_h) = source.dimensions();
+    let amount = amount.min(img_w.max(img_h));
+    let (pad_left, pad_top, pad_right, pad_bottom) = direction.padding(amount);
+    let canvas_w = img_w + pad_left + pad_right;
+    let canvas_h = img_h + pad_top + pad_bottom;
+
+    // Edge-extended pixels give the backend (and the PatchMatch fallback) a sensible start.
+    let canvas = RgbImage::from_fn(canvas_w, canvas_h, |x, y| {
+        let sx = x.saturating_sub(pad_left).min(img_w - 1);
+        let sy = y.saturating_sub(pad_top).min(img_h - 1);
+        *source.get_pixel(sx, sy)
+    });
+
+    let overlap_x = EXPAND_SEAM_OVERLAP.min(img_w / 4);
+    let overlap_y = EXPAND_SEAM_OVERLAP.min(img_h / 4);
+    let keep_left = pad_left + if pad_left > 0 { overlap_x } else { 0 };
+    let keep_right = pad_left + img_w - if pad_right > 0 { overlap_x } else { 0 };
+    let keep_top = pad_top + if pad_top > 0 { overlap_y } else { 0 };
+    let keep_bottom = pad_top + img_h - if pad_bottom > 0 { overlap_y } else { 0 };
+    let mask_bitmap = GrayImage::from_fn(canvas_w, canvas_h, |x, y| {
+        let kept = (keep_left..keep_right).contains(&x) && (keep_top..keep_bottom).contains(&y);
+        image::Luma([if kept { 0 } else { 255 }])
+    });
+
+    let bounds = mask_bounds(&mask_bitmap).ok_or_else(|| "Nothing to expand.".to_string())?;
+    let region_rect @ (region_x, region_y, region_w, region_h) =
+        context_region(bounds, (canvas_w, canvas_h));
+
+    let canvas_image = DynamicImage::ImageRgb8(canvas);
+    let region_image = canvas_image.crop_imm(region_x, region_y, region_w, region_h);
+    let region_mask =
+        image::imageops::crop_imm(&mask_bitmap, region_x, region_y, region_w, region_h).to_image();
+
+    let generation_params = ai_connector::GenerationParams {
+        prompt,
+        ..Default::default()
     };
+    let patch_rgba = generative_fill_region(
+        &path,
+        region_rect,
+        &region_image,
+        &region_mask,
+        generation_params,
+        true,
+        token,
+        &settings,
+        &app_handle,
+    )
+    .await?;
 
-    let (patch_w, patch_h) = patch_rgba.dimensions();
-    let final_patch = if patch_w != img_w || patch_h != img_h {
+    let patch_rgba = if patch_rgba.dimensions() != (region_w, region_h) {
         image::imageops::resize(
             &patch_rgba,
-            img_w,
-            img_h,
+            region_w,
+            region_h,
             image::imageops::FilterType::Lanczos3,
         )
     } else {
-        patch_rgba.clone()
+        patch_rgba
     };
 
-    let mask_raw = mask_bitmap.as_raw();
-    let img_w_usize = img_w as usize;
-    let img_h_usize = img_h as usize;
+    let mut expanded = canvas_image.into_rgb8();
+    for (x, y, mask_pixel) in region_mask.enumerate_pixels() {
+        if mask_pixel[0] > 0 {
+            let patch_pixel = patch_rgba.get_pixel(x, y);
+            expanded.put_pixel(
+                region_x + x,
+                region_y + y,
+                Rgb([patch_pixel[0], patch_pixel[1], patch_pixel[2]]),
+            );
+        }
+    }
+    let expanded = DynamicImage::ImageRgb8(expanded);
 
-    let mut min_y = img_h_usize;
-    let mut max_y = 0;
+    let mut preview_buf = Cursor::new(Vec::new());
+    expanded
+        .thumbnail(EXPAND_PREVIEW_SIZE, EXPAND_PREVIEW_SIZE)
+        .to_rgb8()
+        .write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(
+            &mut preview_buf,
+            90,
+        ))
+        .map_err(|e| e.to_string())?;
+    let preview = format!(
+        "data:image/jpeg;base64,{}",
+        general_purpose::STANDARD.encode(preview_buf.get_ref())
+    );
 
-    for y in 0..img_h_usize {
-        let row_start = y * img_w_usize;
-        if mask_raw[row_start..row_start + img_w_usize]
-            .iter()
-            .any(|&p| p > 0)
+    *state.expand_result.lock().unwrap() = Some(expanded);
+
+    Ok(ExpandResult {
+        width: canvas_w,
+        height: canvas_h,
+        offset_x: pad_left,
+        offset_y: pad_top,
+        preview,
+    })
+}
+
+#[tauri::command]
+pub async fn save_expanded_image(
+    original_path_str: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let expanded_image = state.expand_result.lock().unwrap().take().ok_or_else(|| {
+        "No expanded image found in memory. It might have already been saved or cleared."
+            .to_string()
+    })?;
+
+    let (source_path, _) = crate::file_management::parse_virtual_path(&original_path_str);
+    let parent_dir = source_path
+        .parent()
+        .ok_or_else(|| "Could not determine parent directory.".to_string())?;
+    let stem = source_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("expanded");
+    let output_path = parent_dir.join(format!("{}_Expanded.png", stem));
+
+    expanded_image
+        .save(&output_path)
+        .map_err(|e| format!("Failed to save image: {}", e))?;
+
+    let _ =
+        crate::exif_processing::write_rrexif_sidecar(&source_path.to_string_lossy(), &output_path);
+
+    Ok(output_path.to_string_lossy().to_string())
+}
+
+/// Fills `region_mask` within `region_image` using the configured generative backend.
+#[allow(clippy::too_many_arguments)]
+async fn generative_fill_region(
+    path: &str,
+    region_rect: (u32, u32, u32, u32),
+    region_image: &DynamicImage,
+    region_mask: &GrayImage,
+    generation_params: ai_connector::GenerationParams,
+    outpaint: bool,
+    token: Option<String>,
+    settings: &AppSettings,
+    app_handle: &tauri::AppHandle,
+) -> Result<RgbaImage, String> {
+    Ok(
+        if settings.ai_provider.as_deref() == Some("cloud")
+            && let Some(auth_token) = token
         {
-            min_y = y;
-            break;
-        }
+            let base_url = "https://getrapidraw.com/api";
+            let (real_path_buf, _) = crate::file_management::parse_virtual_path(path);
+
+            ai_connector::process_inpainting(
+                base_url,
+                &real_path_buf.to_string_lossy(),
+                region_rect,
+                region_image,
+                &mask_to_rgba(region_mask),
+                generation_params,
+                Some(&auth_token),
+                ai_connector::ConnectorTimeouts::from_settings(settings),
+                ai_connector::InpaintMode::Blocking,
+            )
+            .await
+            .map_err(|e| e.to_string())?
+        } else if settings.ai_provider.as_deref() == Some("ai-connector")
+            && let Some(address) = &settings.ai_connector_address
+        {
+            let base_url = format!("http://{}", address);
+            let (real_path_buf, _) = crate::file_management::parse_virtual_path(path);
+            let timeouts = ai_connector::ConnectorTimeouts::from_settings(settings);
+
+            let capabilities = ai_connector::check_status(address, timeouts)
+                .await
+                .unwrap_or_default();
+            if !capabilities.inpaint {
+                return Err("The AI connector does not support inpainting.".to_string());
+            }
+            if outpaint && !capabilities.outpaint {
+                return Err(
+                    "The AI connector does not support outpainting. Update the connector or switch to the cloud provider to expand the canvas."
+                        .to_string(),
+                );
+            }
+
+            // The caller scales the patch back up to the region size.
+            let (region_w, region_h) = region_image.dimensions();
+            let downscaled = match capabilities.max_resolution {
+                Some(max_resolution) if region_w.max(region_h) > max_resolution => {
+                    let scale = max_resolution as f32 / region_w.max(region_h) as f32;
+                    let width = ((region_w as f32 * scale).round() as u32).max(1);
+                    let height = ((region_h as f32 * scale).round() as u32).max(1);
+                    Some((
+                        region_image.resize_exact(
+                            width,
+                            height,
+                            image::imageops::FilterType::Lanczos3,
+                        ),
+                        image::imageops::resize(
+                            region_mask,
+                            width,
+                            height,
+                            image::imageops::FilterType::Triangle,
+                        ),
+                    ))
+                }
+                _ => None,
+            };
+            let (region_image, region_mask) = match &downscaled {
+                Some((image, mask)) => (image, mask),
+                None => (region_image, region_mask),
+            };
+
+            let report_progress = |progress: f32| {
+                let _ = app_handle.emit(
+                    "inpaint-progress",
+                    serde_json::json!({ "percentage": (progress * 100.0).round() as u32 }),
+                );
+            };
+            let mode = if capabilities.jobs {
+                ai_connector::InpaintMode::Job(&report_progress)
+            } else {
+                ai_connector::InpaintMode::Blocking
+            };
+
+            ai_connector::process_inpainting(
+                &base_url,
+                &real_path_buf.to_string_lossy(),
+                region_rect,
+                region_image,
+                &mask_to_rgba(region_mask),
+                generation_params,
+                None,
+                timeouts,
+                mode,
+            )
+            .await
+            .map_err(|e| e.to_string())?
+        } else {
+            return Err(
+                "No generative backend configured or connection invalid. Please check your AI settings."
+                    .to_string(),
+            );
+        },
+    )
+}
+
+/// Minimum context, in pixels, kept around the mask when inpainting a cropped region.
+const INPAINT_CONTEXT_MIN_MARGIN: u32 = 128;
+const INPAINT_CONTEXT_RATIO: f32 = 1.5;
+
+/// Grows the inclusive mask bounds by the inpaint context margin, clamped to the image.
+/// Returns `(x, y, width, height)`.
+fn context_region(
+    (min_x, min_y, max_x, max_y): (u32, u32, u32, u32),
+    (img_w, img_h): (u32, u32),
+) -> (u32, u32, u32, u32) {
+    let margin = INPAINT_CONTEXT_MIN_MARGIN
+        .max(((max_x - min_x).max(max_y - min_y) as f32 * INPAINT_CONTEXT_RATIO).round() as u32);
+    let region_x = min_x.saturating_sub(margin);
+    let region_y = min_y.saturating_sub(margin);
+    let region_w = (max_x + margin).min(img_w - 1) - region_x + 1;
+    let region_h = (max_y + margin).min(img_h - 1) - region_y + 1;
+    (region_x, region_y, region_w, region_h)
+}
+
+fn mask_to_rgba(mask: &GrayImage) -> DynamicImage {
+    let (width, height) = mask.dimensions();
+    let mut rgba_mask = RgbaImage::new(width, height);
+    for (src_val, dst_chunk) in mask.as_raw().iter().zip(rgba_mask.chunks_mut(4)) {
+        let intensity = *src_val;
+        dst_chunk[0] = intensity;
+        dst_chunk[1] = intensity;
+        dst_chunk[2] = intensity;
+        dst_chunk[3] = 255;
     }
-    if min_y == img_h_usize {
-        return Err("Mask is empty.".to_string());
+    DynamicImage::ImageRgba8(rgba_mask)
+}
+
+const PATCH_MATCH_RADIUS: u32 = 3;
+const PATCH_MATCH_ITERATIONS: u32 = 6;
+
+/// Deterministic per-pixel random stream so repeated fills of the same mask are identical.
+fn patch_match_random(seed: u64) -> u64 {
+    let mut x = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+struct PatchMatchRegion {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 3]>,
+    hole: Vec<bool>,
+}
+
+impl PatchMatchRegion {
+    fn patch_distance(&self, target: (usize, usize), source: (usize, usize), radius: i32) -> f32 {
+        let mut distance = 0.0;
+        let mut count = 0u32;
+        for dy in -radius..=radius {
+            let ty = target.1 as i32 + dy;
+            if ty < 0 || ty >= self.height as i32 {
+                continue;
+            }
+            let sy = (source.1 as i32 + dy) as usize;
+            for dx in -radius..=radius {
+                let tx = target.0 as i32 + dx;
+                if tx < 0 || tx >= self.width as i32 {
+                    continue;
+                }
+                let sx = (source.0 as i32 + dx) as usize;
+                let t = self.pixels[ty as usize * self.width + tx as usize];
+                let s = self.pixels[sy * self.width + sx];
+                distance += (t[0] - s[0]).powi(2) + (t[1] - s[1]).powi(2) + (t[2] - s[2]).powi(2);
+                count += 1;
+            }
+        }
+        distance / count.max(1) as f32
     }
+}
 
-    for y in (min_y..img_h_usize).rev() {
-        let row_start = y * img_w_usize;
-        if mask_raw[row_start..row_start + img_w_usize]
-            .iter()
-            .any(|&p| p > 0)
-        {
-            max_y = y;
-            break;
+/// Exemplar-based hole filling with PatchMatch. Every pixel whose patch touches the hole is
+/// matched to a fully known patch elsewhere in the surrounding region, and hole pixels are
+/// re-estimated by voting over the overlapping matches. Runs offline without any model.
+pub fn perform_fast_inpaint(
+    source_image: &DynamicImage,
+    mask_bitmap: &GrayImage,
+    patch_radius: u32,
+    iterations: u32,
+) -> RgbaImage {
+    let mut output = source_image.to_rgba8();
+    let (img_w, img_h) = output.dimensions();
+    let radius = patch_radius.max(1) as i32;
+
+    let Some((min_x, min_y, max_x, max_y)) = mask_bounds(mask_bitmap) else {
+        return output;
+    };
+    let pad = ((max_x - min_x).max(max_y - min_y) + 1).max(patch_radius * 16);
+    let x0 = min_x.saturating_sub(pad);
+    let y0 = min_y.saturating_sub(pad);
+    let x1 = (max_x + pad).min(img_w - 1);
+    let y1 = (max_y + pad).min(img_h - 1);
+    let width = (x1 - x0 + 1) as usize;
+    let height = (y1 - y0 + 1) as usize;
+
+    let mut region = PatchMatchRegion {
+        width,
+        height,
+        pixels: Vec::with_capacity(width * height),
+        hole: Vec::with_capacity(width * height),
+    };
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            let p = output.get_pixel(x, y);
+            region.pixels.push([p[0] as f32, p[1] as f32, p[2] as f32]);
+            region.hole.push(mask_bitmap.get_pixel(x, y)[0] > 0);
         }
     }
-    let mut min_x = img_w_usize;
-    let mut max_x = 0;
-    for y in min_y..=max_y {
-        let row_start = y * img_w_usize;
-        let row = &mask_raw[row_start..row_start + img_w_usize];
-        if let Some(first) = row.iter().position(|&p| p > 0)
-            && first < min_x
-        {
-            min_x = first;
+
+    fill_hole_by_diffusion(&mut region);
+
+    // Source patches must lie fully inside the region and contain no hole pixels.
+    let mut hole_integral = vec![0u32; (width + 1) * (height + 1)];
+    for y in 0..height {
+        let mut row_sum = 0;
+        for x in 0..width {
+            row_sum += region.hole[y * width + x] as u32;
+            hole_integral[(y + 1) * (width + 1) + x + 1] =
+                hole_integral[y * (width + 1) + x + 1] + row_sum;
         }
-        if let Some(last) = row.iter().rposition(|&p| p > 0)
-            && last > max_x
-        {
-            max_x = last;
+    }
+    let r = radius as usize;
+    let window_holes = |x: usize, y: usize| -> u32 {
+        let (ax, ay, bx, by) = (x - r, y - r, x + r + 1, y + r + 1);
+        hole_integral[by * (width + 1) + bx] + hole_integral[ay * (width + 1) + ax]
+            - hole_integral[ay * (width + 1) + bx]
+            - hole_integral[by * (width + 1) + ax]
+    };
+    let is_valid_source = |x: usize, y: usize| -> bool {
+        x >= r && y >= r && x + r < width && y + r < height && window_holes(x, y) == 0
+    };
+    let valid_sources: Vec<(usize, usize)> = (r..height.saturating_sub(r))
+        .flat_map(|y| (r..width.saturating_sub(r)).map(move |x| (x, y)))
+        .filter(|&(x, y)| is_valid_source(x, y))
+        .collect();
+    if valid_sources.is_empty() {
+        write_region_back(&region, &mut output, x0, y0);
+        return output;
+    }
+
+    // Targets are all pixels whose patch overlaps the hole.
+    let mut is_target = vec![false; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            if region.hole[y * width + x] {
+                for ty in y.saturating_sub(r)..=(y + r).min(height - 1) {
+                    for tx in x.saturating_sub(r)..=(x + r).min(width - 1) {
+                        is_target[ty * width + tx] = true;
+                    }
+                }
+            }
         }
     }
+    let targets: Vec<(usize, usize)> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| is_target[y * width + x])
+        .collect();
+
+    let mut nnf: Vec<Option<(usize, usize)>> = vec![None; width * height];
+    for &(x, y) in &targets {
+        let pick = patch_match_random((y * width + x) as u64) as usize % valid_sources.len();
+        nnf[y * width + x] = Some(valid_sources[pick]);
+    }
 
-    let min_x_u32 = min_x as u32;
-    let min_y_u32 = min_y as u32;
-    let crop_w = (max_x - min_x + 1) as u32;
-    let crop_h = (max_y - min_y + 1) as u32;
+    // Each pass reads the previous field and writes the next; only targets are ever set,
+    // so the two buffers can be swapped without copying.
+    let mut next_nnf: Vec<Option<(usize, usize)>> = vec![None; width * height];
+    for iteration in 0..iterations.max(1) {
+        let previous = &nnf;
+        let step: i32 = if iteration % 2 == 0 { 1 } else { -1 };
+        let matches: Vec<((usize, usize), (usize, usize), f32)> = targets
+            .par_iter()
+            .map(|&(x, y)| {
+                let mut best = previous[y * width + x].expect("targets are initialised");
+                let mut best_distance = region.patch_distance((x, y), best, radius);
+
+                let mut consider = |candidate: (i32, i32), best: &mut (usize, usize)| {
+                    if candidate.0 < 0 || candidate.1 < 0 {
+                        return;
+                    }
+                    let candidate = (candidate.0 as usize, candidate.1 as usize);
+                    if candidate != *best && is_valid_source(candidate.0, candidate.1) {
+                        let distance = region.patch_distance((x, y), candidate, radius);
+                        if distance < best_distance {
+                            best_distance = distance;
+                            *best = candidate;
+                        }
+                    }
+                };
 
-    let mut color_image = RgbImage::new(crop_w, crop_h);
+                for (nx, ny) in [(x as i32 - step, y as i32), (x as i32, y as i32 - step)] {
+                    if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {
+                        continue;
+                    }
+                    if let Some(neighbour) = previous[ny as usize * width + nx as usize] {
+                        let shifted = (
+                            neighbour.0 as i32 + (x as i32 - nx),
+                            neighbour.1 as i32 + (y as i32 - ny),
+                        );
+                        consider(shifted, &mut best);
+                    }
+                }
 
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            let px_x = x as u32;
-            let px_y = y as u32;
-            let mask_value = mask_bitmap.get_pixel(px_x, px_y)[0];
+                let mut search_radius = width.max(height) as i32;
+                let mut seed = ((iteration as u64) << 40) ^ (y * width + x) as u64;
+                while search_radius >= 1 {
+                    seed = patch_match_random(seed);
+                    let dx = (seed % (2 * search_radius as u64 + 1)) as i32 - search_radius;
+                    let dy = ((seed >> 32) % (2 * search_radius as u64 + 1)) as i32 - search_radius;
+                    let current = best;
+                    consider((current.0 as i32 + dx, current.1 as i32 + dy), &mut best);
+                    search_radius /= 2;
+                }
 
-            let out_x = px_x - min_x_u32;
-            let out_y = px_y - min_y_u32;
+                ((x, y), best, best_distance)
+            })
+            .collect();
 
-            if mask_value > 0 {
-                let patch_pixel = final_patch.get_pixel(px_x, px_y);
-                color_image.put_pixel(
-                    out_x,
-                    out_y,
-                    Rgb([patch_pixel[0], patch_pixel[1], patch_pixel[2]]),
-                );
-            } else {
-                color_image.put_pixel(out_x, out_y, Rgb([0, 0, 0]));
-            }
+        for &((x, y), source, _) in &matches {
+            next_nnf[y * width + x] = Some(source);
         }
+        std::mem::swap(&mut nnf, &mut next_nnf);
+        vote_hole_pixels(&mut region, &nnf, &matches, radius);
     }
 
-    let output_mask =
-        image::imageops::crop_imm(&mask_bitmap, min_x_u32, min_y_u32, crop_w, crop_h).to_image();
+    write_region_back(&region, &mut output, x0, y0);
+    output
+}
 
-    let quality = 95;
-    let mut color_buf = Cursor::new(Vec::with_capacity(32768));
-    color_image
-        .write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(
-            &mut color_buf,
-            quality,
-        ))
-        .map_err(|e| e.to_string())?;
-    let color_base64 = general_purpose::STANDARD.encode(color_buf.get_ref());
+fn mask_bounds(mask: &GrayImage) -> Option<(u32, u32, u32, u32)> {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, p) in mask.enumerate_pixels() {
+        if p[0] > 0 {
+            bounds = Some(match bounds {
+                Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                None => (x, y, x, y),
+            });
+        }
+    }
+    bounds
+}
 
-    let mut mask_buf = Cursor::new(Vec::with_capacity(32768));
-    output_mask
-        .write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(
-            &mut mask_buf,
-            quality,
-        ))
-        .map_err(|e| e.to_string())?;
-    let mask_base64 = general_purpose::STANDARD.encode(mask_buf.get_ref());
+/// Onion-peel initialisation: hole pixels take the average of their known neighbours,
+/// layer by layer from the boundary inwards.
+fn fill_hole_by_diffusion(region: &mut PatchMatchRegion) {
+    let (width, height) = (region.width, region.height);
+    let mut known: Vec<bool> = region.hole.iter().map(|&h| !h).collect();
+    loop {
+        let mut layer = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                if known[y * width + x] {
+                    continue;
+                }
+                let mut sum = [0.0f32; 3];
+                let mut count = 0.0;
+                for (nx, ny) in [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ] {
+                    if nx < width && ny < height && known[ny * width + nx] {
+                        let p = region.pixels[ny * width + nx];
+                        sum[0] += p[0];
+                        sum[1] += p[1];
+                        sum[2] += p[2];
+                        count += 1.0;
+                    }
+                }
+                if count > 0.0 {
+                    layer.push((y * width + x, sum.map(|c| c / count)));
+                }
+            }
+        }
+        if layer.is_empty() {
+            break;
+        }
+        for (index, color) in layer {
+            region.pixels[index] = color;
+            known[index] = true;
+        }
+    }
+}
 
-    let result_json = serde_json::json!({
-        "color": color_base64,
-        "mask": mask_base64,
-        "offsetX": min_x_u32,
-        "offsetY": min_y_u32,
-        "width": crop_w,
-        "height": crop_h,
-        "isSrgbEncoded": is_raw
-    })
-    .to_string();
+fn vote_hole_pixels(
+    region: &mut PatchMatchRegion,
+    nnf: &[Option<(usize, usize)>],
+    matches: &[((usize, usize), (usize, usize), f32)],
+    radius: i32,
+) {
+    let (width, height) = (region.width, region.height);
+    let mean_distance =
+        matches.iter().map(|m| m.2).sum::<f32>() / matches.len().max(1) as f32 + 1e-3;
+    let mut weights = vec![0.0f32; width * height];
+    for &((x, y), _, distance) in matches {
+        weights[y * width + x] = (-distance / mean_distance).exp();
+    }
 
-    Ok(result_json)
+    let pixels = &region.pixels;
+    let hole = &region.hole;
+    let voted: Vec<(usize, [f32; 3])> = (0..width * height)
+        .into_par_iter()
+        .filter(|&i| hole[i])
+        .map(|i| {
+            let (x, y) = ((i % width) as i32, (i / width) as i32);
+            let mut sum = [0.0f32; 3];
+            let mut total = 0.0f32;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let (qx, qy) = (x + dx, y + dy);
+                    if qx < 0 || qy < 0 || qx >= width as i32 || qy >= height as i32 {
+                        continue;
+                    }
+                    let q = qy as usize * width + qx as usize;
+                    if let Some(source) = nnf[q] {
+                        let sx = (source.0 as i32 - dx) as usize;
+                        let sy = (source.1 as i32 - dy) as usize;
+                        let p = pixels[sy * width + sx];
+                        let w = weights[q];
+                        sum[0] += p[0] * w;
+                        sum[1] += p[1] * w;
+                        sum[2] += p[2] * w;
+                        total += w;
+                    }
+                }
+            }
+            let color = if total > 0.0 {
+                sum.map(|c| c / total)
+            } else {
+                pixels[i]
+            };
+            (i, color)
+        })
+        .collect();
+
+    for (i, color) in voted {
+        region.pixels[i] = color;
+    }
+}
+
+fn write_region_back(region: &PatchMatchRegion, output: &mut RgbaImage, x0: u32, y0: u32) {
+    for (i, (&is_hole, color)) in region.hole.iter().zip(&region.pixels).enumerate() {
+        if is_hole {
+            let x = x0 + (i % region.width) as u32;
+            let y = y0 + (i / region.width) as u32;
+            let alpha = output.get_pixel(x, y)[3];
+            output.put_pixel(
+                x,
+                y,
+                Rgba([
+                    color[0].round().clamp(0.0, 255.0) as u8,
+                    color[1].round().clamp(0.0, 255.0) as u8,
+                    color[2].round().clamp(0.0, 255.0) as u8,
+                    alpha,
+                ]),
+            );
+        }
+    }
 }
@@ -722,6 +722,31 @@ pub fn find_best_lens_match(
     None
 }
 
+fn normalized_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Looks up the sensor crop factor for an EXIF make/model pair in the lensfun camera list.
+pub fn find_camera_crop_factor(db: &LensDatabase, maker: &str, model: &str) -> Option<f32> {
+    let maker = normalized_name(maker.trim().trim_matches('"'));
+    let model = normalized_name(model.trim().trim_matches('"'));
+    if model.is_empty() {
+        return None;
+    }
+    let names_match = |names: &[MultiName], wanted: &str| {
+        names.iter().any(|n| normalized_name(&n.value) == wanted)
+    };
+
+    db.cameras
+        .iter()
+        .filter(|camera| camera.cropfactor > 0.0 && names_match(&camera.model, &model))
+        .max_by_key(|camera| names_match(&camera.maker, &maker))
+        .map(|camera| camera.cropfactor)
+}
+
 #[tauri::command]
 pub fn autodetect_lens(
     maker: String,
@@ -13,12 +13,18 @@ mod android_integration;
 mod app_settings;
 mod app_state;
 mod cache_utils;
+mod color_management;
+mod contact_sheet;
+mod cpu_processing;
 mod culling;
 mod denoising;
+mod dng_writer;
 mod exif_processing;
 mod export_processing;
 mod file_management;
+mod folder_watch;
 mod formats;
+mod geotagging;
 mod gpu_processing;
 mod hdr_deghosting;
 mod image_loader;
@@ -32,10 +38,16 @@ mod negative_conversion;
 mod panorama_stitching;
 mod panorama_utils;
 mod preset_converter;
+mod print_layout;
 mod raw_processing;
+mod secondary_window;
+mod session;
 mod tagging;
 mod tagging_utils;
+mod timelapse;
+mod upscaling;
 mod window_customizer;
+mod xmp_sidecar;
 
 use std::collections::{HashMap, hash_map::DefaultHasher};
 use std::fs;
@@ -44,7 +56,7 @@ use std::io::Cursor;
 use std::io::Write;
 use std::panic;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::thread;
 
@@ -57,9 +69,11 @@ use image::codecs::jpeg::JpegEncoder;
 use image::{DynamicImage, GenericImageView, ImageBuffer, ImageFormat, Luma, RgbImage, Rgba};
 use image_hdr::hdr_merge_images;
 use image_hdr::input::HDRInput;
+use imageproc::distance_transform::Norm;
 use imageproc::drawing::draw_line_segment_mut;
 use imageproc::edges::canny;
 use imageproc::hough::{LineDetectionOptions, detect_lines};
+use imageproc::morphology::dilate;
 use imgref::ImgRef;
 use mozjpeg_rs::{Encoder, Preset};
 use rgb::{FromSlice, RGBA8};
@@ -71,23 +85,30 @@ use tempfile::NamedTempFile;
 use tokio::sync::Mutex as TokioMutex;
 
 use crate::cache_utils::{
-    DecodedImageCache, GEOMETRY_KEYS, calculate_full_job_hash, calculate_geometry_hash,
+    DecodedImageCache, GEOMETRY_KEYS, LruCache, calculate_full_job_hash, calculate_geometry_hash,
     calculate_transform_hash, calculate_visual_hash,
 };
+use crate::color_management::{ColorSample, OutputColorSpace};
 use crate::file_management::{parse_virtual_path, read_file_mapped};
 use crate::formats::is_raw_file;
-use crate::hdr_deghosting::{align_hdr_frames, assert_uniform_dimensions, load_hdr_frames};
+use crate::hdr_deghosting::{
+    HDR_ALIGN_STAGE_END, HDR_MERGE_STAGE_END, HdrFrame, align_hdr_frames,
+    assert_uniform_dimensions, blend_frame_into_result, check_hdr_cancelled, load_hdr_frames,
+    match_frame_to_result,
+};
 use crate::image_loader::{composite_patches_on_image, load_and_composite};
 use crate::image_processing::{
     Crop, GeometryParams, RenderRequest, apply_coarse_rotation, apply_cpu_default_raw_processing,
     apply_flip, apply_geometry_warp, apply_linear_to_srgb, downscale_f32_image,
-    get_all_adjustments_from_json, get_or_init_gpu_context, process_and_get_dynamic_image,
-    resolve_tonemapper_override, resolve_tonemapper_override_from_handle, warp_image_geometry,
+    get_all_adjustments_from_json, get_gpu_context_or_fallback, get_or_init_gpu_context,
+    process_and_get_dynamic_image, resolve_tonemapper_override,
+    resolve_tonemapper_override_from_handle, warp_image_geometry,
 };
 use crate::mask_generation::{
     MaskDefinition, generate_mask_bitmap, get_cached_or_generate_mask,
     resolve_warped_image_for_masks,
 };
+use crate::panorama_stitching::emit_stage_progress;
 use crate::window_customizer::PinchZoomDisablePlugin;
 pub use adjustment_utils::*;
 pub use android_integration::*;
@@ -153,24 +174,8 @@ pub fn generate_transformed_preview(
     adjustments: &serde_json::Value,
     preview_dim: u32,
 ) -> Result<(DynamicImage, f32, (f32, f32)), String> {
-    let transform_hash = calculate_transform_hash(adjustments);
-
-    let (transformed_full_res, unscaled_crop_offset) = {
-        let mut cache_lock = state.full_transformed_cache.lock().unwrap();
-        if let Some((hash, img, offset)) = cache_lock.as_ref() {
-            if *hash == transform_hash {
-                (Arc::clone(img), *offset)
-            } else {
-                let (arc_img, offset) = compute_full_transformed_res(loaded_image, adjustments)?;
-                *cache_lock = Some((transform_hash, Arc::clone(&arc_img), offset));
-                (arc_img, offset)
-            }
-        } else {
-            let (arc_img, offset) = compute_full_transformed_res(loaded_image, adjustments)?;
-            *cache_lock = Some((transform_hash, Arc::clone(&arc_img), offset));
-            (arc_img, offset)
-        }
-    };
+    let (transformed_full_res, unscaled_crop_offset) =
+        get_full_transformed_res(state, loaded_image, adjustments)?;
 
     let (full_res_w, full_res_h) = transformed_full_res.dimensions();
 
@@ -189,6 +194,23 @@ pub fn generate_transformed_preview(
     Ok((final_preview_base, scale_for_gpu, unscaled_crop_offset))
 }
 
+fn get_full_transformed_res(
+    state: &tauri::State<AppState>,
+    loaded_image: &LoadedImage,
+    adjustments: &serde_json::Value,
+) -> Result<(Arc<DynamicImage>, (f32, f32)), String> {
+    let transform_hash = calculate_transform_hash(adjustments);
+    let mut cache_lock = state.full_transformed_cache.lock().unwrap();
+    if let Some((hash, img, offset)) = cache_lock.as_ref()
+        && *hash == transform_hash
+    {
+        return Ok((Arc::clone(img), *offset));
+    }
+    let (arc_img, offset) = compute_full_transformed_res(loaded_image, adjustments)?;
+    *cache_lock = Some((transform_hash, Arc::clone(&arc_img), offset));
+    Ok((arc_img, offset))
+}
+
 fn compute_full_transformed_res(
     loaded_image: &LoadedImage,
     adjustments: &serde_json::Value,
@@ -210,6 +232,41 @@ fn compute_full_transformed_res(
     Ok((Arc::new(transformed_img.into_owned()), offset))
 }
 
+/// Eyedropper white balance. `x`/`y` are normalized to the displayed (cropped and
+/// transformed) image, `radius` is in full-resolution pixels. Sampling happens before any
+/// adjustments, so the returned slider values are absolute and replace the current ones.
+#[tauri::command]
+fn compute_wb_from_point(
+    js_adjustments: serde_json::Value,
+    x: f32,
+    y: f32,
+    radius: Option<u32>,
+    state: tauri::State<AppState>,
+) -> Result<serde_json::Value, String> {
+    let loaded_image = state
+        .original_image
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("No image loaded for white balance")?;
+    let mut adjustments = js_adjustments;
+    hydrate_adjustments(&state, &mut adjustments);
+
+    let (transformed, _) = get_full_transformed_res(&state, &loaded_image, &adjustments)?;
+    let sample = image_processing::sample_linear_average(
+        &transformed,
+        x,
+        y,
+        radius.unwrap_or(4),
+        loaded_image.is_raw,
+    )
+    .ok_or("Sample point is outside the image")?;
+    let (temperature, tint) = image_processing::white_balance_to_neutralize(sample)
+        .ok_or("Sampled area is too dark or saturated to use as a neutral reference")?;
+
+    Ok(serde_json::json!({ "temperature": temperature, "tint": tint }))
+}
+
 #[tauri::command]
 fn get_image_dimensions(path: String) -> Result<ImageDimensions, String> {
     let (source_path, _) = parse_virtual_path(&path);
@@ -311,6 +368,126 @@ async fn update_wgpu_transform(
     Ok(())
 }
 
+const PREVIEW_JOB_SUPERSEDED: &str = "Preview job superseded by a newer one";
+
+/// Renders the current edit at full resolution on a single background worker and hands
+/// it to the analytics worker, so the scopes catch clipping that the downscaled preview
+/// misses. Queued jobs coalesce to the newest one, and a render that is already stale is
+/// skipped before it starts.
+fn start_full_res_scopes_worker(app_handle: tauri::AppHandle) {
+    let state = app_handle.state::<AppState>();
+    let (tx, rx): (Sender<FullResScopesJob>, Receiver<FullResScopesJob>) = mpsc::channel();
+    *state.full_res_scopes_tx.lock().unwrap() = Some(tx);
+
+    std::thread::spawn(move || {
+        while let Ok(mut job) = rx.recv() {
+            while let Ok(latest) = rx.try_recv() {
+                job = latest;
+            }
+
+            let state = app_handle.state::<AppState>();
+            let is_current =
+                || state.preview_job_generation.load(Ordering::SeqCst) == job.generation;
+            if !is_current() {
+                continue;
+            }
+
+            match render_full_res_scopes(&app_handle, &state, &job, &is_current) {
+                Ok(image) if is_current() => {
+                    let _ = job.analytics.sender.send(AnalyticsJob {
+                        path: job.analytics.path,
+                        image: Arc::new(image),
+                        compute_waveform: job.analytics.compute_waveform,
+                        active_waveform_channel: job.analytics.active_waveform_channel,
+                        histogram_region: job.analytics.histogram_region,
+                    });
+                }
+                Ok(_) => {}
+                Err(e) if e == PREVIEW_JOB_SUPERSEDED => {}
+                Err(e) => log::warn!("Full resolution scopes failed: {}", e),
+            }
+        }
+    });
+}
+
+fn render_full_res_scopes(
+    app_handle: &tauri::AppHandle,
+    state: &tauri::State<AppState>,
+    job: &FullResScopesJob,
+    is_current: &dyn Fn() -> bool,
+) -> Result<DynamicImage, String> {
+    let loaded_image = &job.loaded_image;
+    let adjustments = &job.adjustments;
+    let (transformed, unscaled_crop_offset) =
+        get_full_transformed_res(state, loaded_image, adjustments)?;
+    let (width, height) = transformed.dimensions();
+
+    let mask_definitions: Vec<MaskDefinition> = adjustments
+        .get("masks")
+        .and_then(|m| serde_json::from_value(m.clone()).ok())
+        .unwrap_or_default();
+    let mask_bitmaps: Vec<ImageBuffer<Luma<u8>, Vec<u8>>> = mask_definitions
+        .iter()
+        .filter_map(|def| {
+            get_cached_or_generate_mask(
+                state,
+                def,
+                width,
+                height,
+                1.0,
+                unscaled_crop_offset,
+                adjustments,
+            )
+        })
+        .collect();
+    if !is_current() {
+        return Err(PREVIEW_JOB_SUPERSEDED.to_string());
+    }
+
+    let is_raw = loaded_image.is_raw;
+    let tm_override = resolve_tonemapper_override_from_handle(app_handle, is_raw);
+    let mut all_adjustments = get_all_adjustments_from_json(adjustments, is_raw, tm_override);
+    all_adjustments.global.show_clipping = 0;
+    all_adjustments
+        .global
+        .set_grain_frame(1.0, unscaled_crop_offset);
+    let lut = lut_processing::resolve_lut_for_adjustments(state, adjustments);
+
+    let context = get_gpu_context_or_fallback(state, app_handle);
+    image_processing::process_and_get_dynamic_image(
+        context.as_ref(),
+        state,
+        &transformed,
+        calculate_full_job_hash(&loaded_image.path, adjustments),
+        RenderRequest {
+            adjustments: all_adjustments,
+            mask_bitmaps: &mask_bitmaps,
+            lut,
+            roi: None,
+        },
+        "full_res_scopes",
+    )
+}
+
+/// Both sides come from one factor so the small preview keeps the aspect ratio the masks
+/// are generated with.
+fn interactive_preview_size((w, h): (u32, u32), target_size: u32) -> (u32, u32) {
+    let factor = target_size as f32 / w.max(h).max(1) as f32;
+    let small_w = ((w as f32 * factor).round() as u32).max(1);
+    let small_h = ((h as f32 * factor).round() as u32).max(1);
+    (small_w, small_h)
+}
+
+/// Masks take a single scale, so split the rounding error of the two axes instead of
+/// letting the height absorb all of it.
+fn interactive_mask_scale((orig_w, orig_h): (u32, u32), (small_w, small_h): (u32, u32)) -> f32 {
+    if orig_w > 0 && orig_h > 0 {
+        (small_w as f32 / orig_w as f32 + small_h as f32 / orig_h as f32) / 2.0
+    } else {
+        1.0
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn process_preview_job(
     app_handle: &tauri::AppHandle,
@@ -321,9 +498,11 @@ fn process_preview_job(
     roi: Option<(f32, f32, f32, f32)>,
     compute_waveform: bool,
     active_waveform_channel: Option<&str>,
+    histogram_region: Option<(f32, f32, f32, f32)>,
+    generation: usize,
 ) -> Result<Vec<u8>, String> {
     let fn_start = std::time::Instant::now();
-    let context = get_or_init_gpu_context(&state, app_handle)?;
+    let context = get_gpu_context_or_fallback(&state, app_handle);
     hydrate_adjustments(&state, &mut adjustments_json);
     let adjustments_clone = adjustments_json;
 
@@ -341,7 +520,7 @@ fn process_preview_job(
     let default_preview_dim = settings.editor_preview_resolution.unwrap_or(1920);
     let preview_dim = target_resolution.unwrap_or(default_preview_dim);
     #[cfg(not(any(target_os = "linux", target_os = "android")))]
-    let use_wgpu_renderer = settings.use_wgpu_renderer.unwrap_or(true);
+    let use_wgpu_renderer = settings.use_wgpu_renderer.unwrap_or(true) && context.is_some();
     #[cfg(any(target_os = "linux", target_os = "android"))]
     let use_wgpu_renderer = false;
 
@@ -382,14 +561,8 @@ fn process_preview_job(
     } else {
         let small = if interactive_divisor > 1.0 {
             let target_size = (preview_dim as f32 / interactive_divisor) as u32;
-            let (w, h) = final_preview_base.dimensions();
-            let (small_w, small_h) = if w > h {
-                let ratio = h as f32 / w as f32;
-                (target_size, (target_size as f32 * ratio) as u32)
-            } else {
-                let ratio = w as f32 / h as f32;
-                ((target_size as f32 * ratio) as u32, target_size)
-            };
+            let (small_w, small_h) =
+                interactive_preview_size(final_preview_base.dimensions(), target_size);
             Arc::new(image_processing::downscale_f32_image(
                 &final_preview_base,
                 small_w,
@@ -419,9 +592,10 @@ fn process_preview_job(
     drop(cached_preview_lock);
 
     let (processing_image, effective_scale, jpeg_quality) = if is_interactive {
-        let orig_w = final_preview_base.width() as f32;
-        let small_w = small_preview_base.width() as f32;
-        let scale_factor = if orig_w > 0.0 { small_w / orig_w } else { 1.0 };
+        let scale_factor = interactive_mask_scale(
+            final_preview_base.dimensions(),
+            small_preview_base.dimensions(),
+        );
         let new_scale = scale_for_gpu * scale_factor;
         (small_preview_base, new_scale, interactive_quality)
     } else {
@@ -466,11 +640,23 @@ fn process_preview_job(
         })
         .collect();
 
+    // A newer job was queued while this one was transforming, so its frame would be
+    // stale before it reached the screen. Skip the GPU pass and let the worker move on.
+    if state.preview_job_generation.load(Ordering::SeqCst) != generation {
+        return Err(PREVIEW_JOB_SUPERSEDED.to_string());
+    }
+
     let is_raw = loaded_image.is_raw;
     let tm_override = resolve_tonemapper_override_from_handle(app_handle, is_raw);
-    let final_adjustments = get_all_adjustments_from_json(&adjustments_clone, is_raw, tm_override);
-    let lut_path = adjustments_clone["lutPath"].as_str();
-    let lut = lut_path.and_then(|p| lut_processing::get_or_load_lut(&state, p).ok());
+    let mut final_adjustments =
+        get_all_adjustments_from_json(&adjustments_clone, is_raw, tm_override);
+    final_adjustments
+        .global
+        .set_grain_frame(effective_scale, unscaled_crop_offset);
+    final_adjustments
+        .global
+        .set_clipping_overlay(&settings.clipping_overlay);
+    let lut = lut_processing::resolve_lut_for_adjustments(&state, &adjustments_clone);
 
     let wants_analytics = !(is_interactive && pixel_roi.is_some());
     let channel_filter = if is_interactive {
@@ -489,15 +675,35 @@ fn process_preview_job(
                 path: loaded_image.path.clone(),
                 compute_waveform,
                 active_waveform_channel: channel_filter,
+                histogram_region,
                 sender: tx,
             })
     } else {
         None
     };
 
+    if !is_interactive
+        && settings.full_res_scopes.unwrap_or(false)
+        && let Some(analytics) = &analytics_config
+        && let Some(scopes_tx) = state.full_res_scopes_tx.lock().unwrap().as_ref()
+    {
+        let _ = scopes_tx.send(FullResScopesJob {
+            loaded_image: loaded_image.clone(),
+            adjustments: adjustments_clone.clone(),
+            analytics: AnalyticsConfig {
+                path: analytics.path.clone(),
+                compute_waveform: analytics.compute_waveform,
+                active_waveform_channel: analytics.active_waveform_channel.clone(),
+                histogram_region: analytics.histogram_region,
+                sender: analytics.sender.clone(),
+            },
+            generation,
+        });
+    }
+
     let final_processed_image_result =
         crate::image_processing::process_and_get_dynamic_image_with_analytics(
-            &context,
+            context.as_ref(),
             &state,
             &processing_image,
             new_transform_hash,
@@ -513,7 +719,7 @@ fn process_preview_job(
         );
 
     if let Ok(final_processed_image) = final_processed_image_result {
-        if use_wgpu_renderer {
+        if use_wgpu_renderer && let Some(context) = &context {
             let _ = context.device.poll(wgpu::PollType::Wait {
                 submission_index: None,
                 timeout: Some(std::time::Duration::from_millis(500)),
@@ -609,7 +815,8 @@ fn start_analytics_worker(app_handle: tauri::AppHandle) {
                 job = latest;
             }
 
-            if let Ok(histogram_data) = image_processing::calculate_histogram_from_image(&job.image)
+            if let Ok(histogram_data) =
+                image_processing::calculate_histogram_from_image(&job.image, job.histogram_region)
             {
                 let _ = app_handle.emit(
                     "histogram-update",
@@ -644,6 +851,10 @@ fn start_preview_worker(app_handle: tauri::AppHandle) {
                 job = latest_job;
             }
 
+            if !job.is_interactive {
+                secondary_window::mirror_preview(&app_handle, &job.adjustments);
+            }
+
             let state = app_handle.state::<AppState>();
             let responder = job.responder;
             match process_preview_job(
@@ -655,10 +866,15 @@ fn start_preview_worker(app_handle: tauri::AppHandle) {
                 job.roi,
                 job.compute_waveform,
                 job.active_waveform_channel.as_deref(),
+                job.histogram_region,
+                job.generation,
             ) {
                 Ok(bytes) => {
                     let _ = responder.send(bytes);
                 }
+                Err(e) if e == PREVIEW_JOB_SUPERSEDED => {
+                    log::debug!("Skipped stale preview job {}", job.generation);
+                }
                 Err(e) => {
                     log::error!("Preview worker error: {}", e);
                 }
@@ -675,6 +891,7 @@ async fn apply_adjustments(
     roi: Option<(f32, f32, f32, f32)>,
     compute_waveform: bool,
     active_waveform_channel: Option<String>,
+    histogram_region: Option<(f32, f32, f32, f32)>,
     state: tauri::State<'_, AppState>,
 ) -> Result<Response, String> {
     let (tx, rx) = tokio::sync::oneshot::channel();
@@ -682,6 +899,7 @@ async fn apply_adjustments(
     {
         let tx_guard = state.preview_worker_tx.lock().unwrap();
         if let Some(worker_tx) = &*tx_guard {
+            let generation = state.preview_job_generation.fetch_add(1, Ordering::SeqCst) + 1;
             let job = PreviewJob {
                 adjustments: js_adjustments,
                 is_interactive,
@@ -689,6 +907,8 @@ async fn apply_adjustments(
                 roi,
                 compute_waveform,
                 active_waveform_channel,
+                histogram_region,
+                generation,
                 responder: tx,
             };
             worker_tx
@@ -711,7 +931,7 @@ fn generate_uncropped_preview(
     state: tauri::State<AppState>,
     app_handle: tauri::AppHandle,
 ) -> Result<(), String> {
-    let context = get_or_init_gpu_context(&state, &app_handle)?;
+    let context = get_gpu_context_or_fallback(&state, &app_handle);
     let mut adjustments_clone = js_adjustments.clone();
     hydrate_adjustments(&state, &mut adjustments_clone);
 
@@ -800,11 +1020,10 @@ fn generate_uncropped_preview(
         let tm_override = resolve_tonemapper_override_from_handle(&app_handle, is_raw);
         let uncropped_adjustments =
             get_all_adjustments_from_json(&adjustments_clone, is_raw, tm_override);
-        let lut_path = adjustments_clone["lutPath"].as_str();
-        let lut = lut_path.and_then(|p| lut_processing::get_or_load_lut(&state, p).ok());
+        let lut = lut_processing::resolve_lut_for_adjustments(&state, &adjustments_clone);
 
         if let Ok(processed_image) = process_and_get_dynamic_image(
-            &context,
+            context.as_ref(),
             &state,
             &processing_base,
             unique_hash,
@@ -912,7 +1131,7 @@ async fn preview_geometry_transform(
         if let Some(cached_image) = maybe_cached_image {
             cached_image
         } else {
-            let context = get_or_init_gpu_context(&state, &app_handle)?;
+            let context = get_gpu_context_or_fallback(&state, &app_handle);
 
             let original_image = {
                 let guard = state.original_image.lock().unwrap();
@@ -964,12 +1183,11 @@ async fn preview_geometry_transform(
             let tm_override = resolve_tonemapper_override_from_handle(&app_handle, is_raw);
             let all_adjustments =
                 get_all_adjustments_from_json(&temp_adjustments, is_raw, tm_override);
-            let lut_path = temp_adjustments["lutPath"].as_str();
-            let lut = lut_path.and_then(|p| lut_processing::get_or_load_lut(&state, p).ok());
+            let lut = lut_processing::resolve_lut_for_adjustments(&state, &temp_adjustments);
             let mask_bitmaps = Vec::new();
 
             let processed_base = process_and_get_dynamic_image(
-                &context,
+                context.as_ref(),
                 &state,
                 &preview_base,
                 visual_hash,
@@ -982,25 +1200,23 @@ async fn preview_geometry_transform(
                 "preview_geometry_transform_base_gen",
             )?;
 
-            let mut cache = state.geometry_cache.lock().unwrap();
-            if cache.len() > 5 {
-                cache.clear();
-            }
-            cache.insert(visual_hash, processed_base.clone());
+            state
+                .geometry_cache
+                .lock()
+                .unwrap()
+                .insert(visual_hash, processed_base.clone());
 
             processed_base
         }
     };
 
     let final_image = tokio::task::spawn_blocking(move || -> DynamicImage {
-        let mut adjusted_params = params;
-
-        if is_raw {
-            // approximate linear vignetting correction on gamma-baked & tonemapped geometry preview
-            adjusted_params.lens_vignette_amount *= 0.4;
-        } else {
-            adjusted_params.lens_vignette_amount *= 0.8;
-        }
+        // Raw exports correct vignetting on linear sensor data, but this preview is warped
+        // after processing. Non-raw exports warp the encoded pixels, same as here.
+        let adjusted_params = GeometryParams {
+            vignette_on_display_referred: is_raw,
+            ..params
+        };
 
         let warped_image = warp_image_geometry(&base_image_to_warp, adjusted_params);
         let orientation_steps = js_adjustments["orientationSteps"].as_u64().unwrap_or(0) as u8;
@@ -1096,27 +1312,26 @@ pub fn get_original_image(
     ))
 }
 
-#[tauri::command]
-fn generate_preset_preview(
-    js_adjustments: serde_json::Value,
-    state: tauri::State<AppState>,
-    app_handle: tauri::AppHandle,
-) -> Result<Response, String> {
-    let context = get_or_init_gpu_context(&state, &app_handle)?;
+pub fn render_adjusted_preview(
+    js_adjustments: &serde_json::Value,
+    target_dim: u32,
+    debug_tag: &str,
+    state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
+) -> Result<DynamicImage, String> {
+    let context = get_gpu_context_or_fallback(state, app_handle);
 
     let loaded_image = state
         .original_image
         .lock()
         .unwrap()
         .clone()
-        .ok_or("No original image loaded for preset preview")?;
+        .ok_or("No original image loaded for preview")?;
     let is_raw = loaded_image.is_raw;
-    let unique_hash = calculate_full_job_hash(&loaded_image.path, &js_adjustments);
-
-    const PRESET_PREVIEW_DIM: u32 = 400;
+    let unique_hash = calculate_full_job_hash(&loaded_image.path, js_adjustments);
 
     let (preview_image, scale_for_gpu, unscaled_crop_offset) =
-        generate_transformed_preview(&state, &loaded_image, &js_adjustments, PRESET_PREVIEW_DIM)?;
+        generate_transformed_preview(state, &loaded_image, js_adjustments, target_dim)?;
 
     let (img_w, img_h) = preview_image.dimensions();
 
@@ -1134,25 +1349,27 @@ fn generate_preset_preview(
         .iter()
         .filter_map(|def| {
             get_cached_or_generate_mask(
-                &state,
+                state,
                 def,
                 img_w,
                 img_h,
                 scale_for_gpu,
                 scaled_crop_offset,
-                &js_adjustments,
+                js_adjustments,
             )
         })
         .collect();
 
-    let tm_override = resolve_tonemapper_override_from_handle(&app_handle, is_raw);
-    let all_adjustments = get_all_adjustments_from_json(&js_adjustments, is_raw, tm_override);
-    let lut_path = js_adjustments["lutPath"].as_str();
-    let lut = lut_path.and_then(|p| lut_processing::get_or_load_lut(&state, p).ok());
-
-    let processed_image = process_and_get_dynamic_image(
-        &context,
-        &state,
+    let tm_override = resolve_tonemapper_override_from_handle(app_handle, is_raw);
+    let mut all_adjustments = get_all_adjustments_from_json(js_adjustments, is_raw, tm_override);
+    all_adjustments
+        .global
+        .set_grain_frame(scale_for_gpu, unscaled_crop_offset);
+    let lut = lut_processing::resolve_lut_for_adjustments(state, js_adjustments);
+
+    process_and_get_dynamic_image(
+        context.as_ref(),
+        state,
         &preview_image,
         unique_hash,
         RenderRequest {
@@ -1161,7 +1378,24 @@ fn generate_preset_preview(
             lut,
             roi: None,
         },
+        debug_tag,
+    )
+}
+
+#[tauri::command]
+fn generate_preset_preview(
+    js_adjustments: serde_json::Value,
+    state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Response, String> {
+    const PRESET_PREVIEW_DIM: u32 = 400;
+
+    let processed_image = render_adjusted_preview(
+        &js_adjustments,
+        PRESET_PREVIEW_DIM,
         "generate_preset_preview",
+        &state,
+        &app_handle,
     )?;
 
     let mut buf = Cursor::new(Vec::new());
@@ -1173,6 +1407,267 @@ fn generate_preset_preview(
     Ok(Response::new(buf.into_inner()))
 }
 
+const FULLSCREEN_PREVIEW_DIM: u32 = 3840;
+
+pub fn render_fullscreen_preview(
+    js_adjustments: &serde_json::Value,
+    state: &tauri::State<AppState>,
+    app_handle: &tauri::AppHandle,
+) -> Result<Vec<u8>, String> {
+    let mut js_adjustments = js_adjustments.clone();
+    hydrate_adjustments(state, &mut js_adjustments);
+
+    let processed_image = render_adjusted_preview(
+        &js_adjustments,
+        FULLSCREEN_PREVIEW_DIM,
+        "generate_fullscreen_preview",
+        state,
+        app_handle,
+    )?;
+
+    let mut buf = Cursor::new(Vec::new());
+    processed_image
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut buf, 90))
+        .map_err(|e| e.to_string())?;
+    Ok(buf.into_inner())
+}
+
+#[tauri::command]
+fn generate_fullscreen_preview(
+    js_adjustments: serde_json::Value,
+    state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Response, String> {
+    render_fullscreen_preview(&js_adjustments, &state, &app_handle).map(Response::new)
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ComparePreviews {
+    a: String,
+    b: String,
+}
+
+/// Copies the crop and geometry of `source` onto `target`, so both sides of a comparison
+/// frame the image identically.
+fn copy_geometry_adjustments(source: &serde_json::Value, target: &mut serde_json::Value) {
+    let Some(target_map) = target.as_object_mut() else {
+        return;
+    };
+    let frame_keys = [
+        "crop",
+        "aspectRatio",
+        "rotation",
+        "orientationSteps",
+        "flipHorizontal",
+        "flipVertical",
+    ];
+    for key in GEOMETRY_KEYS.iter().chain(frame_keys.iter()) {
+        match source.get(*key) {
+            Some(value) => {
+                target_map.insert(key.to_string(), value.clone());
+            }
+            None => {
+                target_map.remove(*key);
+            }
+        }
+    }
+}
+
+/// Renders the loaded image with two sets of adjustments at preview resolution. Both use
+/// the crop and geometry of `adjustments_a`, the current edit, and are returned together
+/// so the frontend can show them side by side.
+#[tauri::command]
+fn compare_adjustments(
+    adjustments_a: serde_json::Value,
+    adjustments_b: serde_json::Value,
+    state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<ComparePreviews, String> {
+    let mut adjustments_a = adjustments_a;
+    let mut adjustments_b = adjustments_b;
+    copy_geometry_adjustments(&adjustments_a, &mut adjustments_b);
+    hydrate_adjustments(&state, &mut adjustments_a);
+    hydrate_adjustments(&state, &mut adjustments_b);
+
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let preview_dim = settings.editor_preview_resolution.unwrap_or(1920);
+
+    let encode = |adjustments: &serde_json::Value, debug_tag: &str| -> Result<String, String> {
+        let processed_image =
+            render_adjusted_preview(adjustments, preview_dim, debug_tag, &state, &app_handle)?;
+        let mut buf = Cursor::new(Vec::new());
+        processed_image
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut buf, 90))
+            .map_err(|e| e.to_string())?;
+        let base64_str = general_purpose::STANDARD.encode(buf.into_inner());
+        Ok(format!("data:image/jpeg;base64,{}", base64_str))
+    };
+
+    Ok(ComparePreviews {
+        a: encode(&adjustments_a, "compare_adjustments_a")?,
+        b: encode(&adjustments_b, "compare_adjustments_b")?,
+    })
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SoftProofPreview {
+    image: String,
+    out_of_gamut_fraction: f32,
+}
+
+#[tauri::command]
+fn soft_proof_preview(
+    js_adjustments: serde_json::Value,
+    icc_profile_path: String,
+    gamut_warning: Option<bool>,
+    state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<SoftProofPreview, String> {
+    const SOFT_PROOF_PREVIEW_DIM: u32 = 1920;
+
+    let profile = color_management::load_icc_profile(&icc_profile_path)?;
+    let processed_image = render_adjusted_preview(
+        &js_adjustments,
+        SOFT_PROOF_PREVIEW_DIM,
+        "soft_proof_preview",
+        &state,
+        &app_handle,
+    )?;
+
+    let (proofed, out_of_gamut) =
+        color_management::soft_proof(&processed_image, &profile, gamut_warning.unwrap_or(true))?;
+    log::info!(
+        "Soft proof against '{}': {:.2}% of pixels out of gamut",
+        icc_profile_path,
+        out_of_gamut * 100.0
+    );
+
+    let mut buf = Cursor::new(Vec::new());
+    proofed
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut buf, 90))
+        .map_err(|e| e.to_string())?;
+    let base64_str = general_purpose::STANDARD.encode(buf.into_inner());
+
+    Ok(SoftProofPreview {
+        image: format!("data:image/jpeg;base64,{}", base64_str),
+        out_of_gamut_fraction: out_of_gamut,
+    })
+}
+
+const DEFAULT_FOCUS_PEAKING_THRESHOLD: f32 = 60.0;
+
+/// Returns a transparent PNG (as a data URL) with in-focus edges of the processed preview
+/// painted in `color`. A higher `threshold` keeps only the sharpest edges.
+#[tauri::command]
+fn generate_focus_peaking(
+    js_adjustments: serde_json::Value,
+    threshold: Option<f32>,
+    color: Option<String>,
+    state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let preview_dim = settings.editor_preview_resolution.unwrap_or(1920);
+
+    let mut adjustments = js_adjustments;
+    hydrate_adjustments(&state, &mut adjustments);
+    let processed_image = render_adjusted_preview(
+        &adjustments,
+        preview_dim,
+        "generate_focus_peaking",
+        &state,
+        &app_handle,
+    )?;
+
+    let high_threshold = threshold
+        .unwrap_or(DEFAULT_FOCUS_PEAKING_THRESHOLD)
+        .clamp(1.0, 1000.0);
+    let edges = canny(
+        &processed_image.to_luma8(),
+        high_threshold * 0.5,
+        high_threshold,
+    );
+    // Canny edges are a single pixel wide, which is too thin to see on a zoomed-out canvas.
+    let edges = dilate(&edges, Norm::LInf, 1);
+
+    let peak_color = color
+        .as_deref()
+        .map(|c| c.trim_start_matches('#'))
+        .filter(|hex| hex.len() == 6)
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .map(|value| Rgba([(value >> 16) as u8, (value >> 8) as u8, value as u8, 255]))
+        .unwrap_or(Rgba([255, 0, 0, 255]));
+
+    let overlay = ImageBuffer::from_fn(edges.width(), edges.height(), |x, y| {
+        if edges.get_pixel(x, y)[0] > 0 {
+            peak_color
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    });
+
+    let mut buf = Cursor::new(Vec::new());
+    DynamicImage::ImageRgba8(overlay)
+        .write_to(&mut buf, ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+
+    let base64_str = general_purpose::STANDARD.encode(buf.get_ref());
+    Ok(format!("data:image/png;base64,{}", base64_str))
+}
+
+/// Color picker readout. `x`/`y` are normalized to the displayed (cropped and transformed)
+/// image and `radius` is in full-resolution pixels, like `compute_wb_from_point`, but the
+/// sample is taken from the rendered preview so it reflects the current adjustments.
+#[tauri::command]
+fn sample_color(
+    js_adjustments: serde_json::Value,
+    x: f32,
+    y: f32,
+    radius: Option<u32>,
+    space: Option<OutputColorSpace>,
+    state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<ColorSample, String> {
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let preview_dim = settings.editor_preview_resolution.unwrap_or(1920);
+
+    let mut adjustments = js_adjustments;
+    hydrate_adjustments(&state, &mut adjustments);
+    let processed_image = render_adjusted_preview(
+        &adjustments,
+        preview_dim,
+        "sample_color",
+        &state,
+        &app_handle,
+    )?;
+
+    let loaded_image = state
+        .original_image
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("No image loaded for color sampling")?;
+    let (transformed, _) = get_full_transformed_res(&state, &loaded_image, &adjustments)?;
+    let effective_scale = if transformed.width() > 0 {
+        processed_image.width() as f32 / transformed.width() as f32
+    } else {
+        1.0
+    };
+    let preview_radius = (radius.unwrap_or(2) as f32 * effective_scale).round() as u32;
+
+    color_management::sample_processed_color(
+        &processed_image,
+        x,
+        y,
+        preview_radius,
+        space.unwrap_or_default(),
+    )
+}
+
 #[tauri::command]
 async fn fetch_community_presets() -> Result<Vec<CommunityPreset>, String> {
     let client = reqwest::Client::new();
@@ -1204,7 +1699,7 @@ async fn generate_all_community_previews(
     state: tauri::State<'_, AppState>,
     app_handle: tauri::AppHandle,
 ) -> Result<HashMap<String, Vec<u8>>, String> {
-    let context = get_or_init_gpu_context(&state, &app_handle)?;
+    let context = get_gpu_context_or_fallback(&state, &app_handle);
     let mut results: HashMap<String, Vec<u8>> = HashMap::new();
 
     const TILE_DIM: u32 = 360;
@@ -1296,13 +1791,12 @@ async fn generate_all_community_previews(
             let tm_override = resolve_tonemapper_override_from_handle(&app_handle, *is_raw);
             let all_adjustments =
                 get_all_adjustments_from_json(&scaled_adjustments, *is_raw, tm_override);
-            let lut_path = js_adjustments["lutPath"].as_str();
-            let lut = lut_path.and_then(|p| lut_processing::get_or_load_lut(&state, p).ok());
+            let lut = lut_processing::resolve_lut_for_adjustments(&state, &js_adjustments);
 
             let unique_hash = preset_hash.wrapping_add(i as u64);
 
             let processed_image_dynamic = crate::image_processing::process_and_get_dynamic_image(
-                &context,
+                context.as_ref(),
                 &state,
                 transformed_image.as_ref(),
                 unique_hash,
@@ -1392,26 +1886,24 @@ async fn merge_hdr(
     }
 
     let hdr_result_handle = state.hdr_result.clone();
-    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let settings =
+        image_loader::upright_merge_settings(load_settings(app_handle.clone()).unwrap_or_default());
 
-    let mut frames = load_hdr_frames(&paths, &app_handle, &settings)?;
-    assert_uniform_dimensions(&frames)?;
-    align_hdr_frames(&mut frames, &app_handle);
-
-    let images: Vec<HDRInput> = frames
-        .iter()
-        .map(|(path, img, exposure, gains)| {
-            HDRInput::with_image(img, *exposure, *gains)
-                .map_err(|e| format!("Failed to prepare HDR input for {}: {}", path, e))
-        })
-        .collect::<Result<Vec<HDRInput>, String>>()?;
+    let cancel_token = state.hdr_cancellation_token.clone();
+    cancel_token.store(false, Ordering::SeqCst);
 
-    log::info!("Starting HDR merge of {} images", images.len());
-    let mut hdr_merged = hdr_merge_images(&mut images.into()).map_err(|e| e.to_string())?;
-    hdr_merged =
-        image_hdr::stretch::apply_histogram_stretch(&hdr_merged).map_err(|e| e.to_string())?;
-    hdr_merged = apply_linear_to_srgb(hdr_merged);
-    log::info!("HDR merge completed");
+    let (hdr_merged, frames) = match merge_hdr_frames(&paths, &settings, &cancel_token, &app_handle)
+    {
+        Ok(merged) => merged,
+        Err(e) => {
+            if cancel_token.load(Ordering::SeqCst) {
+                *hdr_result_handle.lock().unwrap() = None;
+                *state.hdr_frames.lock().unwrap() = None;
+                let _ = app_handle.emit("hdr-error", &e);
+            }
+            return Err(e);
+        }
+    };
 
     let mut buf = Cursor::new(Vec::new());
     if let Err(e) = hdr_merged.to_rgb8().write_to(&mut buf, ImageFormat::Png) {
@@ -1421,9 +1913,16 @@ async fn merge_hdr(
     let base64_str = general_purpose::STANDARD.encode(buf.get_ref());
     let final_base64 = format!("data:image/png;base64,{}", base64_str);
 
-    let _ = app_handle.emit("hdr-progress", "Creating preview...");
+    emit_stage_progress(
+        &app_handle,
+        "hdr-progress",
+        "preview",
+        HDR_MERGE_STAGE_END,
+        "Creating preview...",
+    );
 
     *hdr_result_handle.lock().unwrap() = Some(hdr_merged);
+    *state.hdr_frames.lock().unwrap() = Some(frames);
 
     let _ = app_handle.emit(
         "hdr-complete",
@@ -1434,6 +1933,98 @@ async fn merge_hdr(
     Ok(())
 }
 
+fn merge_hdr_frames(
+    paths: &[String],
+    settings: &AppSettings,
+    cancel_token: &AtomicBool,
+    app_handle: &tauri::AppHandle,
+) -> Result<(DynamicImage, Vec<HdrFrame>), String> {
+    let mut frames = load_hdr_frames(paths, app_handle, settings, cancel_token)?;
+    assert_uniform_dimensions(&frames)?;
+    align_hdr_frames(&mut frames, app_handle, cancel_token);
+    check_hdr_cancelled(cancel_token)?;
+
+    emit_stage_progress(
+        app_handle,
+        "hdr-progress",
+        "merging",
+        HDR_ALIGN_STAGE_END,
+        "Merging exposures...",
+    );
+    let images: Vec<HDRInput> = frames
+        .iter()
+        .map(|(path, img, exposure, gains)| {
+            HDRInput::with_image(img, *exposure, *gains)
+                .map_err(|e| format!("Failed to prepare HDR input for {}: {}", path, e))
+        })
+        .collect::<Result<Vec<HDRInput>, String>>()?;
+
+    log::info!("Starting HDR merge of {} images", images.len());
+    let mut hdr_merged = hdr_merge_images(&mut images.into()).map_err(|e| e.to_string())?;
+    check_hdr_cancelled(cancel_token)?;
+    hdr_merged =
+        image_hdr::stretch::apply_histogram_stretch(&hdr_merged).map_err(|e| e.to_string())?;
+    hdr_merged = apply_linear_to_srgb(hdr_merged);
+    log::info!("HDR merge completed");
+    check_hdr_cancelled(cancel_token)?;
+
+    Ok((hdr_merged, frames))
+}
+
+#[tauri::command]
+fn cancel_hdr(state: tauri::State<AppState>) -> Result<(), String> {
+    state.hdr_cancellation_token.store(true, Ordering::SeqCst);
+    println!("HDR merge cancellation requested.");
+    Ok(())
+}
+
+#[tauri::command]
+async fn refine_hdr_blend(
+    region_mask_base64: String,
+    source_index: usize,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let hdr_frames = state.hdr_frames.clone();
+    let hdr_result = state.hdr_result.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let mask = crate::mask_generation::decode_base64_mask(&region_mask_base64)
+            .ok_or_else(|| "Failed to decode region mask.".to_string())?;
+
+        let mut result_guard = hdr_result.lock().unwrap();
+        let merged = result_guard
+            .as_ref()
+            .ok_or_else(|| "No HDR result in memory to refine.".to_string())?;
+        let source = {
+            let frames_guard = hdr_frames.lock().unwrap();
+            let frames = frames_guard.as_ref().ok_or_else(|| {
+                "No HDR source frames in memory. Merge the images first.".to_string()
+            })?;
+            let (_, image, _, _) = frames.get(source_index).ok_or_else(|| {
+                format!(
+                    "Source index {} is out of range for {} frames.",
+                    source_index,
+                    frames.len()
+                )
+            })?;
+            match_frame_to_result(image, merged)?
+        };
+        let refined = blend_frame_into_result(merged, &source, &mask)?;
+
+        let mut buf = Cursor::new(Vec::new());
+        refined
+            .to_rgb8()
+            .write_to(&mut buf, ImageFormat::Png)
+            .map_err(|e| format!("Failed to encode hdr preview: {}", e))?;
+        *result_guard = Some(refined);
+
+        let base64_str = general_purpose::STANDARD.encode(buf.get_ref());
+        Ok(format!("data:image/png;base64,{}", base64_str))
+    })
+    .await
+    .map_err(|e| format!("Task execution failed: {}", e))?
+}
+
 #[tauri::command]
 async fn save_hdr(
     first_path_str: String,
@@ -1442,6 +2033,7 @@ async fn save_hdr(
     let hdr_image = state.hdr_result.lock().unwrap().take().ok_or_else(|| {
         "No hdr image found in memory to save. It might have already been saved.".to_string()
     })?;
+    *state.hdr_frames.lock().unwrap() = None;
 
     let (first_path, _) = parse_virtual_path(&first_path_str);
     let parent_dir = first_path
@@ -1510,6 +2102,43 @@ async fn save_collage(base64_data: String, first_path_str: String) -> Result<Str
     Ok(output_path.to_string_lossy().to_string())
 }
 
+fn load_composite_for_path(
+    source_path: &std::path::Path,
+    source_path_str: &str,
+    js_adjustments: &Value,
+    use_fast_raw_dev: bool,
+    settings: &AppSettings,
+) -> Result<DynamicImage, String> {
+    match read_file_mapped(source_path) {
+        Ok(mmap) => load_and_composite(
+            &mmap,
+            source_path_str,
+            js_adjustments,
+            use_fast_raw_dev,
+            settings,
+            None,
+        )
+        .map_err(|e| e.to_string()),
+        Err(e) => {
+            log::warn!(
+                "Failed to memory-map file '{}': {}. Falling back to standard read.",
+                source_path_str,
+                e
+            );
+            let bytes = fs::read(source_path).map_err(|io_err| io_err.to_string())?;
+            load_and_composite(
+                &bytes,
+                source_path_str,
+                js_adjustments,
+                use_fast_raw_dev,
+                settings,
+                None,
+            )
+            .map_err(|e| e.to_string())
+        }
+    }
+}
+
 #[tauri::command]
 async fn generate_preview_for_path(
     path: String,
@@ -1518,40 +2147,19 @@ async fn generate_preview_for_path(
 ) -> Result<Response, String> {
     tokio::task::spawn_blocking(move || {
         let state = app_handle.state::<AppState>();
-        let context = get_or_init_gpu_context(&state, &app_handle)?;
+        let context = get_gpu_context_or_fallback(&state, &app_handle);
         let (source_path, _) = parse_virtual_path(&path);
         let source_path_str = source_path.to_string_lossy().to_string();
         let is_raw = is_raw_file(&source_path_str);
         let settings = load_settings(app_handle.clone()).unwrap_or_default();
 
-        let base_image = match read_file_mapped(&source_path) {
-            Ok(mmap) => load_and_composite(
-                &mmap,
-                &source_path_str,
-                &js_adjustments,
-                false,
-                &settings,
-                None,
-            )
-            .map_err(|e| e.to_string())?,
-            Err(e) => {
-                log::warn!(
-                    "Failed to memory-map file '{}': {}. Falling back to standard read.",
-                    source_path_str,
-                    e
-                );
-                let bytes = fs::read(&source_path).map_err(|io_err| io_err.to_string())?;
-                load_and_composite(
-                    &bytes,
-                    &source_path_str,
-                    &js_adjustments,
-                    false,
-                    &settings,
-                    None,
-                )
-                .map_err(|e| e.to_string())?
-            }
-        };
+        let base_image = load_composite_for_path(
+            &source_path,
+            &source_path_str,
+            &js_adjustments,
+            false,
+            &settings,
+        )?;
 
         let (transformed_image, unscaled_crop_offset) =
             apply_all_transformations(Cow::Borrowed(&base_image), &js_adjustments);
@@ -1579,12 +2187,11 @@ async fn generate_preview_for_path(
 
         let tm_override = resolve_tonemapper_override(&settings, is_raw);
         let all_adjustments = get_all_adjustments_from_json(&js_adjustments, is_raw, tm_override);
-        let lut_path = js_adjustments["lutPath"].as_str();
-        let lut = lut_path.and_then(|p| lut_processing::get_or_load_lut(&state, p).ok());
+        let lut = lut_processing::resolve_lut_for_adjustments(&state, &js_adjustments);
         let unique_hash = calculate_full_job_hash(&source_path_str, &js_adjustments);
 
         let final_image = process_and_get_dynamic_image(
-            &context,
+            context.as_ref(),
             &state,
             transformed_image.as_ref(),
             unique_hash,
@@ -1611,6 +2218,92 @@ async fn generate_preview_for_path(
     .map_err(|e| format!("Task execution failed: {}", e))?
 }
 
+const PATH_HISTOGRAM_DIM: u32 = 720;
+
+/// Histogram of any image rendered with `js_adjustments`, for comparing scopes side by side.
+/// Renders through the GPU pipeline at low resolution, under its own cache key so the result
+/// can never be mistaken for a full-size render of the same edit.
+#[tauri::command]
+async fn generate_histogram_for_path(
+    path: String,
+    js_adjustments: Value,
+    app_handle: tauri::AppHandle,
+) -> Result<image_processing::HistogramData, String> {
+    tokio::task::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let context = get_gpu_context_or_fallback(&state, &app_handle);
+        let (source_path, _) = parse_virtual_path(&path);
+        let source_path_str = source_path.to_string_lossy().to_string();
+        let is_raw = is_raw_file(&source_path_str);
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
+
+        let base_image = load_composite_for_path(
+            &source_path,
+            &source_path_str,
+            &js_adjustments,
+            true,
+            &settings,
+        )?;
+        let (transformed_image, unscaled_crop_offset) =
+            apply_all_transformations(Cow::Borrowed(&base_image), &js_adjustments);
+        let full_w = transformed_image.width();
+        let preview_image =
+            downscale_f32_image(&transformed_image, PATH_HISTOGRAM_DIM, PATH_HISTOGRAM_DIM);
+        drop(transformed_image);
+        let (img_w, img_h) = preview_image.dimensions();
+        let scale = img_w as f32 / full_w.max(1) as f32;
+        let scaled_crop_offset = (
+            unscaled_crop_offset.0 * scale,
+            unscaled_crop_offset.1 * scale,
+        );
+
+        let mask_definitions: Vec<MaskDefinition> = js_adjustments
+            .get("masks")
+            .and_then(|m| serde_json::from_value(m.clone()).ok())
+            .unwrap_or_default();
+        let mask_bitmaps: Vec<ImageBuffer<Luma<u8>, Vec<u8>>> = mask_definitions
+            .iter()
+            .filter_map(|def| {
+                generate_mask_bitmap(def, img_w, img_h, scale, scaled_crop_offset, None)
+            })
+            .collect();
+
+        let tm_override = resolve_tonemapper_override(&settings, is_raw);
+        let mut all_adjustments =
+            get_all_adjustments_from_json(&js_adjustments, is_raw, tm_override);
+        all_adjustments
+            .global
+            .set_grain_frame(scale, unscaled_crop_offset);
+        let lut = lut_processing::resolve_lut_for_adjustments(&state, &js_adjustments);
+
+        let mut hasher = DefaultHasher::new();
+        (
+            "histogram_for_path",
+            calculate_full_job_hash(&source_path_str, &js_adjustments),
+            img_w,
+            img_h,
+        )
+            .hash(&mut hasher);
+
+        let processed = process_and_get_dynamic_image(
+            context.as_ref(),
+            &state,
+            &preview_image,
+            hasher.finish(),
+            RenderRequest {
+                adjustments: all_adjustments,
+                mask_bitmaps: &mask_bitmaps,
+                lut,
+                roi: None,
+            },
+            "generate_histogram_for_path",
+        )?;
+        image_processing::calculate_histogram_from_image(&processed, None)
+    })
+    .await
+    .map_err(|e| format!("Task execution failed: {}", e))?
+}
+
 #[cfg(target_os = "linux")]
 fn is_nvidia_gpu() -> bool {
     std::path::Path::new("/proc/driver/nvidia/version").exists()
@@ -1950,6 +2643,15 @@ fn frontend_ready(
             &session.source
         );
     }
+
+    // An "open with" file or external edit takes precedence over the previous session.
+    if is_first_run
+        && open_with_file.is_none()
+        && edit_session.is_none()
+        && let Some(session) = session::load_session(&app_handle)
+    {
+        let _ = app_handle.emit("restore-session", session);
+    }
     Ok(LaunchPayload {
         open_with_file,
         edit_session,
@@ -2034,8 +2736,11 @@ pub fn run() {
 
             {
                 let state = app.state::<AppState>();
-                let cache_size = settings.image_cache_size.unwrap_or(5) as usize;
-                state.decoded_image_cache.lock().unwrap().set_capacity(cache_size);
+                cache_utils::apply_cache_settings(&state, &settings);
+                let budget_mb = settings.gpu_memory_budget_mb.unwrap_or(2048) as u64;
+                state
+                    .gpu_memory_budget
+                    .store(budget_mb * 1024 * 1024, Ordering::SeqCst);
             }
 
             if crash_flag_path.exists() {
@@ -2106,6 +2811,7 @@ pub fn run() {
 
             start_preview_worker(app_handle.clone());
             start_analytics_worker(app_handle.clone());
+            start_full_res_scopes_worker(app_handle.clone());
             file_management::start_thumbnail_workers(app_handle.clone());
             file_management::start_metadata_workers(app_handle.clone());
             jxl_oxide::integration::register_image_decoding_hook();
@@ -2267,6 +2973,7 @@ pub fn run() {
                 });
             }
 
+            session::spawn_session_saver(app.handle().clone());
             crate::register_exit_handler();
             Ok(())
         })
@@ -2278,23 +2985,37 @@ pub fn run() {
             gpu_context: Mutex::new(None),
             gpu_image_cache: Mutex::new(None),
             gpu_processor: Mutex::new(None),
+            gpu_degraded_mode: AtomicBool::new(false),
+            gpu_memory_budget: AtomicU64::new(0),
             ai_state: Mutex::new(None),
             ai_init_lock: TokioMutex::new(()),
             export_task_handle: Mutex::new(None),
+            export_cancellation_token: Arc::new(AtomicBool::new(false)),
             hdr_result: Arc::new(Mutex::new(None)),
+            hdr_frames: Arc::new(Mutex::new(None)),
+            hdr_cancellation_token: Arc::new(AtomicBool::new(false)),
+            panorama_cancellation_token: Arc::new(AtomicBool::new(false)),
+            print_cancellation_token: Arc::new(AtomicBool::new(false)),
             panorama_result: Arc::new(Mutex::new(None)),
             denoise_result: Arc::new(Mutex::new(None)),
+            expand_result: Arc::new(Mutex::new(None)),
+            upscale_result: Arc::new(Mutex::new(None)),
             indexing_task_handle: Mutex::new(None),
-            lut_cache: Mutex::new(HashMap::new()),
+            lut_cache: Mutex::new(LruCache::new(DEFAULT_LUT_CACHE_SIZE as usize)),
             initial_file_path: Mutex::new(None),
             pending_edit_session: Mutex::new(None),
+            pending_session: Mutex::new(None),
             thumbnail_cancellation_token: Arc::new(AtomicBool::new(false)),
             thumbnail_progress: Mutex::new(ThumbnailProgressTracker { total: 0, completed: 0 }),
             preview_worker_tx: Mutex::new(None),
+            preview_job_generation: Arc::new(AtomicUsize::new(0)),
+            preview_mirror: Mutex::new(PreviewMirror::default()),
+            preview_mirror_generation: AtomicUsize::new(0),
             analytics_worker_tx: Mutex::new(None),
-            mask_cache: Mutex::new(HashMap::new()),
+            full_res_scopes_tx: Mutex::new(None),
+            mask_cache: Mutex::new(LruCache::new(DEFAULT_MASK_CACHE_SIZE as usize)),
             patch_cache: Mutex::new(HashMap::new()),
-            geometry_cache: Mutex::new(HashMap::new()),
+            geometry_cache: Mutex::new(LruCache::new(DEFAULT_GEOMETRY_CACHE_SIZE as usize)),
             thumbnail_geometry_cache: Mutex::new(HashMap::new()),
             lens_db: Mutex::new(None),
             load_image_generation: Arc::new(AtomicUsize::new(0)),
@@ -2303,18 +3024,33 @@ pub fn run() {
             decoded_image_cache: Mutex::new(DecodedImageCache::new(5)),
             thumbnail_manager: ThumbnailManager::new(),
             metadata_manager: MetadataManager::new(),
+            folder_watch: Mutex::new(None),
         })
         .invoke_handler(tauri::generate_handler![
             apply_adjustments,
             generate_preview_for_path,
+            generate_histogram_for_path,
             generate_original_transformed_preview,
             generate_preset_preview,
+            generate_fullscreen_preview,
+            compare_adjustments,
+            secondary_window::open_secondary_window,
+            soft_proof_preview,
+            generate_focus_peaking,
+            sample_color,
             generate_uncropped_preview,
             preview_geometry_transform,
             get_log_file_path,
             frontend_log,
             save_collage,
+            contact_sheet::generate_contact_sheet,
+            print_layout::print_images,
+            print_layout::cancel_print,
+            timelapse::export_timelapse,
+            timelapse::deflicker_sequence,
             merge_hdr,
+            cancel_hdr,
+            refine_hdr_blend,
             save_hdr,
             lut_processing::load_and_parse_lut,
             lut_processing::list_luts,
@@ -2326,6 +3062,7 @@ pub fn run() {
             save_temp_file,
             get_image_dimensions,
             frontend_ready,
+            session::save_session,
             cancel_thumbnail_generation,
             update_wgpu_transform,
             android_integration::resolve_android_content_uri_name,
@@ -2333,6 +3070,10 @@ pub fn run() {
             cache_utils::clear_image_caches,
             app_settings::load_settings,
             app_settings::save_settings,
+            xmp_sidecar::write_xmp_sidecar,
+            xmp_sidecar::read_xmp_sidecar,
+            gpu_processing::list_gpu_adapters,
+            gpu_processing::get_gpu_memory_stats,
             ai_commands::generate_ai_subject_mask,
             ai_commands::precompute_ai_subject_mask,
             ai_commands::generate_ai_foreground_mask,
@@ -2341,18 +3082,26 @@ pub fn run() {
             ai_commands::check_ai_connector_status,
             ai_commands::test_ai_connector_connection,
             inpainting::invoke_generative_replace_with_mask_def,
+            inpainting::generative_expand,
+            inpainting::save_expanded_image,
             inpainting::generate_manual_cleanup_patch,
+            inpainting::heal_region,
             denoising::apply_denoising,
             denoising::batch_denoise_images,
             denoising::save_denoised_image,
+            upscaling::upscale_image,
+            upscaling::save_upscaled_image,
             image_loader::load_image,
             image_loader::is_image_cached,
             panorama_stitching::stitch_panorama,
+            panorama_stitching::cancel_panorama,
             panorama_stitching::save_panorama,
             export_processing::export_images,
             export_processing::cancel_export,
             export_processing::estimate_export_sizes,
+            export_processing::extract_previews,
             image_processing::calculate_auto_adjustments,
+            compute_wb_from_point,
             mask_generation::generate_mask_overlay,
             file_management::update_exif_fields,
             file_management::get_supported_file_types,
@@ -2362,27 +3111,38 @@ pub fn run() {
             file_management::get_folder_tree,
             file_management::get_folder_children,
             file_management::get_pinned_folder_trees,
+            file_management::generate_thumbnails,
             file_management::update_thumbnail_queue,
+            file_management::prioritize_thumbnails,
             file_management::create_folder,
             file_management::delete_folder,
             file_management::copy_files,
             file_management::move_files,
             file_management::rename_folder,
             file_management::rename_files,
+            file_management::rename_files_templated,
             file_management::duplicate_file,
             file_management::show_in_finder,
             file_management::delete_files_from_disk,
             file_management::delete_files_with_associated,
             file_management::save_metadata_and_update_thumbnail,
             file_management::apply_adjustments_to_paths,
+            file_management::copy_adjustments,
+            file_management::paste_adjustments,
             file_management::load_metadata,
             file_management::load_presets,
             file_management::save_presets,
             file_management::get_or_create_internal_library_root,
             file_management::reset_adjustments_for_paths,
             file_management::apply_auto_adjustments_to_paths,
+            file_management::match_to_reference,
             file_management::handle_import_presets_from_file,
             file_management::handle_import_legacy_presets_from_file,
+            file_management::import_external_preset,
+            folder_watch::start_folder_watch,
+            folder_watch::stop_folder_watch,
+            raw_processing::get_raw_white_balance,
+            raw_processing::apply_calibration_frames,
             file_management::handle_export_presets_to_file,
             file_management::save_community_preset,
             file_management::clear_all_sidecars,
@@ -2391,6 +3151,13 @@ pub fn run() {
             file_management::set_rating_for_paths,
             file_management::import_files,
             file_management::create_virtual_copy,
+            file_management::save_edit_snapshot,
+            file_management::list_edit_snapshots,
+            file_management::restore_edit_snapshot,
+            file_management::delete_edit_snapshot,
+            file_management::undo_edit,
+            file_management::redo_edit,
+            file_management::get_edit_history_state,
             file_management::get_albums,
             file_management::save_albums,
             file_management::add_to_album,
@@ -2400,7 +3167,16 @@ pub fn run() {
             tagging::clear_all_tags,
             tagging::add_tag_for_paths,
             tagging::remove_tag_for_paths,
+            tagging::get_tag_hierarchy,
+            tagging::set_tag_hierarchy,
+            tagging::add_tag_synonym,
+            geotagging::download_geonames_data,
+            geotagging::has_geonames_data,
+            geotagging::geotag_to_location,
+            geotagging::get_gps_points,
+            geotagging::set_gps_for_paths,
             culling::cull_images,
+            culling::find_duplicates,
             lens_correction::get_lensfun_makers,
             lens_correction::get_lensfun_lenses_for_maker,
             lens_correction::autodetect_lens,
@@ -2426,6 +3202,7 @@ pub fn run() {
                 }
                 tauri::RunEvent::ExitRequested { api, .. } => {
                     api.prevent_exit();
+                    session::flush_pending_session(app_handle);
 
                     #[cfg(target_os = "macos")]
                     unsafe { libc::_exit(0); }
@@ -2434,6 +3211,7 @@ pub fn run() {
                     std::process::exit(0);
                 }
                 tauri::RunEvent::Exit => {
+                    session::flush_pending_session(app_handle);
                     #[cfg(target_os = "macos")]
                     unsafe { libc::_exit(0); }
 
@@ -2444,3 +3222,89 @@ pub fn run() {
             }
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mask_generation::{SubMask, SubMaskMode};
+    use image::GrayImage;
+
+    fn centroid_and_coverage(mask: &GrayImage) -> (f64, f64, f64) {
+        let (w, h) = mask.dimensions();
+        let (mut sum, mut sx, mut sy) = (0.0f64, 0.0f64, 0.0f64);
+        for (x, y, p) in mask.enumerate_pixels() {
+            let v = p[0] as f64 / 255.0;
+            sum += v;
+            sx += v * (x as f64 + 0.5);
+            sy += v * (y as f64 + 0.5);
+        }
+        assert!(sum > 0.0, "mask is empty");
+        (
+            sx / sum / w as f64,
+            sy / sum / h as f64,
+            sum / (w as f64 * h as f64),
+        )
+    }
+
+    #[test]
+    fn interactive_mask_matches_full_preview_mask() {
+        let mask = MaskDefinition {
+            id: "m".to_string(),
+            name: "radial".to_string(),
+            visible: true,
+            invert: false,
+            opacity: 100.0,
+            adjustments: serde_json::json!({}),
+            sub_masks: vec![SubMask {
+                id: "s".to_string(),
+                mask_type: "radial".to_string(),
+                visible: true,
+                invert: false,
+                opacity: 100.0,
+                mode: SubMaskMode::Additive,
+                parameters: serde_json::json!({
+                    "centerX": 2600.0,
+                    "centerY": 1500.0,
+                    "radiusX": 700.0,
+                    "radiusY": 450.0,
+                    "rotation": 20.0,
+                    "feather": 0.5,
+                }),
+            }],
+        };
+
+        // A 5000x3333 crop at (300, 200) in a 6000x4000 frame, shown at 1920 px.
+        let full_dims = (1920u32, 1280u32);
+        let full_scale = 1920.0 / 5000.0;
+        let unscaled_crop_offset = (300.0f32, 200.0f32);
+
+        for divisor in [1.4f32, 1.5, 1.8] {
+            let target_size = (full_dims.0 as f32 / divisor) as u32;
+            let small_dims = interactive_preview_size(full_dims, target_size);
+            let small_scale = full_scale * interactive_mask_scale(full_dims, small_dims);
+
+            let render = |(w, h): (u32, u32), scale: f32| {
+                let offset = (
+                    unscaled_crop_offset.0 * scale,
+                    unscaled_crop_offset.1 * scale,
+                );
+                generate_mask_bitmap(&mask, w, h, scale, offset, None).unwrap()
+            };
+            let full = centroid_and_coverage(&render(full_dims, full_scale));
+            let small = centroid_and_coverage(&render(small_dims, small_scale));
+
+            assert!(
+                (full.0 - small.0).abs() < 2e-3,
+                "x centroid {full:?} vs {small:?}"
+            );
+            assert!(
+                (full.1 - small.1).abs() < 2e-3,
+                "y centroid {full:?} vs {small:?}"
+            );
+            assert!(
+                (full.2 - small.2).abs() / full.2 < 0.02,
+                "coverage {full:?} vs {small:?}"
+            );
+        }
+    }
+}
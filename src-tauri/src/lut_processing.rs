@@ -6,6 +6,7 @@ use crate::android_integration::{
 use anyhow::anyhow;
 use image::{DynamicImage, GenericImageView, Rgb, Rgb32FImage};
 use serde::Serialize;
+use serde_json::Value;
 use std::fs::{File, copy, create_dir_all, read_dir};
 use std::io::{BufRead, BufReader, Cursor};
 use std::path::{Path, PathBuf};
@@ -26,6 +27,85 @@ use crate::image_processing::{
 pub struct Lut {
     pub size: u32,
     pub data: Vec<f32>,
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    pub title: Option<String>,
+}
+
+impl Lut {
+    pub fn new(size: u32, data: Vec<f32>) -> Self {
+        Lut {
+            size,
+            data,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            title: None,
+        }
+    }
+
+    /// Heuristic for LUTs built for log-encoded footage (e.g. LogC/S-Log to Rec.709).
+    /// These map the low end of the input range to black and have a steep mid-tone
+    /// slope, which crushes an image that is already display-referred.
+    pub fn appears_log_input(&self) -> bool {
+        if self
+            .title
+            .as_deref()
+            .is_some_and(|t| t.to_lowercase().contains("log"))
+        {
+            return true;
+        }
+        if self.domain_max.iter().any(|&v| v > 1.0) || self.domain_min.iter().any(|&v| v < 0.0) {
+            return true;
+        }
+
+        let neutral = |v: f32| {
+            let out = self.sample_trilinear([v; 3]);
+            (out[0] + out[1] + out[2]) / 3.0
+        };
+        let crushed_shadows = neutral(0.1) < 0.02;
+        let midtone_slope = (neutral(0.6) - neutral(0.4)) / 0.2;
+        crushed_shadows && midtone_slope > 1.4
+    }
+
+    pub fn sample_trilinear(&self, c: [f32; 3]) -> [f32; 3] {
+        let size = self.size as usize;
+        if size < 2 || self.data.len() < size * size * size * 3 {
+            return c;
+        }
+        let max_index = (size - 1) as f32;
+        let scaled = c.map(|v| v.clamp(0.0, 1.0) * max_index);
+        let i0 = scaled.map(|v| v.floor() as usize);
+        let i1 = i0.map(|v| (v + 1).min(size - 1));
+        let f = [
+            scaled[0] - i0[0] as f32,
+            scaled[1] - i0[1] as f32,
+            scaled[2] - i0[2] as f32,
+        ];
+
+        let fetch = |r: usize, g: usize, b: usize| -> [f32; 3] {
+            let idx = ((b * size + g) * size + r) * 3;
+            [self.data[idx], self.data[idx + 1], self.data[idx + 2]]
+        };
+        let lerp = |a: [f32; 3], b: [f32; 3], t: f32| -> [f32; 3] {
+            [
+                a[0] + (b[0] - a[0]) * t,
+                a[1] + (b[1] - a[1]) * t,
+                a[2] + (b[2] - a[2]) * t,
+            ]
+        };
+
+        let c00 = lerp(fetch(i0[0], i0[1], i0[2]), fetch(i1[0], i0[1], i0[2]), f[0]);
+        let c10 = lerp(fetch(i0[0], i1[1], i0[2]), fetch(i1[0], i1[1], i0[2]), f[0]);
+        let c01 = lerp(fetch(i0[0], i0[1], i1[2]), fetch(i1[0], i0[1], i1[2]), f[0]);
+        let c11 = lerp(fetch(i0[0], i1[1], i1[2]), fetch(i1[0], i1[1], i1[2]), f[0]);
+        lerp(lerp(c00, c10, f[1]), lerp(c01, c11, f[1]), f[2])
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LutStackEntry {
+    pub path: String,
+    pub intensity: f32,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -35,8 +115,12 @@ pub struct LutEntry {
 }
 
 #[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct LutParseResult {
     pub size: u32,
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    pub is_log_input: bool,
 }
 
 #[derive(Serialize)]
@@ -45,6 +129,12 @@ pub struct LutPreview {
     pub thumb: Option<String>,
 }
 
+const LUT_EXTENSIONS: &[&str] = &["cube", "3dl", "png", "jpg", "jpeg", "tif", "tiff"];
+
+fn is_lut_extension(extension: &str) -> bool {
+    LUT_EXTENSIONS.contains(&extension)
+}
+
 pub fn get_luts_dir(app_data_dir: &Path) -> anyhow::Result<PathBuf> {
     let luts_dir = app_data_dir.join("luts");
     if !luts_dir.exists() {
@@ -65,7 +155,7 @@ pub fn list_luts_in_dir(dir: &Path) -> anyhow::Result<Vec<LutEntry>> {
             .and_then(|s| s.to_str())
             .unwrap_or("")
             .to_lowercase();
-        if extension == "cube" || extension == "3dl" {
+        if is_lut_extension(&extension) {
             let name = path
                 .file_stem()
                 .and_then(|s| s.to_str())
@@ -151,10 +241,37 @@ fn import_android_lut(source: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn parse_domain_line(parts: &[&str], line_num: usize, line: &str) -> anyhow::Result<[f32; 3]> {
+    if parts.len() < 4 {
+        return Err(anyhow!(
+            "Malformed {} on line {}: '{}'",
+            parts[0],
+            line_num,
+            line
+        ));
+    }
+    let mut values = [0.0f32; 3];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = parts[i + 1].parse().map_err(|e| {
+            anyhow!(
+                "Failed to parse {} on line {}: '{}'. Error: {}",
+                parts[0],
+                line_num,
+                line,
+                e
+            )
+        })?;
+    }
+    Ok(values)
+}
+
 fn parse_cube(reader: impl BufRead) -> anyhow::Result<Lut> {
     let mut size: Option<u32> = None;
     let mut data: Vec<f32> = Vec::new();
     let mut line_num = 0;
+    let mut domain_min = [0.0f32; 3];
+    let mut domain_max = [1.0f32; 3];
+    let mut title: Option<String> = None;
 
     for line in reader.lines() {
         line_num += 1;
@@ -171,7 +288,16 @@ fn parse_cube(reader: impl BufRead) -> anyhow::Result<Lut> {
         }
 
         match parts[0].to_uppercase().as_str() {
-            "TITLE" | "DOMAIN_MIN" | "DOMAIN_MAX" => continue,
+            "TITLE" => {
+                title = Some(
+                    trimmed[parts[0].len()..]
+                        .trim()
+                        .trim_matches('"')
+                        .to_string(),
+                );
+            }
+            "DOMAIN_MIN" => domain_min = parse_domain_line(&parts, line_num, &line)?,
+            "DOMAIN_MAX" => domain_max = parse_domain_line(&parts, line_num, &line)?,
 
             "LUT_3D_SIZE" => {
                 if parts.len() < 2 {
@@ -243,9 +369,20 @@ fn parse_cube(reader: impl BufRead) -> anyhow::Result<Lut> {
         ));
     }
 
+    if (0..3).any(|i| domain_max[i] <= domain_min[i]) {
+        return Err(anyhow!(
+            "Invalid LUT domain: DOMAIN_MIN {:?} must be below DOMAIN_MAX {:?}",
+            domain_min,
+            domain_max
+        ));
+    }
+
     Ok(Lut {
         size: lut_size,
         data,
+        domain_min,
+        domain_max,
+        title,
     })
 }
 
@@ -283,7 +420,7 @@ fn parse_3dl(reader: impl BufRead) -> anyhow::Result<Lut> {
         ));
     }
 
-    Ok(Lut { size, data })
+    Ok(Lut::new(size, data))
 }
 
 fn parse_hald(image: DynamicImage) -> anyhow::Result<Lut> {
@@ -296,26 +433,28 @@ fn parse_hald(image: DynamicImage) -> anyhow::Result<Lut> {
         ));
     }
 
-    let total_pixels = width * height;
-    let size = (total_pixels as f64).cbrt().round() as u32;
-
-    if size * size * size != total_pixels {
+    // A HALD CLUT of level L is an L^3 x L^3 image holding an L^2 sized cube.
+    let level = (width as f64).cbrt().round() as u32;
+    if level < 2 || level * level * level != width {
         return Err(anyhow!(
-            "Invalid HALD image dimensions: total pixels ({}) is not a perfect cube.",
-            total_pixels
+            "Invalid HALD image dimensions {}x{}: side length must be a cube of the HALD level (e.g. 512x512 for level 8)",
+            width,
+            height
         ));
     }
+    let size = level * level;
 
-    let mut data = Vec::with_capacity((total_pixels * 3) as usize);
-    let rgb_image = image.to_rgb8();
+    let total_pixels = (width * height) as usize;
+    let mut data = Vec::with_capacity(total_pixels * 3);
+    let rgb_image = image.to_rgb32f();
 
     for pixel in rgb_image.pixels() {
-        data.push(pixel[0] as f32 / 255.0);
-        data.push(pixel[1] as f32 / 255.0);
-        data.push(pixel[2] as f32 / 255.0);
+        data.push(pixel[0].clamp(0.0, 1.0));
+        data.push(pixel[1].clamp(0.0, 1.0));
+        data.push(pixel[2].clamp(0.0, 1.0));
     }
 
-    Ok(Lut { size, data })
+    Ok(Lut::new(size, data))
 }
 
 pub fn parse_lut_file(path_str: &str) -> anyhow::Result<Lut> {
@@ -383,7 +522,7 @@ pub fn parse_lut_file(path_str: &str) -> anyhow::Result<Lut> {
                 parse_3dl(BufReader::new(file))
             }
         }
-        "png" | "jpg" | "jpeg" | "tiff" => {
+        "png" | "jpg" | "jpeg" | "tif" | "tiff" => {
             let img = if let Some(b) = bytes {
                 image::load_from_memory(&b)?
             } else {
@@ -440,7 +579,117 @@ pub fn convert_image_to_cube_lut(image: &DynamicImage, size: u32) -> Result<Vec<
     Ok(out.into_bytes())
 }
 
-pub fn get_or_load_lut(state: &State<AppState>, path: &str) -> Result<Arc<Lut>, String> {
+/// Reads the `luts` stack from adjustments, falling back to the legacy single `lutPath`.
+pub fn lut_stack_from_adjustments(js_adjustments: &Value) -> Vec<LutStackEntry> {
+    if let Some(entries) = js_adjustments["luts"].as_array()
+        && !entries.is_empty()
+    {
+        return entries
+            .iter()
+            .filter_map(|entry| {
+                let path = entry["path"].as_str()?.to_string();
+                let intensity =
+                    (entry["intensity"].as_f64().unwrap_or(100.0) as f32 / 100.0).clamp(0.0, 1.0);
+                Some(LutStackEntry { path, intensity })
+            })
+            .collect();
+    }
+
+    match js_adjustments["lutPath"].as_str() {
+        Some(path) => vec![LutStackEntry {
+            path: path.to_string(),
+            intensity: (js_adjustments["lutIntensity"].as_f64().unwrap_or(100.0) as f32 / 100.0)
+                .clamp(0.0, 1.0),
+        }],
+        None => Vec::new(),
+    }
+}
+
+pub fn has_lut_stack(js_adjustments: &Value) -> bool {
+    js_adjustments["luts"]
+        .as_array()
+        .is_some_and(|entries| !entries.is_empty())
+}
+
+fn compose_lut_stack(layers: &[(Arc<Lut>, f32)]) -> Lut {
+    let size = layers
+        .iter()
+        .map(|(lut, _)| lut.size)
+        .max()
+        .unwrap_or(2)
+        .max(2);
+    let max_index = (size - 1) as f32;
+    let mut data = Vec::with_capacity((size * size * size * 3) as usize);
+
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                let mut color = [
+                    r as f32 / max_index,
+                    g as f32 / max_index,
+                    b as f32 / max_index,
+                ];
+                for (lut, intensity) in layers {
+                    let mapped = lut.sample_trilinear(color);
+                    for c in 0..3 {
+                        color[c] += (mapped[c] - color[c]) * intensity;
+                    }
+                }
+                data.extend_from_slice(&color);
+            }
+        }
+    }
+
+    Lut::new(size, data)
+}
+
+/// Resolves the LUT to upload for a set of adjustments. A `luts` stack is baked into a
+/// single cube with each entry's intensity applied, so the shader only ever sees one LUT.
+pub fn resolve_lut_for_adjustments(state: &AppState, js_adjustments: &Value) -> Option<Arc<Lut>> {
+    let stack = lut_stack_from_adjustments(js_adjustments);
+    if stack.is_empty() {
+        return None;
+    }
+    if !has_lut_stack(js_adjustments) {
+        return load_lut_cached(state, &stack[0].path).ok();
+    }
+
+    let stack_key = format!(
+        "stack:{}",
+        stack
+            .iter()
+            .map(|e| format!("{}@{:.3}", e.path, e.intensity))
+            .collect::<Vec<_>>()
+            .join("|")
+    );
+    if let Some(lut) = state.lut_cache.lock().unwrap().get(&stack_key) {
+        return Some(lut.clone());
+    }
+
+    let layers: Vec<(Arc<Lut>, f32)> = stack
+        .iter()
+        .filter_map(|entry| match load_lut_cached(state, &entry.path) {
+            Ok(lut) => Some((lut, entry.intensity)),
+            Err(e) => {
+                log::warn!("Skipping LUT '{}' in stack: {}", entry.path, e);
+                None
+            }
+        })
+        .collect();
+    if layers.is_empty() {
+        return None;
+    }
+
+    let composed = Arc::new(compose_lut_stack(&layers));
+    state
+        .lut_cache
+        .lock()
+        .unwrap()
+        .insert(stack_key, composed.clone());
+    Some(composed)
+}
+
+fn load_lut_cached(state: &AppState, path: &str) -> Result<Arc<Lut>, String> {
     let mut cache = state.lut_cache.lock().unwrap();
     if let Some(lut) = cache.get(path) {
         return Ok(lut.clone());
@@ -452,6 +701,10 @@ pub fn get_or_load_lut(state: &State<AppState>, path: &str) -> Result<Arc<Lut>,
     Ok(arc_lut)
 }
 
+pub fn get_or_load_lut(state: &State<AppState>, path: &str) -> Result<Arc<Lut>, String> {
+    load_lut_cached(state, path)
+}
+
 #[tauri::command]
 pub fn list_luts(app_handle: AppHandle) -> Result<Vec<LutEntry>, String> {
     let data_dir = app_handle
@@ -495,7 +748,7 @@ fn list_luts_in_cache() -> anyhow::Result<Vec<LutEntry>> {
             .and_then(|s| s.to_str())
             .unwrap_or("")
             .to_lowercase();
-        if extension == "cube" || extension == "3dl" {
+        if is_lut_extension(&extension) {
             let name = path
                 .file_stem()
                 .and_then(|s| s.to_str())
@@ -584,7 +837,7 @@ pub fn remove_lut(app_handle: AppHandle, path: String) -> Result<Vec<LutEntry>,
 }
 
 fn render_lut_swatch(
-    context: &crate::image_processing::GpuContext,
+    context: Option<&crate::image_processing::GpuContext>,
     state: &State<AppState>,
     base_image: &DynamicImage,
     transform_hash: u64,
@@ -626,7 +879,7 @@ pub fn generate_lut_previews(
     state: State<AppState>,
     app_handle: AppHandle,
 ) -> Result<Vec<LutPreview>, String> {
-    let context = crate::image_processing::get_or_init_gpu_context(&state, &app_handle)?;
+    let context = crate::image_processing::get_gpu_context_or_fallback(&state, &app_handle);
     let loaded_image = state
         .original_image
         .lock()
@@ -652,7 +905,7 @@ pub fn generate_lut_previews(
         .into_iter()
         .map(|path| {
             let thumb = render_lut_swatch(
-                &context,
+                context.as_ref(),
                 &state,
                 &base_image,
                 transform_hash,
@@ -669,10 +922,15 @@ pub fn generate_lut_previews(
 #[tauri::command]
 pub fn load_and_parse_lut(path: String, state: State<AppState>) -> Result<LutParseResult, String> {
     let lut = parse_lut_file(&path).map_err(|e| e.to_string())?;
-    let lut_size = lut.size;
+    let result = LutParseResult {
+        size: lut.size,
+        domain_min: lut.domain_min,
+        domain_max: lut.domain_max,
+        is_log_input: lut.appears_log_input(),
+    };
 
     let mut cache = state.lut_cache.lock().unwrap();
     cache.insert(path, Arc::new(lut));
 
-    Ok(LutParseResult { size: lut_size })
+    Ok(result)
 }
@@ -84,6 +84,14 @@ pub struct AiPatchDefinition {
     pub invert: bool,
     pub prompt: String,
     #[serde(default)]
+    pub negative_prompt: Option<String>,
+    #[serde(default)]
+    pub guidance_scale: Option<f32>,
+    #[serde(default)]
+    pub steps: Option<u32>,
+    #[serde(default)]
+    pub seed: Option<i64>,
+    #[serde(default)]
     pub patch_data: Option<PatchData>,
     #[serde(default = "default_opacity")]
     pub opacity: f32,
@@ -855,7 +863,7 @@ fn generate_ai_bitmap_from_full_mask(
     final_mask
 }
 
-fn generate_ai_bitmap_from_base64(data_url: &str, tf: &TransformParams) -> Option<GrayImage> {
+pub fn decode_base64_mask(data_url: &str) -> Option<GrayImage> {
     let b64_data = if let Some(idx) = data_url.find(',') {
         &data_url[idx + 1..]
     } else {
@@ -863,8 +871,11 @@ fn generate_ai_bitmap_from_base64(data_url: &str, tf: &TransformParams) -> Optio
     };
 
     let decoded_bytes = general_purpose::STANDARD.decode(b64_data).ok()?;
-    let full_mask_image = image::load_from_memory(&decoded_bytes).ok()?.to_luma8();
+    Some(image::load_from_memory(&decoded_bytes).ok()?.to_luma8())
+}
 
+fn generate_ai_bitmap_from_base64(data_url: &str, tf: &TransformParams) -> Option<GrayImage> {
+    let full_mask_image = decode_base64_mask(data_url)?;
     Some(generate_ai_bitmap_from_full_mask(&full_mask_image, tf))
 }
 
@@ -1481,7 +1492,7 @@ pub fn get_cached_or_generate_mask(
     let key = hasher.finish();
 
     {
-        let cache = state.mask_cache.lock().unwrap();
+        let mut cache = state.mask_cache.lock().unwrap();
         if let Some(img) = cache.get(&key) {
             return Some(img.clone());
         }
@@ -1500,11 +1511,7 @@ pub fn get_cached_or_generate_mask(
     );
 
     if let Some(img) = &generated {
-        let mut cache = state.mask_cache.lock().unwrap();
-        if cache.len() > 50 {
-            cache.clear();
-        }
-        cache.insert(key, img.clone());
+        state.mask_cache.lock().unwrap().insert(key, img.clone());
     }
 
     generated
@@ -1,20 +1,23 @@
 use crate::app_settings::load_settings;
 use crate::app_state::AppState;
-use crate::file_management::parse_virtual_path;
+use crate::file_management::{parse_virtual_path, read_file_mapped};
 use base64::{Engine as _, engine::general_purpose};
 use image::ImageFormat;
 use image::{DynamicImage, GenericImageView, GrayImage, Rgb32FImage};
 use nalgebra::Matrix3;
 use rayon::prelude::*;
+use serde::Serialize;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 use std::io::Cursor;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::time::Instant;
 use tauri::{AppHandle, Emitter};
 
 use crate::formats::is_raw_file;
 use crate::image_processing::apply_cpu_default_raw_processing;
+use crate::panorama_utils::stitching::BlendMode;
 use crate::panorama_utils::{processing, stitching};
 
 pub const BRIEF_DESCRIPTOR_SIZE: usize = 256;
@@ -52,9 +55,97 @@ pub struct MatchInfo {
     pub inliers: usize,
 }
 
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StageProgress {
+    pub message: String,
+    pub stage: String,
+    pub percentage: f32,
+}
+
+/// Emits a progress event carrying both the legacy text message and a determinate
+/// 0-100 percentage for the current stage.
+pub fn emit_stage_progress(
+    app_handle: &AppHandle,
+    event: &str,
+    stage: &str,
+    percentage: f32,
+    message: impl Into<String>,
+) {
+    let _ = app_handle.emit(
+        event,
+        StageProgress {
+            message: message.into(),
+            stage: stage.to_string(),
+            percentage: percentage.clamp(0.0, 100.0),
+        },
+    );
+}
+
+const PANORAMA_CANCELLED: &str = "Panorama stitching cancelled.";
+
+// Cumulative percentages at which each stitching stage ends.
+pub const FEATURES_STAGE_END: f32 = 35.0;
+pub const MATCHING_STAGE_END: f32 = 55.0;
+pub const BLENDING_STAGE_END: f32 = 98.0;
+
+/// Inlier count at which a pair is considered fully reliable.
+const CONFIDENT_PAIR_INLIERS: f32 = 80.0;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PairAlignment {
+    pub first: String,
+    pub second: String,
+    pub inliers: usize,
+    pub confidence: f32,
+}
+
+#[derive(Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StitchReport {
+    pub pairs: Vec<PairAlignment>,
+    pub unregistered: Vec<String>,
+    pub confidence: f32,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StitchFailure {
+    pub message: String,
+    #[serde(flatten)]
+    pub report: StitchReport,
+}
+
+impl From<String> for StitchFailure {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            report: StitchReport::default(),
+        }
+    }
+}
+
+fn check_cancelled(cancel_token: &AtomicBool) -> Result<(), StitchFailure> {
+    if cancel_token.load(Ordering::SeqCst) {
+        Err(PANORAMA_CANCELLED.to_string().into())
+    } else {
+        Ok(())
+    }
+}
+
+fn display_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
 #[tauri::command]
 pub async fn stitch_panorama(
     paths: Vec<String>,
+    blend_mode: Option<BlendMode>,
     app_handle: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> Result<(), String> {
@@ -68,13 +159,26 @@ pub async fn stitch_panorama(
         .collect();
 
     let panorama_result_handle = state.panorama_result.clone();
+    let cancel_token = state.panorama_cancellation_token.clone();
+    cancel_token.store(false, Ordering::SeqCst);
 
     let task = tokio::task::spawn_blocking(move || {
-        let panorama_result = stitch_images(source_paths, app_handle.clone());
+        let panorama_result = stitch_images(
+            source_paths,
+            blend_mode.unwrap_or_default(),
+            &cancel_token,
+            app_handle.clone(),
+        );
 
         match panorama_result {
-            Ok(panorama_image) => {
-                let _ = app_handle.emit("panorama-progress", "Creating preview...");
+            Ok((panorama_image, report)) => {
+                emit_stage_progress(
+                    &app_handle,
+                    "panorama-progress",
+                    "preview",
+                    99.0,
+                    "Creating preview...",
+                );
 
                 let (w, h) = panorama_image.dimensions();
                 let (new_w, new_h) = if w > h {
@@ -103,13 +207,17 @@ pub async fn stitch_panorama(
                     "panorama-complete",
                     serde_json::json!({
                         "base64": final_base64,
+                        "report": report,
                     }),
                 );
                 Ok(())
             }
-            Err(e) => {
-                let _ = app_handle.emit("panorama-error", e.clone());
-                Err(e)
+            Err(failure) => {
+                if cancel_token.load(Ordering::SeqCst) {
+                    *panorama_result_handle.lock().unwrap() = None;
+                }
+                let _ = app_handle.emit("panorama-error", failure.clone());
+                Err(failure.message)
             }
         }
     });
@@ -121,6 +229,15 @@ pub async fn stitch_panorama(
     }
 }
 
+#[tauri::command]
+pub fn cancel_panorama(state: tauri::State<AppState>) -> Result<(), String> {
+    state
+        .panorama_cancellation_token
+        .store(true, Ordering::SeqCst);
+    println!("Panorama stitching cancellation requested.");
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn save_panorama(
     first_path_str: String,
@@ -173,49 +290,80 @@ pub async fn save_panorama(
     Ok(output_path.to_string_lossy().to_string())
 }
 
-fn stitch_images(image_paths: Vec<String>, app_handle: AppHandle) -> Result<DynamicImage, String> {
+fn stitch_images(
+    image_paths: Vec<String>,
+    blend_mode: BlendMode,
+    cancel_token: &AtomicBool,
+    app_handle: AppHandle,
+) -> Result<(DynamicImage, StitchReport), StitchFailure> {
     if image_paths.len() < 2 {
-        return Err("At least two images are required for a panorama.".to_string());
+        return Err("At least two images are required for a panorama."
+            .to_string()
+            .into());
     }
 
-    let _ = app_handle.emit("panorama-progress", "Starting panorama process...");
+    emit_stage_progress(
+        &app_handle,
+        "panorama-progress",
+        "start",
+        0.0,
+        "Starting panorama process...",
+    );
     println!(
         "Starting panorama stitching process for {} images...",
         image_paths.len()
     );
 
-    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let settings = crate::image_loader::upright_merge_settings(
+        load_settings(app_handle.clone()).unwrap_or_default(),
+    );
 
     let start_time = Instant::now();
-    let _ = app_handle.emit("panorama-progress", "Loading and preparing images...");
+    emit_stage_progress(
+        &app_handle,
+        "panorama-progress",
+        "features",
+        0.0,
+        "Loading and preparing images...",
+    );
     println!("Loading and preparing images (in parallel)...");
     let brief_pairs = processing::generate_brief_pairs();
+    let total_images = image_paths.len();
+    let loaded_count = AtomicUsize::new(0);
 
     let image_data_results: Vec<Result<ImageInfo, String>> = image_paths
         .par_iter()
         .enumerate()
         .map(|(i, filename)| {
-            let _ = app_handle.emit(
+            if cancel_token.load(Ordering::SeqCst) {
+                return Err(PANORAMA_CANCELLED.to_string());
+            }
+            emit_stage_progress(
+                &app_handle,
                 "panorama-progress",
-                format!(
-                    "Processing '{}'",
-                    Path::new(filename)
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                ),
+                "features",
+                FEATURES_STAGE_END * loaded_count.load(Ordering::SeqCst) as f32
+                    / total_images as f32,
+                format!("Processing '{}'", display_name(filename)),
             );
             println!("  - Processing '{}'", filename);
 
-            let file_bytes = fs::read(filename)
-                .map_err(|e| format!("Failed to read image {}: {}", filename, e))?;
+            let file_slice: Vec<u8>;
+            let mmap_guard;
+            let file_bytes: &[u8] = match read_file_mapped(Path::new(filename)) {
+                Ok(mmap) => {
+                    mmap_guard = Some(mmap);
+                    mmap_guard.as_ref().unwrap()
+                }
+                Err(_) => {
+                    file_slice = fs::read(filename)
+                        .map_err(|e| format!("Failed to read image {}: {}", filename, e))?;
+                    &file_slice
+                }
+            };
 
             let mut dynamic_image = crate::image_loader::load_base_image_from_bytes(
-                &file_bytes,
-                filename,
-                false,
-                &settings,
-                None,
+                file_bytes, filename, false, &settings, None,
             )
             .map_err(|e| format!("Failed to load image {}: {}", filename, e))?;
 
@@ -242,6 +390,7 @@ fn stitch_images(image_paths: Vec<String>, app_handle: AppHandle) -> Result<Dyna
 
             let features = processing::find_features(&gray_small, &brief_pairs);
             println!("    Found {} features in '{}'", features.len(), filename);
+            loaded_count.fetch_add(1, Ordering::SeqCst);
 
             Ok(ImageInfo {
                 id: i,
@@ -258,7 +407,7 @@ fn stitch_images(image_paths: Vec<String>, app_handle: AppHandle) -> Result<Dyna
     for result in image_data_results {
         match result {
             Ok(info) => image_data.push(info),
-            Err(e) => return Err(e),
+            Err(e) => return Err(e.into()),
         }
     }
 
@@ -267,8 +416,16 @@ fn stitch_images(image_paths: Vec<String>, app_handle: AppHandle) -> Result<Dyna
         start_time.elapsed()
     );
 
+    check_cancelled(cancel_token)?;
+
     let start_time = Instant::now();
-    let _ = app_handle.emit("panorama-progress", "Finding image matches...");
+    emit_stage_progress(
+        &app_handle,
+        "panorama-progress",
+        "matching",
+        FEATURES_STAGE_END,
+        "Finding image matches...",
+    );
     println!("Finding all pairwise matches (in parallel)...");
     let mut pairwise_matches: HashMap<(usize, usize), MatchInfo> = HashMap::new();
 
@@ -347,20 +504,45 @@ fn stitch_images(image_paths: Vec<String>, app_handle: AppHandle) -> Result<Dyna
     );
 
     if pairwise_matches.is_empty() {
-        return Err(
-            "No suitable matches found between any pair of images. Cannot create a panorama."
-                .to_string(),
-        );
+        return Err(StitchFailure {
+            message:
+                "No suitable matches found between any pair of images. Cannot create a panorama."
+                    .to_string(),
+            report: StitchReport {
+                unregistered: image_data
+                    .iter()
+                    .map(|i| display_name(&i.filename))
+                    .collect(),
+                ..Default::default()
+            },
+        });
     }
 
+    check_cancelled(cancel_token)?;
+
     let start_time = Instant::now();
-    let _ = app_handle.emit("panorama-progress", "Determining stitching order...");
+    emit_stage_progress(
+        &app_handle,
+        "panorama-progress",
+        "matching",
+        MATCHING_STAGE_END,
+        "Determining stitching order...",
+    );
     println!("Determining stitching order...");
-    let (ordered_indices, global_homographies) =
+    let (ordered_indices, global_homographies, tree_edges) =
         build_stitching_order(&image_data, &pairwise_matches);
+    let report = build_stitch_report(
+        &image_data,
+        &pairwise_matches,
+        &ordered_indices,
+        &tree_edges,
+    );
 
     if ordered_indices.len() < 2 {
-        return Err("Could not find a connected sequence of at least two images.".to_string());
+        return Err(StitchFailure {
+            message: "Could not find a connected sequence of at least two images.".to_string(),
+            report,
+        });
     }
 
     let ordered_filenames: Vec<_> = ordered_indices
@@ -374,18 +556,20 @@ fn stitch_images(image_paths: Vec<String>, app_handle: AppHandle) -> Result<Dyna
         })
         .collect();
     println!("Stitching order determined: {:?}", ordered_filenames);
-    let _ = app_handle.emit(
+    emit_stage_progress(
+        &app_handle,
         "panorama-progress",
+        "matching",
+        MATCHING_STAGE_END,
         format!("Stitching order: {}", ordered_filenames.join(" -> ")),
     );
 
     let stitched_images_info: Vec<&ImageInfo> =
         ordered_indices.iter().map(|&i| &image_data[i]).collect();
-    let unstitched_count = image_data.len() - stitched_images_info.len();
-    if unstitched_count > 0 {
+    if !report.unregistered.is_empty() {
         let warning_msg = format!(
-            "Warning: {} image(s) could not be matched and will be excluded.",
-            unstitched_count
+            "Warning: {} didn't have enough overlap and will be excluded.",
+            report.unregistered.join(", ")
         );
         println!("{}", warning_msg);
         let _ = app_handle.emit("panorama-warning", warning_msg);
@@ -396,20 +580,85 @@ fn stitch_images(image_paths: Vec<String>, app_handle: AppHandle) -> Result<Dyna
     );
 
     let start_time = Instant::now();
-    let _ = app_handle.emit("panorama-progress", "Warping and blending images...");
-    println!("Warping and blending full-resolution images with progressive optimal seams...");
+    let blend_stage = match blend_mode {
+        BlendMode::Feather => "Warping and feather blending images...",
+        BlendMode::Multiband => "Warping and multiband blending images...",
+    };
+    emit_stage_progress(
+        &app_handle,
+        "panorama-progress",
+        "warping",
+        MATCHING_STAGE_END,
+        blend_stage,
+    );
+    println!(
+        "Warping and blending full-resolution images with progressive optimal seams ({:?})...",
+        blend_mode
+    );
 
     let panorama = stitching::progressive_seam_stitcher(
         &stitched_images_info,
         &global_homographies,
+        blend_mode,
+        cancel_token,
         app_handle.clone(),
     );
+    check_cancelled(cancel_token)?;
 
     println!("Stitching completed in {:.2?}\n", start_time.elapsed());
 
-    let _ = app_handle.emit("panorama-progress", "Finalizing panorama...");
+    emit_stage_progress(
+        &app_handle,
+        "panorama-progress",
+        "finalizing",
+        BLENDING_STAGE_END,
+        "Finalizing panorama...",
+    );
+
+    Ok((DynamicImage::ImageRgb32F(panorama), report))
+}
+
+/// Overall confidence is the weakest link in the stitching tree, scaled down by the share
+/// of images that could not be registered at all.
+fn build_stitch_report(
+    images: &[ImageInfo],
+    matches: &HashMap<(usize, usize), MatchInfo>,
+    ordered_indices: &[usize],
+    tree_edges: &[(usize, usize)],
+) -> StitchReport {
+    let pairs: Vec<PairAlignment> = tree_edges
+        .iter()
+        .map(|&(u, v)| {
+            let inliers = matches.get(&(u.min(v), u.max(v))).map_or(0, |m| m.inliers);
+            PairAlignment {
+                first: display_name(&images[u].filename),
+                second: display_name(&images[v].filename),
+                inliers,
+                confidence: (inliers as f32 / CONFIDENT_PAIR_INLIERS).min(1.0),
+            }
+        })
+        .collect();
+
+    let registered: HashSet<usize> = ordered_indices.iter().copied().collect();
+    let unregistered: Vec<String> = images
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !registered.contains(i))
+        .map(|(_, info)| display_name(&info.filename))
+        .collect();
 
-    Ok(DynamicImage::ImageRgb32F(panorama))
+    let weakest_pair = pairs
+        .iter()
+        .map(|p| p.confidence)
+        .reduce(f32::min)
+        .unwrap_or(0.0);
+    let registered_share = registered.len() as f32 / images.len().max(1) as f32;
+
+    StitchReport {
+        pairs,
+        unregistered,
+        confidence: weakest_pair * registered_share,
+    }
 }
 
 struct Dsu {
@@ -444,9 +693,13 @@ impl Dsu {
 fn build_stitching_order(
     images: &[ImageInfo],
     matches: &HashMap<(usize, usize), MatchInfo>,
-) -> (Vec<usize>, HashMap<usize, Matrix3<f64>>) {
+) -> (
+    Vec<usize>,
+    HashMap<usize, Matrix3<f64>>,
+    Vec<(usize, usize)>,
+) {
     if images.is_empty() {
-        return (vec![], HashMap::new());
+        return (vec![], HashMap::new(), vec![]);
     }
     let n = images.len();
     if n < 2 {
@@ -454,7 +707,7 @@ fn build_stitching_order(
         if n == 1 {
             homographies.insert(0, Matrix3::identity());
         }
-        return ((0..n).collect(), homographies);
+        return ((0..n).collect(), homographies, vec![]);
     }
 
     let mut edges = Vec::new();
@@ -488,6 +741,7 @@ fn build_stitching_order(
     let mut global_homographies = HashMap::new();
     let mut q = VecDeque::new();
     let mut visited = HashSet::new();
+    let mut tree_edges = Vec::new();
 
     q.push_back((start_node, Matrix3::identity()));
     visited.insert(start_node);
@@ -512,11 +766,12 @@ fn build_stitching_order(
                     };
 
                     let h_v_global = h_u_global * h_vu;
+                    tree_edges.push((u, v));
                     q.push_back((v, h_v_global));
                 }
             }
         }
     }
 
-    (ordered_indices, global_homographies)
+    (ordered_indices, global_homographies, tree_edges)
 }
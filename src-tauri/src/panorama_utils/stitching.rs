@@ -1,12 +1,28 @@
-use crate::panorama_stitching::ImageInfo;
-use image::{GrayImage, Rgb, Rgb32FImage};
+use crate::panorama_stitching::{
+    BLENDING_STAGE_END, ImageInfo, MATCHING_STAGE_END, emit_stage_progress,
+};
+use image::imageops::{self, FilterType};
+use image::{GrayImage, ImageBuffer, Luma, Rgb, Rgb32FImage};
 use nalgebra::{Matrix3, Point3};
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
-use tauri::{AppHandle, Emitter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::AppHandle;
 
 const FEATHER_WIDTH: f64 = 100.0;
+const MULTIBAND_MAX_LEVELS: usize = 6;
+
+type WeightImage = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BlendMode {
+    #[default]
+    Feather,
+    Multiband,
+}
 
 struct SeamContext<'a> {
     pano: &'a Rgb32FImage,
@@ -34,6 +50,8 @@ struct SeamInfo {
 pub fn progressive_seam_stitcher(
     images: &[&ImageInfo],
     global_homographies: &HashMap<usize, Matrix3<f64>>,
+    blend_mode: BlendMode,
+    cancel_token: &AtomicBool,
     app_handle: AppHandle,
 ) -> Rgb32FImage {
     if images.is_empty() {
@@ -105,6 +123,9 @@ pub fn progressive_seam_stitcher(
         });
 
     for (i, &img_to_add_info) in images.iter().skip(1).enumerate() {
+        if cancel_token.load(Ordering::SeqCst) {
+            break;
+        }
         let progress_msg = format!(
             "Stitching image {} of {}: {}",
             i + 2,
@@ -114,7 +135,15 @@ pub fn progressive_seam_stitcher(
                 .unwrap_or_default()
                 .to_string_lossy()
         );
-        let _ = app_handle.emit("panorama-progress", &progress_msg);
+        let stage_progress = MATCHING_STAGE_END
+            + (BLENDING_STAGE_END - MATCHING_STAGE_END) * i as f32 / (images.len() - 1) as f32;
+        emit_stage_progress(
+            &app_handle,
+            "panorama-progress",
+            "warping",
+            stage_progress,
+            progress_msg,
+        );
         println!("  - Progressively stitching '{}'", img_to_add_info.filename);
 
         let h_add = &global_homographies[&img_to_add_info.id];
@@ -173,6 +202,27 @@ pub fn progressive_seam_stitcher(
             println!("    - New image is on the {} side of the seam.", side);
         }
 
+        if blend_mode == BlendMode::Multiband {
+            emit_stage_progress(
+                &app_handle,
+                "panorama-progress",
+                "blending",
+                stage_progress,
+                format!("Multiband blending image {} of {}", i + 2, images.len()),
+            );
+            let seam = use_seam.then_some((&orientation, seam_coords.as_slice()));
+            multiband_blend_into(
+                &mut panorama,
+                &mut panorama_mask,
+                img_to_add,
+                &h_add_inv,
+                (offset_x, offset_y),
+                seam,
+                new_image_is_dominant_side,
+            );
+            continue;
+        }
+
         match orientation {
             SeamOrientation::Vertical => {
                 panorama
@@ -364,6 +414,199 @@ pub fn progressive_seam_stitcher(
     panorama
 }
 
+fn new_image_owns_pixel(
+    orientation: &SeamOrientation,
+    seam_coords: &[i32],
+    new_image_is_dominant_side: bool,
+    x: usize,
+    y: usize,
+) -> bool {
+    let (position, seam) = match orientation {
+        SeamOrientation::Vertical => (x as i32, seam_coords[y]),
+        SeamOrientation::Horizontal => (y as i32, seam_coords[x]),
+    };
+    if new_image_is_dominant_side {
+        position > seam
+    } else {
+        position < seam
+    }
+}
+
+/// Blends the warped image into the panorama with a Laplacian pyramid, using the seam as a
+/// hard split. Low frequencies are mixed over wide transitions and fine detail over narrow
+/// ones, which hides exposure differences without ghosting edges.
+fn multiband_blend_into(
+    panorama: &mut Rgb32FImage,
+    panorama_mask: &mut GrayImage,
+    img_to_add: &Rgb32FImage,
+    h_add_inv: &Matrix3<f64>,
+    (offset_x, offset_y): (f64, f64),
+    seam: Option<(&SeamOrientation, &[i32])>,
+    new_image_is_dominant_side: bool,
+) {
+    let (width, height) = panorama.dimensions();
+    let row_len = width as usize * 3;
+
+    let mut base_pano = panorama.clone();
+    let mut base_add = Rgb32FImage::new(width, height);
+    let mut weights = WeightImage::new(width, height);
+    let mut add_coverage = GrayImage::new(width, height);
+
+    base_pano
+        .par_chunks_mut(row_len)
+        .zip(base_add.par_chunks_mut(row_len))
+        .zip(weights.par_chunks_mut(width as usize))
+        .zip(add_coverage.par_chunks_mut(width as usize))
+        .zip(panorama_mask.par_chunks(width as usize))
+        .enumerate()
+        .for_each(
+            |(y, ((((pano_row, add_row), weight_row), coverage_row), mask_row))| {
+                for x in 0..width as usize {
+                    let target_p = Point3::new(x as f64 - offset_x, y as f64 - offset_y, 1.0);
+                    let source_p = h_add_inv * target_p;
+                    let sx = source_p.x / source_p.z;
+                    let sy = source_p.y / source_p.z;
+                    let is_on_add = sx >= 0.0
+                        && sx < img_to_add.width() as f64
+                        && sy >= 0.0
+                        && sy < img_to_add.height() as f64;
+                    let is_on_pano = mask_row[x] > 0;
+                    let px = x * 3..x * 3 + 3;
+
+                    // Each side is filled with the other's pixels outside its footprint so the
+                    // low-pass levels don't bleed black into the overlap.
+                    if is_on_add {
+                        let color = get_interpolated_pixel(img_to_add, sx, sy);
+                        add_row[px.clone()].copy_from_slice(&color.0);
+                        if !is_on_pano {
+                            pano_row[px].copy_from_slice(&color.0);
+                        }
+                        coverage_row[x] = 255;
+                    } else {
+                        add_row[px.clone()].copy_from_slice(&pano_row[px]);
+                    }
+
+                    weight_row[x] = match (is_on_add, is_on_pano, seam) {
+                        (true, true, Some((orientation, coords))) => {
+                            if new_image_owns_pixel(
+                                orientation,
+                                coords,
+                                new_image_is_dominant_side,
+                                x,
+                                y,
+                            ) {
+                                1.0
+                            } else {
+                                0.0
+                            }
+                        }
+                        (true, _, _) => 1.0,
+                        _ => 0.0,
+                    };
+                }
+            },
+        );
+
+    let levels = ((width.min(height).max(1) as f32).log2() as usize)
+        .saturating_sub(4)
+        .clamp(1, MULTIBAND_MAX_LEVELS);
+    let pano_pyramid = laplacian_pyramid(base_pano, levels);
+    let add_pyramid = laplacian_pyramid(base_add, levels);
+    let weight_pyramid = gaussian_pyramid(weights, levels);
+
+    let mut blended: Vec<Rgb32FImage> = pano_pyramid
+        .into_iter()
+        .zip(add_pyramid)
+        .zip(&weight_pyramid)
+        .map(|((mut pano_level, add_level), weight_level)| {
+            pano_level
+                .par_chunks_mut(3)
+                .zip(add_level.as_raw().par_chunks(3))
+                .zip(weight_level.as_raw().par_iter())
+                .for_each(|((pano_px, add_px), &weight)| {
+                    for (p, a) in pano_px.iter_mut().zip(add_px) {
+                        *p += (a - *p) * weight;
+                    }
+                });
+            pano_level
+        })
+        .collect();
+
+    let mut result = blended.pop().expect("pyramid has at least one level");
+    while let Some(mut level) = blended.pop() {
+        let (w, h) = level.dimensions();
+        let upsampled = imageops::resize(&result, w, h, FilterType::Triangle);
+        level
+            .as_mut()
+            .par_iter_mut()
+            .zip(upsampled.as_raw().par_iter())
+            .for_each(|(l, u)| *l += u);
+        result = level;
+    }
+
+    panorama
+        .par_chunks_mut(row_len)
+        .zip(panorama_mask.par_chunks_mut(width as usize))
+        .zip(result.par_chunks(row_len))
+        .zip(add_coverage.par_chunks(width as usize))
+        .for_each(|(((pano_row, mask_row), result_row), coverage_row)| {
+            for (x, (mask, &coverage)) in mask_row.iter_mut().zip(coverage_row).enumerate() {
+                if coverage == 0 && *mask == 0 {
+                    continue;
+                }
+                let px = x * 3..x * 3 + 3;
+                for (p, r) in pano_row[px.clone()].iter_mut().zip(&result_row[px]) {
+                    *p = r.max(0.0);
+                }
+                if coverage > 0 {
+                    *mask = 255;
+                }
+            }
+        });
+}
+
+fn laplacian_pyramid(image: Rgb32FImage, levels: usize) -> Vec<Rgb32FImage> {
+    let mut pyramid = Vec::with_capacity(levels + 1);
+    let mut current = image;
+    for _ in 0..levels {
+        let (w, h) = current.dimensions();
+        let down = imageops::resize(
+            &current,
+            (w / 2).max(1),
+            (h / 2).max(1),
+            FilterType::Triangle,
+        );
+        let up = imageops::resize(&down, w, h, FilterType::Triangle);
+        current
+            .as_mut()
+            .par_iter_mut()
+            .zip(up.as_raw().par_iter())
+            .for_each(|(c, u)| *c -= u);
+        pyramid.push(current);
+        current = down;
+    }
+    pyramid.push(current);
+    pyramid
+}
+
+fn gaussian_pyramid(image: WeightImage, levels: usize) -> Vec<WeightImage> {
+    let mut pyramid = Vec::with_capacity(levels + 1);
+    let mut current = image;
+    for _ in 0..levels {
+        let (w, h) = current.dimensions();
+        let down = imageops::resize(
+            &current,
+            (w / 2).max(1),
+            (h / 2).max(1),
+            FilterType::Triangle,
+        );
+        pyramid.push(current);
+        current = down;
+    }
+    pyramid.push(current);
+    pyramid
+}
+
 fn find_adaptive_seam(ctx: &SeamContext) -> Option<SeamInfo> {
     let h_add_inv = ctx.h_add.try_inverse().unwrap();
     let (w_add, h_add_img) = ctx.img_to_add.dimensions();
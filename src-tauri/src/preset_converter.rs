@@ -28,7 +28,7 @@ fn num_to_json(num: Num) -> Option<Value> {
     }
 }
 
-fn get_attr_as_f64(attrs: &HashMap<String, String>, key: &str) -> Option<f64> {
+pub fn get_attr_as_f64(attrs: &HashMap<String, String>, key: &str) -> Option<f64> {
     attrs
         .get(key)
         .and_then(|s| s.trim_start_matches('+').parse::<f64>().ok())
@@ -91,7 +91,7 @@ fn extract_tone_curve_points(xmp_str: &str, curve_name: &str) -> Option<Vec<Valu
     }
 }
 
-pub fn convert_xmp_to_preset(xmp_content: &str) -> Result<Preset, String> {
+pub fn parse_crs_attributes(xmp_content: &str) -> Result<HashMap<String, String>, String> {
     let xmp_one_line = xmp_content.split('\n').collect::<Vec<_>>().join(" ");
 
     let attr_re = Regex::new(r#"crs:([A-Za-z0-9]+)="([^"]*)""#)
@@ -100,6 +100,50 @@ pub fn convert_xmp_to_preset(xmp_content: &str) -> Result<Preset, String> {
     for cap in attr_re.captures_iter(&xmp_one_line) {
         attrs.insert(cap[1].to_string(), cap[2].to_string());
     }
+    Ok(attrs)
+}
+
+fn parametric_curve_points(attrs: &HashMap<String, String>) -> Option<Vec<Value>> {
+    let regions = [
+        "ParametricShadows",
+        "ParametricDarks",
+        "ParametricLights",
+        "ParametricHighlights",
+    ];
+    let amounts: Vec<f64> = regions
+        .iter()
+        .map(|key| get_attr_as_f64(attrs, key).unwrap_or(0.0))
+        .collect();
+    if amounts.iter().all(|a| a.abs() < f64::EPSILON) {
+        return None;
+    }
+
+    let shadow_split = get_attr_as_f64(attrs, "ParametricShadowSplit").unwrap_or(25.0);
+    let midtone_split = get_attr_as_f64(attrs, "ParametricMidtoneSplit").unwrap_or(50.0);
+    let highlight_split = get_attr_as_f64(attrs, "ParametricHighlightSplit").unwrap_or(75.0);
+    let centers = [
+        shadow_split / 2.0,
+        (shadow_split + midtone_split) / 2.0,
+        (midtone_split + highlight_split) / 2.0,
+        (highlight_split + 100.0) / 2.0,
+    ];
+
+    // Approximates each parametric region as a single control point at its center.
+    const MAX_REGION_SHIFT: f64 = 32.0;
+    let mut points = vec![json!({ "x": 0, "y": 0 })];
+    for (center, amount) in centers.iter().zip(amounts.iter()) {
+        let x = (center / 100.0 * 255.0).round();
+        let y = (x + amount / 100.0 * MAX_REGION_SHIFT)
+            .round()
+            .clamp(0.0, 255.0);
+        points.push(json!({ "x": x as u32, "y": y as u32 }));
+    }
+    points.push(json!({ "x": 255, "y": 255 }));
+    Some(points)
+}
+
+pub fn convert_xmp_to_adjustments(xmp_content: &str) -> Result<Map<String, Value>, String> {
+    let attrs = parse_crs_attributes(xmp_content)?;
 
     let mut adjustments = Map::new();
     let mut hsl_map = Map::new();
@@ -116,7 +160,7 @@ pub fn convert_xmp_to_preset(xmp_content: &str) -> Result<Preset, String> {
         ("Dehaze", "dehaze"),
         ("Vibrance", "vibrance"),
         ("Saturation", "saturation"),
-        ("Texture", "structure"),
+        ("Texture", "texture"),
         ("SharpenRadius", "sharpenRadius"),
         ("SharpenDetail", "sharpenDetail"),
         ("SharpenEdgeMasking", "sharpenMasking"),
@@ -133,6 +177,10 @@ pub fn convert_xmp_to_preset(xmp_content: &str) -> Result<Preset, String> {
         ("PostCropVignetteMidpoint", "vignetteMidpoint"),
         ("PostCropVignetteFeather", "vignetteFeather"),
         ("PostCropVignetteRoundness", "vignetteRoundness"),
+        (
+            "PostCropVignetteHighlightContrast",
+            "vignetteHighlightPriority",
+        ),
         ("GrainAmount", "grainAmount"),
         ("GrainSize", "grainSize"),
         ("GrainFrequency", "grainRoughness"),
@@ -333,10 +381,20 @@ pub fn convert_xmp_to_preset(xmp_content: &str) -> Result<Preset, String> {
             curves_map.insert(rr_curve.to_string(), Value::Array(points));
         }
     }
+    if !curves_map.contains_key("luma")
+        && let Some(points) = parametric_curve_points(&attrs)
+    {
+        curves_map.insert("luma".to_string(), Value::Array(points));
+    }
     if !curves_map.is_empty() {
         adjustments.insert("curves".to_string(), Value::Object(curves_map));
     }
 
+    Ok(adjustments)
+}
+
+pub fn convert_xmp_to_preset(xmp_content: &str) -> Result<Preset, String> {
+    let adjustments = convert_xmp_to_adjustments(xmp_content)?;
     let preset_name =
         extract_xmp_name(xmp_content).unwrap_or_else(|| "Imported Preset".to_string());
 
@@ -349,3 +407,285 @@ pub fn convert_xmp_to_preset(xmp_content: &str) -> Result<Preset, String> {
         preset_type: Some("style".to_string()),
     })
 }
+
+const MAPPED_CRS_KEYS: &[&str] = &[
+    "Exposure2012",
+    "Contrast2012",
+    "Highlights2012",
+    "Shadows2012",
+    "Whites2012",
+    "Blacks2012",
+    "Clarity2012",
+    "Dehaze",
+    "Vibrance",
+    "Saturation",
+    "Texture",
+    "Sharpness",
+    "SharpenRadius",
+    "SharpenDetail",
+    "SharpenEdgeMasking",
+    "LuminanceSmoothing",
+    "ColorNoiseReduction",
+    "ColorNoiseReductionDetail",
+    "ColorNoiseReductionSmoothness",
+    "ChromaticAberrationRedCyan",
+    "ChromaticAberrationBlueYellow",
+    "PostCropVignetteAmount",
+    "PostCropVignetteMidpoint",
+    "PostCropVignetteFeather",
+    "PostCropVignetteRoundness",
+    "PostCropVignetteHighlightContrast",
+    "GrainAmount",
+    "GrainSize",
+    "GrainFrequency",
+    "Temperature",
+    "AsShotTemperature",
+    "Tint",
+    "SplitToningBalance",
+];
+
+const MAPPED_CRS_PREFIXES: &[&str] = &[
+    "HueAdjustment",
+    "SaturationAdjustment",
+    "LuminanceAdjustment",
+    "SplitToning",
+    "ColorGrade",
+    "Parametric",
+    "ToneCurve",
+];
+
+const IGNORED_CRS_KEYS: &[&str] = &[
+    "Version",
+    "ProcessVersion",
+    "HasSettings",
+    "PresetType",
+    "UUID",
+    "SupportsAmount",
+    "SupportsColor",
+    "SupportsMonochrome",
+    "SupportsHighDynamicRange",
+    "SupportsNormalDynamicRange",
+    "SupportsSceneReferred",
+    "SupportsOutputReferred",
+    "CameraModelRestriction",
+    "Copyright",
+    "ContactInfo",
+    "Name",
+    "ShortName",
+    "SortName",
+    "Group",
+    "Description",
+];
+
+fn log_unmapped_crs_keys(attrs: &HashMap<String, String>) {
+    let mut unmapped: Vec<&str> = attrs
+        .keys()
+        .map(|k| k.as_str())
+        .filter(|k| {
+            !MAPPED_CRS_KEYS.contains(k)
+                && !IGNORED_CRS_KEYS.contains(k)
+                && !MAPPED_CRS_PREFIXES.iter().any(|p| k.starts_with(p))
+        })
+        .collect();
+    if !unmapped.is_empty() {
+        unmapped.sort_unstable();
+        log::info!(
+            "Skipping {} unsupported preset settings: {}",
+            unmapped.len(),
+            unmapped.join(", ")
+        );
+    }
+}
+
+fn extract_balanced_block(content: &str, open_idx: usize) -> Option<&str> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (offset, ch) in content[open_idx..].char_indices() {
+        if in_string {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match ch {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&content[open_idx + 1..open_idx + offset]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_lua_table_entries(body: &str) -> Vec<(String, String)> {
+    let bytes = body.as_bytes();
+    let mut entries = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        while i < bytes.len() && (bytes[i].is_ascii_whitespace() || bytes[i] == b',') {
+            i += 1;
+        }
+        let key_start = i;
+        while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+            i += 1;
+        }
+        let key = &body[key_start..i];
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if key.is_empty() || i >= bytes.len() || bytes[i] != b'=' {
+            // Positional entries and anything unexpected are skipped up to the next separator.
+            while i < bytes.len() && bytes[i] != b',' {
+                if bytes[i] == b'{' {
+                    i += extract_balanced_block(body, i).map_or(1, |b| b.len() + 2);
+                } else {
+                    i += 1;
+                }
+            }
+            continue;
+        }
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+
+        let value_start = i;
+        if i < bytes.len() && bytes[i] == b'{' {
+            match extract_balanced_block(body, i) {
+                Some(block) => i += block.len() + 2,
+                None => break,
+            }
+        } else if i < bytes.len() && bytes[i] == b'"' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != b'"' {
+                if bytes[i] == b'\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(bytes.len());
+        } else {
+            while i < bytes.len() && bytes[i] != b',' && bytes[i] != b'\n' {
+                i += 1;
+            }
+        }
+        entries.push((key.to_string(), body[value_start..i].trim().to_string()));
+    }
+
+    entries
+}
+
+fn escape_xml_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unquote_lua_string(value: &str) -> Option<String> {
+    let value = value.strip_prefix("ZSTR").map_or(value, |v| v.trim());
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .map(|v| v.replace("\\\"", "\"").replace("\\n", "\n"))
+}
+
+/// Converts a legacy Lightroom `.lrtemplate` Lua table into an equivalent crs: XMP packet.
+pub fn convert_lrtemplate_to_xmp(content: &str) -> Option<String> {
+    let settings_re = Regex::new(r"settings\s*=\s*\{").ok()?;
+    let settings_match = settings_re.find(content)?;
+    let settings_body = extract_balanced_block(content, settings_match.end() - 1)?;
+
+    let name = Regex::new(r#"title\s*=\s*(?:ZSTR\s*)?"((?:[^"\\]|\\.)*)""#)
+        .ok()?
+        .captures(content)
+        .or_else(|| {
+            Regex::new(r#"internalName\s*=\s*"((?:[^"\\]|\\.)*)""#)
+                .ok()?
+                .captures(content)
+        })
+        .and_then(|c| c.get(1).map(|m| m.as_str().to_string()));
+
+    let number_re = Regex::new(r"-?\d+(?:\.\d+)?").ok()?;
+    let mut attributes = String::new();
+    let mut elements = String::new();
+
+    for (key, value) in parse_lua_table_entries(settings_body) {
+        if value.starts_with('{') {
+            if !key.starts_with("ToneCurve") {
+                log::info!("Skipping unsupported table setting '{}' in lrtemplate", key);
+                continue;
+            }
+            let numbers: Vec<&str> = number_re.find_iter(&value).map(|m| m.as_str()).collect();
+            if numbers.len() < 4 || numbers.len() % 2 != 0 {
+                continue;
+            }
+            elements.push_str(&format!("   <crs:{}>\n    <rdf:Seq>\n", key));
+            for pair in numbers.chunks(2) {
+                elements.push_str(&format!("     <rdf:li>{}, {}</rdf:li>\n", pair[0], pair[1]));
+            }
+            elements.push_str(&format!("    </rdf:Seq>\n   </crs:{}>\n", key));
+        } else if let Some(text) = unquote_lua_string(&value) {
+            attributes.push_str(&format!("\n    crs:{}=\"{}\"", key, escape_xml_attr(&text)));
+        } else {
+            let normalized = match value.as_str() {
+                "true" => "True".to_string(),
+                "false" => "False".to_string(),
+                other => other.to_string(),
+            };
+            attributes.push_str(&format!(
+                "\n    crs:{}=\"{}\"",
+                key,
+                escape_xml_attr(&normalized)
+            ));
+        }
+    }
+
+    if let Some(name) = name {
+        elements.push_str(&format!(
+            "   <crs:Name>\n    <rdf:Alt>\n     <rdf:li xml:lang=\"x-default\">{}</rdf:li>\n    </rdf:Alt>\n   </crs:Name>\n",
+            escape_xml_attr(&name)
+        ));
+    }
+
+    Some(format!(
+        r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:crs="http://ns.adobe.com/camera-raw-settings/1.0/"{}>
+{}  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#,
+        attributes, elements
+    ))
+}
+
+pub fn convert_external_preset(content: &str, is_lrtemplate: bool) -> Result<Preset, String> {
+    let xmp_content = if is_lrtemplate {
+        let embedded_xmp = Regex::new(r#"(?s)s.xmp = "(.*)""#)
+            .ok()
+            .and_then(|re| re.captures(content))
+            .and_then(|caps| caps.get(1).map(|m| m.as_str().replace(r#"\""#, r#"""#)));
+        match embedded_xmp {
+            Some(xmp) => xmp,
+            None => convert_lrtemplate_to_xmp(content)
+                .ok_or_else(|| "Could not find develop settings in lrtemplate".to_string())?,
+        }
+    } else {
+        content.to_string()
+    };
+
+    log_unmapped_crs_keys(&parse_crs_attributes(&xmp_content)?);
+    convert_xmp_to_preset(&xmp_content)
+}
@@ -0,0 +1,336 @@
+use std::fs;
+use std::io::Cursor;
+use std::sync::atomic::Ordering;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, GenericImageView};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::app_settings::load_settings;
+use crate::app_state::AppState;
+use crate::export_processing::render_path_from_sidecar;
+use crate::file_management::parse_virtual_path;
+use crate::image_processing::get_gpu_context_or_fallback;
+
+const MM_TO_PT: f32 = 72.0 / 25.4;
+const MM_PER_INCH: f32 = 25.4;
+const CAPTION_FONT_PT: f32 = 8.0;
+const CAPTION_GAP_MM: f32 = 1.5;
+// Rough average glyph width of Helvetica, used to centre captions without font metrics.
+const CAPTION_CHAR_WIDTH_EM: f32 = 0.5;
+const PRINT_JPEG_QUALITY: u8 = 92;
+const MAX_PRINT_DPI: u32 = 1200;
+const PRINT_CANCELLED: &str = "Print cancelled.";
+
+fn default_page_width_mm() -> f32 {
+    210.0
+}
+
+fn default_page_height_mm() -> f32 {
+    297.0
+}
+
+fn default_dpi() -> u32 {
+    300
+}
+
+fn default_margin_mm() -> f32 {
+    10.0
+}
+
+fn default_spacing_mm() -> f32 {
+    5.0
+}
+
+fn default_grid() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PrintLayout {
+    #[serde(default = "default_page_width_mm")]
+    pub page_width_mm: f32,
+    #[serde(default = "default_page_height_mm")]
+    pub page_height_mm: f32,
+    #[serde(default = "default_dpi")]
+    pub dpi: u32,
+    #[serde(default = "default_margin_mm")]
+    pub margin_mm: f32,
+    #[serde(default = "default_spacing_mm")]
+    pub spacing_mm: f32,
+    #[serde(default = "default_grid")]
+    pub columns: u32,
+    #[serde(default = "default_grid")]
+    pub rows: u32,
+    #[serde(default)]
+    pub captions: bool,
+    pub output_path: String,
+}
+
+#[derive(Serialize, Clone)]
+struct PrintProgress {
+    current: usize,
+    total: usize,
+}
+
+/// Page geometry in PDF points, with the origin at the bottom left of the page.
+struct PageGrid {
+    page_w: f32,
+    page_h: f32,
+    margin: f32,
+    spacing: f32,
+    cell_w: f32,
+    cell_h: f32,
+    image_area_h: f32,
+    columns: u32,
+    rows: u32,
+}
+
+impl PageGrid {
+    fn new(layout: &PrintLayout) -> Result<Self, String> {
+        let columns = layout.columns.max(1);
+        let rows = layout.rows.max(1);
+        let caption_h_mm = if layout.captions {
+            CAPTION_FONT_PT / MM_TO_PT + CAPTION_GAP_MM
+        } else {
+            0.0
+        };
+        let cell_w_mm = (layout.page_width_mm
+            - 2.0 * layout.margin_mm
+            - (columns - 1) as f32 * layout.spacing_mm)
+            / columns as f32;
+        let cell_h_mm = (layout.page_height_mm
+            - 2.0 * layout.margin_mm
+            - (rows - 1) as f32 * layout.spacing_mm)
+            / rows as f32;
+        if cell_w_mm <= 0.0 || cell_h_mm - caption_h_mm <= 0.0 {
+            return Err("Margins and spacing leave no room for images on the page.".to_string());
+        }
+
+        Ok(Self {
+            page_w: layout.page_width_mm * MM_TO_PT,
+            page_h: layout.page_height_mm * MM_TO_PT,
+            margin: layout.margin_mm * MM_TO_PT,
+            spacing: layout.spacing_mm * MM_TO_PT,
+            cell_w: cell_w_mm * MM_TO_PT,
+            cell_h: cell_h_mm * MM_TO_PT,
+            image_area_h: (cell_h_mm - caption_h_mm) * MM_TO_PT,
+            columns,
+            rows,
+        })
+    }
+
+    fn cells_per_page(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    /// Pixel size of a cell's image area at the given DPI.
+    fn target_pixels(&self, dpi: u32) -> (u32, u32) {
+        let to_px = |pt: f32| ((pt / MM_TO_PT / MM_PER_INCH * dpi as f32).round() as u32).max(1);
+        (to_px(self.cell_w), to_px(self.image_area_h))
+    }
+
+    /// Bottom-left corner of the cell at `slot`, in points.
+    fn cell_origin(&self, slot: usize) -> (f32, f32) {
+        let column = (slot as u32 % self.columns) as f32;
+        let row = (slot as u32 / self.columns) as f32;
+        let x = self.margin + column * (self.cell_w + self.spacing);
+        let top = self.page_h - self.margin - row * (self.cell_h + self.spacing);
+        (x, top - self.cell_h)
+    }
+}
+
+/// Maps a caption onto WinAnsiEncoding for the built-in Helvetica font. Characters outside
+/// Latin-1 have no glyph there and are replaced.
+fn win_ansi_bytes(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+fn encode_print_jpeg(image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let mut jpeg = Cursor::new(Vec::new());
+    image
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, PRINT_JPEG_QUALITY))
+        .map_err(|e| e.to_string())?;
+    Ok(jpeg.into_inner())
+}
+
+fn check_print_cancelled(state: &AppState) -> Result<(), String> {
+    if state.print_cancellation_token.load(Ordering::SeqCst) {
+        Err(PRINT_CANCELLED.to_string())
+    } else {
+        Ok(())
+    }
+}
+
+#[tauri::command]
+pub fn cancel_print(state: tauri::State<AppState>) -> Result<(), String> {
+    state.print_cancellation_token.store(true, Ordering::SeqCst);
+    println!("Print layout cancellation requested.");
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn print_images(
+    paths: Vec<String>,
+    layout: PrintLayout,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    if paths.is_empty() {
+        return Err("No images selected for printing.".to_string());
+    }
+    let grid = PageGrid::new(&layout)?;
+    let dpi = layout.dpi.clamp(72, MAX_PRINT_DPI);
+
+    app_handle
+        .state::<AppState>()
+        .print_cancellation_token
+        .store(false, Ordering::SeqCst);
+
+    tokio::task::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
+        let context = get_gpu_context_or_fallback(&state, &app_handle);
+        let target = grid.target_pixels(dpi);
+        let total = paths.len();
+
+        let mut alloc = Ref::new(1);
+        let catalog_id = alloc.bump();
+        let pages_id = alloc.bump();
+        let font_id = alloc.bump();
+        let mut pdf = Pdf::new();
+        let mut page_ids = Vec::new();
+        let mut rendered_count = 0;
+
+        for (page_index, page_paths) in paths.chunks(grid.cells_per_page()).enumerate() {
+            let mut content = Content::new();
+            let mut xobjects: Vec<(String, Ref)> = Vec::new();
+
+            for (slot, path) in page_paths.iter().enumerate() {
+                check_print_cancelled(&state)?;
+                let index = page_index * grid.cells_per_page() + slot;
+                let rendered = render_path_from_sidecar(
+                    path,
+                    context.as_ref(),
+                    &settings,
+                    &state,
+                    &app_handle,
+                    "print_images",
+                    Some(target),
+                )
+                .and_then(|image| encode_print_jpeg(&image).map(|jpeg| (image.dimensions(), jpeg)));
+                let _ = app_handle.emit(
+                    "print-progress",
+                    PrintProgress {
+                        current: index + 1,
+                        total,
+                    },
+                );
+                let ((pixel_w, pixel_h), jpeg) = match rendered {
+                    Ok(rendered) => rendered,
+                    Err(e) => {
+                        log::warn!("Print layout skipped '{}': {}", path, e);
+                        continue;
+                    }
+                };
+                rendered_count += 1;
+
+                let image_id = alloc.bump();
+                let mut xobject = pdf.image_xobject(image_id, &jpeg);
+                xobject.filter(Filter::DctDecode);
+                xobject.width(pixel_w as i32);
+                xobject.height(pixel_h as i32);
+                xobject.color_space().device_rgb();
+                xobject.bits_per_component(8);
+                xobject.finish();
+                let image_name = format!("Im{}", image_id.get());
+
+                let (cell_x, cell_y) = grid.cell_origin(slot);
+                let scale = (grid.cell_w / pixel_w as f32).min(grid.image_area_h / pixel_h as f32);
+                let draw_w = pixel_w as f32 * scale;
+                let draw_h = pixel_h as f32 * scale;
+                let area_y = cell_y + grid.cell_h - grid.image_area_h;
+                let x = cell_x + (grid.cell_w - draw_w) / 2.0;
+                let y = area_y + (grid.image_area_h - draw_h) / 2.0;
+                content.save_state();
+                content.transform([draw_w, 0.0, 0.0, draw_h, x, y]);
+                content.x_object(Name(image_name.as_bytes()));
+                content.restore_state();
+                xobjects.push((image_name, image_id));
+
+                if layout.captions {
+                    let caption = parse_virtual_path(path)
+                        .0
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    let text_w =
+                        caption.chars().count() as f32 * CAPTION_FONT_PT * CAPTION_CHAR_WIDTH_EM;
+                    let text_x = cell_x + ((grid.cell_w - text_w) / 2.0).max(0.0);
+                    let text_y = area_y - CAPTION_GAP_MM * MM_TO_PT - CAPTION_FONT_PT * 0.8;
+                    content.begin_text();
+                    content.set_font(Name(b"F1"), CAPTION_FONT_PT);
+                    content.next_line(text_x, text_y);
+                    content.show(Str(&win_ansi_bytes(&caption)));
+                    content.end_text();
+                }
+            }
+
+            if xobjects.is_empty() {
+                continue;
+            }
+
+            let content_id = alloc.bump();
+            pdf.stream(content_id, &content.finish());
+            let page_id = alloc.bump();
+            let mut page = pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, grid.page_w, grid.page_h));
+            page.parent(pages_id);
+            page.contents(content_id);
+            let mut resources = page.resources();
+            resources.fonts().pair(Name(b"F1"), font_id);
+            resources.x_objects().pairs(
+                xobjects
+                    .iter()
+                    .map(|(name, id)| (Name(name.as_bytes()), *id)),
+            );
+            resources.finish();
+            page.finish();
+            page_ids.push(page_id);
+        }
+
+        if rendered_count == 0 {
+            return Err("None of the selected images could be rendered for printing.".to_string());
+        }
+        check_print_cancelled(&state)?;
+
+        pdf.type1_font(font_id)
+            .base_font(Name(b"Helvetica"))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+        pdf.pages(pages_id)
+            .kids(page_ids.iter().copied())
+            .count(page_ids.len() as i32);
+        pdf.catalog(catalog_id).pages(pages_id);
+
+        fs::write(&layout.output_path, pdf.finish())
+            .map_err(|e| format!("Failed to write PDF: {}", e))?;
+        log::info!(
+            "Wrote {} page print layout with {} of {} images to {}",
+            page_ids.len(),
+            rendered_count,
+            total,
+            layout.output_path
+        );
+        Ok(layout.output_path.clone())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
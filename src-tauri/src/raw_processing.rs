@@ -1,32 +1,242 @@
+use crate::app_settings::DemosaicQuality;
 use crate::image_processing::apply_orientation;
 use anyhow::{Result, anyhow};
 use image::{DynamicImage, ImageBuffer, Rgba};
 use rawler::{
     decoders::{Orientation, RawDecodeParams},
     imgop::develop::{DemosaicAlgorithm, Intermediate, ProcessingStep, RawDevelop},
-    rawimage::{RawImage, RawPhotometricInterpretation},
+    imgop::xyz::Illuminant,
+    imgop::{Dimension, Point, Rect},
+    rawimage::{RawImage, RawImageData, RawPhotometricInterpretation},
     rawsource::RawSource,
 };
+use rayon::prelude::*;
+use serde::Serialize;
 use std::sync::{
-    Arc,
+    Arc, Mutex,
     atomic::{AtomicUsize, Ordering},
 };
+use std::time::SystemTime;
+
+const MIN_FLAT_GAIN: f32 = 0.05;
+/// Fraction of the black-to-white range above which a photosite counts as saturated.
+const CLIPPED_SENSOR_THRESHOLD: f32 = 0.98;
+
+/// Master dark and normalized flat planes in raw sensor layout, with the black level removed.
+pub struct CalibrationFrames {
+    pub width: usize,
+    pub height: usize,
+    dark: Option<Vec<f32>>,
+    flat: Option<Vec<f32>>,
+}
+
+/// A frame path and its modification time, so a master re-exported in place is reloaded.
+type FrameKey = (String, Option<SystemTime>);
+type CalibrationKey = (Option<FrameKey>, Option<FrameKey>);
+
+static CALIBRATION_CACHE: Mutex<Option<(CalibrationKey, Arc<CalibrationFrames>)>> =
+    Mutex::new(None);
+
+fn first_black_level(raw_image: &RawImage) -> f32 {
+    raw_image
+        .blacklevel
+        .levels
+        .first()
+        .map(|r| r.as_f32())
+        .unwrap_or(0.0)
+}
+
+fn decode_sensor_plane(path: &str) -> Result<(usize, usize, Vec<f32>)> {
+    let bytes = std::fs::read(path).map_err(|e| anyhow!("Failed to read '{}': {}", path, e))?;
+    let source = RawSource::new_from_slice(&bytes);
+    let decoder = rawler::get_decoder(&source)?;
+    let raw_image = decoder.raw_image(&source, &RawDecodeParams::default(), false)?;
+    if raw_image.cpp != 1 || is_linear_raw_format(&raw_image) {
+        return Err(anyhow!(
+            "'{}' is not a mosaiced raw file and cannot be used as a calibration frame",
+            path
+        ));
+    }
+
+    let black = first_black_level(&raw_image);
+    let plane = match &raw_image.data {
+        RawImageData::Integer(data) => data.iter().map(|&v| (v as f32 - black).max(0.0)).collect(),
+        RawImageData::Float(data) => data.iter().map(|&v| (v - black).max(0.0)).collect(),
+    };
+    Ok((raw_image.width, raw_image.height, plane))
+}
+
+/// Scales the flat so each 2x2 CFA site averages to 1.0, keeping colour balance intact.
+fn normalize_flat(plane: &mut [f32], width: usize) {
+    let site = |i: usize| ((i / width) % 2) * 2 + (i % width) % 2;
+    let mut sums = [0.0f64; 4];
+    let mut counts = [0u64; 4];
+    for (i, v) in plane.iter().enumerate() {
+        sums[site(i)] += *v as f64;
+        counts[site(i)] += 1;
+    }
+    let means: [f32; 4] =
+        std::array::from_fn(|s| (sums[s] / counts[s].max(1) as f64).max(f64::EPSILON) as f32);
+    for (i, v) in plane.iter_mut().enumerate() {
+        *v = (*v / means[site(i)]).max(MIN_FLAT_GAIN);
+    }
+}
+
+pub fn load_calibration_frames(
+    dark_path: Option<&str>,
+    flat_path: Option<&str>,
+) -> Result<CalibrationFrames> {
+    let dark = dark_path.map(decode_sensor_plane).transpose()?;
+    let flat = flat_path.map(decode_sensor_plane).transpose()?;
+
+    let (width, height) = match (&dark, &flat) {
+        (Some((dw, dh, _)), Some((fw, fh, _))) if (dw, dh) != (fw, fh) => {
+            return Err(anyhow!(
+                "Dark frame ({}x{}) and flat field ({}x{}) have different dimensions",
+                dw,
+                dh,
+                fw,
+                fh
+            ));
+        }
+        (Some((w, h, _)), _) | (None, Some((w, h, _))) => (*w, *h),
+        (None, None) => return Err(anyhow!("No calibration frames were provided")),
+    };
+
+    let flat = flat.map(|(w, _, mut plane)| {
+        normalize_flat(&mut plane, w);
+        plane
+    });
+
+    Ok(CalibrationFrames {
+        width,
+        height,
+        dark: dark.map(|(_, _, plane)| plane),
+        flat,
+    })
+}
+
+fn frame_key(path: Option<&str>) -> Option<FrameKey> {
+    path.map(|p| {
+        let modified = std::fs::metadata(p).and_then(|m| m.modified()).ok();
+        (p.to_string(), modified)
+    })
+}
+
+/// Identifies the configured frames and their file versions, for keying caches of
+/// calibrated images.
+pub fn calibration_cache_tag(dark_path: Option<&str>, flat_path: Option<&str>) -> String {
+    let describe = |frame: Option<FrameKey>| match frame {
+        Some((path, modified)) => {
+            let nanos = modified
+                .and_then(|m| m.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_nanos());
+            format!("{}@{}", path, nanos)
+        }
+        None => String::new(),
+    };
+    format!(
+        "dark={};flat={}",
+        describe(frame_key(dark_path)),
+        describe(frame_key(flat_path))
+    )
+}
 
+/// Returns the calibration frames for the given paths, decoding them only when the paths or
+/// the files change.
+pub fn cached_calibration_frames(
+    dark_path: Option<&str>,
+    flat_path: Option<&str>,
+) -> Result<Option<Arc<CalibrationFrames>>> {
+    if dark_path.is_none() && flat_path.is_none() {
+        return Ok(None);
+    }
+    let key = (frame_key(dark_path), frame_key(flat_path));
+    let mut cache = CALIBRATION_CACHE.lock().unwrap();
+    if let Some((cached_key, frames)) = cache.as_ref()
+        && *cached_key == key
+    {
+        return Ok(Some(frames.clone()));
+    }
+    let frames = Arc::new(load_calibration_frames(dark_path, flat_path)?);
+    *cache = Some((key, frames.clone()));
+    Ok(Some(frames))
+}
+
+pub fn clear_calibration_cache() {
+    *CALIBRATION_CACHE.lock().unwrap() = None;
+}
+
+fn apply_calibration(raw_image: &mut RawImage, frames: &CalibrationFrames) -> Result<()> {
+    if raw_image.cpp != 1 || is_linear_raw_format(raw_image) {
+        return Err(anyhow!(
+            "Calibration frames can only be applied to mosaiced raw files"
+        ));
+    }
+    if (raw_image.width, raw_image.height) != (frames.width, frames.height) {
+        return Err(anyhow!(
+            "Calibration frames are {}x{} but the image is {}x{}",
+            frames.width,
+            frames.height,
+            raw_image.width,
+            raw_image.height
+        ));
+    }
+
+    let black = first_black_level(raw_image);
+    let calibrate = |i: usize, value: f32| -> f32 {
+        let mut signal = value - black;
+        if let Some(dark) = &frames.dark {
+            signal -= dark[i];
+        }
+        if let Some(flat) = &frames.flat {
+            signal /= flat[i];
+        }
+        black + signal.max(0.0)
+    };
+
+    match &mut raw_image.data {
+        RawImageData::Integer(data) => {
+            for (i, v) in data.iter_mut().enumerate() {
+                *v = calibrate(i, *v as f32).round().min(u16::MAX as f32) as u16;
+            }
+        }
+        RawImageData::Float(data) => {
+            for (i, v) in data.iter_mut().enumerate() {
+                *v = calibrate(i, *v);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn develop_raw_image(
     file_bytes: &[u8],
     fast_demosaic: bool,
     highlight_compression: f32,
     linear_mode: String,
+    demosaic_quality: DemosaicQuality,
+    half_res: bool,
+    wb_override: Option<[f32; 4]>,
+    calibration: Option<Arc<CalibrationFrames>>,
     cancel_token: Option<(Arc<AtomicUsize>, usize)>,
-) -> Result<DynamicImage> {
-    let (developed_image, orientation) = develop_internal(
+) -> Result<(DynamicImage, f32)> {
+    let (developed_image, orientation, clipped_percentage) = develop_internal(
         file_bytes,
         fast_demosaic,
         highlight_compression,
         linear_mode,
+        demosaic_quality,
+        half_res,
+        wb_override,
+        calibration,
         cancel_token,
     )?;
-    Ok(apply_orientation(developed_image, orientation))
+    Ok((
+        apply_orientation(developed_image, orientation),
+        clipped_percentage,
+    ))
 }
 
 fn is_linear_raw_format(raw_image: &RawImage) -> bool {
@@ -45,13 +255,118 @@ fn srgb_to_linear(value: f32) -> f32 {
     }
 }
 
+/// Percentage of sensor samples at or near the white level, measured on the undeveloped data.
+fn clipped_sensor_percentage(raw_image: &RawImage, white_level: f32, black_level: f32) -> f32 {
+    let threshold = black_level + (white_level - black_level) * CLIPPED_SENSOR_THRESHOLD;
+    let (clipped, total) = match &raw_image.data {
+        RawImageData::Integer(data) => (
+            data.par_iter().filter(|&&v| v as f32 >= threshold).count(),
+            data.len(),
+        ),
+        RawImageData::Float(data) => (
+            data.par_iter().filter(|&&v| v >= threshold).count(),
+            data.len(),
+        ),
+    };
+    if total == 0 {
+        0.0
+    } else {
+        clipped as f32 / total as f32 * 100.0
+    }
+}
+
+/// Position in a mosaic binned by `bin_bayer_mosaic` that an input column or row maps to.
+/// Each 4x4 block becomes a 2x2 block with the same CFA phase.
+fn binned_mosaic_coord(coord: usize, binned_len: usize) -> usize {
+    (2 * (coord / 4) + coord % 2).min(binned_len)
+}
+
+fn binned_mosaic_rect(rect: &Rect, binned_width: usize, binned_height: usize) -> Rect {
+    let x = binned_mosaic_coord(rect.p.x, binned_width);
+    let y = binned_mosaic_coord(rect.p.y, binned_height);
+    let end_x = binned_mosaic_coord(rect.p.x + rect.d.w, binned_width);
+    let end_y = binned_mosaic_coord(rect.p.y + rect.d.h, binned_height);
+    Rect::new(
+        Point::new(x, y),
+        Dimension::new(end_x.saturating_sub(x), end_y.saturating_sub(y)),
+    )
+}
+
+/// Halves a Bayer mosaic before demosaicing by averaging the four same-colour sites in
+/// every 4x4 block, so the demosaic only runs on a quarter of the photosites while the CFA
+/// layout stays intact. Returns `false`, leaving the image untouched, for sensors without
+/// a 2x2 pattern such as X-Trans.
+fn bin_bayer_mosaic(raw_image: &mut RawImage) -> bool {
+    let is_bayer = matches!(
+        &raw_image.photometric,
+        RawPhotometricInterpretation::Cfa(config) if config.cfa.width == 2 && config.cfa.height == 2
+    );
+    if !is_bayer || raw_image.cpp != 1 || raw_image.width < 4 || raw_image.height < 4 {
+        return false;
+    }
+
+    let (width, height) = (raw_image.width, raw_image.height);
+    let (binned_width, binned_height) = ((width / 4) * 2, (height / 4) * 2);
+    let source_index = |x: usize, y: usize, dx: usize, dy: usize| {
+        let sx = 4 * (x / 2) + x % 2 + 2 * dx;
+        let sy = 4 * (y / 2) + y % 2 + 2 * dy;
+        sy * width + sx
+    };
+
+    match &mut raw_image.data {
+        RawImageData::Integer(data) => {
+            let binned: Vec<u16> = (0..binned_width * binned_height)
+                .into_par_iter()
+                .map(|i| {
+                    let (x, y) = (i % binned_width, i / binned_width);
+                    let sum: u32 = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                        .iter()
+                        .map(|&(dx, dy)| data[source_index(x, y, dx, dy)] as u32)
+                        .sum();
+                    ((sum + 2) / 4) as u16
+                })
+                .collect();
+            *data = binned;
+        }
+        RawImageData::Float(data) => {
+            let binned: Vec<f32> = (0..binned_width * binned_height)
+                .into_par_iter()
+                .map(|i| {
+                    let (x, y) = (i % binned_width, i / binned_width);
+                    [(0, 0), (1, 0), (0, 1), (1, 1)]
+                        .iter()
+                        .map(|&(dx, dy)| data[source_index(x, y, dx, dy)])
+                        .sum::<f32>()
+                        / 4.0
+                })
+                .collect();
+            *data = binned;
+        }
+    }
+
+    raw_image.width = binned_width;
+    raw_image.height = binned_height;
+    raw_image.active_area = raw_image
+        .active_area
+        .map(|rect| binned_mosaic_rect(&rect, binned_width, binned_height));
+    raw_image.crop_area = raw_image
+        .crop_area
+        .map(|rect| binned_mosaic_rect(&rect, binned_width, binned_height));
+    true
+}
+
+#[allow(clippy::too_many_arguments)]
 fn develop_internal(
     file_bytes: &[u8],
     fast_demosaic: bool,
     highlight_compression: f32,
     linear_mode: String,
+    demosaic_quality: DemosaicQuality,
+    half_res: bool,
+    wb_override: Option<[f32; 4]>,
+    calibration: Option<Arc<CalibrationFrames>>,
     cancel_token: Option<(Arc<AtomicUsize>, usize)>,
-) -> Result<(DynamicImage, Orientation)> {
+) -> Result<(DynamicImage, Orientation, f32)> {
     let check_cancel = || -> Result<()> {
         if let Some((tracker, generation)) = &cancel_token
             && tracker.load(Ordering::SeqCst) != *generation
@@ -68,6 +383,13 @@ fn develop_internal(
 
     check_cancel()?;
     let mut raw_image: RawImage = decoder.raw_image(&source, &RawDecodeParams::default(), false)?;
+    if let Some(frames) = &calibration {
+        // Frames from another camera or a non-mosaiced file must not break the develop;
+        // the image is simply left uncalibrated.
+        if let Err(e) = apply_calibration(&mut raw_image, frames) {
+            log::info!("Skipping calibration frames: {}", e);
+        }
+    }
 
     let metadata = decoder.raw_metadata(&source, &RawDecodeParams::default())?;
     let orientation = metadata
@@ -91,12 +413,9 @@ fn develop_internal(
         .first()
         .cloned()
         .unwrap_or(u16::MAX as u32) as f32;
-    let original_black_level = raw_image
-        .blacklevel
-        .levels
-        .first()
-        .map(|r| r.as_f32())
-        .unwrap_or(0.0);
+    let original_black_level = first_black_level(&raw_image);
+    let clipped_percentage =
+        clipped_sensor_percentage(&raw_image, original_white_level, original_black_level);
 
     for level in raw_image.whitelevel.0.iter_mut() {
         *level = u32::MAX;
@@ -114,11 +433,22 @@ fn develop_internal(
         developer.demosaic_algorithm = DemosaicAlgorithm::Speed;
         developer.steps.retain(|&step| step != ProcessingStep::SRgb);
     } else {
+        developer.demosaic_algorithm = match demosaic_quality {
+            DemosaicQuality::Fast => DemosaicAlgorithm::Speed,
+            DemosaicQuality::High => DemosaicAlgorithm::Quality,
+        };
         developer.steps.retain(|&step| step != ProcessingStep::SRgb);
     }
 
     raw_image.wb_coeffs =
         crate::multi_exposure::neutralize_wb_if_multiexposure(raw_image.wb_coeffs, file_bytes);
+    if let Some(wb) = wb_override {
+        raw_image.wb_coeffs = wb;
+    }
+
+    if half_res && !is_linear_format && !bin_bayer_mosaic(&mut raw_image) {
+        log::debug!("Half resolution decode needs a 2x2 CFA, developing at full size");
+    }
 
     check_cancel()?;
     let mut developed_intermediate = developer.develop_intermediate(&raw_image)?;
@@ -230,7 +560,7 @@ fn develop_internal(
         }
     };
 
-    Ok((dynamic_image, orientation))
+    Ok((dynamic_image, orientation, clipped_percentage))
 }
 
 pub fn get_fast_demosaic_scale_factor(
@@ -242,16 +572,203 @@ pub fn get_fast_demosaic_scale_factor(
     if let Ok(decoder) = rawler::get_decoder(&source)
         && let Ok(raw_img) = decoder.raw_image(&source, &RawDecodeParams::default(), true)
     {
-        let max_orig = (raw_img.width as f32).max(raw_img.height as f32);
-        let max_comp = (decoded_width as f32).max(decoded_height as f32);
-        if max_orig > 0.0 {
-            let ratio = max_comp / max_orig;
-            if ratio > 0.1 && ratio < 0.35 {
-                return 0.25;
-            } else if (0.35..0.75).contains(&ratio) {
-                return 0.5;
-            }
+        return reduced_decode_scale(
+            raw_img.width as u32,
+            raw_img.height as u32,
+            decoded_width,
+            decoded_height,
+        );
+    }
+    1.0
+}
+
+fn reduced_decode_scale(
+    raw_width: u32,
+    raw_height: u32,
+    decoded_width: u32,
+    decoded_height: u32,
+) -> f32 {
+    let max_orig = (raw_width as f32).max(raw_height as f32);
+    let max_comp = (decoded_width as f32).max(decoded_height as f32);
+    if max_orig > 0.0 {
+        let ratio = max_comp / max_orig;
+        if ratio > 0.1 && ratio < 0.35 {
+            return 0.25;
+        } else if (0.35..0.75).contains(&ratio) {
+            return 0.5;
         }
     }
     1.0
 }
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WhiteBalancePreset {
+    pub name: String,
+    pub temperature: u32,
+    pub multipliers: [f32; 4],
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RawWhiteBalance {
+    pub as_shot: [f32; 4],
+    pub presets: Vec<WhiteBalancePreset>,
+}
+
+const WB_PRESETS: &[(&str, u32)] = &[
+    ("Daylight", 5500),
+    ("Cloudy", 6500),
+    ("Shade", 7500),
+    ("Tungsten", 2850),
+    ("Fluorescent", 3800),
+    ("Flash", 5900),
+];
+
+fn normalize_to_green(wb: [f32; 4]) -> [f32; 4] {
+    let green = if wb[1] > 0.0 { wb[1] } else { 1.0 };
+    let fourth = if wb[3].is_finite() && wb[3] > 0.0 {
+        wb[3]
+    } else {
+        wb[1]
+    };
+    [wb[0] / green, 1.0, wb[2] / green, fourth / green]
+}
+
+// Kim et al. cubic approximation of the Planckian locus, valid for 1667K..25000K.
+fn planckian_xy(temperature: f64) -> (f64, f64) {
+    let t = temperature.clamp(1667.0, 25000.0);
+    let x = if t <= 4000.0 {
+        -0.2661239e9 / t.powi(3) - 0.2343589e6 / t.powi(2) + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t.powi(3) + 2.1070379e6 / t.powi(2) + 0.2226347e3 / t + 0.240390
+    };
+    let y = if t <= 2222.0 {
+        -1.1063814 * x.powi(3) - 1.34811020 * x.powi(2) + 2.18555832 * x - 0.20219683
+    } else if t <= 4000.0 {
+        -0.9549476 * x.powi(3) - 1.37418593 * x.powi(2) + 2.09137015 * x - 0.16748867
+    } else {
+        3.0817580 * x.powi(3) - 5.87338670 * x.powi(2) + 3.75112997 * x - 0.37001483
+    };
+    (x, y)
+}
+
+/// Derives camera WB multipliers for a given color temperature from the camera's
+/// XYZ-to-camera matrix: the illuminant's white point is projected into camera space
+/// and each channel is scaled so that white comes out neutral.
+fn multipliers_for_temperature(xyz_to_cam: &[f32], temperature: u32) -> Option<[f32; 4]> {
+    let rows = xyz_to_cam.len() / 3;
+    if rows < 3 {
+        return None;
+    }
+    let (x, y) = planckian_xy(temperature as f64);
+    let xyz = [x / y, 1.0, (1.0 - x - y) / y];
+
+    let mut cam = [0.0f64; 4];
+    for (row, value) in cam.iter_mut().enumerate().take(rows.min(4)) {
+        *value = (0..3)
+            .map(|col| xyz_to_cam[row * 3 + col] as f64 * xyz[col])
+            .sum();
+    }
+    if rows == 3 {
+        cam[3] = cam[1];
+    }
+    if cam.iter().any(|&c| c <= 1e-6) {
+        return None;
+    }
+
+    let wb = cam.map(|c| (1.0 / c) as f32);
+    Some(normalize_to_green(wb))
+}
+
+#[tauri::command]
+pub fn get_raw_white_balance(path: String) -> Result<RawWhiteBalance, String> {
+    let (source_path, _) = crate::file_management::parse_virtual_path(&path);
+    if !crate::formats::is_raw_file(&source_path) {
+        return Err("White balance multipliers are only available for raw files".to_string());
+    }
+    let file_bytes =
+        crate::file_management::read_file_mapped(&source_path).map_err(|e| e.to_string())?;
+
+    let source = RawSource::new_from_slice(&file_bytes);
+    let decoder = rawler::get_decoder(&source).map_err(|e| e.to_string())?;
+    let raw_image = decoder
+        .raw_image(&source, &RawDecodeParams::default(), true)
+        .map_err(|e| e.to_string())?;
+
+    let as_shot = normalize_to_green(raw_image.wb_coeffs);
+    let matrix = raw_image
+        .color_matrix
+        .get(&Illuminant::D65)
+        .or_else(|| raw_image.color_matrix.values().next());
+
+    let presets = match matrix {
+        Some(matrix) => WB_PRESETS
+            .iter()
+            .filter_map(|(name, temperature)| {
+                multipliers_for_temperature(matrix, *temperature).map(|multipliers| {
+                    WhiteBalancePreset {
+                        name: name.to_string(),
+                        temperature: *temperature,
+                        multipliers,
+                    }
+                })
+            })
+            .collect(),
+        None => {
+            log::warn!("No color matrix available for {}", source_path.display());
+            Vec::new()
+        }
+    };
+
+    Ok(RawWhiteBalance { as_shot, presets })
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibrationFramesInfo {
+    pub width: usize,
+    pub height: usize,
+    pub has_dark: bool,
+    pub has_flat: bool,
+}
+
+/// Validates and stores the master dark and flat field used for every raw load. Passing
+/// neither path disables calibration.
+#[tauri::command]
+pub async fn apply_calibration_frames(
+    dark_path: Option<String>,
+    flat_path: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, crate::AppState>,
+) -> Result<Option<CalibrationFramesInfo>, String> {
+    let dark_path = dark_path.filter(|p| !p.is_empty());
+    let flat_path = flat_path.filter(|p| !p.is_empty());
+
+    let info = if dark_path.is_some() || flat_path.is_some() {
+        let (dark, flat) = (dark_path.clone(), flat_path.clone());
+        let frames = tokio::task::spawn_blocking(move || {
+            load_calibration_frames(dark.as_deref(), flat.as_deref())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+        Some(CalibrationFramesInfo {
+            width: frames.width,
+            height: frames.height,
+            has_dark: frames.dark.is_some(),
+            has_flat: frames.flat.is_some(),
+        })
+    } else {
+        None
+    };
+
+    let mut settings = crate::app_settings::load_settings(app_handle.clone())?;
+    settings.calibration_dark_frame = dark_path;
+    settings.calibration_flat_field = flat_path;
+    crate::app_settings::save_settings(settings, app_handle)?;
+    clear_calibration_cache();
+    // Decoded images already carry the previous frames' correction.
+    crate::cache_utils::clear_image_caches(state);
+    Ok(info)
+}
@@ -0,0 +1,138 @@
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use base64::{Engine as _, engine::general_purpose};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::app_state::AppState;
+
+pub const SECONDARY_WINDOW_LABEL: &str = "secondary";
+
+pub struct MirrorJob {
+    generation: usize,
+    adjustments: serde_json::Value,
+}
+
+/// Queues a render of `adjustments` for the secondary window if one is open. A single
+/// worker renders the mirror; queued requests coalesce to the newest one, and a request
+/// that was superseded before its turn is never rendered.
+pub fn mirror_preview(app_handle: &AppHandle, adjustments: &serde_json::Value) {
+    let state = app_handle.state::<AppState>();
+    let mut mirror = state.preview_mirror.lock().unwrap();
+    mirror.last_adjustments = Some(adjustments.clone());
+    if mirror.target_label.is_none() {
+        return;
+    }
+    let generation = state
+        .preview_mirror_generation
+        .fetch_add(1, Ordering::SeqCst)
+        + 1;
+    let tx = mirror
+        .worker_tx
+        .get_or_insert_with(|| start_mirror_worker(app_handle.clone()));
+    let _ = tx.send(MirrorJob {
+        generation,
+        adjustments: adjustments.clone(),
+    });
+}
+
+fn start_mirror_worker(app_handle: AppHandle) -> Sender<MirrorJob> {
+    let (tx, rx): (Sender<MirrorJob>, Receiver<MirrorJob>) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        while let Ok(mut job) = rx.recv() {
+            while let Ok(latest_job) = rx.try_recv() {
+                job = latest_job;
+            }
+
+            let state = app_handle.state::<AppState>();
+            let is_current =
+                || state.preview_mirror_generation.load(Ordering::SeqCst) == job.generation;
+            let Some(label) = state.preview_mirror.lock().unwrap().target_label.clone() else {
+                continue;
+            };
+            if !is_current() {
+                continue;
+            }
+
+            let path = state
+                .original_image
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|image| image.path.clone());
+            let bytes =
+                match crate::render_fullscreen_preview(&job.adjustments, &state, &app_handle) {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        log::warn!("Secondary window preview failed: {}", e);
+                        continue;
+                    }
+                };
+            if !is_current() {
+                continue;
+            }
+            let _ = app_handle.emit_to(
+                label.as_str(),
+                "secondary-preview-update",
+                serde_json::json!({
+                    "path": path,
+                    "dataUrl": format!(
+                        "data:image/jpeg;base64,{}",
+                        general_purpose::STANDARD.encode(&bytes)
+                    ),
+                }),
+            );
+        }
+    });
+
+    tx
+}
+
+#[tauri::command]
+pub fn open_secondary_window(app_handle: AppHandle) -> Result<(), String> {
+    #[cfg(target_os = "android")]
+    {
+        let _ = app_handle;
+        Err("A second window is not available on this platform.".to_string())
+    }
+
+    #[cfg(not(target_os = "android"))]
+    {
+        if let Some(window) = app_handle.get_webview_window(SECONDARY_WINDOW_LABEL) {
+            let _ = window.show();
+            let _ = window.set_focus();
+            return Ok(());
+        }
+
+        let window = tauri::WebviewWindowBuilder::new(
+            &app_handle,
+            SECONDARY_WINDOW_LABEL,
+            tauri::WebviewUrl::App("index.html".into()),
+        )
+        .title("RapidRAW - Second View")
+        .inner_size(1280.0, 800.0)
+        .build()
+        .map_err(|e| format!("Failed to open second window: {}", e))?;
+
+        let state = app_handle.state::<AppState>();
+        let last_adjustments = {
+            let mut mirror = state.preview_mirror.lock().unwrap();
+            mirror.target_label = Some(SECONDARY_WINDOW_LABEL.to_string());
+            mirror.last_adjustments.clone()
+        };
+
+        let app_handle_for_events = app_handle.clone();
+        window.on_window_event(move |event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                let state = app_handle_for_events.state::<AppState>();
+                state.preview_mirror.lock().unwrap().target_label = None;
+            }
+        });
+
+        if let Some(adjustments) = last_adjustments {
+            mirror_preview(&app_handle, &adjustments);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::app_state::AppState;
+
+const SESSION_FILE: &str = "session.json";
+const SESSION_SAVE_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// What the editor had open when it last closed. `ui_state` is owned by the frontend
+/// (panel visibility, active tabs) and stored verbatim.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorSession {
+    #[serde(default)]
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub selected_image: Option<String>,
+    #[serde(default)]
+    pub ui_state: serde_json::Value,
+}
+
+fn session_path(app_handle: &AppHandle) -> Option<PathBuf> {
+    app_handle
+        .path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join(SESSION_FILE))
+}
+
+pub fn load_session(app_handle: &AppHandle) -> Option<EditorSession> {
+    let contents = fs::read_to_string(session_path(app_handle)?).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(session) => Some(session),
+        Err(e) => {
+            log::warn!("Ignoring unreadable session file: {}", e);
+            None
+        }
+    }
+}
+
+fn write_session(app_handle: &AppHandle, session: &EditorSession) {
+    let Some(path) = session_path(app_handle) else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    match serde_json::to_string(session) {
+        Ok(json) => {
+            if let Err(e) = fs::write(&path, json) {
+                log::warn!("Failed to write session file: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Failed to serialize session: {}", e),
+    }
+}
+
+/// Writes the latest pending session, if any. Called on a timer and when the window closes.
+pub fn flush_pending_session(app_handle: &AppHandle) {
+    let state = app_handle.state::<AppState>();
+    let pending = state.pending_session.lock().unwrap().take();
+    if let Some(session) = pending {
+        write_session(app_handle, &session);
+    }
+}
+
+pub fn spawn_session_saver(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SESSION_SAVE_INTERVAL).await;
+            flush_pending_session(&app_handle);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn save_session(session: EditorSession, state: tauri::State<AppState>) {
+    *state.pending_session.lock().unwrap() = Some(session);
+}
@@ -43,9 +43,9 @@ struct GlobalAdjustments {
     tint: f32,
     vibrance: f32,
     hue: f32,
-    _pad_color1: f32,
-    _pad_color2: f32,
-    _pad_color3: f32,
+    grain_seed: f32,
+    grain_offset_x: f32,
+    grain_offset_y: f32,
 
     sharpness: f32,
     luma_noise_reduction: f32,
@@ -66,18 +66,18 @@ struct GlobalAdjustments {
     chromatic_aberration_blue_yellow: f32,
     show_clipping: u32,
     is_raw_image: u32,
-    _pad_ca1: f32,
+    grain_scale: f32,
 
     has_lut: u32,
     lut_intensity: f32,
     tonemapper_mode: u32,
-    _pad_lut2: f32,
-    _pad_lut3: f32,
-    _pad_lut4: f32,
-    _pad_lut5: f32,
+    defringe_purple_amount: f32,
+    defringe_purple_hue: f32,
+    defringe_green_amount: f32,
+    defringe_green_hue: f32,
 
-    _pad_agx1: f32,
-    _pad_agx2: f32,
+    defringe_purple_range: f32,
+    defringe_green_range: f32,
     _pad_agx3: f32,
     agx_pipe_to_rendering_matrix: mat3x3<f32>,
     agx_rendering_to_pipe_matrix: mat3x3<f32>,
@@ -106,8 +106,8 @@ struct GlobalAdjustments {
     red_curve_count: u32,
     green_curve_count: u32,
     blue_curve_count: u32,
-    _pad_end1: f32,
-    _pad_end2: f32,
+    vignette_highlight_priority: f32,
+    texture_amount: f32,
     _pad_end3: f32,
     _pad_end4: f32,
 
@@ -115,6 +115,13 @@ struct GlobalAdjustments {
     halation_amount: f32,
     flare_amount: f32,
     sharpness_threshold: f32,
+
+    clip_highlight_color: vec4<f32>,
+    clip_shadow_color: vec4<f32>,
+    clip_highlight_threshold: f32,
+    clip_shadow_threshold: f32,
+    clip_zebra: u32,
+    _pad_clip: f32,
 }
 
 struct MaskAdjustments {
@@ -143,7 +150,7 @@ struct MaskAdjustments {
     sharpness_threshold: f32,
 
     hue: f32,
-    _pad_cg1: f32,
+    texture_amount: f32,
     _pad_cg2: f32,
     color_grading_shadows: ColorGradeSettings,
     color_grading_midtones: ColorGradeSettings,
@@ -176,6 +183,10 @@ struct AllAdjustments {
     tile_offset_x: u32,
     tile_offset_y: u32,
     mask_atlas_cols: u32,
+    image_offset_x: u32,
+    image_offset_y: u32,
+    image_width: u32,
+    image_height: u32,
 }
 
 struct HslRange {
@@ -789,8 +800,8 @@ fn apply_centre_local_contrast(
     if (centre_amount == 0.0) {
         return color_in;
     }
-    let full_dims_f = vec2<f32>(textureDimensions(input_texture));
-    let coord_f = vec2<f32>(coords_i);
+    let full_dims_f = full_image_dims();
+    let coord_f = image_space_coord(coords_i);
     let midpoint = 0.4;
     let feather = 0.375;
     let aspect = full_dims_f.y / full_dims_f.x;
@@ -818,8 +829,8 @@ fn apply_centre_tonal_and_color(
     if (centre_amount == 0.0) {
         return color_in;
     }
-    let full_dims_f = vec2<f32>(textureDimensions(input_texture));
-    let coord_f = vec2<f32>(coords_i);
+    let full_dims_f = full_image_dims();
+    let coord_f = image_space_coord(coords_i);
     let midpoint = 0.4;
     let feather = 0.375;
     let aspect = full_dims_f.y / full_dims_f.x;
@@ -1106,6 +1117,48 @@ fn apply_ca_correction(coords: vec2<u32>, ca_rc: f32, ca_by: f32) -> vec3<f32> {
     return vec3<f32>(r, g, b);
 }
 
+// `half_width` is the hue distance in degrees where the weight reaches zero.
+fn get_defringe_hue_weight(hue: f32, center: f32, half_width: f32) -> f32 {
+    let dist = min(abs(hue - center), 360.0 - abs(hue - center));
+    let width = max(half_width, 1.0);
+    return 1.0 - smoothstep(width * 0.5, width, dist);
+}
+
+// Desaturates purple/green fringe colors that sit next to a bright edge.
+fn apply_defringe(
+    coords: vec2<u32>,
+    color: vec3<f32>,
+    purple_amount: f32,
+    purple_hue: f32,
+    purple_range: f32,
+    green_amount: f32,
+    green_hue: f32,
+    green_range: f32
+) -> vec3<f32> {
+    let max_coords = vec2<i32>(textureDimensions(input_texture)) - vec2<i32>(1);
+    let center = vec2<i32>(coords);
+    var max_luma = 0.0;
+    for (var dy: i32 = -2; dy <= 2; dy += 2) {
+        for (var dx: i32 = -2; dx <= 2; dx += 2) {
+            let p = clamp(center + vec2<i32>(dx, dy), vec2<i32>(0), max_coords);
+            max_luma = max(max_luma, get_luma(textureLoad(input_texture, vec2<u32>(p), 0).rgb));
+        }
+    }
+
+    let luma = get_luma(color);
+    let edge = smoothstep(0.5, 0.8, max_luma) * smoothstep(0.1, 0.3, max_luma - luma);
+    if (edge <= 0.0) {
+        return color;
+    }
+
+    let hsv = rgb_to_hsv(color);
+    let saturation_gate = smoothstep(0.05, 0.2, hsv.y);
+    let purple = purple_amount * get_defringe_hue_weight(hsv.x, purple_hue, purple_range);
+    let green = green_amount * get_defringe_hue_weight(hsv.x, green_hue, green_range);
+    let weight = clamp(max(purple, green) * edge * saturation_gate, 0.0, 1.0);
+    return mix(color, vec3<f32>(luma), weight);
+}
+
 const AGX_EPSILON: f32 = 1.0e-6;
 const AGX_MIN_EV: f32 = -15.2;
 const AGX_MAX_EV: f32 = 5.0;
@@ -1238,6 +1291,19 @@ fn apply_all_curves(color: vec3<f32>, luma_curve: array<Point, 16>, luma_curve_c
     }
 }
 
+// Oversized images are rendered in regions; position-dependent effects must use
+// the dimensions and coordinates of the whole image rather than the region's.
+fn full_image_dims() -> vec2<f32> {
+    if (adjustments.image_width > 0u && adjustments.image_height > 0u) {
+        return vec2<f32>(f32(adjustments.image_width), f32(adjustments.image_height));
+    }
+    return vec2<f32>(textureDimensions(input_texture));
+}
+
+fn image_space_coord(coords: vec2<i32>) -> vec2<f32> {
+    return vec2<f32>(coords) + vec2<f32>(f32(adjustments.image_offset_x), f32(adjustments.image_offset_y));
+}
+
 fn get_mask_influence(mask_index: u32, coords: vec2<u32>) -> f32 {
     return textureLoad(mask_textures, vec2<i32>(coords), i32(mask_index), 0).r;
 }
@@ -1443,7 +1509,7 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     if (id.x >= out_dims.x || id.y >= out_dims.y) { return; }
 
     const REFERENCE_DIMENSION: f32 = 1080.0;
-    let full_dims = vec2<f32>(textureDimensions(input_texture));
+    let full_dims = full_image_dims();
     let current_ref_dim = min(full_dims.x, full_dims.y);
     let scale = max(0.1, current_ref_dim / REFERENCE_DIMENSION);
 
@@ -1456,6 +1522,20 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     if (abs(ca_rc) > 0.000001 || abs(ca_by) > 0.000001) {
         color_from_texture = apply_ca_correction(absolute_coord, ca_rc, ca_by);
     }
+    let defringe_purple = adjustments.global.defringe_purple_amount;
+    let defringe_green = adjustments.global.defringe_green_amount;
+    if (defringe_purple > 0.0 || defringe_green > 0.0) {
+        color_from_texture = apply_defringe(
+            absolute_coord,
+            color_from_texture,
+            defringe_purple,
+            adjustments.global.defringe_purple_hue,
+            adjustments.global.defringe_purple_range,
+            defringe_green,
+            adjustments.global.defringe_green_hue,
+            adjustments.global.defringe_green_range
+        );
+    }
     let original_alpha = textureLoad(input_texture, absolute_coord, 0).a;
 
     var initial_linear_rgb: vec3<f32>;
@@ -1482,6 +1562,7 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     var t_clarity = adjustments.global.clarity;
     var t_dehaze = adjustments.global.dehaze;
     var t_structure = adjustments.global.structure;
+    var t_texture = adjustments.global.texture_amount;
     var t_glow = adjustments.global.glow_amount;
     var t_halation = adjustments.global.halation_amount;
     var t_flare = adjustments.global.flare_amount;
@@ -1520,6 +1601,7 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
             t_clarity += m.clarity * influence;
             t_dehaze += m.dehaze * influence;
             t_structure += m.structure * influence;
+            t_texture += m.texture_amount * influence;
 
             t_glow += m.glow_amount * influence;
             t_halation += m.halation_amount * influence;
@@ -1577,6 +1659,7 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     }
     locally_contrasted_rgb += sharpness_delta;
 
+    locally_contrasted_rgb = apply_local_contrast(locally_contrasted_rgb, tonal_blurred, t_texture, is_raw, 1u, 0.0);
     locally_contrasted_rgb = apply_local_contrast(locally_contrasted_rgb, clarity_blurred, t_clarity, is_raw, 1u, 0.0);
     locally_contrasted_rgb = apply_local_contrast(locally_contrasted_rgb, structure_blurred, t_structure, is_raw, 1u, 0.0);
     locally_contrasted_rgb = apply_centre_local_contrast(locally_contrasted_rgb, adjustments.global.centre, absolute_coord_i, clarity_blurred, is_raw);
@@ -1596,7 +1679,7 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
         );
     }
     if (t_flare > 0.0) {
-        let uv = vec2<f32>(absolute_coord) / full_dims;
+        let uv = image_space_coord(absolute_coord_i) / full_dims;
         var flare_color = textureSampleLevel(flare_texture, flare_sampler, uv, 0.0).rgb;
         flare_color *= 1.4;
         flare_color = flare_color * flare_color;
@@ -1645,8 +1728,8 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     }
 
     if (adjustments.global.vignette_amount != 0.0) {
-        let full_dims_f = vec2<f32>(textureDimensions(input_texture));
-        let coord_f = vec2<f32>(absolute_coord);
+        let full_dims_f = full_image_dims();
+        let coord_f = image_space_coord(absolute_coord_i);
         let v_amount = adjustments.global.vignette_amount;
         let v_mid = adjustments.global.vignette_midpoint;
         let v_round = 1.0 - adjustments.global.vignette_roundness;
@@ -1657,7 +1740,11 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
         let d = length(uv_round * vec2<f32>(1.0, aspect)) * 0.5;
         let vignette_mask = smoothstep(v_mid - v_feather, v_mid + v_feather, d);
         if (v_amount < 0.0) {
-            composite_rgb_linear *= (1.0 + v_amount * vignette_mask);
+            // Highlight priority keeps bright areas near the frame edge from going muddy.
+            let highlight_protect = adjustments.global.vignette_highlight_priority
+                * smoothstep(0.4, 1.0, get_luma(composite_rgb_linear));
+            let darkening = 1.0 + v_amount * vignette_mask * (1.0 - highlight_protect);
+            composite_rgb_linear *= darkening;
         } else {
             composite_rgb_linear = mix(composite_rgb_linear, vec3<f32>(1.0), v_amount * vignette_mask);
         }
@@ -1666,7 +1753,7 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     var base_srgb: vec3<f32>;
     if (adjustments.global.tonemapper_mode == 1u) {
         base_srgb = agx_full_transform(composite_rgb_linear);
-    } else if (is_raw == 1u) {
+    } else if (is_raw == 1u && adjustments.global.tonemapper_mode != 2u) {
         var srgb_emulated = linear_to_srgb(composite_rgb_linear);
         const BRIGHTNESS_GAMMA: f32 = 1.1;
         srgb_emulated = pow(srgb_emulated, vec3<f32>(1.0 / BRIGHTNESS_GAMMA));
@@ -1704,9 +1791,14 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     }
 
     if (adjustments.global.grain_amount > 0.0) {
-        let coord = vec2<f32>(absolute_coord_i);
+        // Grain lives in full-resolution pixel space of the uncropped image and is offset by
+        // a per-image seed, so previews and exports render the same deterministic pattern.
+        const GRAIN_PIXEL_SCALE: f32 = 3.0;
+        let grain_origin = vec2<f32>(adjustments.global.grain_offset_x, adjustments.global.grain_offset_y);
+        let seed_offset = vec2<f32>(adjustments.global.grain_seed * 37.17, adjustments.global.grain_seed * 11.31);
+        let coord = vec2<f32>(absolute_coord_i) / adjustments.global.grain_scale + grain_origin + seed_offset;
         let amount = adjustments.global.grain_amount * 0.5;
-        let grain_frequency = (1.0 / max(adjustments.global.grain_size, 0.1)) / scale;
+        let grain_frequency = (1.0 / max(adjustments.global.grain_size, 0.1)) / GRAIN_PIXEL_SCALE;
         let roughness = adjustments.global.grain_roughness;
         let luma = max(0.0, get_luma(final_rgb));
         let luma_mask = smoothstep(0.0, 0.15, luma) * (1.0 - smoothstep(0.6, 1.0, luma));
@@ -1719,14 +1811,15 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     }
 
     if (adjustments.global.show_clipping == 1u) {
-        let HIGHLIGHT_WARNING_COLOR = vec3<f32>(1.0, 0.0, 0.0);
-        let SHADOW_WARNING_COLOR = vec3<f32>(0.0, 0.0, 1.0);
-        let HIGHLIGHT_CLIP_THRESHOLD = 0.998;
-        let SHADOW_CLIP_THRESHOLD = 0.002;
-        if (any(final_rgb > vec3<f32>(HIGHLIGHT_CLIP_THRESHOLD))) {
-            final_rgb = HIGHLIGHT_WARNING_COLOR;
-        } else if (any(final_rgb < vec3<f32>(SHADOW_CLIP_THRESHOLD))) {
-            final_rgb = SHADOW_WARNING_COLOR;
+        const ZEBRA_STRIPE_WIDTH: u32 = 6u;
+        let on_stripe = adjustments.global.clip_zebra == 0u
+            || ((absolute_coord.x + absolute_coord.y) / ZEBRA_STRIPE_WIDTH) % 2u == 0u;
+        if (on_stripe) {
+            if (any(final_rgb > vec3<f32>(adjustments.global.clip_highlight_threshold))) {
+                final_rgb = adjustments.global.clip_highlight_color.rgb;
+            } else if (any(final_rgb < vec3<f32>(adjustments.global.clip_shadow_threshold))) {
+                final_rgb = adjustments.global.clip_shadow_color.rgb;
+            }
         }
     }
 
@@ -16,7 +16,7 @@ use walkdir::WalkDir;
 
 use crate::file_management::{self, parse_virtual_path};
 use crate::formats::is_supported_image_file;
-use crate::hierarchy::TAG_HIERARCHY;
+use crate::hierarchy::TagHierarchy;
 use crate::image_processing::ImageMetadata;
 use crate::{AppState, candidates::TAG_CANDIDATES};
 
@@ -148,6 +148,7 @@ pub fn generate_tags_with_clip(
     tokenizer: &Tokenizer,
     custom_tags: Option<Vec<String>>,
     max_tags: usize,
+    hierarchy: &TagHierarchy,
 ) -> Result<Vec<String>> {
     let image_input = preprocess_clip_image(image);
 
@@ -235,11 +236,7 @@ pub fn generate_tags_with_clip(
         }
 
         for tag in &initial_tags {
-            if let Some(parents) = TAG_HIERARCHY.get(tag.as_str()) {
-                for &parent in parents {
-                    final_tags_set.insert(parent.to_string());
-                }
-            }
+            final_tags_set.extend(hierarchy.ancestors(hierarchy.canonical(tag)));
         }
     }
 
@@ -267,6 +264,7 @@ pub async fn start_background_indexing(
     let max_concurrent_tasks = settings.tagging_thread_count.unwrap_or(3).max(1) as usize;
     let custom_ai_tags = settings.custom_ai_tags.clone();
     let ai_tag_count = settings.ai_tag_count.unwrap_or(10) as usize;
+    let hierarchy = Arc::new(load_tag_hierarchy(&app_handle));
 
     let clip_models = crate::ai_processing::get_or_init_clip_models(
         &app_handle,
@@ -327,6 +325,7 @@ pub async fn start_background_indexing(
                 let gpu_context_inner = gpu_context.clone();
                 let processed_count_inner = Arc::clone(&processed_count);
                 let tags_inner = Arc::clone(&custom_ai_tags_shared);
+                let hierarchy_inner = Arc::clone(&hierarchy);
 
                 async move {
                     let path_str = path.to_string_lossy().to_string();
@@ -354,6 +353,7 @@ pub async fn start_background_indexing(
                                     &clip_models_inner.tokenizer,
                                     (*tags_inner).clone(),
                                     ai_tag_count,
+                                    &hierarchy_inner,
                                 ) {
                                     println!("Found AI tags for {}: {:?}", path_str, ai_tags);
 
@@ -437,13 +437,107 @@ fn modify_tags_for_path(
     fs::write(sidecar_path, json_string).map_err(|e| e.to_string())
 }
 
+fn get_tag_hierarchy_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(data_dir.join("tag_hierarchy.json"))
+}
+
+fn load_tag_hierarchy(app_handle: &AppHandle) -> TagHierarchy {
+    let Ok(path) = get_tag_hierarchy_path(app_handle) else {
+        return TagHierarchy::builtin();
+    };
+    let mut hierarchy = match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("Invalid tag hierarchy file {}: {}", path.display(), e);
+            TagHierarchy::builtin()
+        }),
+        Err(_) => TagHierarchy::builtin(),
+    };
+    hierarchy.normalize_synonyms();
+    hierarchy
+}
+
+fn write_tag_hierarchy(app_handle: &AppHandle, hierarchy: &TagHierarchy) -> Result<(), String> {
+    let path = get_tag_hierarchy_path(app_handle)?;
+    let json_string = serde_json::to_string_pretty(hierarchy).map_err(|e| e.to_string())?;
+    fs::write(path, json_string).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_tag_hierarchy(app_handle: AppHandle) -> Result<TagHierarchy, String> {
+    Ok(load_tag_hierarchy(&app_handle))
+}
+
+#[tauri::command]
+pub fn set_tag_hierarchy(mut hierarchy: TagHierarchy, app_handle: AppHandle) -> Result<(), String> {
+    hierarchy.normalize_synonyms();
+    write_tag_hierarchy(&app_handle, &hierarchy)
+}
+
+#[tauri::command]
+pub fn add_tag_synonym(
+    synonym: String,
+    canonical: String,
+    app_handle: AppHandle,
+) -> Result<TagHierarchy, String> {
+    let synonym = synonym.trim().to_lowercase();
+    let canonical = canonical.trim().to_lowercase();
+    if synonym.is_empty() || canonical.is_empty() {
+        return Err("Tag names cannot be empty".to_string());
+    }
+    if synonym == canonical {
+        return Err("A tag cannot be a synonym of itself".to_string());
+    }
+
+    let mut hierarchy = load_tag_hierarchy(&app_handle);
+    if hierarchy.synonyms.contains_key(&canonical) {
+        return Err(format!("'{}' is itself a synonym", canonical));
+    }
+    hierarchy.synonyms.insert(synonym, canonical);
+    write_tag_hierarchy(&app_handle, &hierarchy)?;
+    Ok(hierarchy)
+}
+
+/// Resolves synonyms and appends every ancestor, keeping the `user:` prefix so expanded
+/// parents stay user tags.
+fn expand_tag(tag: &str, hierarchy: &TagHierarchy) -> Vec<String> {
+    let (prefix, name) = match tag.strip_prefix(USER_TAG_PREFIX) {
+        Some(name) => (USER_TAG_PREFIX, name),
+        None => ("", tag),
+    };
+    let canonical = hierarchy.canonical(name);
+
+    std::iter::once(canonical.to_string())
+        .chain(hierarchy.ancestors(canonical))
+        .map(|t| format!("{}{}", prefix, t))
+        .collect()
+}
+
 #[tauri::command]
-pub fn add_tag_for_paths(paths: Vec<String>, tag: String) -> Result<(), String> {
+pub fn add_tag_for_paths(
+    paths: Vec<String>,
+    tag: String,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let hierarchy = load_tag_hierarchy(&app_handle);
+    let expanded = if tag.starts_with(COLOR_TAG_PREFIX) {
+        vec![tag]
+    } else {
+        expand_tag(&tag, &hierarchy)
+    };
+
     paths.par_iter().for_each(|path| {
-        let tag_clone = tag.clone();
         if let Err(e) = modify_tags_for_path(path, |tags| {
-            if !tags.contains(&tag_clone) {
-                tags.push(tag_clone.clone());
+            for tag in &expanded {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
             }
         }) {
             eprintln!("Failed to add tag to {}: {}", path, e);
@@ -1,5 +1,6 @@
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 pub static TAG_HIERARCHY: Lazy<HashMap<&'static str, Vec<&'static str>>> = Lazy::new(|| {
     let mut m = HashMap::new();
@@ -191,3 +192,68 @@ pub static TAG_HIERARCHY: Lazy<HashMap<&'static str, Vec<&'static str>>> = Lazy:
 
     m
 });
+
+/// User-editable taxonomy. `parents` maps a tag to its direct parents, `synonyms` maps
+/// an alternative spelling to the canonical tag it should be stored as.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TagHierarchy {
+    #[serde(default)]
+    pub parents: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub synonyms: HashMap<String, String>,
+}
+
+impl TagHierarchy {
+    pub fn builtin() -> Self {
+        Self {
+            parents: TAG_HIERARCHY
+                .iter()
+                .map(|(tag, parents)| {
+                    (
+                        tag.to_string(),
+                        parents.iter().map(|p| p.to_string()).collect(),
+                    )
+                })
+                .collect(),
+            synonyms: HashMap::new(),
+        }
+    }
+
+    /// Lowercases synonym keys so hand-edited files match regardless of case.
+    pub fn normalize_synonyms(&mut self) {
+        self.synonyms = std::mem::take(&mut self.synonyms)
+            .into_iter()
+            .map(|(synonym, canonical)| (synonym.trim().to_lowercase(), canonical))
+            .collect();
+    }
+
+    /// Case-insensitive synonym lookup. Expects keys normalised by `normalize_synonyms`.
+    pub fn canonical<'a>(&'a self, tag: &'a str) -> &'a str {
+        if self.synonyms.is_empty() {
+            return tag;
+        }
+        self.synonyms
+            .get(&tag.trim().to_lowercase())
+            .map_or(tag, String::as_str)
+    }
+
+    /// All ancestors of `tag`, nearest first. Cycles in a hand-edited hierarchy are
+    /// tolerated by visiting every tag at most once.
+    pub fn ancestors(&self, tag: &str) -> Vec<String> {
+        let mut seen: HashSet<&str> = HashSet::from([tag]);
+        let mut queue: VecDeque<&str> = VecDeque::from([tag]);
+        let mut ancestors = Vec::new();
+
+        while let Some(current) = queue.pop_front() {
+            for parent in self.parents.get(current).into_iter().flatten() {
+                let parent = self.canonical(parent);
+                if seen.insert(parent) {
+                    ancestors.push(parent.to_string());
+                    queue.push_back(parent);
+                }
+            }
+        }
+        ancestors
+    }
+}
@@ -0,0 +1,491 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread::{self, JoinHandle};
+
+use image::{DynamicImage, GenericImageView, RgbImage, imageops};
+use rayon::prelude::*;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::app_settings::{AppSettings, load_settings};
+use crate::app_state::AppState;
+use crate::exif_processing;
+use crate::export_processing::render_path_from_sidecar;
+use crate::file_management::{
+    parse_virtual_path, save_adjustments_with_history, update_thumbnail_queue,
+};
+use crate::formats::is_raw_file;
+use crate::image_loader::load_base_image_from_bytes;
+use crate::image_processing::{
+    apply_cpu_default_raw_processing, downscale_f32_image, get_gpu_context_or_fallback,
+    linear_to_srgb_scalar, srgb_to_linear_scalar,
+};
+
+const MAX_TIMELAPSE_FPS: f32 = 120.0;
+const MIN_TIMELAPSE_HEIGHT: u32 = 144;
+const MAX_TIMELAPSE_HEIGHT: u32 = 4320;
+pub const DEFLICKER_RADIUS: usize = 3;
+const DEFLICKER_MIN_GAIN: f32 = 0.5;
+const DEFLICKER_MAX_GAIN: f32 = 2.0;
+const LUMINANCE_SAMPLE_TARGET: u64 = 65_536;
+const DEFLICKER_ANALYSIS_DIM: u32 = 256;
+const DEFLICKER_MAX_COMPENSATION: f64 = 2.0;
+const EXPOSURE_LIMIT: f64 = 5.0;
+
+#[derive(Serialize, Clone)]
+struct TimelapseProgress {
+    current: usize,
+    total: usize,
+}
+
+/// Log-average linear luminance of an image, estimated from a strided sample of its pixels.
+pub fn mean_log_luminance(image: &RgbImage) -> f32 {
+    let pixel_count = image.width() as u64 * image.height() as u64;
+    let stride = (pixel_count / LUMINANCE_SAMPLE_TARGET).max(1) as usize;
+    let (sum, count) = image
+        .pixels()
+        .step_by(stride)
+        .fold((0.0f64, 0u64), |(sum, count), p| {
+            let r = srgb_to_linear_scalar(p[0] as f32 / 255.0);
+            let g = srgb_to_linear_scalar(p[1] as f32 / 255.0);
+            let b = srgb_to_linear_scalar(p[2] as f32 / 255.0);
+            let lum = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            (sum + (lum.max(1e-4) as f64).ln(), count + 1)
+        });
+    if count == 0 {
+        return 0.0;
+    }
+    (sum / count as f64) as f32
+}
+
+/// Centred moving average of `values[index]`, clipped to the available neighbours.
+pub fn window_mean(values: &[f32], index: usize, radius: usize) -> f32 {
+    let start = index.saturating_sub(radius);
+    let end = (index + radius + 1).min(values.len());
+    let window = &values[start..end];
+    window.iter().sum::<f32>() / window.len() as f32
+}
+
+/// Smooths a sequence of log luminances with a centred moving average.
+pub fn smooth_luminance(values: &[f32], radius: usize) -> Vec<f32> {
+    (0..values.len())
+        .map(|i| window_mean(values, i, radius))
+        .collect()
+}
+
+fn apply_gain(image: &mut RgbImage, gain: f32) {
+    if (gain - 1.0).abs() < 1e-3 {
+        return;
+    }
+    let lut: Vec<u8> = (0..=255u8)
+        .map(|v| {
+            let linear = srgb_to_linear_scalar(v as f32 / 255.0) * gain;
+            (linear_to_srgb_scalar(linear.min(1.0)) * 255.0).round() as u8
+        })
+        .collect();
+    for channel in image.iter_mut() {
+        *channel = lut[*channel as usize];
+    }
+}
+
+fn even_dimension(value: f32) -> u32 {
+    ((value / 2.0).round() as u32 * 2).max(2)
+}
+
+fn encoder_args(output_path: &Path) -> Result<Vec<&'static str>, String> {
+    let extension = output_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "mp4" | "m4v" => Ok(vec![
+            "-c:v",
+            "libx264",
+            "-preset",
+            "medium",
+            "-crf",
+            "18",
+            "-pix_fmt",
+            "yuv420p",
+            "-movflags",
+            "+faststart",
+        ]),
+        "mov" | "mkv" => Ok(vec![
+            "-c:v", "libx264", "-preset", "medium", "-crf", "18", "-pix_fmt", "yuv420p",
+        ]),
+        "webm" => Ok(vec![
+            "-c:v",
+            "libvpx-vp9",
+            "-b:v",
+            "0",
+            "-crf",
+            "30",
+            "-pix_fmt",
+            "yuv420p",
+        ]),
+        _ => Err(format!(
+            "Unsupported timelapse container '.{}'. Use .mp4, .mov, .mkv or .webm.",
+            extension
+        )),
+    }
+}
+
+/// Picks the ffmpeg binary: the path from settings, then one bundled next to the app
+/// executable, then whatever is on the PATH.
+fn resolve_ffmpeg(settings: &AppSettings) -> PathBuf {
+    if let Some(path) = settings
+        .ffmpeg_path
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        return PathBuf::from(path);
+    }
+    let binary = if cfg!(windows) {
+        "ffmpeg.exe"
+    } else {
+        "ffmpeg"
+    };
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(binary)))
+        .filter(|bundled| bundled.is_file())
+        .unwrap_or_else(|| PathBuf::from(binary))
+}
+
+struct Encoder {
+    child: Child,
+    stderr: JoinHandle<String>,
+}
+
+fn spawn_encoder(
+    ffmpeg: &Path,
+    output_path: &Path,
+    width: u32,
+    height: u32,
+    fps: f32,
+) -> Result<Encoder, String> {
+    let codec_args = encoder_args(output_path)?;
+    let mut child = Command::new(ffmpeg)
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgb24",
+        ])
+        .args(["-s", &format!("{}x{}", width, height)])
+        .args(["-r", &fps.to_string()])
+        .args(["-i", "-"])
+        .args(codec_args)
+        .arg(output_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                format!(
+                    "ffmpeg was not found at '{}'. Install ffmpeg or set its path in the settings to export timelapses.",
+                    ffmpeg.display()
+                )
+            } else {
+                format!("Failed to start ffmpeg: {}", e)
+            }
+        })?;
+
+    // Drain stderr while frames are written so a chatty ffmpeg cannot fill the pipe and stall.
+    let mut stderr_pipe = child.stderr.take().ok_or("Failed to open ffmpeg output")?;
+    let stderr = thread::spawn(move || {
+        let mut log = Vec::new();
+        let _ = stderr_pipe.read_to_end(&mut log);
+        String::from_utf8_lossy(&log).into_owned()
+    });
+    Ok(Encoder { child, stderr })
+}
+
+fn finish_encoder(mut encoder: Encoder) -> Result<(), String> {
+    let status = encoder.child.wait().map_err(|e| e.to_string())?;
+    let log = encoder.stderr.join().unwrap_or_default();
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("ffmpeg failed: {}", log.trim()))
+    }
+}
+
+fn abort_encoder(mut encoder: Encoder) {
+    let _ = encoder.child.kill();
+    let _ = encoder.child.wait();
+    let _ = encoder.stderr.join();
+}
+
+fn write_frame(
+    stdin: &mut ChildStdin,
+    mut frame: RgbImage,
+    gain: Option<f32>,
+) -> Result<(), String> {
+    if let Some(gain) = gain {
+        apply_gain(&mut frame, gain);
+    }
+    stdin
+        .write_all(frame.as_raw())
+        .map_err(|e| format!("Failed to write frame to ffmpeg: {}", e))
+}
+
+fn fit_frame(image: &DynamicImage, width: u32, height: u32) -> RgbImage {
+    image
+        .resize_to_fill(width, height, imageops::FilterType::Lanczos3)
+        .to_rgb8()
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeflickerFrame {
+    pub path: String,
+    pub capture_time: String,
+    /// Measured brightness in stops, including the exposure already in the sidecar.
+    pub luminance: f64,
+    pub smoothed_luminance: f64,
+    pub compensation: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeflickerResult {
+    pub window_size: usize,
+    pub frames: Vec<DeflickerFrame>,
+    pub skipped: Vec<String>,
+}
+
+fn analyze_frame_luminance(path: &str, settings: &AppSettings) -> Result<f32, String> {
+    let (source_path, _) = parse_virtual_path(path);
+    let source_path_str = source_path.to_string_lossy().to_string();
+    let bytes = fs::read(&source_path).map_err(|e| e.to_string())?;
+    let image = load_base_image_from_bytes(&bytes, &source_path_str, true, settings, None)
+        .map_err(|e| e.to_string())?;
+    let mut preview = downscale_f32_image(&image, DEFLICKER_ANALYSIS_DIM, DEFLICKER_ANALYSIS_DIM);
+    if is_raw_file(&source_path_str) {
+        apply_cpu_default_raw_processing(&mut preview);
+    }
+    Ok(mean_log_luminance(&preview.to_rgb8()))
+}
+
+/// Smooths brightness across a sequence in capture order and writes the per-frame exposure
+/// compensation into each sidecar. With `dry_run` only the curve is returned.
+#[tauri::command]
+pub async fn deflicker_sequence(
+    paths: Vec<String>,
+    window_size: Option<usize>,
+    dry_run: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<DeflickerResult, String> {
+    if paths.len() < 3 {
+        return Err("Select at least three images to deflicker.".to_string());
+    }
+    let window_size = window_size.unwrap_or(DEFLICKER_RADIUS * 2 + 1).max(1);
+    let radius = window_size / 2;
+    let dry_run = dry_run.unwrap_or(false);
+    let handle = app_handle.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let settings = load_settings(handle.clone()).unwrap_or_default();
+
+        let mut analyzed: Vec<_> = paths
+            .par_iter()
+            .map(|path| {
+                let (source_path, sidecar_path) = parse_virtual_path(path);
+                let captured = exif_processing::get_creation_date_from_path(&source_path);
+                let exposure = exif_processing::load_sidecar(&sidecar_path).adjustments["exposure"]
+                    .as_f64()
+                    .unwrap_or(0.0);
+                let luminance = analyze_frame_luminance(path, &settings);
+                (path.clone(), captured, exposure, luminance)
+            })
+            .collect();
+        analyzed.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut skipped = Vec::new();
+        let mut frames = Vec::new();
+        for (path, captured, exposure, luminance) in analyzed {
+            match luminance {
+                Ok(luminance) => frames.push((path, captured, exposure, luminance)),
+                Err(e) => {
+                    log::warn!("Deflicker skipped '{}': {}", path, e);
+                    skipped.push(path);
+                }
+            }
+        }
+
+        let stops: Vec<f32> = frames
+            .iter()
+            .map(|(_, _, exposure, luminance)| {
+                (*luminance as f64 / std::f64::consts::LN_2 + exposure) as f32
+            })
+            .collect();
+        let smoothed = smooth_luminance(&stops, radius);
+
+        let mut results = Vec::with_capacity(frames.len());
+        for ((path, captured, exposure, _), (luminance, target)) in
+            frames.into_iter().zip(stops.into_iter().zip(smoothed))
+        {
+            let compensation = (target as f64 - luminance as f64)
+                .clamp(-DEFLICKER_MAX_COMPENSATION, DEFLICKER_MAX_COMPENSATION);
+
+            if !dry_run {
+                let (_, sidecar_path) = parse_virtual_path(&path);
+                let mut adjustments = exif_processing::load_sidecar(&sidecar_path).adjustments;
+                if !adjustments.is_object() {
+                    adjustments = serde_json::json!({});
+                }
+                adjustments["exposure"] = (exposure + compensation)
+                    .clamp(-EXPOSURE_LIMIT, EXPOSURE_LIMIT)
+                    .into();
+                save_adjustments_with_history(&path, adjustments, &settings)?;
+            }
+
+            results.push(DeflickerFrame {
+                path,
+                capture_time: captured.to_rfc3339(),
+                luminance: luminance as f64,
+                smoothed_luminance: target as f64,
+                compensation,
+            });
+        }
+
+        Ok::<_, String>(DeflickerResult {
+            window_size,
+            frames: results,
+            skipped,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    if !dry_run {
+        update_thumbnail_queue(
+            result.frames.iter().map(|f| f.path.clone()).collect(),
+            app_handle,
+        )?;
+    }
+    Ok(result)
+}
+
+/// Renders each frame from its sidecar and pipes it to ffmpeg, which is looked up as
+/// described in `resolve_ffmpeg`.
+#[tauri::command]
+pub async fn export_timelapse(
+    paths: Vec<String>,
+    fps: f32,
+    resolution: u32,
+    output_path: String,
+    deflicker: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    if paths.len() < 2 {
+        return Err("Select at least two images for a timelapse.".to_string());
+    }
+    if !(fps > 0.0 && fps <= MAX_TIMELAPSE_FPS) {
+        return Err(format!(
+            "Frame rate must be between 0 and {} fps.",
+            MAX_TIMELAPSE_FPS
+        ));
+    }
+    let deflicker = deflicker.unwrap_or(false);
+    let height =
+        even_dimension(resolution.clamp(MIN_TIMELAPSE_HEIGHT, MAX_TIMELAPSE_HEIGHT) as f32);
+    encoder_args(Path::new(&output_path))?;
+
+    tokio::task::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let settings = load_settings(app_handle.clone()).unwrap_or_default();
+        let context = get_gpu_context_or_fallback(&state, &app_handle);
+        let total = paths.len();
+
+        let render = |path: &str| {
+            render_path_from_sidecar(
+                path,
+                context.as_ref(),
+                &settings,
+                &state,
+                &app_handle,
+                "export_timelapse",
+                None,
+            )
+            .map_err(|e| format!("Failed to render '{}': {}", path, e))
+        };
+
+        let first = render(&paths[0])?;
+        let (first_w, first_h) = first.dimensions();
+        let width = even_dimension(height as f32 * first_w as f32 / first_h.max(1) as f32);
+
+        let mut encoder = spawn_encoder(
+            &resolve_ffmpeg(&settings),
+            Path::new(&output_path),
+            width,
+            height,
+            fps,
+        )?;
+        let mut stdin = encoder
+            .child
+            .stdin
+            .take()
+            .ok_or("Failed to open ffmpeg input")?;
+
+        let mut luminance: Vec<f32> = Vec::with_capacity(total);
+        let mut pending: VecDeque<RgbImage> = VecDeque::new();
+        let mut written = 0;
+
+        let mut result: Result<(), String> = (|| {
+            let mut next_frame = Some(fit_frame(&first, width, height));
+            drop(first);
+            for (index, path) in paths.iter().enumerate() {
+                let frame = match next_frame.take() {
+                    Some(frame) => frame,
+                    None => fit_frame(&render(path)?, width, height),
+                };
+                if deflicker {
+                    luminance.push(mean_log_luminance(&frame));
+                }
+                pending.push_back(frame);
+
+                let buffered = if deflicker { DEFLICKER_RADIUS } else { 0 };
+                while pending.len() > buffered || (index + 1 == total && !pending.is_empty()) {
+                    let frame = pending.pop_front().unwrap();
+                    let gain = deflicker.then(|| {
+                        let target = window_mean(&luminance, written, DEFLICKER_RADIUS);
+                        (target - luminance[written])
+                            .exp()
+                            .clamp(DEFLICKER_MIN_GAIN, DEFLICKER_MAX_GAIN)
+                    });
+                    write_frame(&mut stdin, frame, gain)?;
+                    written += 1;
+                    let _ = app_handle.emit(
+                        "timelapse-progress",
+                        TimelapseProgress {
+                            current: written,
+                            total,
+                        },
+                    );
+                }
+            }
+            Ok(())
+        })();
+
+        drop(stdin);
+        if result.is_err() {
+            abort_encoder(encoder);
+        } else {
+            result = finish_encoder(encoder);
+        }
+        result.map(|_| output_path)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
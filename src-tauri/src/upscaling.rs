@@ -0,0 +1,238 @@
+use std::io::Cursor;
+
+use base64::{Engine as _, engine::general_purpose};
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::ai_connector::{self, ConnectorTimeouts};
+use crate::app_settings::load_settings;
+use crate::app_state::AppState;
+use crate::panorama_stitching::emit_stage_progress;
+
+const UPSCALE_PREVIEW_SIZE: u32 = 2048;
+/// Upper bound on the upscaled output, so a 4x of a high resolution render does not try
+/// to allocate several gigabytes.
+const MAX_UPSCALED_PIXELS: u64 = 150_000_000;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpscaleResult {
+    pub width: u32,
+    pub height: u32,
+    pub used_connector: bool,
+    pub preview: String,
+}
+
+/// Size the render must be reduced to before upscaling by `scale`, so the output stays
+/// under `MAX_UPSCALED_PIXELS` and the input under the connector's `max_resolution`.
+/// Returns `None` when the render already fits.
+fn fit_upscale_input(
+    (width, height): (u32, u32),
+    scale: u32,
+    max_resolution: Option<u32>,
+) -> Option<(u32, u32)> {
+    let output_pixels = width as u64 * height as u64 * (scale as u64 * scale as u64);
+    let mut factor = 1.0f64;
+    if output_pixels > MAX_UPSCALED_PIXELS {
+        factor = (MAX_UPSCALED_PIXELS as f64 / output_pixels as f64).sqrt();
+    }
+    if let Some(max_resolution) = max_resolution {
+        let long_edge = width.max(height).max(1);
+        factor = factor.min(max_resolution as f64 / long_edge as f64);
+    }
+    if factor >= 1.0 {
+        return None;
+    }
+    Some((
+        ((width as f64 * factor).floor() as u32).max(1),
+        ((height as f64 * factor).floor() as u32).max(1),
+    ))
+}
+
+fn limit_upscale_input(
+    image: DynamicImage,
+    scale: u32,
+    max_resolution: Option<u32>,
+) -> DynamicImage {
+    match fit_upscale_input(image.dimensions(), scale, max_resolution) {
+        Some((width, height)) => {
+            log::info!(
+                "Reducing upscale input from {}x{} to {}x{}",
+                image.width(),
+                image.height(),
+                width,
+                height
+            );
+            image.resize_exact(width, height, image::imageops::FilterType::Lanczos3)
+        }
+        None => image,
+    }
+}
+
+fn upscale_lanczos(image: &DynamicImage, scale: u32) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    image.resize_exact(
+        width * scale,
+        height * scale,
+        image::imageops::FilterType::Lanczos3,
+    )
+}
+
+#[tauri::command]
+pub async fn upscale_image(
+    scale: u32,
+    current_adjustments: Value,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<UpscaleResult, String> {
+    if scale != 2 && scale != 4 {
+        return Err(format!("Unsupported upscale factor {}x.", scale));
+    }
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+
+    emit_stage_progress(
+        &app_handle,
+        "upscale-progress",
+        "render",
+        5.0,
+        "Rendering image...",
+    );
+    let processed = crate::render_adjusted_preview(
+        &current_adjustments,
+        u32::MAX,
+        "upscale",
+        &state,
+        &app_handle,
+    )?;
+    let processed = DynamicImage::ImageRgb8(processed.to_rgb8());
+
+    let connector_address = settings
+        .ai_connector_address
+        .as_deref()
+        .filter(|_| settings.ai_provider.as_deref() == Some("ai-connector"));
+
+    let mut used_connector = false;
+    let upscaled = match connector_address {
+        Some(address) => {
+            let timeouts = ConnectorTimeouts::from_settings(&settings);
+            let capabilities = ai_connector::check_status(address, timeouts)
+                .await
+                .map_err(|e| e.to_string())?;
+            if !capabilities.upscale {
+                return Err("The AI connector does not support upscaling.".to_string());
+            }
+
+            emit_stage_progress(
+                &app_handle,
+                "upscale-progress",
+                "upscale",
+                20.0,
+                format!("Upscaling {}x on the AI connector...", scale),
+            );
+            used_connector = true;
+            let processed = limit_upscale_input(processed, scale, capabilities.max_resolution);
+            ai_connector::process_upscale(
+                &format!("http://{}", address),
+                &processed,
+                scale,
+                timeouts,
+            )
+            .await
+            .map_err(|e| e.to_string())?
+        }
+        None => {
+            log::info!("No AI connector configured, upscaling with Lanczos");
+            emit_stage_progress(
+                &app_handle,
+                "upscale-progress",
+                "upscale",
+                20.0,
+                format!("Upscaling {}x...", scale),
+            );
+            tokio::task::spawn_blocking(move || {
+                upscale_lanczos(&limit_upscale_input(processed, scale, None), scale)
+            })
+            .await
+            .map_err(|e| format!("Upscaling task failed: {}", e))?
+        }
+    };
+
+    emit_stage_progress(
+        &app_handle,
+        "upscale-progress",
+        "preview",
+        90.0,
+        "Generating preview...",
+    );
+    let (width, height) = upscaled.dimensions();
+    let mut preview_buf = Cursor::new(Vec::new());
+    upscaled
+        .thumbnail(UPSCALE_PREVIEW_SIZE, UPSCALE_PREVIEW_SIZE)
+        .to_rgb8()
+        .write_to(&mut preview_buf, ImageFormat::Jpeg)
+        .map_err(|e| format!("Failed to encode preview: {}", e))?;
+    let preview = format!(
+        "data:image/jpeg;base64,{}",
+        general_purpose::STANDARD.encode(preview_buf.get_ref())
+    );
+
+    *state.upscale_result.lock().unwrap() = Some(upscaled);
+    emit_stage_progress(&app_handle, "upscale-progress", "done", 100.0, "Done");
+
+    Ok(UpscaleResult {
+        width,
+        height,
+        used_connector,
+        preview,
+    })
+}
+
+#[tauri::command]
+pub async fn save_upscaled_image(
+    original_path_str: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let upscaled_image = state.upscale_result.lock().unwrap().take().ok_or_else(|| {
+        "No upscaled image found in memory. It might have already been saved or cleared."
+            .to_string()
+    })?;
+
+    let (source_path, _) = crate::file_management::parse_virtual_path(&original_path_str);
+    let parent_dir = source_path
+        .parent()
+        .ok_or_else(|| "Could not determine parent directory.".to_string())?;
+    let stem = source_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("upscaled");
+    let output_path = parent_dir.join(format!("{}_Upscaled.png", stem));
+
+    upscaled_image
+        .save(&output_path)
+        .map_err(|e| format!("Failed to save image: {}", e))?;
+
+    let _ =
+        crate::exif_processing::write_rrexif_sidecar(&source_path.to_string_lossy(), &output_path);
+
+    Ok(output_path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upscale_input_is_limited_by_output_size_and_connector_resolution() {
+        assert_eq!(fit_upscale_input((2000, 1500), 4, None), None);
+
+        let (width, height) = fit_upscale_input((8000, 6000), 4, None).unwrap();
+        assert!(width as u64 * height as u64 * 16 <= MAX_UPSCALED_PIXELS);
+        assert!((width as f64 / height as f64 - 4.0 / 3.0).abs() < 0.01);
+
+        assert_eq!(
+            fit_upscale_input((2000, 1000), 2, Some(1024)),
+            Some((1024, 512))
+        );
+    }
+}
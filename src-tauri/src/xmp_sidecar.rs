@@ -0,0 +1,339 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde_json::{Value, json};
+use tauri::Manager;
+
+use crate::AppState;
+use crate::file_management::{parse_virtual_path, resolve_xmp_path, sync_metadata_to_xmp};
+use crate::image_processing::{Crop, ImageMetadata};
+use crate::preset_converter::{convert_xmp_to_adjustments, get_attr_as_f64, parse_crs_attributes};
+
+const CRS_NAMESPACE: &str = "http://ns.adobe.com/camera-raw-settings/1.0/";
+
+fn format_signed_int(value: f64) -> String {
+    format!("{:+}", value.round() as i64)
+}
+
+fn adjustment_f64(adjustments: &Value, key: &str) -> Option<f64> {
+    adjustments.get(key).and_then(|v| v.as_f64())
+}
+
+/// Source dimensions as the crop sees them: RapidRAW stores crops after its quarter-turn
+/// rotation, so odd `orientationSteps` swap width and height. Export and import both go
+/// through this so the normalised crop edges round-trip.
+fn oriented_dims((width, height): (u32, u32), orientation_steps: u64) -> (f64, f64) {
+    if orientation_steps % 2 == 1 {
+        (height as f64, width as f64)
+    } else {
+        (width as f64, height as f64)
+    }
+}
+
+fn build_crs_attributes(adjustments: &Value, dims: Option<(u32, u32)>) -> Vec<(String, String)> {
+    let mut attrs: Vec<(String, String)> = vec![
+        ("ProcessVersion".to_string(), "11.0".to_string()),
+        ("HasSettings".to_string(), "True".to_string()),
+    ];
+
+    if let Some(exposure) = adjustment_f64(adjustments, "exposure") {
+        attrs.push(("Exposure2012".to_string(), format!("{:+.2}", exposure)));
+    }
+
+    let direct_mappings = [
+        ("contrast", "Contrast2012"),
+        ("highlights", "Highlights2012"),
+        ("whites", "Whites2012"),
+        ("blacks", "Blacks2012"),
+        ("clarity", "Clarity2012"),
+        ("dehaze", "Dehaze"),
+        ("vibrance", "Vibrance"),
+        ("saturation", "Saturation"),
+        ("texture", "Texture"),
+        ("vignetteAmount", "PostCropVignetteAmount"),
+        ("grainAmount", "GrainAmount"),
+    ];
+    for (rr_key, xmp_key) in direct_mappings {
+        if let Some(value) = adjustment_f64(adjustments, rr_key) {
+            attrs.push((
+                xmp_key.to_string(),
+                format_signed_int(value.clamp(-100.0, 100.0)),
+            ));
+        }
+    }
+
+    // Inverse of the 1.5x shadow boost applied when importing Lightroom presets.
+    if let Some(shadows) = adjustment_f64(adjustments, "shadows") {
+        attrs.push((
+            "Shadows2012".to_string(),
+            format_signed_int((shadows / 1.5).clamp(-100.0, 100.0)),
+        ));
+    }
+
+    let crop: Option<Crop> = adjustments
+        .get("crop")
+        .and_then(|c| serde_json::from_value(c.clone()).ok());
+    if let (Some(crop), Some((width, height))) = (crop, dims)
+        && width > 0
+        && height > 0
+    {
+        let orientation_steps = adjustments["orientationSteps"].as_u64().unwrap_or(0);
+        let (w, h) = oriented_dims((width, height), orientation_steps);
+        let left = (crop.x / w).clamp(0.0, 1.0);
+        let top = (crop.y / h).clamp(0.0, 1.0);
+        let right = ((crop.x + crop.width) / w).clamp(0.0, 1.0);
+        let bottom = ((crop.y + crop.height) / h).clamp(0.0, 1.0);
+        let rotation = adjustment_f64(adjustments, "rotation").unwrap_or(0.0);
+
+        attrs.push(("HasCrop".to_string(), "True".to_string()));
+        attrs.push(("CropLeft".to_string(), format!("{:.6}", left)));
+        attrs.push(("CropTop".to_string(), format!("{:.6}", top)));
+        attrs.push(("CropRight".to_string(), format!("{:.6}", right)));
+        attrs.push(("CropBottom".to_string(), format!("{:.6}", bottom)));
+        attrs.push(("CropAngle".to_string(), format!("{:.2}", rotation)));
+    } else {
+        attrs.push(("HasCrop".to_string(), "False".to_string()));
+    }
+
+    attrs
+}
+
+/// Sets the mapped `crs:` keys in an existing sidecar, whether written as attributes or as
+/// elements. Every other `crs:` setting (white balance, HSL, tone curves, lens corrections
+/// and so on) is left exactly as Lightroom wrote it.
+fn merge_crs_attributes(content: &str, attrs: &[(String, String)]) -> Result<String, String> {
+    let mut merged = content.to_string();
+    let mut missing = Vec::new();
+
+    for (key, value) in attrs {
+        let escaped = regex::escape(key);
+        let re_attr = Regex::new(&format!(r#"(\s+crs:{}\s*=\s*")[^"]*(")"#, escaped))
+            .map_err(|e| e.to_string())?;
+        let re_elem = Regex::new(&format!(r#"(<crs:{}>)[^<]*(</crs:{}>)"#, escaped, escaped))
+            .map_err(|e| e.to_string())?;
+        let replacement = format!("${{1}}{}${{2}}", value.replace('$', "$$"));
+
+        if re_attr.is_match(&merged) {
+            merged = re_attr.replace(&merged, replacement.as_str()).to_string();
+        } else if re_elem.is_match(&merged) {
+            merged = re_elem.replace(&merged, replacement.as_str()).to_string();
+        } else {
+            missing.push((key, value));
+        }
+    }
+
+    if missing.is_empty() {
+        return Ok(merged);
+    }
+
+    let re_crs_ns = Regex::new(r#"\s+xmlns:crs\s*=\s*"[^"]*""#).map_err(|e| e.to_string())?;
+    let mut injected = String::new();
+    let insert_at = match re_crs_ns.find(&merged) {
+        Some(ns) => ns.end(),
+        None => {
+            injected.push_str(&format!("\n    xmlns:crs=\"{}\"", CRS_NAMESPACE));
+            merged
+                .find("<rdf:Description")
+                .map(|idx| idx + "<rdf:Description".len())
+                .ok_or_else(|| "XMP file has no rdf:Description element".to_string())?
+        }
+    };
+    for (key, value) in missing {
+        injected.push_str(&format!("\n    crs:{}=\"{}\"", key, value));
+    }
+
+    merged.insert_str(insert_at, &injected);
+    Ok(merged)
+}
+
+pub fn write_xmp_for_source(
+    source_path: &Path,
+    metadata: &ImageMetadata,
+    dims: Option<(u32, u32)>,
+) -> Result<PathBuf, String> {
+    let xmp_path =
+        resolve_xmp_path(source_path).unwrap_or_else(|| source_path.with_extension("xmp"));
+
+    let existing = if xmp_path.exists() {
+        fs::read_to_string(&xmp_path).map_err(|e| e.to_string())?
+    } else {
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="RapidRAW">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/">
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#
+            .to_string()
+    };
+
+    let attrs = build_crs_attributes(&metadata.adjustments, dims);
+    let merged = merge_crs_attributes(&existing, &attrs)?;
+    fs::write(&xmp_path, merged).map_err(|e| e.to_string())?;
+
+    sync_metadata_to_xmp(source_path, metadata, false);
+
+    Ok(xmp_path)
+}
+
+fn resolve_source_dimensions(
+    state: &tauri::State<AppState>,
+    source_path: &Path,
+) -> Option<(u32, u32)> {
+    if let Some(loaded) = state.original_image.lock().unwrap().as_ref()
+        && parse_virtual_path(&loaded.path).0 == source_path
+    {
+        return Some((loaded.image.width(), loaded.image.height()));
+    }
+    image::image_dimensions(source_path).ok()
+}
+
+#[tauri::command]
+pub fn write_xmp_sidecar(path: String, app_handle: tauri::AppHandle) -> Result<String, String> {
+    let state = app_handle.state::<AppState>();
+    let (source_path, sidecar_path) = parse_virtual_path(&path);
+    let source_path_str = source_path.to_string_lossy().to_string();
+    let metadata = crate::exif_processing::load_sidecar(&sidecar_path);
+
+    let dims = resolve_source_dimensions(&state, &source_path);
+    if dims.is_none() {
+        log::warn!(
+            "Could not determine dimensions for {}, crop will not be written to XMP",
+            source_path_str
+        );
+    }
+
+    let xmp_path = write_xmp_for_source(&source_path, &metadata, dims)?;
+    Ok(xmp_path.to_string_lossy().to_string())
+}
+
+/// `orientation_steps` is the quarter-turn rotation the imported edit will be applied with,
+/// so the crop is read against the same oriented dimensions the export wrote it from.
+pub fn adjustments_from_xmp(
+    xmp_content: &str,
+    dims: Option<(u32, u32)>,
+    orientation_steps: u64,
+) -> Result<Value, String> {
+    let mut adjustments = convert_xmp_to_adjustments(xmp_content)?;
+    let attrs = parse_crs_attributes(xmp_content)?;
+
+    let has_crop = attrs
+        .get("HasCrop")
+        .is_some_and(|v| v.eq_ignore_ascii_case("true"));
+    if has_crop && let Some(dims) = dims {
+        let left = get_attr_as_f64(&attrs, "CropLeft").unwrap_or(0.0);
+        let top = get_attr_as_f64(&attrs, "CropTop").unwrap_or(0.0);
+        let right = get_attr_as_f64(&attrs, "CropRight").unwrap_or(1.0);
+        let bottom = get_attr_as_f64(&attrs, "CropBottom").unwrap_or(1.0);
+        let (w, h) = oriented_dims(dims, orientation_steps);
+
+        if right > left && bottom > top {
+            adjustments.insert(
+                "crop".to_string(),
+                json!({
+                    "x": (left * w).round(),
+                    "y": (top * h).round(),
+                    "width": ((right - left) * w).round(),
+                    "height": ((bottom - top) * h).round(),
+                }),
+            );
+        }
+        if let Some(angle) = get_attr_as_f64(&attrs, "CropAngle") {
+            adjustments.insert("rotation".to_string(), json!(angle));
+        }
+    }
+
+    Ok(Value::Object(adjustments))
+}
+
+pub fn has_native_adjustments(adjustments: &Value) -> bool {
+    match adjustments.as_object() {
+        Some(obj) => obj.keys().any(|k| k != "rating"),
+        None => false,
+    }
+}
+
+#[tauri::command]
+pub fn read_xmp_sidecar(path: String, app_handle: tauri::AppHandle) -> Result<Value, String> {
+    let state = app_handle.state::<AppState>();
+    let (source_path, sidecar_path) = parse_virtual_path(&path);
+    let xmp_path = resolve_xmp_path(&source_path)
+        .ok_or_else(|| format!("No XMP sidecar found for {}", source_path.display()))?;
+    let content = fs::read_to_string(&xmp_path).map_err(|e| e.to_string())?;
+
+    let dims = resolve_source_dimensions(&state, &source_path);
+    let orientation_steps =
+        crate::exif_processing::load_sidecar(&sidecar_path).adjustments["orientationSteps"]
+            .as_u64()
+            .unwrap_or(0);
+    adjustments_from_xmp(&content, dims, orientation_steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_keeps_unmapped_lightroom_settings() {
+        let existing = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:crs="http://ns.adobe.com/camera-raw-settings/1.0/"
+    crs:Temperature="5200"
+    crs:Tint="+8"
+    crs:Exposure2012="+0.50"
+    crs:HueAdjustmentRed="-12"
+    crs:LensProfileEnable="1">
+   <crs:ToneCurvePV2012>
+    <rdf:Seq>
+     <rdf:li>0, 0</rdf:li>
+     <rdf:li>255, 255</rdf:li>
+    </rdf:Seq>
+   </crs:ToneCurvePV2012>
+   <crs:Contrast2012>+10</crs:Contrast2012>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+        let attrs = vec![
+            ("Exposure2012".to_string(), "+1.25".to_string()),
+            ("Contrast2012".to_string(), "-20".to_string()),
+            ("HasCrop".to_string(), "False".to_string()),
+        ];
+
+        let merged = merge_crs_attributes(existing, &attrs).unwrap();
+
+        assert!(merged.contains(r#"crs:Exposure2012="+1.25""#));
+        assert!(!merged.contains("+0.50"));
+        assert!(merged.contains("<crs:Contrast2012>-20</crs:Contrast2012>"));
+        assert!(merged.contains(r#"crs:HasCrop="False""#));
+        assert_eq!(merged.matches("xmlns:crs=").count(), 1);
+
+        for kept in [
+            r#"crs:Temperature="5200""#,
+            r#"crs:Tint="+8""#,
+            r#"crs:HueAdjustmentRed="-12""#,
+            r#"crs:LensProfileEnable="1""#,
+            "<crs:ToneCurvePV2012>",
+            "<rdf:li>255, 255</rdf:li>",
+        ] {
+            assert!(merged.contains(kept), "lost {}", kept);
+        }
+    }
+
+    #[test]
+    fn merge_declares_the_namespace_when_missing() {
+        let existing = r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Rating="3"/>
+ </rdf:RDF>"#;
+        let attrs = vec![("Exposure2012".to_string(), "+1.00".to_string())];
+
+        let merged = merge_crs_attributes(existing, &attrs).unwrap();
+
+        assert!(merged.contains(&format!(r#"xmlns:crs="{}""#, CRS_NAMESPACE)));
+        assert!(merged.contains(r#"crs:Exposure2012="+1.00""#));
+        assert!(merged.contains(r#"xmp:Rating="3""#));
+    }
+}
@@ -272,6 +272,7 @@ function App() {
     handleSelectAlbum,
     handleOpenFolder,
     handleContinueSession,
+    handleRestoreSession,
   } = useAppNavigation({
     clearThumbnailQueue,
     refs: navigationRefs,
@@ -450,9 +451,30 @@ function App() {
     }
   }, [activeRightPanel, activeMaskContainerId, activeAiPatchContainerId, setEditor]);
 
+  const sessionToRestore = useProcessStore((state) => state.sessionToRestore);
+  useEffect(() => {
+    if (!sessionToRestore) return;
+    useProcessStore.getState().setProcess({ sessionToRestore: null });
+    handleRestoreSession(sessionToRestore);
+  }, [sessionToRestore]);
+
+  useEffect(() => {
+    if (!currentFolderPath && !selectedImage?.path) return;
+    invoke(Invokes.SaveSession, {
+      session: {
+        folder: currentFolderPath,
+        selectedImage: selectedImage?.path ?? null,
+        uiState: { activeRightPanel },
+      },
+    }).catch((e) => console.error('Failed to save session:', e));
+  }, [currentFolderPath, selectedImage?.path, activeRightPanel]);
+
   useEffect(() => {
     const unlisten = listen('ai-connector-status-update', (event: any) => {
-      setEditor({ isAIConnectorConnected: event.payload.connected });
+      setEditor({
+        isAIConnectorConnected: event.payload.connected,
+        aiConnectorCapabilities: event.payload.capabilities ?? null,
+      });
     });
     invoke(Invokes.CheckAIConnectorStatus);
     const interval = setInterval(() => invoke(Invokes.CheckAIConnectorStatus), 10000);
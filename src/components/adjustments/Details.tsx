@@ -158,6 +158,72 @@ export default function DetailsPanel({
           />
         </div>
       )}
+
+      {!isForMask && adjustmentVisibility.chromaticAberration !== false && (
+        <div className="p-2 bg-bg-tertiary rounded-md">
+          <Text variant={TextVariants.heading} className="mb-2">
+            {t('adjustments.details.defringe')}
+          </Text>
+          <Slider
+            label={t('adjustments.details.purpleAmount')}
+            max={100}
+            min={0}
+            onChange={(e: any) => handleAdjustmentChange(DetailsAdjustment.DefringePurpleAmount, e.target.value)}
+            step={1}
+            value={adjustments.defringePurpleAmount}
+            onDragStateChange={onDragStateChange}
+          />
+          <Slider
+            label={t('adjustments.details.purpleHue')}
+            max={360}
+            min={0}
+            onChange={(e: any) => handleAdjustmentChange(DetailsAdjustment.DefringePurpleHue, e.target.value)}
+            step={1}
+            value={adjustments.defringePurpleHue}
+            defaultValue={290}
+            onDragStateChange={onDragStateChange}
+          />
+          <Slider
+            label={t('adjustments.details.purpleRange')}
+            max={90}
+            min={10}
+            onChange={(e: any) => handleAdjustmentChange(DetailsAdjustment.DefringePurpleRange, e.target.value)}
+            step={1}
+            value={adjustments.defringePurpleRange}
+            defaultValue={40}
+            onDragStateChange={onDragStateChange}
+          />
+          <Slider
+            label={t('adjustments.details.greenAmount')}
+            max={100}
+            min={0}
+            onChange={(e: any) => handleAdjustmentChange(DetailsAdjustment.DefringeGreenAmount, e.target.value)}
+            step={1}
+            value={adjustments.defringeGreenAmount}
+            onDragStateChange={onDragStateChange}
+          />
+          <Slider
+            label={t('adjustments.details.greenHue')}
+            max={360}
+            min={0}
+            onChange={(e: any) => handleAdjustmentChange(DetailsAdjustment.DefringeGreenHue, e.target.value)}
+            step={1}
+            value={adjustments.defringeGreenHue}
+            defaultValue={110}
+            onDragStateChange={onDragStateChange}
+          />
+          <Slider
+            label={t('adjustments.details.greenRange')}
+            max={90}
+            min={10}
+            onChange={(e: any) => handleAdjustmentChange(DetailsAdjustment.DefringeGreenRange, e.target.value)}
+            step={1}
+            value={adjustments.defringeGreenRange}
+            defaultValue={40}
+            onDragStateChange={onDragStateChange}
+          />
+        </div>
+      )}
     </div>
   );
 }
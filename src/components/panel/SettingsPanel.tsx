@@ -520,6 +520,7 @@ export default function SettingsPanel({
 
   const [aiProvider, setAiProvider] = useState(appSettings?.aiProvider || 'cpu');
   const [aiConnectorAddress, setAiConnectorAddress] = useState<string>(appSettings?.aiConnectorAddress || '');
+  const [ffmpegPath, setFfmpegPath] = useState<string>(appSettings?.ffmpegPath || '');
   const [newShortcut, setNewShortcut] = useState('');
   const [newAiTag, setNewAiTag] = useState('');
 
@@ -561,6 +562,14 @@ export default function SettingsPanel({
     [t],
   );
 
+  const thumbnailFormatOptions = useMemo<OptionItem<string>[]>(
+    () => [
+      { value: 'jpeg', label: t('settings.processing.thumbnailFormats.jpeg') },
+      { value: 'webp', label: t('settings.processing.thumbnailFormats.webp') },
+    ],
+    [t],
+  );
+
   const livePreviewQualityOptions = useMemo<OptionItem<string>[]>(
     () => [
       { value: 'full', label: t('settings.processing.qualities.full') },
@@ -634,6 +643,9 @@ export default function SettingsPanel({
     if (appSettings?.aiConnectorAddress !== aiConnectorAddress) {
       setAiConnectorAddress(appSettings?.aiConnectorAddress || '');
     }
+    if (appSettings?.ffmpegPath !== ffmpegPath) {
+      setFfmpegPath(appSettings?.ffmpegPath || '');
+    }
     if (appSettings?.aiProvider !== aiProvider) {
       setAiProvider(appSettings?.aiProvider || 'cpu');
     }
@@ -897,8 +909,16 @@ export default function SettingsPanel({
     }
     setTestStatus({ testing: true, message: t('settings.processing.ai.connector.testing'), success: null });
     try {
-      await invoke(Invokes.TestAIConnectorConnection, { address: aiConnectorAddress });
-      setTestStatus({ testing: false, message: t('settings.processing.ai.connector.success'), success: true });
+      const result: { latencyMs: number; capabilities: { models: Array<string> } } = await invoke(
+        Invokes.TestAIConnectorConnection,
+        { address: aiConnectorAddress },
+      );
+      const models = result.capabilities.models.length > 0 ? ` · ${result.capabilities.models.join(', ')}` : '';
+      setTestStatus({
+        testing: false,
+        message: `${t('settings.processing.ai.connector.success')} (${result.latencyMs} ms)${models}`,
+        success: true,
+      });
     } catch (err) {
       setTestStatus({ testing: false, message: t('settings.processing.ai.connector.failed'), success: false });
       console.error('AI Connector connection test failed:', err);
@@ -1129,6 +1149,18 @@ export default function SettingsPanel({
                           </motion.div>
                         )}
                       </AnimatePresence>
+
+                      <SettingItem
+                        label={t('settings.general.writeRatingToFile')}
+                        description={t('settings.general.writeRatingToFileDesc')}
+                      >
+                        <Switch
+                          checked={appSettings?.writeRatingToFile ?? false}
+                          id="write-rating-to-file-toggle"
+                          label={t('settings.general.writeRatingToFileToggle')}
+                          onChange={(checked) => onSettingsChange({ ...appSettings, writeRatingToFile: checked })}
+                        />
+                      </SettingItem>
                     </div>
 
                     <SettingItem
@@ -1836,6 +1868,46 @@ export default function SettingsPanel({
                       />
                     </SettingItem>
 
+                    <SettingItem
+                      description={t('settings.processing.thumbnailFormatDesc')}
+                      label={t('settings.processing.thumbnailFormat')}
+                    >
+                      <Dropdown
+                        onChange={(value: any) => onSettingsChange({ ...appSettings, thumbnailFormat: value })}
+                        options={thumbnailFormatOptions}
+                        value={appSettings?.thumbnailFormat || 'jpeg'}
+                        triggerClassName="bg-bg-primary"
+                      />
+                    </SettingItem>
+
+                    <SettingItem
+                      description={t('settings.processing.thumbnailShowEditsDesc')}
+                      label={t('settings.processing.thumbnailShowEdits')}
+                    >
+                      <Switch
+                        checked={appSettings?.thumbnailShowEdits ?? true}
+                        id="thumbnail-show-edits-toggle"
+                        label={t('settings.processing.thumbnailShowEditsToggle')}
+                        onChange={(checked) => onSettingsChange({ ...appSettings, thumbnailShowEdits: checked })}
+                      />
+                    </SettingItem>
+
+                    <SettingItem
+                      description={t('settings.processing.ffmpegPathDesc')}
+                      label={t('settings.processing.ffmpegPath')}
+                    >
+                      <Input
+                        id="ffmpeg-path"
+                        onBlur={() => onSettingsChange({ ...appSettings, ffmpegPath: ffmpegPath.trim() || undefined })}
+                        onChange={(e: any) => setFfmpegPath(e.target.value)}
+                        onKeyDown={(e: any) => e.stopPropagation()}
+                        placeholder="ffmpeg"
+                        type="text"
+                        value={ffmpegPath}
+                        bgClassName="bg-bg-primary"
+                      />
+                    </SettingItem>
+
                     <SettingItem
                       label={t('settings.processing.workerThreads')}
                       description={t('settings.processing.workerThreadsDesc')}
@@ -1870,6 +1942,18 @@ export default function SettingsPanel({
                       />
                     </SettingItem>
 
+                    <SettingItem
+                      label={t('settings.processing.renderCache')}
+                      description={t('settings.processing.renderCacheDesc')}
+                    >
+                      <Switch
+                        checked={appSettings?.renderCacheEnabled ?? false}
+                        id="render-cache-toggle"
+                        label={t('settings.processing.renderCacheLabel')}
+                        onChange={(checked) => onSettingsChange({ ...appSettings, renderCacheEnabled: checked })}
+                      />
+                    </SettingItem>
+
                     <SettingItem
                       label={t('settings.processing.wgpu')}
                       description={
@@ -2164,6 +2248,23 @@ export default function SettingsPanel({
                                 </Text>
                               )}
                             </SettingItem>
+                            <SettingItem
+                              label={t('settings.processing.ai.connector.timeout')}
+                              description={t('settings.processing.ai.connector.timeoutDesc')}
+                            >
+                              <Slider
+                                label={t('settings.processing.ai.connector.timeoutLabel')}
+                                min={30}
+                                max={900}
+                                step={30}
+                                value={appSettings?.aiConnectorTimeoutSecs ?? 180}
+                                defaultValue={180}
+                                onChange={(e: any) =>
+                                  onSettingsChange({ ...appSettings, aiConnectorTimeoutSecs: parseInt(e.target.value) })
+                                }
+                                fillOrigin="min"
+                              />
+                            </SettingItem>
                           </div>
                         </motion.div>
                       )}
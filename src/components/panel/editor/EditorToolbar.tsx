@@ -173,6 +173,12 @@ const EditorToolbar = memo(
           lutSize: 'LUT Size',
           chromaticAberrationBlueYellow: 'Chromatic Aberration Blue/Yellow',
           chromaticAberrationRedCyan: 'Chromatic Aberration Red/Cyan',
+          defringePurpleAmount: 'Defringe Purple Amount',
+          defringePurpleHue: 'Defringe Purple Hue',
+          defringePurpleRange: 'Defringe Purple Range',
+          defringeGreenAmount: 'Defringe Green Amount',
+          defringeGreenHue: 'Defringe Green Hue',
+          defringeGreenRange: 'Defringe Green Range',
           centré: 'Centré',
           lumaNoiseReduction: 'Luma Noise Reduction',
           colorNoiseReduction: 'Color Noise Reduction',
@@ -285,6 +285,7 @@ export default function AIPanel() {
   const adjustments = useEditorStore((s) => s.adjustments);
   const brushSettings = useEditorStore((s) => s.brushSettings);
   const isAIConnectorConnected = useEditorStore((s) => s.isAIConnectorConnected);
+  const aiConnectorCapabilities = useEditorStore((s) => s.aiConnectorCapabilities);
   const isGeneratingAi = useEditorStore((s) => s.isGeneratingAi);
   const isGeneratingAiMask = useEditorStore((s) => s.isGeneratingAiMask);
   const selectedImage = useEditorStore((s) => s.selectedImage);
@@ -304,7 +305,7 @@ export default function AIPanel() {
   const [cloudUsage, setCloudUsage] = useState<{ requests: number; limit: number; month: string } | null>(null);
 
   const isGenerativeAvailable =
-    (aiProvider === 'cloud' && !!isSignedIn && !!isPro) || (aiProvider === 'ai-connector' && isAIConnectorConnected);
+    (aiProvider === 'cloud' && !!isSignedIn && !!isPro) || (aiProvider === 'ai-connector' && isAIConnectorConnected && aiConnectorCapabilities?.inpaint !== false);
 
   useEffect(() => {
     if (aiProvider !== 'cloud' || !isSignedIn || !isPro) return;
@@ -1865,6 +1866,7 @@ function SettingsPanel({
   isGenerativeAvailable,
 }: any) {
   const { t } = useTranslation();
+  const aiGenerationProgress = useEditorStore((s) => s.aiGenerationProgress);
   const isActive = !!container;
   const isComponentMode = !!activeSubMask;
   const displayContainer = container || PLACEHOLDER_PATCH;
@@ -2005,7 +2007,9 @@ function SettingsPanel({
               )}
               <span className="ml-2">
                 {isGeneratingAi || displayContainer.isLoading
-                  ? t('editor.ai.settings.generating')
+                  ? aiGenerationProgress !== null
+                    ? t('editor.ai.settings.generatingProgress', { percentage: aiGenerationProgress })
+                    : t('editor.ai.settings.generating')
                   : useFastInpaint
                     ? t('editor.ai.settings.inpaintSelectionButton')
                     : t('editor.ai.settings.generateWithAiButton')}
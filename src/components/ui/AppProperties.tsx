@@ -34,32 +34,42 @@ export enum Invokes {
   ApplyAdjustments = 'apply_adjustments',
   ApplyAdjustmentsToPaths = 'apply_adjustments_to_paths',
   ApplyAutoAdjustmentsToPaths = 'apply_auto_adjustments_to_paths',
+  ApplyCalibrationFrames = 'apply_calibration_frames',
   ApplyDenoising = 'apply_denoising',
   CalculateAutoAdjustments = 'calculate_auto_adjustments',
   CancelExport = 'cancel_export',
+  CancelPrint = 'cancel_print',
   CheckAIConnectorStatus = 'check_ai_connector_status',
   ClearAllSidecars = 'clear_all_sidecars',
   ClearAiTags = 'clear_ai_tags',
   ClearAllTags = 'clear_all_tags',
   ClearThumbnailCache = 'clear_thumbnail_cache',
+  CompareAdjustments = 'compare_adjustments',
   CopyFiles = 'copy_files',
   CreateFolder = 'create_folder',
   CreateVirtualCopy = 'create_virtual_copy',
   CullImages = 'cull_images',
   DeleteFolder = 'delete_folder',
+  DeflickerSequence = 'deflicker_sequence',
+  DownloadGeonamesData = 'download_geonames_data',
   DuplicateFile = 'duplicate_file',
   EstimateExportSizes = 'estimate_export_sizes',
   ExportImages = 'export_images',
+  ExportTimelapse = 'export_timelapse',
+  ExtractPreviews = 'extract_previews',
   FrontendLog = 'frontend_log',
   GenerateAiForegroundMask = 'generate_ai_foreground_mask',
   GenerateAiSkyMask = 'generate_ai_sky_mask',
   GenerateAiSubjectMask = 'generate_ai_subject_mask',
   GenerateFullscreenPreview = 'generate_fullscreen_preview',
+  GenerateHistogramForPath = 'generate_histogram_for_path',
   GeneratePreviewForPath = 'generate_preview_for_path',
   GenerateMaskOverlay = 'generate_mask_overlay',
   GeneratePresetPreview = 'generate_preset_preview',
+  GenerateThumbnails = 'generate_thumbnails',
   GenerateThumbnailsProgressive = 'generate_thumbnails_progressive',
   GenerateUncroppedPreview = 'generate_uncropped_preview',
+  GenerativeExpand = 'generative_expand',
   GetFolderTree = 'get_folder_tree',
   GetFolderChildren = 'get_folder_children',
   GetLogFilePath = 'get_log_file_path',
@@ -69,6 +79,7 @@ export enum Invokes {
   HandleExportPresetsToFile = 'handle_export_presets_to_file',
   HandleImportPresetsFromFile = 'handle_import_presets_from_file',
   HandleImportLegacyPresetsFromFile = 'handle_import_legacy_presets_from_file',
+  HasGeonamesData = 'has_geonames_data',
   ImportFiles = 'import_files',
   InvokeGenerativeReplace = 'invoke_generative_replace',
   InvokeGenerativeReplaseWithMaskDef = 'invoke_generative_replace_with_mask_def',
@@ -79,25 +90,33 @@ export enum Invokes {
   LoadPresets = 'load_presets',
   LoadSettings = 'load_settings',
   MoveFiles = 'move_files',
+  PrintImages = 'print_images',
   ReadExifForPaths = 'read_exif_for_paths',
   RemoveTagForPaths = 'remove_tag_for_paths',
   RenameFiles = 'rename_files',
+  RenameFilesTemplated = 'rename_files_templated',
   RenameFolder = 'rename_folder',
   ResetAdjustmentsForPaths = 'reset_adjustments_for_paths',
   SaveMetadataAndUpdateThumbnail = 'save_metadata_and_update_thumbnail',
   SaveCollage = 'save_collage',
   SaveDenoisedImage = 'save_denoised_image',
+  SaveExpandedImage = 'save_expanded_image',
+  SaveUpscaledImage = 'save_upscaled_image',
   SavePanorama = 'save_panorama',
   SaveHdr = 'save_hdr',
   SavePresets = 'save_presets',
+  SaveSession = 'save_session',
   SaveSettings = 'save_settings',
   SetColorLabelForPaths = 'set_color_label_for_paths',
   SetRatingForPaths = 'set_rating_for_paths',
   ShowInFinder = 'show_in_finder',
+  SoftProofPreview = 'soft_proof_preview',
   StartBackgroundIndexing = 'start_background_indexing',
   StitchPanorama = 'stitch_panorama',
   MergeHdr = 'merge_hdr',
+  OpenSecondaryWindow = 'open_secondary_window',
   TestAIConnectorConnection = 'test_ai_connector_connection',
+  UpscaleImage = 'upscale_image',
   UpdateWgpuTransform = 'update_wgpu_transform',
   UpdateExifFields = 'update_exif_fields',
   FetchCommunityPresets = 'fetch_community_presets',
@@ -163,10 +182,12 @@ export enum ThumbnailAspectRatio {
 
 export interface AppSettings {
   aiConnectorAddress?: string;
+  aiConnectorTimeoutSecs?: number;
   aiProvider?: string;
   decorations?: any;
   editorPreviewResolution?: number;
   enableZoomHifi?: boolean;
+  ffmpegPath?: string;
   useFullDpiRendering?: boolean;
   highResZoomMultiplier?: number;
   enableLivePreviews?: boolean;
@@ -183,6 +204,8 @@ export interface AppSettings {
   theme: Theme;
   thumbnailSize?: ThumbnailSize;
   thumbnailAspectRatio?: ThumbnailAspectRatio;
+  thumbnailFormat?: string;
+  thumbnailShowEdits?: boolean;
   uiVisibility?: UiVisibility;
   adjustmentVisibility?: { [key: string]: boolean };
   rawHighlightCompression?: number;
@@ -195,6 +218,7 @@ export interface AppSettings {
   linearRawMode?: string;
   enableXmpSync?: boolean;
   createXmpIfMissing?: boolean;
+  writeRatingToFile?: boolean;
   isWaveformVisible?: boolean;
   waveformHeight?: number;
   activeWaveformChannel?: string;
@@ -207,6 +231,7 @@ export interface AppSettings {
   defaultNonRawTonemapper?: string;
   copyPasteSettings?: CopyPasteSettings;
   enableFocusMode?: boolean;
+  renderCacheEnabled?: boolean;
   openTreeSections?: string[];
   folderIcons?: Record<string, string>;
   exifOverlay?: ExifOverlay;
@@ -244,6 +269,12 @@ export interface FilterCriteria {
   editedStatus?: EditedStatus;
 }
 
+export interface EditorSession {
+  folder: string | null;
+  selectedImage: string | null;
+  uiState: any;
+}
+
 export interface Folder {
   children: any;
   id?: string | undefined;
@@ -296,6 +327,7 @@ export interface Progress {
 }
 
 export interface SelectedImage {
+  clippedHighlightPercentage?: number | null;
   exif: any;
   height: number;
   isRaw: boolean;
@@ -308,6 +340,11 @@ export interface SelectedImage {
   width: number;
 }
 
+export interface SoftProofPreview {
+  image: string;
+  outOfGamutFraction: number;
+}
+
 export interface SortCriteria {
   key: string;
   label?: string;
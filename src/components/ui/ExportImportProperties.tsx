@@ -47,6 +47,19 @@ export interface ExportSettings {
   watermark: WatermarkSettings | null;
   exportMasks?: boolean;
   preserveFolders?: boolean;
+  outputSharpening?: OutputSharpening;
+}
+
+export enum OutputSharpeningMedium {
+  None = 'none',
+  Screen = 'screen',
+  PrintMatte = 'printMatte',
+  PrintGlossy = 'printGlossy',
+}
+
+export interface OutputSharpening {
+  medium: OutputSharpeningMedium;
+  amount: number;
 }
 
 export enum WatermarkAnchor {
@@ -118,5 +131,9 @@ export interface ExportPreset {
   watermarkScale: number;
   watermarkSpacing: number;
   watermarkOpacity: number;
+  outputSharpening?: OutputSharpening;
+  outputIccProfile?: string | null;
+  skipExisting?: boolean;
+  skipExistingOnlyIfNewer?: boolean;
   lastExportPath?: string;
 }
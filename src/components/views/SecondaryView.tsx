@@ -0,0 +1,30 @@
+import { useEffect, useState } from 'react';
+import { listen } from '@tauri-apps/api/event';
+
+interface SecondaryPreview {
+  dataUrl: string;
+  path: string | null;
+}
+
+export default function SecondaryView() {
+  const [preview, setPreview] = useState<SecondaryPreview | null>(null);
+
+  useEffect(() => {
+    const unlisten = listen('secondary-preview-update', (event: any) => {
+      setPreview(event.payload as SecondaryPreview);
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  return (
+    <div className="h-screen w-screen bg-black flex items-center justify-center overflow-hidden">
+      {preview ? (
+        <img alt={preview.path ?? ''} className="max-h-full max-w-full object-contain" src={preview.dataUrl} />
+      ) : (
+        <p className="text-sm text-neutral-400">Waiting for the editor...</p>
+      )}
+    </div>
+  );
+}
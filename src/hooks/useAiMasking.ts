@@ -110,7 +110,7 @@ export function useAiMasking() {
         aiPatches: prev.aiPatches.map((p: AiPatch) => (p.id === patchId ? { ...p, isLoading: true, prompt } : p)),
       }));
 
-      setEditor({ isGeneratingAi: true });
+      setEditor({ isGeneratingAi: true, aiGenerationProgress: null });
 
       try {
         const newPatchDataJson: any = await invoke(Invokes.InvokeGenerativeReplaseWithMaskDef, {
@@ -145,7 +145,7 @@ export function useAiMasking() {
           aiPatches: prev.aiPatches.map((p: AiPatch) => (p.id === patchId ? { ...p, isLoading: false } : p)),
         }));
       } finally {
-        setEditor({ isGeneratingAi: false });
+        setEditor({ isGeneratingAi: false, aiGenerationProgress: null });
       }
     },
     [setAdjustments, setEditor],
@@ -162,7 +162,7 @@ export function useAiMasking() {
       )?.id;
       if (!patchId) return;
 
-      setEditor({ isGeneratingAi: true });
+      setEditor({ isGeneratingAi: true, aiGenerationProgress: null });
       setAdjustments((prev: Partial<Adjustments>) => ({
         ...prev,
         aiPatches: prev.aiPatches?.map((p: AiPatch) => (p.id === patchId ? { ...p, isLoading: true } : p)),
@@ -234,7 +234,7 @@ export function useAiMasking() {
           aiPatches: prev.aiPatches?.map((p: AiPatch) => (p.id === patchId ? { ...p, isLoading: false } : p)),
         }));
       } finally {
-        setEditor({ isGeneratingAi: false });
+        setEditor({ isGeneratingAi: false, aiGenerationProgress: null });
       }
     },
     [setAdjustments, setEditor],
@@ -1,5 +1,6 @@
 import { useEffect, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { useShallow } from 'zustand/react/shallow';
 import { useSettingsStore } from '../store/useSettingsStore';
 import { useUIStore } from '../store/useUIStore';
@@ -222,7 +223,18 @@ export const useAppInitialization = ({
           });
         }
 
-        invoke('frontend_ready')
+        const unlistenRestore = listen('restore-session', (event: any) => {
+          const session = event.payload;
+          if (session?.uiState?.activeRightPanel !== undefined) {
+            setUI({ activeRightPanel: session.uiState.activeRightPanel });
+          }
+          if (session?.folder || session?.selectedImage) {
+            useProcessStore.getState().setProcess({ sessionToRestore: session });
+          }
+        });
+
+        unlistenRestore
+          .then(() => invoke('frontend_ready'))
           .then((launch: any) => {
             if (launch?.editSession) {
               useProcessStore.getState().setProcess({ externalEditSession: launch.editSession });
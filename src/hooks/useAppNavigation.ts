@@ -8,7 +8,7 @@ import { useEditorStore } from '../store/useEditorStore';
 import { useUIStore } from '../store/useUIStore';
 import { useProcessStore } from '../store/useProcessStore';
 import { useSettingsStore } from '../store/useSettingsStore';
-import { Invokes, LibraryViewMode, ImageFile } from '../components/ui/AppProperties';
+import { Invokes, LibraryViewMode, ImageFile, EditorSession } from '../components/ui/AppProperties';
 import { INITIAL_ADJUSTMENTS, normalizeLoadedAdjustments } from '../utils/adjustments';
 import { globalImageCache } from '../utils/ImageLRUCache';
 import { debouncedSave, debouncedSetHistory } from './useEditorActions';
@@ -492,7 +492,7 @@ export function useAppNavigation({ clearThumbnailQueue, refs }: AppNavigationPro
     }
   };
 
-  const handleContinueSession = () => {
+  const restoreLibrary = (folderToSelect?: string | null) => {
     const restore = async () => {
       const { appSettings } = useSettingsStore.getState();
       const { setLibrary } = useLibraryStore.getState();
@@ -506,7 +506,7 @@ export function useAppNavigation({ clearThumbnailQueue, refs }: AppNavigationPro
       if (rootFolders.length === 0) return;
 
       const folderState = appSettings?.lastFolderState;
-      const pathToSelect = folderState?.currentFolderPath || rootFolders[0];
+      const pathToSelect = folderToSelect || folderState?.currentFolderPath || rootFolders[0];
 
       setLibrary({ rootPaths: rootFolders });
 
@@ -586,12 +586,26 @@ export function useAppNavigation({ clearThumbnailQueue, refs }: AppNavigationPro
       }
     };
 
-    restore().catch((err) => {
-      console.error('Failed to restore session:', err);
-      toast.error('Failed to restore session. A folder may have been moved or deleted.');
-      handleGoHome();
-      useLibraryStore.getState().setLibrary({ isTreeLoading: false });
-    });
+    return restore()
+      .then(() => true)
+      .catch((err) => {
+        console.error('Failed to restore session:', err);
+        toast.error('Failed to restore session. A folder may have been moved or deleted.');
+        handleGoHome();
+        useLibraryStore.getState().setLibrary({ isTreeLoading: false });
+        return false;
+      });
+  };
+
+  const handleContinueSession = () => {
+    restoreLibrary();
+  };
+
+  const handleRestoreSession = async (session: EditorSession) => {
+    const restored = await restoreLibrary(session.folder);
+    if (restored && session.selectedImage) {
+      handleImageSelect(session.selectedImage);
+    }
   };
 
   return {
@@ -602,5 +616,6 @@ export function useAppNavigation({ clearThumbnailQueue, refs }: AppNavigationPro
     handleSelectAlbum,
     handleOpenFolder,
     handleContinueSession,
+    handleRestoreSession,
   };
 }
@@ -25,7 +25,10 @@ export function useFileOperations(
   };
 
   const executeDelete = useCallback(
-    async (pathsToDelete: Array<string>, options = { includeAssociated: false }) => {
+    async (
+      pathsToDelete: Array<string>,
+      options: { includeAssociated: boolean; permanent?: boolean } = { includeAssociated: false },
+    ) => {
       if (!pathsToDelete || pathsToDelete.length === 0) return;
 
       const { libraryActivePath, setLibrary } = useLibraryStore.getState();
@@ -65,7 +68,7 @@ export function useFileOperations(
 
       try {
         const command = options.includeAssociated ? 'delete_files_with_associated' : 'delete_files_from_disk';
-        await invoke(command, { paths: pathsToDelete });
+        await invoke(command, { paths: pathsToDelete, useTrash: !options.permanent });
         await refreshImageList();
 
         if (selectedImage) {
@@ -81,6 +81,7 @@ export function useImageLoader(cachedEditStateRef: React.RefObject<any>) {
               return {
                 selectedImage: {
                   ...state.selectedImage,
+                  clippedHighlightPercentage: loadImageResult.clipped_highlight_percentage,
                   exif: loadImageResult.exif,
                   height: loadImageResult.height,
                   isRaw: loadImageResult.is_raw,
@@ -202,6 +202,10 @@ export function useTauriListeners({
             errorMessage: typeof event.payload === 'string' ? event.payload : 'Unknown error',
           });
       }),
+      listen('inpaint-progress', (event: any) => {
+        if (isEffectActive && useEditorStore.getState().isGeneratingAi)
+          useEditorStore.getState().setEditor({ aiGenerationProgress: event.payload.percentage });
+      }),
       listen('denoise-progress', (event: any) => {
         if (isEffectActive)
           useUIStore.getState().setUI((state) => ({
@@ -244,7 +248,9 @@ export function useTauriListeners({
         if (isEffectActive) {
           useUIStore.getState().setUI((state) => {
             if (state.panoramaModalState.finalImageBase64 || state.panoramaModalState.error) return state;
-            return { panoramaModalState: { ...state.panoramaModalState, progressMessage: event.payload } };
+            return {
+              panoramaModalState: { ...state.panoramaModalState, progressMessage: event.payload?.message ?? event.payload },
+            };
           });
         }
       }),
@@ -266,7 +272,7 @@ export function useTauriListeners({
           useUIStore.getState().setUI((state) => ({
             panoramaModalState: {
               ...state.panoramaModalState,
-              error: String(event.payload),
+              error: String(event.payload?.message ?? event.payload),
               finalImageBase64: null,
               isProcessing: false,
               progressMessage: null,
@@ -282,7 +288,7 @@ export function useTauriListeners({
               error: null,
               finalImageBase64: null,
               isOpen: true,
-              progressMessage: event.payload,
+              progressMessage: event.payload?.message ?? event.payload,
             },
           }));
         }
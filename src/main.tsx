@@ -1,6 +1,8 @@
 import React from 'react';
 import { createRoot } from 'react-dom/client';
+import { getCurrentWindow } from '@tauri-apps/api/window';
 import App from './App';
+import SecondaryView from './components/views/SecondaryView';
 import { installFrontendLogBridge } from './utils/frontendLogBridge';
 import './styles.css';
 
@@ -9,6 +11,6 @@ installFrontendLogBridge();
 const root = createRoot(document.getElementById('root')!);
 root.render(
   <React.StrictMode>
-    <App />
+    {getCurrentWindow().label === 'secondary' ? <SecondaryView /> : <App />}
   </React.StrictMode>,
 );
@@ -6,6 +6,15 @@ import { ImageDimensions } from '../hooks/useImageRenderSize';
 import { ToolType } from '../components/panel/right/Masks';
 import { OverlayMode } from '../components/panel/right/CropPanel';
 
+export interface AIConnectorCapabilities {
+  inpaint: boolean;
+  outpaint: boolean;
+  upscale: boolean;
+  jobs: boolean;
+  models: Array<string>;
+  maxResolution: number | null;
+}
+
 export interface InteractivePatch {
   url: string;
   normX: number;
@@ -70,7 +79,9 @@ interface EditorState {
   isMaskControlHovered: boolean;
   isGeneratingAiMask: boolean;
   isGeneratingAi: boolean;
+  aiGenerationProgress: number | null;
   isAIConnectorConnected: boolean;
+  aiConnectorCapabilities: AIConnectorCapabilities | null;
   hasRenderedFirstFrame: boolean;
   patchesSentToBackend: Set<string>;
 
@@ -132,7 +143,9 @@ export const useEditorStore = create<EditorState>((set) => ({
 
   isGeneratingAiMask: false,
   isAIConnectorConnected: false,
+  aiConnectorCapabilities: null,
   isGeneratingAi: false,
+  aiGenerationProgress: null,
   isMaskControlHovered: false,
   hasRenderedFirstFrame: false,
   patchesSentToBackend: new Set<string>(),
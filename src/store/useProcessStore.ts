@@ -1,5 +1,5 @@
 import { create } from 'zustand';
-import { Progress } from '../components/ui/AppProperties';
+import { EditorSession, Progress } from '../components/ui/AppProperties';
 import { ExportState, ImportState, Status } from '../components/ui/ExportImportProperties';
 
 export interface ExternalEditSession {
@@ -22,6 +22,7 @@ interface ProcessState {
   isCopied: boolean;
   isPasted: boolean;
   initialFileToOpen: string | null;
+  sessionToRestore: EditorSession | null;
   externalEditSession: ExternalEditSession | null;
 
   setProcess: (state: Partial<ProcessState> | ((state: ProcessState) => Partial<ProcessState>)) => void;
@@ -51,6 +52,7 @@ export const useProcessStore = create<ProcessState>((set, get) => ({
   isCopied: false,
   isPasted: false,
   initialFileToOpen: null,
+  sessionToRestore: null,
   externalEditSession: null,
 
   setProcess: (updater) => {
@@ -70,6 +70,12 @@ export enum DetailsAdjustment {
   SharpnessThreshold = 'sharpnessThreshold',
   ChromaticAberrationRedCyan = 'chromaticAberrationRedCyan',
   ChromaticAberrationBlueYellow = 'chromaticAberrationBlueYellow',
+  DefringePurpleAmount = 'defringePurpleAmount',
+  DefringePurpleHue = 'defringePurpleHue',
+  DefringePurpleRange = 'defringePurpleRange',
+  DefringeGreenAmount = 'defringeGreenAmount',
+  DefringeGreenHue = 'defringeGreenHue',
+  DefringeGreenRange = 'defringeGreenRange',
 }
 
 export enum Effect {
@@ -166,6 +172,12 @@ export interface Adjustments {
   parametricCurve?: ParametricCurve;
   curveMode?: 'point' | 'parametric';
   crop: Crop | null;
+  defringeGreenAmount: number;
+  defringeGreenHue: number;
+  defringeGreenRange: number;
+  defringePurpleAmount: number;
+  defringePurpleHue: number;
+  defringePurpleRange: number;
   dehaze: number;
   exposure: number;
   flipHorizontal: boolean;
@@ -239,8 +251,12 @@ export interface AiPatch {
   isLoading: boolean;
   invert: boolean;
   name: string;
+  guidanceScale?: number;
+  negativePrompt?: string;
   patchData: any | null;
   prompt: string;
+  seed?: number;
+  steps?: number;
   subMasks: Array<SubMask>;
   visible: boolean;
 }
@@ -494,6 +510,12 @@ export const INITIAL_ADJUSTMENTS: Adjustments = {
   pointCurves: getDefaultCurves(),
   parametricCurve: getDefaultParametricCurve(),
   curveMode: 'point',
+  defringeGreenAmount: 0,
+  defringeGreenHue: 110,
+  defringeGreenRange: 40,
+  defringePurpleAmount: 0,
+  defringePurpleHue: 290,
+  defringePurpleRange: 40,
   dehaze: 0,
   exposure: 0,
   flipHorizontal: false,
@@ -752,6 +774,17 @@ export const ADJUSTMENT_GROUPS: Record<string, AdjustmentGroup[]> = {
       label: 'modals.copyPaste.groups.chromaticAberration',
       keys: [DetailsAdjustment.ChromaticAberrationRedCyan, DetailsAdjustment.ChromaticAberrationBlueYellow],
     },
+    {
+      label: 'modals.copyPaste.groups.defringe',
+      keys: [
+        DetailsAdjustment.DefringePurpleAmount,
+        DetailsAdjustment.DefringePurpleHue,
+        DetailsAdjustment.DefringePurpleRange,
+        DetailsAdjustment.DefringeGreenAmount,
+        DetailsAdjustment.DefringeGreenHue,
+        DetailsAdjustment.DefringeGreenRange,
+      ],
+    },
   ],
   effects: [
     {
@@ -842,6 +875,12 @@ export const ADJUSTMENT_SECTIONS: Sections = {
     DetailsAdjustment.ColorNoiseReduction,
     DetailsAdjustment.ChromaticAberrationRedCyan,
     DetailsAdjustment.ChromaticAberrationBlueYellow,
+    DetailsAdjustment.DefringePurpleAmount,
+    DetailsAdjustment.DefringePurpleHue,
+    DetailsAdjustment.DefringePurpleRange,
+    DetailsAdjustment.DefringeGreenAmount,
+    DetailsAdjustment.DefringeGreenHue,
+    DetailsAdjustment.DefringeGreenRange,
   ],
   effects: [
     CreativeAdjustment.GlowAmount,